@@ -3,22 +3,31 @@
 //! A binary responsible for the orchestration and management of HASH Engine (hEngine) processes
 //! that are used to run HASH simulation projects. This CLI is a light-weight implementation of an
 //! [`orchestrator`] which enables the running of an experiment through the command-line, accepting
-//! a variety of [`Args`].
+//! a variety of [`Args`]. Alternatively, it can serve the [HTTP API](orchestrator::api) to submit
+//! and monitor experiments.
 #![allow(clippy::module_inception)]
 
 use std::{
     error::Error,
     fmt,
     fmt::Debug,
+    net::SocketAddr,
     path::PathBuf,
     time::{SystemTime, UNIX_EPOCH},
 };
 
-use clap::{AppSettings, Parser};
-use error_stack::{IntoReport, Result, ResultExt};
+use clap::{AppSettings, Parser, Subcommand};
+use error_stack::{IntoReport, Report, Result, ResultExt};
+use execution::package::experiment::ExperimentName;
 use experiment_control::environment::init_logger;
 use experiment_structure::{ExperimentType, Manifest};
-use orchestrator::{Experiment, ExperimentConfig, Server};
+use memory::shared_memory::{self, SegmentInfo};
+use orchestrator::{
+    api::ApiServer,
+    queue::{ExperimentQueue, QueueConfig},
+    Experiment, ExperimentConfig, Server,
+};
+use uuid::Uuid;
 
 /// Arguments passed to the CLI
 #[derive(Debug, Parser)]
@@ -27,15 +36,75 @@ use orchestrator::{Experiment, ExperimentConfig, Server};
 #[clap(setting(AppSettings::UseLongFormatForHelpSubcommand))]
 pub struct Args {
     /// Path to the project to be run.
+    ///
+    /// Required for running an experiment.
     #[clap(short, long, env = "HASH_PROJECT")]
-    project: PathBuf,
+    project: Option<PathBuf>,
 
     #[clap(flatten)]
     experiment_config: ExperimentConfig,
 
+    #[clap(subcommand)]
+    command: Command,
+}
+
+/// Command to be executed by the CLI.
+#[derive(Debug, Subcommand)]
+pub enum Command {
     /// Experiment type to be run.
+    #[clap(flatten)]
+    Run(ExperimentType),
+    /// Serve a local HTTP API to submit and monitor experiments.
+    Serve {
+        /// Address the HTTP API listens on.
+        #[clap(long, default_value = "127.0.0.1:4005", env = "HASH_API_ADDRESS")]
+        address: SocketAddr,
+    },
+    /// Run many experiments with a limited number of engine processes.
+    ///
+    /// The queue is persisted, so running this command again resumes an interrupted queue.
+    Queue {
+        #[clap(flatten)]
+        queue_config: QueueConfig,
+
+        /// Name of an experiment specified in _experiments.json_ to add to the queue.
+        ///
+        /// May be passed multiple times, requires `--project`.
+        #[clap(long = "experiment")]
+        experiments: Vec<ExperimentName>,
+
+        /// Add a single run with the provided number of steps to the queue, requires `--project`.
+        #[clap(long)]
+        single_run: Option<usize>,
+    },
+    /// Inspect and remove shared-memory segments left behind by engine processes.
     #[clap(subcommand)]
-    r#type: ExperimentType,
+    Shm(ShmCommand),
+    /// Check the project for problems without running it, requires `--project`.
+    ///
+    /// Reports every problem found in the project files instead of stopping at the first one.
+    Validate,
+}
+
+/// Subcommands to manage shared-memory segments.
+#[derive(Debug, Subcommand)]
+pub enum ShmCommand {
+    /// List the shared-memory segments created by the engine.
+    List {
+        /// Only list the segments of the experiment with this ID.
+        #[clap(long)]
+        experiment_id: Option<Uuid>,
+    },
+    /// Remove shared-memory segments which are not used by any process anymore.
+    Reap {
+        /// Only remove the segments of the experiment with this ID.
+        #[clap(long)]
+        experiment_id: Option<Uuid>,
+
+        /// Only print the segments which would be removed.
+        #[clap(long)]
+        dry_run: bool,
+    },
 }
 
 #[derive(Debug)]
@@ -69,24 +138,59 @@ async fn main() -> Result<(), CliError> {
     .attach_printable("Failed to initialize the logger")
     .change_context(CliError)?;
 
+    if let Command::Shm(command) = args.command {
+        return manage_shared_memory(command);
+    }
+    if let Command::Validate = args.command {
+        return validate_project(args.project);
+    }
+
     let nng_listen_url = format!("ipc://hash-orchestrator-{now}");
 
     let (mut experiment_server, handler) = Server::create(nng_listen_url);
     tokio::spawn(async move { experiment_server.run().await });
 
-    let absolute_project_path = args
+    let experiment_type = match args.command {
+        Command::Run(experiment_type) => experiment_type,
+        Command::Serve { address } => {
+            return ApiServer::new(args.experiment_config, handler)
+                .serve(address)
+                .await
+                .change_context(CliError);
+        }
+        Command::Queue {
+            queue_config,
+            experiments,
+            single_run,
+        } => {
+            return run_queue(
+                args.project,
+                args.experiment_config,
+                handler,
+                queue_config,
+                experiments,
+                single_run,
+            )
+            .await;
+        }
+        Command::Shm(_) => unreachable!("shared-memory commands are handled before"),
+        Command::Validate => unreachable!("validation is handled before"),
+    };
+
+    let project = args
         .project
+        .ok_or_else(|| Report::new(CliError))
+        .attach_printable("A project is required to run an experiment, see `--project`")?;
+    let absolute_project_path = project
         .canonicalize()
         .into_report()
-        .attach_printable_lazy(|| {
-            format!("Could not canonicalize project path: {:?}", args.project)
-        })
+        .attach_printable_lazy(|| format!("Could not canonicalize project path: {project:?}"))
         .change_context(CliError)?;
     let manifest = Manifest::from_local(&absolute_project_path)
         .attach_printable_lazy(|| format!("Could not read local project {absolute_project_path:?}"))
         .change_context(CliError)?;
     let experiment_run = manifest
-        .read(args.r#type)
+        .read(experiment_type)
         .attach_printable("Could not read manifest")
         .change_context(CliError)?;
 
@@ -97,3 +201,142 @@ async fn main() -> Result<(), CliError> {
         .await
         .change_context(CliError)
 }
+
+async fn run_queue(
+    project: Option<PathBuf>,
+    experiment_config: ExperimentConfig,
+    handler: orchestrator::Handler,
+    queue_config: QueueConfig,
+    experiments: Vec<ExperimentName>,
+    single_run: Option<usize>,
+) -> Result<(), CliError> {
+    let queue = ExperimentQueue::open(experiment_config, queue_config, handler)
+        .attach_printable("Could not open experiment queue")
+        .change_context(CliError)?;
+
+    let new_experiments = experiments
+        .into_iter()
+        .map(|name| ExperimentType::Simple { name })
+        .chain(single_run.map(|num_steps| ExperimentType::SingleRun { num_steps }))
+        .collect::<Vec<_>>();
+    if !new_experiments.is_empty() {
+        let project = project
+            .ok_or_else(|| Report::new(CliError))
+            .attach_printable("A project is required to queue an experiment, see `--project`")?
+            .canonicalize()
+            .into_report()
+            .attach_printable("Could not canonicalize project path")
+            .change_context(CliError)?;
+        for experiment_type in new_experiments {
+            queue
+                .push(project.clone(), experiment_type)
+                .change_context(CliError)?;
+        }
+    }
+
+    let failed = queue.run().await.change_context(CliError)?;
+    if failed > 0 {
+        return Err(
+            Report::new(CliError).attach_printable(format!("{failed} queued experiments failed"))
+        );
+    }
+    Ok(())
+}
+
+fn validate_project(project: Option<PathBuf>) -> Result<(), CliError> {
+    let project = project
+        .ok_or_else(|| Report::new(CliError))
+        .attach_printable("A project is required for validation, see `--project`")?
+        .canonicalize()
+        .into_report()
+        .attach_printable("Could not canonicalize project path")
+        .change_context(CliError)?;
+    let manifest = Manifest::from_local(&project)
+        .attach_printable_lazy(|| format!("Could not read local project {project:?}"))
+        .change_context(CliError)?;
+
+    let problems = manifest.validate();
+    if problems.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+    for problem in &problems {
+        println!("{problem}");
+    }
+    Err(Report::new(CliError).attach_printable(format!("{} problems found", problems.len())))
+}
+
+fn print_segments(segments: &[SegmentInfo]) {
+    for segment in segments {
+        let join = |pids: &[u32]| {
+            pids.iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let state = if !segment.owners.is_empty() {
+            format!("owned by {}", join(&segment.owners))
+        } else if !segment.pids.is_empty() {
+            format!("used by {}", join(&segment.pids))
+        } else if !segment.unknown_pids.is_empty() {
+            format!(
+                "unknown, mappings of {} not readable",
+                join(&segment.unknown_pids)
+            )
+        } else {
+            "orphaned".to_owned()
+        };
+        println!(
+            "{}\t{}\t{} bytes\t{state}",
+            segment.os_id, segment.base_id, segment.size
+        );
+    }
+}
+
+fn manage_shared_memory(command: ShmCommand) -> Result<(), CliError> {
+    match command {
+        ShmCommand::List { experiment_id } => {
+            let segments = shared_memory::list_segments(experiment_id)
+                .into_report()
+                .attach_printable("Could not list shared-memory segments")
+                .change_context(CliError)?;
+            print_segments(&segments);
+            let size: u64 = segments.iter().map(|segment| segment.size).sum();
+            let orphaned = segments
+                .iter()
+                .filter(|segment| segment.is_orphaned())
+                .count();
+            println!(
+                "{} segments ({size} bytes), {orphaned} orphaned",
+                segments.len()
+            );
+        }
+        ShmCommand::Reap {
+            experiment_id,
+            dry_run: true,
+        } => {
+            let segments: Vec<_> = shared_memory::list_segments(experiment_id)
+                .into_report()
+                .attach_printable("Could not list shared-memory segments")
+                .change_context(CliError)?
+                .into_iter()
+                .filter(SegmentInfo::is_orphaned)
+                .collect();
+            print_segments(&segments);
+            println!("Would remove {} segments", segments.len());
+        }
+        ShmCommand::Reap {
+            experiment_id,
+            dry_run: false,
+        } => {
+            let removed = shared_memory::reap_orphaned_segments(experiment_id)
+                .into_report()
+                .attach_printable("Could not remove shared-memory segments")
+                .change_context(CliError)?;
+            print_segments(&removed);
+            let size: u64 = removed.iter().map(|segment| segment.size).sum();
+            println!("Removed {} segments ({size} bytes)", removed.len());
+        }
+    }
+    Ok(())
+}
@@ -10,7 +10,7 @@ use std::{error::Error, fmt, sync::Arc};
 use error_stack::{IntoReport, Result, ResultExt};
 use execution::runner::RunnerConfig;
 use experiment_control::{
-    controller::run::{cleanup_experiment, run_experiment},
+    controller::run::{cleanup_experiment, enable_segment_pool, run_experiment},
     environment::{init_logger, Args, Environment},
 };
 use experiment_structure::{ExperimentConfig, FetchDependencies};
@@ -34,6 +34,11 @@ pub fn experiment_config(args: &Args, env: &Environment) -> Result<ExperimentCon
         RunnerConfig {
             js_runner_initial_heap_constraint: args.js_runner_initial_heap_constraint,
             js_runner_max_heap_size: args.js_runner_max_heap_size,
+            js_runner_disable_snapshot: args.js_runner_disable_snapshot,
+            js_node_modules: env.experiment.simulation().node_modules.clone(),
+            python_environment: args.python_environment.clone(),
+            // Chosen by the `python_runner` global of the experiment
+            ..RunnerConfig::default()
         },
     )
     .attach_printable("Could not create experiment config")
@@ -75,6 +80,10 @@ async fn main() -> Result<(), EngineError> {
         config.experiment_run.name()
     );
 
+    if args.shared_memory_pool {
+        enable_segment_pool(args.experiment_id);
+    }
+
     let experiment_result = run_experiment(config, env)
         .await
         .into_report()
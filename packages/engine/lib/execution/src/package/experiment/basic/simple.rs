@@ -25,7 +25,7 @@ struct SimProgress {
 struct SimQueue<'a> {
     max_num_steps: usize,
     pkg_to_exp: &'a mut ExpPkgCtlSend,
-    pending_iter: &'a mut (dyn Iterator<Item = (SimulationId, &'a serde_json::Value)> + Send),
+    pending_iter: &'a mut (dyn Iterator<Item = (SimulationId, serde_json::Value)> + Send),
     active: HashMap<SimulationId, SimProgress>,
     finished: HashMap<SimulationId, SimProgress>,
 }
@@ -40,7 +40,7 @@ impl<'a> SimQueue<'a> {
             let msg = ExperimentControl::StartSim {
                 span_id: tracing::Span::current().id(),
                 sim_id,
-                changed_globals: changed_props.clone(),
+                changed_globals: changed_props,
                 max_num_steps: self.max_num_steps,
             };
             self.pkg_to_exp.send(msg).await?;
@@ -63,6 +63,11 @@ pub struct SimpleExperimentConfig {
     pub num_steps: usize,
     /// Maximum amount of simulations that can be ran in parallel - None is unlimited
     pub max_sims_in_parallel: Option<usize>,
+    /// Globals read by the engine, which are declared by the experiment instead of being changed
+    /// by it, e.g. the stop conditions. They are passed to every simulation run not changing them
+    /// itself.
+    #[serde(default)]
+    pub engine_globals: serde_json::Map<String, serde_json::Value>,
 }
 
 impl SimpleExperiment {
@@ -85,6 +90,12 @@ impl SimpleExperiment {
                 .iter()
                 .enumerate()
                 .map(|(sim_idx, props)| {
+                    let mut props = props.clone();
+                    if let serde_json::Value::Object(props) = &mut props {
+                        for (name, value) in &self.config.engine_globals {
+                            props.entry(name).or_insert_with(|| value.clone());
+                        }
+                    }
                     // We sometimes use 0 as a default/null value, therefore it's not a valid
                     // SimulationShortId
                     (SimulationId::new(sim_idx as u32 + 1), props)
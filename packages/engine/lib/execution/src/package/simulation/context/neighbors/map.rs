@@ -4,7 +4,10 @@ use kdtree::KdTree;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use stateful::state::AgentIndex;
 
-use crate::{package::simulation::state::topology::TopologyConfig, Error, Result};
+use crate::{
+    package::simulation::state::topology::{geo, TopologyConfig},
+    Error, Result,
+};
 
 pub(super) type PositionSubType = f64;
 pub(super) type Position = [PositionSubType; 3];
@@ -20,12 +23,23 @@ pub struct NeighborMap {
 
 pub type NeighborRef = ((Option<[f64; 3]>, AgentIndex), Option<f64>);
 
+/// For a geographic topology, the positions are converted into earth-centered cartesian
+/// coordinates, see [`geo::to_cartesian`].
+///
 /// # Errors
 /// This function will not fail
-fn agents_adjacency_map(agents: &'_ [NeighborRef]) -> Result<Tree<'_>> {
+fn agents_adjacency_map<'a>(
+    agents: &'a [NeighborRef],
+    topology: &TopologyConfig,
+) -> Result<Tree<'a>> {
     let mut tree = kdtree::kdtree::KdTree::new(3);
     agents.iter().try_for_each(|((pos, idx), _)| {
         pos.map_or(Ok(()), |unwrapped| {
+            let unwrapped = if topology.geographic {
+                geo::to_cartesian(&unwrapped)
+            } else {
+                unwrapped
+            };
             tree.add(unwrapped, *idx).map_err(Error::from)
         })
     })?;
@@ -54,7 +68,21 @@ fn gather_neighbors(
     // so let's leave it as is.
 
     let mut final_neighbors = Vec::new();
-    if topology.wrapping_combinations == 1 {
+    if topology.geographic {
+        // Great-circle distances grow with the straight-line distances through the sphere, so the
+        // search radius is converted instead of every distance.
+        let chord_length = geo::chord_length(search_radius);
+        adjacency_map
+            .within(
+                &geo::to_cartesian(position),
+                chord_length * chord_length,
+                &kdtree::distance::squared_euclidean,
+            )
+            .map_err(Error::from)?
+            .into_iter()
+            .filter(|point| !point.1.eq(&idx))
+            .for_each(|point| final_neighbors.push(*point.1));
+    } else if topology.wrapping_combinations == 1 {
         adjacency_map
             .within(position, search_radius, &topology.distance_function)
             .map_err(Error::from)?
@@ -94,7 +122,7 @@ impl NeighborMap {
         topology_config: &TopologyConfig,
     ) -> Result<NeighborMap> {
         let num_states = states.len();
-        let adjacency_map = agents_adjacency_map(&states)?;
+        let adjacency_map = agents_adjacency_map(&states, topology_config)?;
         states
             .par_iter()
             .try_fold(
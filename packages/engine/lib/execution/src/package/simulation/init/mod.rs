@@ -14,6 +14,7 @@ pub mod json;
 mod creator;
 mod message;
 mod name;
+mod schema;
 mod state;
 mod task;
 
@@ -24,6 +25,7 @@ pub use self::{
     creator::InitPackageCreators,
     message::InitTaskMessage,
     name::InitPackageName,
+    schema::{AgentSchemaDeclaration, DeclaredField},
     state::{InitialState, InitialStateName},
     task::InitTask,
 };
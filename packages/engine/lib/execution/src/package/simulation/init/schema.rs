@@ -0,0 +1,250 @@
+//! Declared agent fields, read from the `schema.json` file of a project.
+
+use serde_json::Value;
+use stateful::{
+    agent::Agent,
+    field::{FieldScope, FieldType, FieldTypeVariant, RootFieldSpec, RootFieldSpecCreator},
+};
+
+use crate::{
+    package::simulation::{
+        state::behavior_execution::field_type_from_json, PackageInitConfig, SimPackageArgs,
+    },
+    runner::comms::UserError,
+    Error, Result,
+};
+
+/// An agent field declared in `schema.json`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeclaredField {
+    pub name: String,
+    pub field_type: FieldType,
+    /// Value of the field for agents which don't set it.
+    pub default: Option<Value>,
+}
+
+/// Agent fields declared in the `schema.json` file of a project, e.g.
+///
+/// ```json
+/// {
+///     "keys": {
+///         "age": { "type": "integer", "nullable": false, "default": 0 },
+///         "status": { "type": "category", "categories": ["healthy", "sick"], "nullable": true }
+///     }
+/// }
+/// ```
+///
+/// Fields are declared in the same format as behavior keys, with an optional `"default"` value.
+/// The declared fields are part of the agent schema, and agents from the initial state as well as
+/// agents created during the simulation are checked against them before they are written to
+/// Arrow.
+#[derive(Debug, Clone, Default)]
+pub struct AgentSchemaDeclaration {
+    fields: Vec<DeclaredField>,
+}
+
+impl AgentSchemaDeclaration {
+    pub fn new(config: &PackageInitConfig) -> Result<AgentSchemaDeclaration> {
+        match get_schema_source(&config.packages)? {
+            Some(src) if !src.trim().is_empty() => Self::from_json_str(src),
+            _ => Ok(AgentSchemaDeclaration::default()),
+        }
+    }
+
+    pub fn from_json_str(src: &str) -> Result<AgentSchemaDeclaration> {
+        let json: Value = serde_json::from_str(src)
+            .map_err(|err| Error::from(format!("Could not parse agent schema: {err}")))?;
+        let keys = json
+            .get("keys")
+            .and_then(Value::as_object)
+            .ok_or_else(|| Error::from("Expected agent schema to contain a \"keys\" object"))?;
+
+        let mut fields = Vec::with_capacity(keys.len());
+        for (name, source) in keys {
+            if Agent::is_field_name(name) {
+                return Err(Error::from(format!(
+                    "Agent schema: `{name}` is a built-in field and can't be declared"
+                )));
+            }
+            let field_type = field_type_from_json(name, source)
+                .map_err(|err| Error::from(format!("Agent schema: {err}")))?;
+            let default = source.get("default").cloned();
+            if let Some(default) = &default {
+                check_value(&field_type, default, name).map_err(|err| {
+                    Error::from(format!("Agent schema: invalid default value, {err}"))
+                })?;
+            }
+            fields.push(DeclaredField {
+                name: name.clone(),
+                field_type,
+                default,
+            });
+        }
+        Ok(AgentSchemaDeclaration { fields })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    pub fn fields(&self) -> &[DeclaredField] {
+        &self.fields
+    }
+
+    pub fn field_specs(&self, field_spec_creator: &RootFieldSpecCreator) -> Vec<RootFieldSpec> {
+        self.fields
+            .iter()
+            .map(|field| {
+                field_spec_creator.create(
+                    field.name.clone(),
+                    field.field_type.clone(),
+                    FieldScope::Agent,
+                )
+            })
+            .collect()
+    }
+
+    /// Sets the default values of missing fields and checks the declared fields of `agents`.
+    ///
+    /// Returns one error for every invalid field of every agent.
+    pub fn validate<'a>(&self, agents: impl IntoIterator<Item = &'a mut Agent>) -> Vec<UserError> {
+        let mut errors = Vec::new();
+        for agent in agents {
+            for field in &self.fields {
+                let value = agent.custom.get(&field.name).unwrap_or(&Value::Null);
+                if value.is_null() {
+                    if let Some(default) = &field.default {
+                        agent.custom.insert(field.name.clone(), default.clone());
+                    } else if !field.field_type.nullable {
+                        errors.push(UserError(format!(
+                            "{} is missing the non-nullable field `{}`",
+                            describe_agent(agent),
+                            field.name
+                        )));
+                    }
+                } else if let Err(err) = check_value(&field.field_type, value, &field.name) {
+                    errors.push(UserError(format!("{} has {err}", describe_agent(agent))));
+                }
+            }
+        }
+        errors
+    }
+}
+
+/// Checks if `value` can be stored in a field of type `field_type`, `path` is used to name the
+/// (nested) field in the error message.
+fn check_value(field_type: &FieldType, value: &Value, path: &str) -> Result<(), String> {
+    if value.is_null() {
+        return if field_type.nullable {
+            Ok(())
+        } else {
+            Err(format!("a null value for the non-nullable field `{path}`"))
+        };
+    }
+    let valid = match (&field_type.variant, value) {
+        (FieldTypeVariant::Number, Value::Number(_)) => true,
+        (FieldTypeVariant::Integer | FieldTypeVariant::Timestamp, Value::Number(number)) => {
+            number.is_i64()
+        }
+        (FieldTypeVariant::Boolean, Value::Bool(_)) => true,
+        (FieldTypeVariant::String, Value::String(_)) => true,
+        (FieldTypeVariant::Category(categories), Value::String(category)) => {
+            categories.contains(category)
+        }
+        (FieldTypeVariant::AnyType, _) => true,
+        (FieldTypeVariant::VariableLengthArray(child), Value::Array(values)) => {
+            for (i, value) in values.iter().enumerate() {
+                check_value(child, value, &format!("{path}[{i}]"))?;
+            }
+            true
+        }
+        (FieldTypeVariant::FixedLengthArray { field_type, len }, Value::Array(values))
+            if values.len() == *len =>
+        {
+            for (i, value) in values.iter().enumerate() {
+                check_value(field_type, value, &format!("{path}[{i}]"))?;
+            }
+            true
+        }
+        (FieldTypeVariant::Struct(children), Value::Object(values)) => {
+            for child in children {
+                let value = values.get(&child.name).unwrap_or(&Value::Null);
+                check_value(&child.field_type, value, &format!("{path}.{}", child.name))?;
+            }
+            true
+        }
+        _ => false,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "an invalid value for the field `{path}`: expected {:?}, got {value}",
+            field_type.variant
+        ))
+    }
+}
+
+fn describe_agent(agent: &Agent) -> String {
+    match &agent.agent_name {
+        Some(name) => format!("Agent `{}` ({})", name.0, agent.agent_id),
+        None => format!("Agent {}", agent.agent_id),
+    }
+}
+
+/// Returns the contents of the project's `schema.json`, if provided.
+fn get_schema_source(sim_packages: &[SimPackageArgs]) -> Result<Option<&str>> {
+    for args in sim_packages.iter() {
+        if args.name.as_str() == "schema" {
+            return match &args.data {
+                Value::String(src) => Ok(Some(src)),
+                Value::Null => Ok(None),
+                _ => Err(Error::from("Agent schema must be a string")),
+            };
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn defaults_and_errors() {
+        let schema = AgentSchemaDeclaration::from_json_str(
+            r#"{ "keys": {
+                "age": { "type": "integer", "nullable": false, "default": 0 },
+                "status": { "type": "category", "categories": ["healthy", "sick"], "nullable": false }
+            } }"#,
+        )
+        .unwrap();
+
+        let mut valid = Agent::default();
+        valid.set("status", "sick").unwrap();
+        let mut invalid = Agent::default();
+        invalid.agent_name = Some(stateful::agent::AgentName("bob".to_string()));
+        invalid.set("age", "ten").unwrap();
+
+        let mut agents = [valid, invalid];
+        let errors = schema.validate(&mut agents);
+        assert_eq!(agents[0].get_as_json("age").unwrap(), json!(0));
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].0.contains("`bob`") && errors[0].0.contains("`age`"));
+        assert!(
+            errors[1]
+                .0
+                .contains("missing the non-nullable field `status`")
+        );
+    }
+
+    #[test]
+    fn invalid_default() {
+        let schema = AgentSchemaDeclaration::from_json_str(
+            r#"{ "keys": { "age": { "type": "integer", "nullable": false, "default": 0.5 } } }"#,
+        );
+        assert!(schema.is_err());
+    }
+}
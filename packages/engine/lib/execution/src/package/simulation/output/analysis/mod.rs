@@ -19,7 +19,7 @@ use stateful::{
 };
 use tracing::Span;
 
-use self::analyzer::Analyzer;
+use self::analyzer::{AnalysisSourceRepr, Analyzer};
 pub use self::{
     buffer::AnalysisBuffer,
     config::AnalysisOutputConfig,
@@ -65,6 +65,11 @@ impl OutputPackage for Analysis {
     }
 }
 
+/// Parses and validates an analysis definition (`analysis.json`) without creating the package.
+pub fn validate_analysis_source(analysis_source: &str) -> Result<()> {
+    AnalysisSourceRepr::try_from(analysis_source)?.validate_def()
+}
+
 pub(self) fn get_analysis_source(sim_packages: &[SimPackageArgs]) -> Result<String> {
     for args in sim_packages.iter() {
         if args.name.as_str() == "analysis" {
@@ -2,8 +2,11 @@ use crate::{
     package::{
         experiment::ExperimentId,
         simulation::{
-            output::{analysis::AnalysisBuffer, OutputPartBuffer},
-            OutputPackagesSimConfig, SimulationId,
+            output::{
+                analysis::AnalysisBuffer, json_state::JsonStateOutputConfig, GlobalsChange,
+                OutputPackageName, OutputPartBuffer,
+            },
+            OutputPackagesSimConfig, PackageName, SimulationId,
         },
     },
     Result,
@@ -11,7 +14,10 @@ use crate::{
 
 pub struct OutputBuffers {
     pub json_state: OutputPartBuffer,
+    /// Zero-based indices of the steps appended to `json_state`.
+    pub json_state_steps: Vec<usize>,
     pub analysis: AnalysisBuffer,
+    pub globals_changes: Vec<GlobalsChange>,
 }
 
 impl OutputBuffers {
@@ -20,10 +26,23 @@ impl OutputBuffers {
         sim_id: SimulationId,
         output_packages_sim_config: &OutputPackagesSimConfig,
     ) -> Result<OutputBuffers> {
+        let json_state_config: JsonStateOutputConfig = output_packages_sim_config
+            .map
+            .get(&PackageName::Output(OutputPackageName::JsonState))
+            .map(|value| serde_json::from_value(value.clone()))
+            .transpose()?
+            .unwrap_or_default();
         Ok(OutputBuffers {
             // TODO: This should be dynamically created by the output packages
-            json_state: OutputPartBuffer::new("json_state", exp_id, sim_id)?,
+            json_state: OutputPartBuffer::new(
+                "json_state",
+                exp_id,
+                sim_id,
+                json_state_config.compress,
+            )?,
+            json_state_steps: Vec::new(),
             analysis: AnalysisBuffer::new(output_packages_sim_config)?,
+            globals_changes: Vec::new(),
         })
     }
 }
@@ -1,16 +1,202 @@
+use std::cmp::Ordering;
+
 use serde::{Deserialize, Serialize};
+use stateful::{agent::Agent, field::FieldScope};
+
+use crate::{
+    package::simulation::{PackageInitConfig, SimPackageArgs},
+    Error, Result,
+};
+
+/// Comparison operator of an [`AgentFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FilterOperator {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
 
-use crate::{package::simulation::PackageInitConfig, Result};
+/// Predicate on a field of an agent, e.g. `{ "field": "age", "op": "ge", "value": 18 }`.
+///
+/// Ordering comparisons are only defined between two numbers or two strings. Agents without the
+/// field are compared as if the field was `null`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentFilter {
+    pub field: String,
+    pub op: FilterOperator,
+    pub value: serde_json::Value,
+}
+
+impl AgentFilter {
+    /// Returns if the `agent` satisfies the predicate.
+    pub fn matches(&self, agent: &Agent) -> bool {
+        let value = agent
+            .get_as_json(&self.field)
+            .unwrap_or(serde_json::Value::Null);
+        let ordering = match (&value, &self.value) {
+            (serde_json::Value::Number(lhs), serde_json::Value::Number(rhs)) => lhs
+                .as_f64()
+                .zip(rhs.as_f64())
+                .and_then(|(lhs, rhs)| lhs.partial_cmp(&rhs)),
+            (serde_json::Value::String(lhs), serde_json::Value::String(rhs)) => Some(lhs.cmp(rhs)),
+            _ => None,
+        };
+        match self.op {
+            FilterOperator::Eq => value == self.value,
+            FilterOperator::Ne => value != self.value,
+            FilterOperator::Gt => ordering == Some(Ordering::Greater),
+            FilterOperator::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+            FilterOperator::Lt => ordering == Some(Ordering::Less),
+            FilterOperator::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+        }
+    }
+}
 
-#[derive(Debug, Default, Serialize, Deserialize)]
+/// Configuration of the [JSON state package](super), read from the `"json_state"` object in the
+/// `output.json` file of a project.
+///
+/// Every option is optional, by default all fields of all agents are written at every step.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct JsonStateOutputConfig {
     pub retain_hidden: bool,
     pub retain_private: bool,
+    /// Only write these fields. `agent_id` is always written.
+    pub include_fields: Option<Vec<String>>,
+    /// Never write these fields.
+    pub exclude_fields: Vec<String>,
+    /// Only write every `every`-th step, starting with the first step.
+    pub every: Option<usize>,
+    /// Additionally write these steps, even if they are not covered by `every`.
+    ///
+    /// If `steps` is set without `every`, only these steps are written.
+    pub steps: Vec<usize>,
+    /// Only write agents with one of these names.
+    pub agent_names: Option<Vec<String>>,
+    /// Only write agents matching all of these filters.
+    pub filters: Vec<AgentFilter>,
+    /// Compress the output with gzip and write it as `json_state.json.gz`.
+    pub compress: bool,
 }
 
 impl JsonStateOutputConfig {
-    pub fn new(_config: &PackageInitConfig) -> Result<JsonStateOutputConfig> {
-        // TODO: make this configurable
-        Ok(JsonStateOutputConfig::default())
+    pub fn new(config: &PackageInitConfig) -> Result<JsonStateOutputConfig> {
+        match get_output_source(&config.packages)? {
+            Some(src) if !src.trim().is_empty() => Self::from_json_str(src),
+            _ => Ok(JsonStateOutputConfig::default()),
+        }
+    }
+
+    /// Parses the config from the contents of an `output.json` file.
+    pub fn from_json_str(src: &str) -> Result<JsonStateOutputConfig> {
+        let mut output_config: serde_json::Value = serde_json::from_str(src)
+            .map_err(|err| Error::from(format!("Could not parse output config: {err}")))?;
+        let output_config = match output_config.get_mut("json_state") {
+            Some(json_state) => serde_json::from_value(json_state.take())
+                .map_err(|err| Error::from(format!("Invalid JSON state output config: {err}")))?,
+            None => JsonStateOutputConfig::default(),
+        };
+        output_config.validate()?;
+        Ok(output_config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.every == Some(0) {
+            return Err(Error::from(
+                "JSON state output config: `every` must be greater than 0",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Returns if the state of the step with the zero-based index `step` should be written.
+    pub fn should_output(&self, step: usize) -> bool {
+        match self.every {
+            Some(every) => step % every == 0 || self.steps.contains(&step),
+            None => self.steps.is_empty() || self.steps.contains(&step),
+        }
+    }
+
+    /// Returns if `agent` passes the `agent_names` and `filters` of this config.
+    pub fn matches(&self, agent: &Agent) -> bool {
+        if let Some(agent_names) = &self.agent_names {
+            match &agent.agent_name {
+                Some(name) if agent_names.iter().any(|allowed| allowed == &name.0) => {}
+                _ => return false,
+            }
+        }
+        self.filters.iter().all(|filter| filter.matches(agent))
+    }
+
+    /// Returns if the field called `name` should be written.
+    pub fn retains_field(&self, name: &str) -> bool {
+        if name.starts_with(FieldScope::Hidden.prefix()) && !self.retain_hidden {
+            return false;
+        }
+        if name.starts_with(FieldScope::Private.prefix()) && !self.retain_private {
+            return false;
+        }
+        if let Some(include_fields) = &self.include_fields {
+            if !include_fields.iter().any(|field| field == name) {
+                return false;
+            }
+        }
+        !self.exclude_fields.iter().any(|field| field == name)
+    }
+}
+
+/// Returns the contents of the project's `output.json`, if provided.
+fn get_output_source(sim_packages: &[SimPackageArgs]) -> Result<Option<&str>> {
+    for args in sim_packages.iter() {
+        if args.name.as_str() == "json_state" {
+            return match &args.data {
+                serde_json::Value::String(src) => Ok(Some(src)),
+                serde_json::Value::Null => Ok(None),
+                _ => Err(Error::from("JSON state output config must be a string")),
+            };
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn sampling() {
+        let config: JsonStateOutputConfig =
+            serde_json::from_value(json!({ "every": 10, "steps": [3] })).unwrap();
+        let steps: Vec<_> = (0..25).filter(|&step| config.should_output(step)).collect();
+        assert_eq!(steps, [0, 3, 10, 20]);
+
+        let config: JsonStateOutputConfig =
+            serde_json::from_value(json!({ "steps": [1, 2] })).unwrap();
+        assert!(!config.should_output(0));
+        assert!(config.should_output(2));
+    }
+
+    #[test]
+    fn agent_filters() {
+        let config: JsonStateOutputConfig = serde_json::from_value(json!({
+            "agent_names": ["alice"],
+            "filters": [{ "field": "age", "op": "ge", "value": 18 }],
+        }))
+        .unwrap();
+        let mut agent = Agent::default();
+        agent.agent_name = Some(stateful::agent::AgentName("alice".to_string()));
+        agent.set("age", 17).unwrap();
+        assert!(!config.matches(&agent));
+        agent.set("age", 18).unwrap();
+        assert!(config.matches(&agent));
+        agent.agent_name = None;
+        assert!(!config.matches(&agent));
     }
 }
@@ -9,13 +9,16 @@ use async_trait::async_trait;
 use stateful::{
     agent::{AgentSchema, IntoAgents},
     context::Context,
-    field::{FieldScope, FieldSpecMapAccessor},
+    field::FieldSpecMapAccessor,
     global::Globals,
     state::State,
 };
 use tracing::Span;
 
-pub use self::{config::JsonStateOutputConfig, output::JsonStateOutput};
+pub use self::{
+    config::{AgentFilter, FilterOperator, JsonStateOutputConfig},
+    output::JsonStateOutput,
+};
 use crate::{
     package::simulation::{
         output::{Output, OutputPackage, OutputPackageCreator, OutputPackageName},
@@ -28,6 +31,8 @@ use crate::{
 pub struct JsonState {
     agent_schema: Arc<AgentSchema>,
     output_config: JsonStateOutputConfig,
+    /// Zero-based index of the next step to be output.
+    step: usize,
 }
 
 impl MaybeCpuBound for JsonState {
@@ -41,6 +46,15 @@ impl Package for JsonState {}
 #[async_trait]
 impl OutputPackage for JsonState {
     async fn run(&mut self, state: Arc<State>, _context: Arc<Context>) -> Result<Output> {
+        let step = self.step;
+        self.step += 1;
+        if !self.output_config.should_output(step) {
+            return Ok(Output::JsonStateOutput(JsonStateOutput {
+                step,
+                inner: None,
+            }));
+        }
+
         let state = state.read()?;
         let agent_states: stateful::Result<Vec<_>> = state
             .agent_pool()
@@ -58,22 +72,16 @@ impl OutputPackage for JsonState {
         let agent_states: Vec<_> = agent_states?
             .into_iter()
             .flatten()
+            .filter(|agent| self.output_config.matches(agent))
             .map(|mut agent| {
-                agent.custom.retain(|key, _| {
-                    if key.starts_with(FieldScope::Hidden.prefix()) {
-                        self.output_config.retain_hidden
-                    } else if key.starts_with(FieldScope::Private.prefix()) {
-                        self.output_config.retain_private
-                    } else {
-                        true
-                    }
-                });
+                agent.retain_fields(|field| self.output_config.retains_field(field));
                 agent
             })
             .collect();
 
         Ok(Output::JsonStateOutput(JsonStateOutput {
-            inner: agent_states,
+            step,
+            inner: Some(agent_states),
         }))
     }
 
@@ -102,6 +110,7 @@ impl OutputPackageCreator for JsonStateCreator {
         Ok(Box::new(JsonState {
             agent_schema: Arc::clone(&config.agent_schema),
             output_config,
+            step: 0,
         }))
     }
 
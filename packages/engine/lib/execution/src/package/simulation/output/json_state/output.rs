@@ -2,5 +2,8 @@ use stateful::agent::Agent;
 
 #[derive(Debug)]
 pub struct JsonStateOutput {
-    pub inner: Vec<Agent>,
+    /// Zero-based index of the step the state belongs to.
+    pub step: usize,
+    /// The agents of the step, or `None` if the step was skipped by the output config.
+    pub inner: Option<Vec<Agent>>,
 }
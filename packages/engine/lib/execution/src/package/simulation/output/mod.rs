@@ -25,6 +25,7 @@ mod task;
 use std::sync::Arc;
 
 use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
 use stateful::{context::Context, field::FieldSpecMapAccessor, global::Globals, state::State};
 use tracing::Span;
 
@@ -46,6 +47,17 @@ use crate::{
 pub enum Output {
     AnalysisOutput(AnalysisOutput),
     JsonStateOutput(JsonStateOutput),
+    /// Globals changed by the engine at the beginning of a step, not created by an output
+    /// package.
+    GlobalsChange(GlobalsChange),
+}
+
+/// Globals changed at the beginning of `step`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GlobalsChange {
+    pub step: usize,
+    /// The globals which were set, other globals were kept.
+    pub globals: serde_json::Map<String, serde_json::Value>,
 }
 
 #[async_trait]
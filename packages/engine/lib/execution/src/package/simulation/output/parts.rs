@@ -1,5 +1,6 @@
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf};
 
+use flate2::{write::GzEncoder, Compression};
 use serde::Serialize;
 
 use crate::{
@@ -18,13 +19,15 @@ const CHAR_OPEN_RIGHT_SQUARE_BRACKET: u8 = 0x5D; // ]
 
 /// ### Buffer for list of outputs
 ///
-/// Persists in parts onto disk with an in-memory cache layer
+/// Persists in parts onto disk with an in-memory cache layer. If `compress` is set, every part is
+/// written as a gzip member, so the concatenated parts form a valid gzip file.
 pub struct OutputPartBuffer {
     output_type: &'static str,
     current: Vec<u8>,
     pub parts: Vec<PathBuf>,
     base_path: PathBuf,
     initial_step: bool,
+    compress: bool,
 }
 
 impl OutputPartBuffer {
@@ -32,6 +35,7 @@ impl OutputPartBuffer {
         output_type_name: &'static str,
         experiment_id: &ExperimentId,
         simulation_run_id: SimulationId,
+        compress: bool,
     ) -> Result<OutputPartBuffer> {
         let base_path = std::env::temp_dir()
             .join(experiment_id.to_string())
@@ -49,6 +53,7 @@ impl OutputPartBuffer {
             parts: Vec::new(),
             base_path,
             initial_step: true,
+            compress,
         })
     }
 
@@ -85,7 +90,14 @@ impl OutputPartBuffer {
                 &current[i * MAX_BYTE_SIZE..(i + 1) * MAX_BYTE_SIZE]
             };
 
-            std::fs::write(&path, contents)?;
+            if self.compress {
+                let mut encoder =
+                    GzEncoder::new(std::fs::File::create(&path)?, Compression::default());
+                encoder.write_all(contents)?;
+                encoder.finish()?;
+            } else {
+                std::fs::write(&path, contents)?;
+            }
             self.parts.push(path);
             next_i += 1;
         }
@@ -106,6 +118,10 @@ impl OutputPartBuffer {
         Ok(())
     }
 
+    pub fn is_compressed(&self) -> bool {
+        self.compress
+    }
+
     pub fn finalize(&mut self) -> Result<&[PathBuf]> {
         self.current.push(CHAR_OPEN_RIGHT_SQUARE_BRACKET);
         self.persist_current_on_disk()?;
@@ -1,6 +1,6 @@
 use std::{
     io::{BufReader, BufWriter},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use serde::{Deserialize, Serialize};
@@ -14,7 +14,7 @@ use crate::{
                 persistence::{
                     OutputPersistenceCreator, OutputPersistenceResult, SimulationOutputPersistence,
                 },
-                Output, OutputBuffers,
+                GlobalsChange, Output, OutputBuffers,
             },
             PersistenceConfig, SimulationId,
         },
@@ -53,7 +53,13 @@ impl SimulationOutputPersistence for LocalSimulationOutputPersistence {
                     self.buffers.analysis.add(output)?;
                 }
                 Output::JsonStateOutput(output) => {
-                    self.buffers.json_state.append_step(output.inner)?;
+                    if let Some(agents) = output.inner {
+                        self.buffers.json_state.append_step(agents)?;
+                        self.buffers.json_state_steps.push(output.step);
+                    }
+                }
+                Output::GlobalsChange(change) => {
+                    self.buffers.globals_changes.push(change);
                 }
             }
             Ok(()) as Result<()>
@@ -64,6 +70,7 @@ impl SimulationOutputPersistence for LocalSimulationOutputPersistence {
     async fn finalize(mut self, globals: &Globals) -> Result<Self::OutputPersistenceResult> {
         tracing::trace!("Finalizing output");
         // JSON state
+        let compressed = self.buffers.json_state.is_compressed();
         let parts = self.buffers.json_state.finalize()?;
         let path = self
             .config
@@ -76,7 +83,11 @@ impl SimulationOutputPersistence for LocalSimulationOutputPersistence {
         tracing::info!("Making new output directory: {:?}", path);
         std::fs::create_dir_all(&path)?;
 
-        let json_state_path = path.join("json_state.json");
+        let json_state_path = if compressed {
+            path.join("json_state.json.gz")
+        } else {
+            path.join("json_state.json")
+        };
         std::fs::File::create(&json_state_path)?;
 
         let file_out = std::fs::OpenOptions::new()
@@ -92,6 +103,13 @@ impl SimulationOutputPersistence for LocalSimulationOutputPersistence {
             Ok(())
         })?;
 
+        // The steps of the JSON state are only ambiguous if steps were skipped
+        let steps = &self.buffers.json_state_steps;
+        if steps.iter().enumerate().any(|(index, step)| index != *step) {
+            let steps_path = path.join("json_state_steps.json");
+            std::fs::write(&steps_path, serde_json::to_string(steps)?)?;
+        }
+
         // Analysis
         let analysis_path = path.join("analysis_outputs.json");
         std::fs::File::create(&analysis_path)?;
@@ -105,12 +123,27 @@ impl SimulationOutputPersistence for LocalSimulationOutputPersistence {
         std::fs::File::create(&globals_path)?;
         std::fs::write(&globals_path, serde_json::to_string(globals)?)?;
 
+        // Globals changed during the run
+        write_globals_changes(&path, &self.buffers.globals_changes)?;
+
         Ok(LocalPersistenceResult {
             persistence_path: path.canonicalize()?.to_string_lossy().to_string(),
         })
     }
 }
 
+/// Writes the globals changed during the simulation run to _globals_changes.json_ in `path`, if
+/// any were changed.
+fn write_globals_changes(path: &Path, changes: &[GlobalsChange]) -> Result<()> {
+    if !changes.is_empty() {
+        std::fs::write(
+            path.join("globals_changes.json"),
+            serde_json::to_string(changes)?,
+        )?;
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LocalPersistenceConfig {
     pub output_folder: PathBuf,
@@ -146,3 +179,32 @@ impl OutputPersistenceCreator for LocalOutputPersistence {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn globals_changes_file() {
+        let path = std::env::temp_dir().join(format!("globals-changes-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&path).unwrap();
+
+        write_globals_changes(&path, &[]).unwrap();
+        assert!(!path.join("globals_changes.json").exists());
+
+        let change = GlobalsChange {
+            step: 3,
+            globals: serde_json::from_value(json!({ "a": 2 })).unwrap(),
+        };
+        write_globals_changes(&path, &[change]).unwrap();
+        let written: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(path.join("globals_changes.json")).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(written, json!([{ "step": 3, "globals": { "a": 2 } }]));
+
+        std::fs::remove_dir_all(&path).unwrap();
+    }
+}
@@ -47,10 +47,17 @@ pub enum BehaviorKeyJsonError {
          \"fixed_size_list\"-type sub-types"
     )]
     InvalidKeyLengthType(String),
+    #[error(
+        "Expected key with name {0} to have a non-empty list of unique strings as \"categories\" \
+         sub-field in its \"category\"-type"
+    )]
+    InvalidKeyCategoriesType(String),
     #[error("Invalid built-in key name {0}")]
     InvalidBuiltInKeyName(String),
     #[error("Dynamic access flag must be boolean if present")]
     NonBoolDynamicAccess,
+    #[error("Invalid \"schedule\" field in behavior keys definition: {0}")]
+    InvalidSchedule(String),
 }
 
 impl From<&str> for BehaviorKeyJsonError {
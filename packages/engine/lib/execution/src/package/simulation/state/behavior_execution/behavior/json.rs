@@ -6,6 +6,9 @@ pub(super) enum BaseKeyType {
     String,
     Boolean,
     Number,
+    Integer,
+    Timestamp,
+    Category,
     Struct,
     List,
     FixedSizeList,
@@ -20,6 +23,9 @@ impl TryFrom<&str> for BaseKeyType {
             "string" => Ok(BaseKeyType::String),
             "boolean" => Ok(BaseKeyType::Boolean),
             "number" => Ok(BaseKeyType::Number),
+            "integer" => Ok(BaseKeyType::Integer),
+            "timestamp" => Ok(BaseKeyType::Timestamp),
+            "category" => Ok(BaseKeyType::Category),
             "struct" => Ok(BaseKeyType::Struct),
             "list" => Ok(BaseKeyType::List),
             "fixed_size_list" => Ok(BaseKeyType::FixedSizeList),
@@ -36,7 +42,7 @@ pub(super) fn field_spec_from_json(name: &str, source: &serde_json::Value) -> Re
     })
 }
 
-pub(super) fn field_type_from_json(name: &str, source: &serde_json::Value) -> Result<FieldType> {
+pub(crate) fn field_type_from_json(name: &str, source: &serde_json::Value) -> Result<FieldType> {
     match source {
         serde_json::Value::Object(map) => {
             let key_base_type = match map
@@ -61,6 +67,27 @@ pub(super) fn field_type_from_json(name: &str, source: &serde_json::Value) -> Re
                 BaseKeyType::String => FieldTypeVariant::String,
                 BaseKeyType::Boolean => FieldTypeVariant::Boolean,
                 BaseKeyType::Number => FieldTypeVariant::Number,
+                BaseKeyType::Integer => FieldTypeVariant::Integer,
+                BaseKeyType::Timestamp => FieldTypeVariant::Timestamp,
+                BaseKeyType::Category => {
+                    let categories: Vec<String> = map
+                        .get("categories")
+                        .and_then(|categories| serde_json::from_value(categories.clone()).ok())
+                        .ok_or_else(|| {
+                            BehaviorKeyJsonError::InvalidKeyCategoriesType(name.to_string())
+                        })?;
+                    let unique = categories
+                        .iter()
+                        .enumerate()
+                        .all(|(i, category)| !categories[..i].contains(category));
+                    if categories.is_empty() || !unique {
+                        return Err(BehaviorKeyJsonError::InvalidKeyCategoriesType(
+                            name.to_string(),
+                        )
+                        .into());
+                    }
+                    FieldTypeVariant::Category(categories)
+                }
                 BaseKeyType::Any => FieldTypeVariant::AnyType,
                 BaseKeyType::Struct => {
                     let mut children = vec![];
@@ -69,7 +96,9 @@ pub(super) fn field_type_from_json(name: &str, source: &serde_json::Value) -> Re
                     })? {
                         serde_json::Value::Object(map) => {
                             for (k, v) in map {
-                                children.push(field_spec_from_json(k.as_ref(), v)?);
+                                let child = field_spec_from_json(k.as_ref(), v)?;
+                                ensure_top_level_category(name, &child.field_type)?;
+                                children.push(child);
                             }
                             Ok(())
                         }
@@ -85,6 +114,7 @@ pub(super) fn field_type_from_json(name: &str, source: &serde_json::Value) -> Re
                         BehaviorKeyJsonError::InvalidKeyChildType(name.to_string())
                     })?;
                     let child_key_type = field_type_from_json(name, child_source)?;
+                    ensure_top_level_category(name, &child_key_type)?;
                     FieldTypeVariant::VariableLengthArray(Box::new(child_key_type))
                 }
                 BaseKeyType::FixedSizeList => {
@@ -92,6 +122,7 @@ pub(super) fn field_type_from_json(name: &str, source: &serde_json::Value) -> Re
                         BehaviorKeyJsonError::InvalidKeyChildType(name.to_string())
                     })?;
                     let child_key_type = field_type_from_json(name, child_source)?;
+                    ensure_top_level_category(name, &child_key_type)?;
                     let len = match map.get("length").ok_or_else(|| {
                         BehaviorKeyJsonError::InvalidKeyLengthType(name.to_string())
                     })? {
@@ -117,3 +148,15 @@ pub(super) fn field_type_from_json(name: &str, source: &serde_json::Value) -> Re
         _ => Err(BehaviorKeyJsonError::ExpectedKeyObject(name.to_string()).into()),
     }
 }
+
+/// Categories are stored in the schema metadata, which only supports top-level fields.
+fn ensure_top_level_category(name: &str, child: &FieldType) -> Result<()> {
+    if matches!(child.variant, FieldTypeVariant::Category(_)) {
+        return Err(BehaviorKeyJsonError::from(format!(
+            "Key with name {name} contains a nested \"category\" type, which is only supported \
+             for top-level keys"
+        ))
+        .into());
+    }
+    Ok(())
+}
@@ -5,16 +5,18 @@ use stateful::{
 
 use crate::{
     package::simulation::state::behavior_execution::{
-        behavior::json::field_type_from_json, BehaviorKeyJsonError,
+        behavior::json::field_type_from_json, BehaviorKeyJsonError, BehaviorSchedule,
     },
     Result,
 };
 
-#[derive(Clone, Debug, Eq, PartialEq, Default)]
+#[derive(Clone, Debug, PartialEq, Default)]
 pub struct BehaviorKeys {
     pub inner: FieldSpecMap,
     pub built_in_key_use: Option<Vec<String>>,
     pub dyn_access: bool,
+    /// `None` if the behavior is executed at every step.
+    pub schedule: Option<BehaviorSchedule>,
 }
 
 impl BehaviorKeys {
@@ -116,10 +118,16 @@ impl BehaviorKeys {
             false
         };
 
+        let schedule = map
+            .get("schedule")
+            .map(BehaviorSchedule::from_json)
+            .transpose()?;
+
         Ok(BehaviorKeys {
             inner: field_spec_map,
             built_in_key_use: built_in_key_use?,
             dyn_access,
+            schedule,
         })
     }
 
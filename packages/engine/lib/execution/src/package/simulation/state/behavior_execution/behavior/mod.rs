@@ -2,12 +2,16 @@ mod error;
 mod field;
 mod json;
 mod keys;
+mod schedule;
 
 use std::fmt;
 
 use serde::{Deserialize, Serialize};
 
-pub use self::{error::BehaviorKeyJsonError, field::BehaviorMap};
+pub(crate) use self::json::field_type_from_json;
+pub use self::{
+    error::BehaviorKeyJsonError, field::BehaviorMap, keys::BehaviorKeys, schedule::BehaviorSchedule,
+};
 use crate::{runner::Language, Result};
 
 #[derive(Deserialize, Serialize, Clone)]
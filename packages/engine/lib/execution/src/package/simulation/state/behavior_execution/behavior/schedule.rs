@@ -0,0 +1,111 @@
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::package::simulation::state::behavior_execution::BehaviorKeyJsonError;
+
+fn default_interval() -> usize {
+    1
+}
+
+/// Declares at which steps a behavior is executed, as given by the `"schedule"` field of its
+/// behavior keys:
+///
+/// ```json
+/// {
+///   "keys": {},
+///   "schedule": { "interval": 365, "offset": 1, "probability": 0.5 }
+/// }
+/// ```
+///
+/// The behavior is executed at the steps `offset`, `offset + interval`, `offset + 2 * interval`,
+/// ... and if `probability` is given, only for that share of agents, drawn anew at every step. At
+/// all other steps, the behavior is skipped without invoking a language runner.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BehaviorSchedule {
+    #[serde(default = "default_interval")]
+    interval: usize,
+    #[serde(default)]
+    offset: usize,
+    #[serde(default)]
+    probability: Option<f64>,
+}
+
+impl BehaviorSchedule {
+    pub(super) fn from_json(value: &serde_json::Value) -> Result<Self, BehaviorKeyJsonError> {
+        let schedule: Self = serde_json::from_value(value.clone())
+            .map_err(|err| BehaviorKeyJsonError::InvalidSchedule(err.to_string()))?;
+        if schedule.interval == 0 {
+            return Err(BehaviorKeyJsonError::InvalidSchedule(
+                "\"interval\" must be at least 1".to_string(),
+            ));
+        }
+        if let Some(probability) = schedule.probability {
+            if !(0.0..=1.0).contains(&probability) {
+                return Err(BehaviorKeyJsonError::InvalidSchedule(
+                    "\"probability\" must be between 0 and 1".to_string(),
+                ));
+            }
+        }
+        Ok(schedule)
+    }
+
+    /// Returns `true` if the behavior is only executed for a random share of agents.
+    pub fn has_probability(&self) -> bool {
+        self.probability.is_some()
+    }
+
+    /// Returns if the behavior is executed at `step` for one agent.
+    ///
+    /// `rng` is only used and required if the schedule has a `probability`.
+    pub fn is_due(&self, step: usize, rng: Option<&mut impl Rng>) -> bool {
+        step >= self.offset
+            && (step - self.offset) % self.interval == 0
+            && self.probability.map_or(true, |probability| {
+                rng.expect("schedules with a probability require a random number generator")
+                    .gen_bool(probability)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn due_steps() {
+        let schedule =
+            BehaviorSchedule::from_json(&json!({ "interval": 365, "offset": 10 })).unwrap();
+        let mut rng = StdRng::seed_from_u64(0);
+        let steps: Vec<_> = (0..1000)
+            .filter(|step| schedule.is_due(*step, None::<&mut StdRng>))
+            .collect();
+        assert_eq!(steps, vec![10, 375, 740]);
+
+        let never = BehaviorSchedule::from_json(&json!({ "probability": 0 })).unwrap();
+        assert!((0..100).all(|step| !never.is_due(step, Some(&mut rng))));
+    }
+
+    #[test]
+    fn invalid_schedules() {
+        assert!(BehaviorSchedule::from_json(&json!({ "interval": 0 })).is_err());
+        assert!(BehaviorSchedule::from_json(&json!({ "probability": 1.5 })).is_err());
+        assert!(BehaviorSchedule::from_json(&json!({ "every": 2 })).is_err());
+    }
+
+    #[test]
+    fn seeded_probability() {
+        let schedule = BehaviorSchedule::from_json(&json!({ "probability": 0.5 })).unwrap();
+        let draws = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..64)
+                .map(|step| schedule.is_due(step, Some(&mut rng)))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(draws(1), draws(1));
+        assert_ne!(draws(1), draws(2));
+    }
+}
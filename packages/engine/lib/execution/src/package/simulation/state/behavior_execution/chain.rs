@@ -5,29 +5,45 @@ use memory::arrow::{
     column_with_name_from_record_batch, new_buffer, new_offsets_buffer, record_batch::RecordBatch,
     ColumnChange, IntoArrowChange,
 };
+use rand::Rng;
 use stateful::{agent::AgentBatch, state::StateColumn};
 
 use crate::{
     package::simulation::state::behavior_execution::{
         config::BehaviorId, BehaviorIdInnerDataType, BehaviorIds, BEHAVIOR_INDEX_INNER_COUNT,
     },
+    runner::Language,
     Error, Result,
 };
 
+/// Gathers the behaviors due at `step` of all agents into a column of behavior ids.
+///
+/// Behaviors with a `probability` in their schedule are drawn with `rng`, agent by agent in the
+/// order of `agent_batches`. `rng` is only required if there are such behaviors.
+///
+/// Also returns the language of the first behavior to execute, `None` if no agent has a behavior
+/// to execute.
 pub fn gather_behavior_chains(
     agent_batches: &[&AgentBatch],
     behavior_ids: &BehaviorIds,
     data_types: [arrow2::datatypes::DataType; 3],
     behavior_ids_col_index: usize,
-) -> Result<StateColumn> {
+    step: usize,
+    mut rng: Option<&mut impl Rng>,
+) -> Result<(StateColumn, Option<Language>)> {
     let inner = pool_behavior_list_bytes_iter(agent_batches)?
-        .map(|v| Chain::from_behaviors(&v, behavior_ids))
+        .map(|v| Chain::from_behaviors(&v, behavior_ids, step, rng.as_deref_mut()))
         .collect::<Result<Vec<_>>>()?;
-    Ok(StateColumn::new(Box::new(ChainList {
+    let first_lang = inner
+        .iter()
+        .find_map(|chain| chain.inner.first())
+        .map(|behavior_id| Language::from_index(behavior_id.lang_index() as usize));
+    let column = StateColumn::new(Box::new(ChainList {
         inner,
         behavior_ids_col_index,
         data_types,
-    })))
+    }));
+    Ok((column, first_lang))
 }
 
 pub fn pool_behavior_list_bytes_iter<'a>(
@@ -86,26 +102,33 @@ pub struct Chain {
 }
 
 impl Chain {
-    pub fn from_behaviors(behaviors: &[&[u8]], behavior_ids: &BehaviorIds) -> Result<Self> {
-        Ok(Chain {
-            inner: behaviors
-                .iter()
-                .map(|bytes| {
-                    let index = behavior_ids.get_index(bytes).ok_or_else(|| {
-                        match std::str::from_utf8(bytes) {
-                            Ok(res) => {
-                                Error::from(format!("Could not find behavior with name {}", res))
-                            }
-                            Err(_e) => Error::from(format!(
-                                "Could not parse behavior name. Bytes: {:?}",
-                                bytes[0..bytes.len().min(100)].to_vec()
-                            )),
-                        }
-                    })?;
-                    Ok(*index)
-                })
-                .collect::<Result<_>>()?,
-        })
+    /// Creates the chain of behaviors due at `step`, behaviors which are scheduled for other steps
+    /// are left out.
+    pub fn from_behaviors(
+        behaviors: &[&[u8]],
+        behavior_ids: &BehaviorIds,
+        step: usize,
+        mut rng: Option<&mut impl Rng>,
+    ) -> Result<Self> {
+        let mut inner = Vec::with_capacity(behaviors.len());
+        for bytes in behaviors {
+            let index = *behavior_ids
+                .get_index(bytes)
+                .ok_or_else(|| match std::str::from_utf8(bytes) {
+                    Ok(res) => Error::from(format!("Could not find behavior with name {}", res)),
+                    Err(_e) => Error::from(format!(
+                        "Could not parse behavior name. Bytes: {:?}",
+                        bytes[0..bytes.len().min(100)].to_vec()
+                    )),
+                })?;
+            if behavior_ids
+                .get_schedule(&index)
+                .map_or(true, |schedule| schedule.is_due(step, rng.as_deref_mut()))
+            {
+                inner.push(index);
+            }
+        }
+        Ok(Chain { inner })
     }
 }
 
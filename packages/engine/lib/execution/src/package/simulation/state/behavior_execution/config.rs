@@ -3,7 +3,9 @@ use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    package::simulation::state::behavior_execution::BehaviorMap, runner::Language, Error, Result,
+    package::simulation::state::behavior_execution::{BehaviorMap, BehaviorSchedule},
+    runner::Language,
+    Error, Result,
 };
 
 #[derive(Serialize, Deserialize)]
@@ -34,6 +36,8 @@ pub struct BehaviorIds {
     name_to_index: HashMap<Vec<u8>, BehaviorId>,
     // TODO: UNUSED: Needs triage
     index_to_name: HashMap<BehaviorId, String>,
+    /// Schedules of the behaviors which are not executed at every step.
+    schedules: HashMap<BehaviorId, BehaviorSchedule>,
 }
 
 impl BehaviorIds {
@@ -42,6 +46,7 @@ impl BehaviorIds {
 
         let mut index_to_name = HashMap::new();
         let mut name_to_index = HashMap::new();
+        let mut schedules = HashMap::new();
         for behavior in behaviors.iter_behaviors() {
             let shared = behavior.shared();
             let lang_index = Language::from_file_name(&shared.name)
@@ -51,6 +56,9 @@ impl BehaviorIds {
             lang_counts[lang_index] += 1;
 
             index_to_name.insert(behavior_id, shared.name.clone());
+            if let Some(schedule) = &behavior.keys().schedule {
+                schedules.insert(behavior_id, schedule.clone());
+            }
             name_to_index.insert(shared.name.clone().into_bytes(), behavior_id);
             for alt_name in shared.shortnames.iter() {
                 name_to_index.insert(alt_name.clone().into_bytes(), behavior_id);
@@ -60,6 +68,7 @@ impl BehaviorIds {
         Ok(BehaviorIds {
             index_to_name,
             name_to_index,
+            schedules,
         })
     }
 
@@ -67,6 +76,18 @@ impl BehaviorIds {
         self.name_to_index.get(key)
     }
 
+    /// Returns the schedule of the behavior, `None` if it's executed at every step.
+    pub fn get_schedule(&self, behavior_id: &BehaviorId) -> Option<&BehaviorSchedule> {
+        self.schedules.get(behavior_id)
+    }
+
+    /// Returns `true` if a behavior is only executed for a random share of agents.
+    pub fn has_probability_schedules(&self) -> bool {
+        self.schedules
+            .values()
+            .any(BehaviorSchedule::has_probability)
+    }
+
     // TODO: UNUSED: Needs triage
     #[allow(dead_code)]
     pub fn get_name(&self, behavior_index: &BehaviorId) -> Option<&String> {
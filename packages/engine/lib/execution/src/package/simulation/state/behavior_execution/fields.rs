@@ -5,6 +5,7 @@ use stateful::field::{
 
 use crate::{
     package::simulation::{
+        init::AgentSchemaDeclaration,
         state::behavior_execution::{BehaviorMap, BEHAVIOR_INDEX_INNER_COUNT},
         PackageInitConfig,
     },
@@ -80,7 +81,12 @@ pub(super) fn get_state_field_specs(
         get_behavior_ids_field_spec(field_spec_creator)?,
     ];
 
-    field_specs.extend(behavior_map.all_field_specs.field_specs().cloned());
+    // Fields declared in `schema.json` share the source with the behavior keys, so equal
+    // declarations are merged while conflicting ones are reported as a key clash.
+    let mut agent_field_specs = behavior_map.all_field_specs;
+    agent_field_specs
+        .try_extend(AgentSchemaDeclaration::new(config)?.field_specs(field_spec_creator))?;
+    field_specs.extend(agent_field_specs.drain_field_specs());
     Ok(field_specs)
 }
 
@@ -12,6 +12,7 @@ use std::sync::Arc;
 
 use arrow2::datatypes::Schema;
 use async_trait::async_trait;
+use rand::{rngs::StdRng, SeedableRng};
 use stateful::{
     agent::AgentBatch,
     context::Context,
@@ -22,8 +23,9 @@ use stateful::{
 };
 use tracing::Span;
 
+pub(crate) use self::behavior::field_type_from_json;
 pub use self::{
-    behavior::{Behavior, BehaviorKeyJsonError, BehaviorMap},
+    behavior::{Behavior, BehaviorKeyJsonError, BehaviorKeys, BehaviorMap},
     message::ExecuteBehaviorsTaskMessage,
     task::ExecuteBehaviorsTask,
 };
@@ -45,6 +47,9 @@ use crate::{
 
 pub const BEHAVIOR_INDEX_INNER_COUNT: usize = 2;
 
+/// Name of the global seeding the random number generator of the behavior schedules.
+pub const SEED_GLOBAL: &str = "behavior_schedule_seed";
+
 pub type BehaviorIdInnerDataType = u16;
 pub type BehaviorIndexInnerDataType = f64;
 
@@ -126,16 +131,42 @@ impl StatePackageCreator for BehaviorExecutionCreator {
             behavior_index_col.value(),
         )?;
 
+        let behavior_ids = Arc::clone(self.get_behavior_ids()?);
+        let rng = behavior_ids
+            .has_probability_schedules()
+            .then(|| schedule_rng(&config.globals));
         Ok(Box::new(BehaviorExecution {
-            behavior_ids: Arc::clone(self.get_behavior_ids()?),
+            behavior_ids,
             behavior_ids_col_index,
             behavior_ids_col_data_types,
             behavior_index_col_index,
             comms,
+            step: 1,
+            rng,
         }))
     }
 }
 
+/// Creates the random number generator deciding which agents execute a behavior with a
+/// `probability` in its schedule.
+///
+/// If the [`SEED_GLOBAL`] global is set to a non-negative integer, the generator is seeded from
+/// it, so a simulation run can be reproduced and the runs of an experiment can vary the seed like
+/// any other global. Otherwise, it's seeded from entropy.
+fn schedule_rng(globals: &Globals) -> StdRng {
+    match globals.get(SEED_GLOBAL).map(|seed| (seed, seed.as_u64())) {
+        None => StdRng::from_entropy(),
+        Some((_, Some(seed))) => StdRng::seed_from_u64(seed),
+        Some((seed, None)) => {
+            tracing::warn!(
+                "`{SEED_GLOBAL}` in globals must be a non-negative integer, got {seed}, so \
+                 behavior schedules are drawn from entropy"
+            );
+            StdRng::from_entropy()
+        }
+    }
+}
+
 /// Finds the index of the given field in the [`Schema`].
 pub fn index_of(schema: Arc<Schema>, field_name: &str) -> crate::Result<usize> {
     schema
@@ -152,27 +183,36 @@ pub struct BehaviorExecution {
     behavior_ids_col_data_types: [arrow2::datatypes::DataType; 3],
     behavior_index_col_index: usize,
     comms: PackageComms,
+    /// The current step, state packages run once per step starting with step 1.
+    step: usize,
+    /// Only created if a behavior has a `probability` in its schedule.
+    rng: Option<StdRng>,
 }
 
 impl Package for BehaviorExecution {}
 
 impl BehaviorExecution {
-    /// Iterates over all "behaviors" fields of agents and writes them into their "behaviors" field.
-    /// This fixation guarantees that all behaviors that were there in the beginning of behavior
-    /// execution will be executed accordingly
+    /// Iterates over all "behaviors" fields of agents and writes the behaviors due at the current
+    /// step into their "behaviors" field. This fixation guarantees that all behaviors that were
+    /// there in the beginning of behavior execution will be executed accordingly.
+    ///
+    /// Returns the language of the first behavior to execute, `None` if there are no behaviors to
+    /// execute.
     fn fix_behavior_chains(
         &mut self,
         agent_proxies: &mut PoolWriteProxy<AgentBatch>,
-    ) -> Result<()> {
-        let behavior_ids = chain::gather_behavior_chains(
+    ) -> Result<Option<Language>> {
+        let (behavior_ids, first_lang) = chain::gather_behavior_chains(
             &agent_proxies.batches_iter().collect::<Vec<_>>(),
             &self.behavior_ids,
             self.behavior_ids_col_data_types.clone(),
             self.behavior_ids_col_index,
+            self.step,
+            self.rng.as_mut(),
         )?;
 
         behavior_ids.apply_to(agent_proxies)?;
-        Ok(())
+        Ok(first_lang)
     }
 
     fn reset_behavior_index_col(
@@ -184,35 +224,6 @@ impl BehaviorExecution {
 
         Ok(())
     }
-
-    /// Iterate over languages of first behaviors to choose first language runner to send task to
-    fn get_first_lang<'a>(
-        &self,
-        agent_batches: &mut impl Iterator<Item = &'a AgentBatch>,
-    ) -> Result<Option<Language>> {
-        for agent_pool in agent_batches {
-            for agent_behaviors in
-                chain::behavior_list_bytes_iter(agent_pool.batch.record_batch()?)?
-            {
-                if agent_behaviors.is_empty() {
-                    continue;
-                }
-
-                let first_behavior = agent_behaviors[0];
-                let behavior_lang = self
-                    .behavior_ids
-                    .get_index(first_behavior)
-                    .ok_or_else(|| {
-                        let bytes = Vec::from(first_behavior);
-                        let utf8 = String::from_utf8(bytes.clone());
-                        Error::InvalidBehaviorBytes(bytes, utf8)
-                    })?
-                    .lang_index();
-                return Ok(Some(Language::from_index(behavior_lang as usize)));
-            }
-        }
-        Ok(None)
-    }
 }
 
 impl BehaviorExecution {
@@ -244,23 +255,19 @@ impl StatePackage for BehaviorExecution {
         state_proxy.maybe_reload()?;
         let agent_pool = state_proxy.agent_pool_mut();
 
-        self.fix_behavior_chains(agent_pool)?;
+        let first_lang = self.fix_behavior_chains(agent_pool)?;
+        self.step += 1;
         self.reset_behavior_index_col(agent_pool)?;
         for agent_batch in agent_pool.batches_iter_mut() {
             agent_batch.batch.flush_changes()?;
         }
 
-        // Have to reload state agent batches twice, because we just wrote the language ID of each
-        // behavior into them, but now want to read it from them.
-        // TODO: This could be changed so that they only need to be reloaded once, e.g. by getting
-        //       the first language before fixing behavior chains using the behaviors column strings
-        //       (instead of reading behavior ids in Rust) or by returning the first language from
-        //       fix_behavior_chains.
+        // Have to reload state agent batches, because we just wrote the behavior ids into them
         state_proxy.maybe_reload()?;
-        let lang = match self.get_first_lang(&mut state_proxy.agent_pool().batches_iter())? {
+        let lang = match first_lang {
             Some(lang) => lang,
             None => {
-                tracing::warn!("No behaviors were found to execute");
+                tracing::debug!("No behaviors were found to execute");
                 return Ok(());
             } // No behaviors to execute
         };
@@ -46,6 +46,40 @@ const prepare_user_trace = (error, trace) => {
   };
 };
 
+/// Evaluates the source code of a behavior and returns its `behavior` function.
+///
+/// The code is compiled as a classic script first. `import` and `export` declarations are syntax
+/// errors in classic scripts, so if it doesn't compile, it's evaluated as ES module instead. If it
+/// isn't a valid module either, the error of the classic script is thrown.
+const evaluate_behavior = (name, code, console) => {
+  let script;
+  try {
+    script = new Function(
+      "hash_stdlib",
+      "hstd",
+      "console",
+      `${code}\nreturn behavior`,
+    );
+  } catch (script_error) {
+    if (!(script_error instanceof SyntaxError)) {
+      throw script_error;
+    }
+    // Modules can only access globals. The prefix is on the first line, so line numbers in errors
+    // are unchanged.
+    globalThis.__hash_behavior_console = console;
+    try {
+      return __hash_import_behavior(
+        name,
+        "const hstd = hash_stdlib, console = globalThis.__hash_behavior_console; " +
+          `${code}\nexport { behavior as __hash_behavior };`,
+      ).__hash_behavior;
+    } catch (module_error) {
+      throw module_error instanceof SyntaxError ? script_error : module_error;
+    }
+  }
+  return script(hash_stdlib, hash_stdlib, console);
+};
+
 /// `behavior_descs` should be a list of objects with fields `id`, `name`, `source`, `columns`,
 /// `language` and `dyn_access`.
 const load_behaviors = (experiment, behavior_descs) => {
@@ -83,12 +117,7 @@ const load_behaviors = (experiment, behavior_descs) => {
     const code = desc.source;
     let fn;
     try {
-      fn = new Function(
-        "hash_stdlib",
-        "hstd",
-        "console",
-        `${code}\nreturn behavior`,
-      )(hash_stdlib, hash_stdlib, console);
+      fn = evaluate_behavior(desc.name, code, console);
     } catch (e) {
       // Catch behavior code syntax errors and rethrow.
       Error.prepareStackTrace = prepare_user_trace;
@@ -3,8 +3,8 @@ use std::{collections::HashMap, sync::OnceLock};
 use crate::{
     package::simulation::{
         state::{
-            behavior_execution::BehaviorExecutionCreator, topology::TopologyCreator,
-            StatePackageCreator, StatePackageName,
+            behavior_execution::BehaviorExecutionCreator, raster::RasterCreator,
+            topology::TopologyCreator, StatePackageCreator, StatePackageName,
         },
         PackageInitConfig,
     },
@@ -20,12 +20,13 @@ impl StatePackageCreators {
         static PACKAGE_CREATORS: OnceLock<StatePackageCreators> = OnceLock::new();
         PACKAGE_CREATORS.get_or_try_init(|| {
             tracing::debug!("Initializing State Package Creators");
-            let mut creators = HashMap::<_, Box<dyn StatePackageCreator>>::with_capacity(2);
+            let mut creators = HashMap::<_, Box<dyn StatePackageCreator>>::with_capacity(3);
             creators.insert(
                 StatePackageName::BehaviorExecution,
                 Box::new(BehaviorExecutionCreator::new(config)?),
             );
             creators.insert(StatePackageName::Topology, Box::new(TopologyCreator));
+            creators.insert(StatePackageName::Raster, Box::new(RasterCreator));
             Ok(Self { creators })
         })
     }
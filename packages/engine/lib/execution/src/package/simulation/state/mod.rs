@@ -13,6 +13,7 @@
 //! [`AgentMessages`]: crate::package::simulation::context::agent_messages::AgentMessages
 
 pub mod behavior_execution;
+pub mod raster;
 pub mod topology;
 
 mod creator;
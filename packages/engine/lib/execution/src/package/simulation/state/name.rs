@@ -6,7 +6,10 @@ use stateful::field::PackageId;
 
 use crate::{
     package::simulation::{
-        state::{behavior_execution::BehaviorExecutionCreator, topology::TopologyCreator},
+        state::{
+            behavior_execution::BehaviorExecutionCreator, raster::RasterCreator,
+            topology::TopologyCreator,
+        },
         Dependencies, PackageCreator, PackageIdGenerator, PackageMetadata, PackageType,
     },
     Error, Result,
@@ -18,6 +21,7 @@ use crate::{
 pub enum StatePackageName {
     BehaviorExecution,
     Topology,
+    Raster,
 }
 
 impl StatePackageName {
@@ -46,7 +50,7 @@ impl fmt::Display for StatePackageName {
 
 lazy_static! {
     static ref METADATA: HashMap<StatePackageName, PackageMetadata> = {
-        use StatePackageName::{BehaviorExecution, Topology};
+        use StatePackageName::{BehaviorExecution, Raster, Topology};
         let mut id_creator = PackageIdGenerator::new(PackageType::State);
         let mut m = HashMap::new();
         m.insert(BehaviorExecution, PackageMetadata {
@@ -57,6 +61,10 @@ lazy_static! {
             id: id_creator.next(),
             dependencies: TopologyCreator::dependencies(),
         });
+        m.insert(Raster, PackageMetadata {
+            id: id_creator.next(),
+            dependencies: RasterCreator::dependencies(),
+        });
         m
     };
 }
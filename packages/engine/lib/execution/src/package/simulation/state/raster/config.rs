@@ -0,0 +1,212 @@
+use std::collections::BTreeMap;
+
+use serde::Deserialize;
+use stateful::global::Globals;
+
+use crate::{
+    package::simulation::state::topology::{TopologyConfig, WrappingBehavior},
+    Error, Result,
+};
+
+/// Key in the globals which declares the raster layers.
+pub const RASTER_GLOBAL: &str = "raster";
+
+fn default_cell_size() -> f64 {
+    1.0
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RasterGlobals {
+    #[serde(default = "default_cell_size")]
+    cell_size: f64,
+    layers: BTreeMap<String, LayerGlobals>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LayerGlobals {
+    #[serde(default)]
+    initial: InitialValues,
+    #[serde(default)]
+    diffusion: f64,
+    #[serde(default)]
+    decay: f64,
+}
+
+/// The values a layer starts with.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum InitialValues {
+    /// Every cell starts with the same value.
+    Uniform(f64),
+    /// One row per y-coordinate, each with one value per x-coordinate.
+    Grid(Vec<Vec<f64>>),
+}
+
+impl Default for InitialValues {
+    fn default() -> Self {
+        Self::Uniform(0.0)
+    }
+}
+
+/// Placement of the raster cells within the simulation bounds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridConfig {
+    /// The lower x/y-bounds of the topology, i.e. the corner of the first cell.
+    pub origin: [f64; 2],
+    /// The side length of a (square) cell.
+    pub cell_size: f64,
+    /// The number of cells along the x-axis.
+    pub width: usize,
+    /// The number of cells along the y-axis.
+    pub height: usize,
+    /// Whether the x/y-axis wraps around, i.e. uses [`WrappingBehavior::Continuous`].
+    pub wrap: [bool; 2],
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerConfig {
+    pub name: String,
+    pub initial: InitialValues,
+    /// Fraction of a cell's value shared equally with its eight neighbors each step.
+    pub diffusion: f64,
+    /// Fraction of a cell's value removed each step.
+    pub decay: f64,
+}
+
+/// Configuration of the raster layers, declared in the `raster` object of the globals.
+///
+/// The grid spans the x/y-bounds of the [`TopologyConfig`], so both have to be finite.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RasterConfig {
+    pub grid: GridConfig,
+    /// Ordered by name.
+    pub layers: Vec<LayerConfig>,
+}
+
+impl RasterConfig {
+    /// Reads the raster configuration from [`Globals`].
+    ///
+    /// Returns `None` if no raster layers are declared.
+    pub fn from_globals(globals: &Globals) -> Result<Option<Self>> {
+        let raster = match globals.get(RASTER_GLOBAL) {
+            Some(raster) => raster,
+            None => return Ok(None),
+        };
+        let raster: RasterGlobals = serde_json::from_value(raster.clone())
+            .map_err(|err| Error::GlobalsParseError(format!("{RASTER_GLOBAL}: {err}")))?;
+        if raster.cell_size.is_nan() || raster.cell_size <= 0.0 {
+            return Err(Error::from(format!(
+                "Raster cell size has to be positive, got {}",
+                raster.cell_size
+            )));
+        }
+        if raster.layers.is_empty() {
+            return Err(Error::from("At least one raster layer has to be declared"));
+        }
+
+        let topology = TopologyConfig::from_globals(globals)?;
+        let mut origin = [0.0; 2];
+        let mut cells = [0; 2];
+        let mut wrap = [false; 2];
+        for (dim, axis) in ["x", "y"].into_iter().enumerate() {
+            let bounds = topology.bounds[dim];
+            if !bounds.min.is_finite() || !bounds.max.is_finite() || bounds.min >= bounds.max {
+                return Err(Error::from(format!(
+                    "Raster layers require finite `topology.{axis}_bounds`"
+                )));
+            }
+            origin[dim] = bounds.min;
+            cells[dim] = (((bounds.max - bounds.min) / raster.cell_size).ceil() as usize).max(1);
+            wrap[dim] = topology.wrap_modes[dim] == WrappingBehavior::Continuous;
+        }
+        let grid = GridConfig {
+            origin,
+            cell_size: raster.cell_size,
+            width: cells[0],
+            height: cells[1],
+            wrap,
+        };
+
+        let layers = raster
+            .layers
+            .into_iter()
+            .map(|(name, layer)| {
+                for (rate, value) in [("diffusion", layer.diffusion), ("decay", layer.decay)] {
+                    if !(0.0..=1.0).contains(&value) {
+                        return Err(Error::from(format!(
+                            "The {rate} rate of raster layer `{name}` has to be between 0 and 1, \
+                             got {value}"
+                        )));
+                    }
+                }
+                if let InitialValues::Grid(rows) = &layer.initial {
+                    if rows.len() != grid.height || rows.iter().any(|row| row.len() != grid.width) {
+                        return Err(Error::from(format!(
+                            "The initial values of raster layer `{name}` have to be {} rows of {} \
+                             values",
+                            grid.height, grid.width
+                        )));
+                    }
+                }
+                Ok(LayerConfig {
+                    name,
+                    initial: layer.initial,
+                    diffusion: layer.diffusion,
+                    decay: layer.decay,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(Self { grid, layers }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_from_globals() {
+        assert_eq!(
+            RasterConfig::from_globals(&Globals(json!({}))).unwrap(),
+            None
+        );
+
+        let config = RasterConfig::from_globals(&Globals(json!({
+            "topology": { "x_bounds": [0, 10], "y_bounds": [-2, 2], "wrapping_preset": "torus" },
+            "raster": {
+                "cell_size": 2,
+                "layers": {
+                    "rainfall": { "initial": 3 },
+                    "pheromone": { "diffusion": 0.5, "decay": 0.1 }
+                }
+            }
+        })))
+        .unwrap()
+        .unwrap();
+        assert_eq!(config.grid, GridConfig {
+            origin: [0.0, -2.0],
+            cell_size: 2.0,
+            width: 5,
+            height: 2,
+            wrap: [true, true],
+        });
+        let names: Vec<_> = config.layers.iter().map(|layer| &layer.name).collect();
+        assert_eq!(names, ["pheromone", "rainfall"]);
+        assert_eq!(config.layers[1].initial, InitialValues::Uniform(3.0));
+    }
+
+    #[test]
+    fn test_unbounded_topology() {
+        assert!(
+            RasterConfig::from_globals(&Globals(json!({
+                "raster": { "layers": { "pheromone": {} } }
+            })))
+            .is_err()
+        );
+    }
+}
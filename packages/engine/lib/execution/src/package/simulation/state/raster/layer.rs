@@ -0,0 +1,143 @@
+use crate::package::simulation::state::raster::config::{GridConfig, InitialValues, LayerConfig};
+
+/// Offsets of the eight cells adjacent to a cell.
+const ADJACENT_OFFSETS: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+impl GridConfig {
+    /// Returns the index of the cell offset by `(dx, dy)` from `(x, y)`, wrapping around
+    /// continuous axes. `None` if the cell lies outside the grid.
+    fn offset(&self, (x, y): (usize, usize), dx: isize, dy: isize) -> Option<usize> {
+        fn axis(value: usize, delta: isize, len: usize, wrap: bool) -> Option<usize> {
+            let value = value as isize + delta;
+            if (0..len as isize).contains(&value) {
+                Some(value as usize)
+            } else if wrap {
+                Some(value.rem_euclid(len as isize) as usize)
+            } else {
+                None
+            }
+        }
+
+        let x = axis(x, dx, self.width, self.wrap[0])?;
+        let y = axis(y, dy, self.height, self.wrap[1])?;
+        Some(y * self.width + x)
+    }
+
+    pub(super) fn len(&self) -> usize {
+        self.width * self.height
+    }
+}
+
+/// The values a layer starts with, one per cell, stored row by row.
+pub(super) fn initial_values(config: &LayerConfig, grid: &GridConfig) -> Vec<f64> {
+    let values = match &config.initial {
+        InitialValues::Uniform(value) => vec![*value; grid.len()],
+        InitialValues::Grid(rows) => rows.iter().flatten().copied().collect(),
+    };
+    debug_assert_eq!(values.len(), grid.len());
+    values
+}
+
+/// The kernels of a single named raster layer, whose values are stored in the [`RasterBatch`].
+///
+/// [`RasterBatch`]: stateful::context::RasterBatch
+pub(super) struct Layer {
+    diffusion: f64,
+    decay: f64,
+}
+
+impl Layer {
+    pub(super) fn new(config: &LayerConfig) -> Self {
+        Self {
+            diffusion: config.diffusion,
+            decay: config.decay,
+        }
+    }
+
+    /// Runs the diffusion and decay kernels once on `values`, which are stored row by row.
+    ///
+    /// Each cell shares the `diffusion` fraction of its value equally with its eight neighbors.
+    /// Shares which would leave the grid through a non-wrapping border remain in the cell, so
+    /// diffusion preserves the total of the layer. Afterwards every value shrinks by the `decay`
+    /// fraction.
+    pub(super) fn step(&self, grid: &GridConfig, values: &mut [f64]) {
+        debug_assert_eq!(values.len(), grid.len());
+        if self.diffusion > 0.0 {
+            let previous = values.to_vec();
+            for y in 0..grid.height {
+                for x in 0..grid.width {
+                    let index = y * grid.width + x;
+                    let share = previous[index] * self.diffusion / 8.0;
+                    for (dx, dy) in ADJACENT_OFFSETS {
+                        if let Some(neighbor) = grid.offset((x, y), dx, dy) {
+                            values[neighbor] += share;
+                            values[index] -= share;
+                        }
+                    }
+                }
+            }
+        }
+        if self.decay > 0.0 {
+            let retained = 1.0 - self.decay;
+            values.iter_mut().for_each(|value| *value *= retained);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(wrap: bool) -> GridConfig {
+        GridConfig {
+            origin: [0.0, 0.0],
+            cell_size: 1.0,
+            width: 3,
+            height: 3,
+            wrap: [wrap, wrap],
+        }
+    }
+
+    /// Returns the layer and its values, which are 8 in the center cell and 0 elsewhere.
+    fn layer(grid: &GridConfig, diffusion: f64, decay: f64) -> (Layer, Vec<f64>) {
+        let config = LayerConfig {
+            name: "test".to_owned(),
+            initial: InitialValues::Uniform(0.0),
+            diffusion,
+            decay,
+        };
+        let mut values = initial_values(&config, grid);
+        values[4] = 8.0;
+        (Layer::new(&config), values)
+    }
+
+    #[test]
+    fn test_diffusion_preserves_total() {
+        for wrap in [false, true] {
+            let grid = grid(wrap);
+            let (layer, mut values) = layer(&grid, 1.0, 0.0);
+            layer.step(&grid, &mut values);
+            assert_eq!(values, [1.0, 1.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 1.0]);
+            layer.step(&grid, &mut values);
+            assert!((values.iter().sum::<f64>() - 8.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_decay() {
+        let grid = grid(false);
+        let (layer, mut values) = layer(&grid, 0.0, 0.25);
+        layer.step(&grid, &mut values);
+        assert_eq!(values[4], 6.0);
+        assert_eq!(grid.offset((0, 0), -1, -1), None);
+    }
+}
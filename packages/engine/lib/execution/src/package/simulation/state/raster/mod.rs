@@ -0,0 +1,147 @@
+//! Named raster layers covering the simulation bounds, such as rainfall or pheromones.
+//!
+//! Layers are declared in the `raster` object of the globals and span the x/y-bounds of the
+//! topology. Instead of modelling every cell as an agent, one value per cell is stored in a
+//! [`RasterBatch`] in shared memory, which is created with the initial values before the first
+//! step and held by the [`Context`]. It's synced to the language runners together with the context
+//! batch, where agents read and write the cells at their position in place.
+//!
+//! Each step, after behaviors were executed, this package runs the diffusion and decay kernels on
+//! the layers.
+
+mod config;
+mod layer;
+
+use std::sync::Arc;
+
+use arrow2::datatypes::{DataType, Field, Metadata, Schema};
+use async_trait::async_trait;
+use memory::shared_memory::MemoryId;
+use serde_json::json;
+use stateful::{
+    context::{Context, RasterBatch},
+    field::FieldSpecMapAccessor,
+    global::Globals,
+    proxy::BatchWriteProxy,
+    state::State,
+};
+use tracing::Span;
+
+pub use self::config::{GridConfig, InitialValues, LayerConfig, RasterConfig, RASTER_GLOBAL};
+use self::layer::{initial_values, Layer};
+use crate::{
+    package::simulation::{
+        state::{StatePackage, StatePackageCreator},
+        Package, PackageComms, PackageCreator, PackageCreatorConfig, PackageInitConfig,
+    },
+    Error, Result,
+};
+
+/// Key of the schema metadata of the [`RasterBatch`], which describes the grid as JSON object with
+/// `origin`, `cell_size`, `width`, `height` and `wrap`.
+const GRID_METADATA_KEY: &str = "raster_grid";
+
+/// Creates the [`RasterBatch`] for the raster layers declared in `globals`, filled with their
+/// initial values.
+///
+/// Returns `None` if no raster layers are declared.
+pub fn create_raster_batch(globals: &Globals, memory_id: MemoryId) -> Result<Option<RasterBatch>> {
+    let config = match RasterConfig::from_globals(globals)? {
+        Some(config) => config,
+        None => return Ok(None),
+    };
+
+    let GridConfig {
+        origin,
+        cell_size,
+        width,
+        height,
+        wrap,
+    } = &config.grid;
+    let grid = json!({
+        "origin": origin,
+        "cell_size": cell_size,
+        "width": width,
+        "height": height,
+        "wrap": wrap,
+    });
+    let fields = config
+        .layers
+        .iter()
+        .map(|layer| Field::new(&layer.name, DataType::Float64, false))
+        .collect::<Vec<_>>();
+    let schema = Schema::from(fields).with_metadata(Metadata::from([(
+        GRID_METADATA_KEY.to_owned(),
+        grid.to_string(),
+    )]));
+
+    let layers = config
+        .layers
+        .iter()
+        .map(|layer| initial_values(layer, &config.grid))
+        .collect();
+    Ok(Some(RasterBatch::from_layers(
+        Arc::new(schema),
+        layers,
+        memory_id,
+    )?))
+}
+
+pub struct RasterCreator;
+
+impl StatePackageCreator for RasterCreator {
+    fn create(
+        &self,
+        config: &PackageCreatorConfig,
+        _init_config: &PackageInitConfig,
+        _comms: PackageComms,
+        _accessor: FieldSpecMapAccessor,
+    ) -> Result<Box<dyn StatePackage>> {
+        let layers =
+            RasterConfig::from_globals(&config.globals)?.map(|raster_config| RasterLayers {
+                layers: raster_config.layers.iter().map(Layer::new).collect(),
+                grid: raster_config.grid,
+            });
+        Ok(Box::new(Raster { layers }))
+    }
+}
+
+impl PackageCreator for RasterCreator {}
+
+struct RasterLayers {
+    grid: GridConfig,
+    /// Ordered like the layers in [`RasterConfig`] and the columns of the [`RasterBatch`].
+    layers: Vec<Layer>,
+}
+
+pub struct Raster {
+    /// `None` if no raster layers are declared in the globals.
+    layers: Option<RasterLayers>,
+}
+
+impl Package for Raster {}
+
+#[async_trait]
+impl StatePackage for Raster {
+    async fn run(&mut self, _state: &mut State, context: &Context) -> Result<()> {
+        let raster = match &self.layers {
+            Some(raster) => raster,
+            None => return Ok(()),
+        };
+        tracing::trace!("Running Raster package");
+
+        let raster_batch = context
+            .raster_batch()
+            .ok_or_else(|| Error::from("Raster layers are declared, but were not created"))?;
+        // The runners only access the layers while executing behaviors, which finished already.
+        let mut raster_batch = BatchWriteProxy::new(raster_batch)?;
+        for (index, layer) in raster.layers.iter().enumerate() {
+            layer.step(&raster.grid, raster_batch.layer_mut(index)?);
+        }
+        Ok(())
+    }
+
+    fn span(&self) -> Span {
+        tracing::debug_span!("raster")
+    }
+}
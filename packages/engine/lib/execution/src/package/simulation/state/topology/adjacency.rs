@@ -1,5 +1,5 @@
 use crate::package::simulation::state::topology::{
-    Direction, Position, TopologyConfig, WrappingBehavior,
+    geo, Direction, Position, TopologyConfig, WrappingBehavior,
 };
 
 /// Wrap the position if the agent is out of bounds
@@ -8,6 +8,10 @@ pub fn correct_agent(
     mut dir: Option<&mut Direction>,
     topology: &TopologyConfig,
 ) -> bool {
+    if topology.geographic {
+        return pos.map_or(false, |pos| correct_geographic_agent(pos, dir));
+    }
+
     let mut position_was_corrected = false;
 
     if let Some(ref mut pos) = pos {
@@ -26,6 +30,27 @@ pub fn correct_agent(
     position_was_corrected
 }
 
+/// Moves agents which crossed a pole to the other side of it and wraps the longitude.
+///
+/// Crossing a pole reverses the agent's heading, so both components of the direction are negated.
+fn correct_geographic_agent(pos: &mut Position, dir: Option<&mut Direction>) -> bool {
+    let mut position_was_corrected = false;
+    if pos[1].abs() > 90.0 {
+        pos[1] = 180.0_f64.copysign(pos[1]) - pos[1];
+        pos[0] += 180.0;
+        if let Some(dir) = dir {
+            dir[0] = -dir[0];
+            dir[1] = -dir[1];
+        }
+        position_was_corrected = true;
+    }
+    if !(-180.0..180.0).contains(&pos[0]) {
+        pos[0] = geo::normalize_longitude(pos[0]);
+        position_was_corrected = true;
+    }
+    position_was_corrected
+}
+
 fn wrap_pos_coord(pos: &mut Position, i: usize, config: &TopologyConfig) {
     match config.wrap_modes[i] {
         WrappingBehavior::Continuous => {
@@ -7,7 +7,7 @@ use serde::{
 use serde_json::Value;
 use stateful::global::Globals;
 
-use crate::Result;
+use crate::{package::simulation::state::topology::geo, Result};
 
 // TODO: think about creating a system of ConfigProviders whereby packages can depend on them
 //   and decrease the amount of assumptions the core engine has to make, for example position
@@ -377,6 +377,13 @@ pub struct TopologyConfig {
 
     /// Cache how many wrapped points we need to calculate
     pub wrapping_combinations: usize,
+
+    /// Whether positions are `[lon, lat]` in degrees on a sphere instead of coordinates in a box
+    ///
+    /// Distances and search radii are great-circle distances in metres, see [`geo`].
+    ///
+    /// [`geo`]: crate::package::simulation::state::topology::geo
+    pub geographic: bool,
 }
 
 impl Default for TopologyConfig {
@@ -388,6 +395,7 @@ impl Default for TopologyConfig {
             distance_function: DistanceFunction::default().as_function(),
             move_wrapped_agents: true,
             wrapping_combinations: 1,
+            geographic: false,
         }
     }
 }
@@ -422,6 +430,10 @@ impl TopologyConfig {
         if let Some(serde_json::Value::Object(mut topology_props)) =
             globals.0.get("topology").cloned()
         {
+            if from_json(&mut topology_props, "geographic", default.geographic)? {
+                return Self::geographic(topology_props);
+            }
+
             let bounds = [
                 from_json(&mut topology_props, "x_bounds", default.bounds[0])?,
                 from_json(&mut topology_props, "y_bounds", default.bounds[1])?,
@@ -466,6 +478,7 @@ impl TopologyConfig {
                     "move_wrapped_agents",
                     default.move_wrapped_agents,
                 )?,
+                geographic: false,
             };
             // All keys from the topology object are consumed to check for remaining keys
             for (key, _) in topology_props {
@@ -477,6 +490,67 @@ impl TopologyConfig {
         }
     }
 
+    /// Creates the configuration of a geographic topology from the remaining `topology_props`.
+    ///
+    /// Longitudes span `[-180, 180)` and latitudes `[-90, 90]`, agents leaving this range are moved
+    /// around the sphere instead of being wrapped by the wrapping modes. The box settings, i.e. the
+    /// bounds, the wrapping modes and the distance function, can't be set.
+    fn geographic(
+        mut topology_props: serde_json::Map<String, Value>,
+    ) -> Result<Self, serde_json::Error> {
+        const BOX_KEYS: [&str; 8] = [
+            "x_bounds",
+            "y_bounds",
+            "z_bounds",
+            "wrap_x_mode",
+            "wrap_y_mode",
+            "wrap_z_mode",
+            "wrapping_preset",
+            "distance_function",
+        ];
+        if let Some(key) = BOX_KEYS
+            .iter()
+            .find(|key| topology_props.contains_key(**key))
+        {
+            return Err(de::Error::custom(format!(
+                "\"{key}\" can't be used in a geographic topology"
+            )));
+        }
+
+        let default = Self::default();
+        let search_radius = topology_props
+            .remove("search_radius")
+            .map(serde_json::from_value)
+            .transpose()?;
+        let move_wrapped_agents = topology_props
+            .remove("move_wrapped_agents")
+            .map(serde_json::from_value)
+            .transpose()?
+            .unwrap_or(default.move_wrapped_agents);
+        for (key, _) in topology_props {
+            tracing::warn!("Unused key in geographic topology: \"{key}\"")
+        }
+
+        Ok(Self {
+            bounds: [
+                AxisBoundary {
+                    min: -180.0,
+                    max: 180.0,
+                },
+                AxisBoundary {
+                    min: -90.0,
+                    max: 90.0,
+                },
+                AxisBoundary::default(),
+            ],
+            search_radius,
+            distance_function: geo::haversine,
+            move_wrapped_agents,
+            geographic: true,
+            ..default
+        })
+    }
+
     /// Get the halfway axis of dimensions
     #[must_use]
     pub fn get_half_dim(&self, dim: usize) -> f64 {
@@ -502,6 +576,7 @@ mod tests {
         assert_eq!(lhs.wrapping_combinations, rhs.wrapping_combinations);
         assert_eq!(lhs.move_wrapped_agents, rhs.move_wrapped_agents);
         assert_eq!(lhs.search_radius, rhs.search_radius);
+        assert_eq!(lhs.geographic, rhs.geographic);
     }
 
     #[test]
@@ -585,4 +660,41 @@ mod tests {
         .unwrap();
         assert_equality(&target, &from_json);
     }
+
+    #[test]
+    fn test_geographic() {
+        let config = TopologyConfig::from_globals(&Globals(json!({
+            "topology": {
+                "geographic": true,
+                "search_radius": 1000
+            }
+        })))
+        .unwrap();
+        assert!(config.geographic);
+        assert_eq!(config.search_radius, Some(1000.));
+        assert_eq!(config.wrap_modes, [WrappingBehavior::NoWrap; 3]);
+        assert_eq!(config.bounds[1], AxisBoundary {
+            min: -90.,
+            max: 90.
+        });
+    }
+
+    #[test]
+    fn test_geographic_rejects_box_settings() {
+        for (key, value) in [
+            ("x_bounds", json!([0, 10])),
+            ("wrap_x_mode", json!("continuous")),
+            ("wrapping_preset", json!("torus")),
+            ("distance_function", json!("euclidean")),
+        ] {
+            let err = TopologyConfig::from_globals(&Globals(json!({
+                "topology": {
+                    "geographic": true,
+                    key: value
+                }
+            })))
+            .unwrap_err();
+            assert!(err.to_string().contains(key), "{err}");
+        }
+    }
 }
@@ -0,0 +1,99 @@
+//! Geometry on the sphere used by the geographic topology, where positions are `[lon, lat]` in
+//! degrees and distances are in metres.
+
+use std::f64::consts::PI;
+
+/// The mean radius of the earth in metres.
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// The great-circle distance in metres between two `[lon, lat]` positions.
+#[must_use]
+pub fn haversine(a: &[f64], b: &[f64]) -> f64 {
+    let (lat_a, lat_b) = (a[1].to_radians(), b[1].to_radians());
+    let half_delta_lat = (lat_b - lat_a) / 2.0;
+    let half_delta_lon = (b[0] - a[0]).to_radians() / 2.0;
+    let h = half_delta_lat.sin().powi(2) + lat_a.cos() * lat_b.cos() * half_delta_lon.sin().powi(2);
+    2.0 * EARTH_RADIUS * h.sqrt().min(1.0).asin()
+}
+
+/// Converts a `[lon, lat]` position into earth-centered cartesian coordinates in metres.
+///
+/// The straight-line distance between two converted positions grows strictly with their
+/// great-circle distance, so a kd-tree over converted positions can be searched with
+/// [`chord_length`] without special-casing the antimeridian or the poles.
+#[must_use]
+pub fn to_cartesian(position: &[f64]) -> [f64; 3] {
+    let (lon, lat) = (position[0].to_radians(), position[1].to_radians());
+    [
+        EARTH_RADIUS * lat.cos() * lon.cos(),
+        EARTH_RADIUS * lat.cos() * lon.sin(),
+        EARTH_RADIUS * lat.sin(),
+    ]
+}
+
+/// The straight-line distance between two points on the sphere which are `distance` metres apart
+/// along the surface.
+#[must_use]
+pub fn chord_length(distance: f64) -> f64 {
+    let distance = distance.clamp(0.0, PI * EARTH_RADIUS);
+    2.0 * EARTH_RADIUS * (distance / (2.0 * EARTH_RADIUS)).sin()
+}
+
+/// Maps a longitude in degrees into `[-180, 180)`.
+#[must_use]
+pub fn normalize_longitude(lon: f64) -> f64 {
+    (lon + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Moves `position` by `east` and `north` metres along the great circle in the direction of the
+/// displacement. The altitude at index 2 is left unchanged.
+pub fn displace(position: &mut [f64; 3], east: f64, north: f64) {
+    let distance = east.hypot(north);
+    if distance == 0.0 {
+        return;
+    }
+    let bearing = east.atan2(north);
+    let angle = distance / EARTH_RADIUS;
+    let (lon, lat) = (position[0].to_radians(), position[1].to_radians());
+
+    let new_lat = (lat.sin() * angle.cos() + lat.cos() * angle.sin() * bearing.cos()).asin();
+    let new_lon = lon
+        + (bearing.sin() * angle.sin() * lat.cos()).atan2(angle.cos() - lat.sin() * new_lat.sin());
+
+    position[0] = normalize_longitude(new_lon.to_degrees());
+    position[1] = new_lat.to_degrees();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_haversine() {
+        // London to Paris
+        let distance = haversine(&[-0.1278, 51.5074], &[2.3522, 48.8566]);
+        assert!((distance - 343_556.0).abs() < 500.0, "{distance}");
+        // Across the antimeridian
+        let distance = haversine(&[179.5, 0.0], &[-179.5, 0.0]);
+        assert!((distance - EARTH_RADIUS * 1_f64.to_radians()).abs() < 1e-6);
+        assert!((chord_length(distance) - distance).abs() < 1.0);
+    }
+
+    #[test]
+    fn test_displace() {
+        let mut position = [179.9, 0.0, 0.0];
+        displace(&mut position, EARTH_RADIUS * 0.2_f64.to_radians(), 0.0);
+        assert!((position[0] + 179.9).abs() < 1e-9, "{position:?}");
+
+        let mut position = [10.0, 89.0, 0.0];
+        displace(&mut position, 0.0, EARTH_RADIUS * 2_f64.to_radians());
+        assert!((position[0] + 170.0).abs() < 1e-9, "{position:?}");
+        assert!((position[1] - 89.0).abs() < 1e-9, "{position:?}");
+
+        // The displacement is the great-circle distance travelled
+        let start = [-0.1278, 51.5074, 0.0];
+        let mut position = start;
+        displace(&mut position, 30_000.0, -40_000.0);
+        assert!((haversine(&start, &position) - 50_000.0).abs() < 1e-6);
+    }
+}
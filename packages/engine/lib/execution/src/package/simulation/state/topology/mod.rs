@@ -26,6 +26,7 @@ use crate::{
 
 mod adjacency;
 mod fields;
+pub mod geo;
 
 type PositionSubType = f64;
 type Position = [PositionSubType; 3];
@@ -0,0 +1,60 @@
+//! Directories in which runners cache files between engine processes.
+//!
+//! Cached files are executed or loaded by the engine, so the directories are only accessible by the
+//! current user. Otherwise, another user could place files there which the engine runs.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Returns the cache directory `name` of the current user, creating it if necessary.
+///
+/// This is `hash_engine/<name>` in `$XDG_CACHE_HOME`, or `<name>` next to the binary of the current
+/// process if `XDG_CACHE_HOME` isn't set.
+///
+/// # Errors
+///
+/// - if the directory could not be created
+/// - if the directory is accessible by other users, see [`create_private_directory`]
+pub fn cache_directory(name: &str) -> io::Result<PathBuf> {
+    let directory = match std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|cache_home| cache_home.is_absolute())
+    {
+        Some(cache_home) => cache_home.join("hash_engine").join(name),
+        None => std::env::current_exe()?.with_file_name(name),
+    };
+    create_private_directory(&directory)?;
+    Ok(directory)
+}
+
+/// Creates `directory` only accessible by the current user, or checks that an existing one is.
+///
+/// # Errors
+///
+/// - if the directory could not be created
+/// - if the directory is owned by another user or accessible by other users
+#[cfg(unix)]
+pub fn create_private_directory(directory: &Path) -> io::Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(directory)?;
+    let metadata = fs::metadata(directory)?;
+    // SAFETY: `getuid` is always successful.
+    if metadata.uid() != unsafe { libc::getuid() } || metadata.mode() & 0o077 != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{directory:?} has to be owned by and only be accessible by the current user"),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn create_private_directory(directory: &Path) -> io::Result<()> {
+    fs::create_dir_all(directory)
+}
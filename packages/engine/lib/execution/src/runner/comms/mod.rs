@@ -116,6 +116,9 @@ pub struct NewSimulationRun {
     pub packages: PackageMsgs,
     pub datastore: DatastoreSimulationPayload,
     pub globals: Arc<Globals>,
+    /// If the simulation engine balances the batches across the workers, otherwise batches are
+    /// distributed round-robin.
+    pub balance_workers: bool,
 }
 
 #[derive(Clone)]
@@ -283,12 +283,12 @@ pub struct OutboundFromRunnerMsg {
 }
 
 impl OutboundFromRunnerMsg {
-    pub(crate) fn try_from_nng(
-        msg: nng::Message,
+    /// Parses a finished `RunnerOutboundMsg` flatbuffer.
+    pub(crate) fn try_from_fbs_bytes(
+        msg: &[u8],
         source: Language,
         sent_tasks: &mut HashMap<TaskId, SentTask>,
     ) -> Result<Self> {
-        let msg = msg.as_slice();
         let msg = root_as_runner_outbound_msg(msg);
         let msg = msg.map_err(|err| {
             Error::from(format!(
@@ -1,5 +1,11 @@
+use std::path::PathBuf;
+
 #[derive(Debug, Default, Clone)]
 pub struct RunnerConfig {
     pub js_runner_initial_heap_constraint: Option<usize>,
     pub js_runner_max_heap_size: Option<usize>,
+    pub js_runner_disable_snapshot: bool,
+    pub python_runner_embedded: bool,
+    pub js_node_modules: Option<PathBuf>,
+    pub python_environment: Option<PathBuf>,
 }
@@ -45,12 +45,30 @@ const parse_any_type_fields = (metadata) => {
   return any_type_fields;
 };
 
+/// Returns a map from the names of category fields to their list of categories.
+const parse_category_fields = (metadata) => {
+  const category_fields = metadata.get("category_fields");
+  return category_fields ? JSON.parse(category_fields) : {};
+};
+
 const load_vectors = (record_batch_bytes, schema) => {
   const reader = new arrow.MessageReader(record_batch_bytes);
   const msg = reader.readMessage();
   const header = msg.header();
   const body = reader.readMessageBody(msg.bodyLength);
+  const category_fields = parse_category_fields(schema.metadata);
+  // Batches only contain the keys of dictionary-encoded columns, the dictionary values of category
+  // fields are stored in the schema.
   const dicts = new Map();
+  for (const field of schema.fields) {
+    const categories = category_fields[field.name];
+    if (categories) {
+      dicts.set(
+        field.type.id,
+        arrow.vectorFromArray(categories, new arrow.Utf8()),
+      );
+    }
+  }
   const loader = new reader.VectorLoader(
     body,
     header.nodes,
@@ -68,6 +86,7 @@ const load_vectors = (record_batch_bytes, schema) => {
     const vector = new arrow.makeVector(vector_list[i]);
     const field = schema.fields[i];
     vector.type.is_any = any_type_fields.has(field.name);
+    vector.type.categories = category_fields[field.name];
     vectors[field.name] = vector;
   }
   return vectors;
@@ -118,6 +137,16 @@ const load_marked_vectors = (shared_bytes, schema) => {
   return load_vectors(record_batch_bytes, schema);
 };
 
+/// Returns the bytes of the schema which is stored in `shared_bytes` itself.
+export const load_marked_schema_bytes = (shared_bytes) => {
+  const markers = load_markers(shared_bytes);
+  return new Uint8Array(
+    shared_bytes,
+    markers.schema_offset,
+    markers.schema_size,
+  );
+};
+
 /// `latest_batch` should have `id` (string), and `mem` (ArrayBuffer) fields.
 Batch.prototype.sync = function (latest_batch, schema) {
   const markers = load_markers(latest_batch.mem);
@@ -258,12 +287,32 @@ Batch.prototype.flush_changes = function (schema, skip) {
     // might be missing from `cols`. (But columns that
     // are in `cols` should always be in schema too.)
 
+    const categories = this.vectors[field.name].type.categories;
     if (this.vectors[field.name].type.is_any) {
       for (var i_agent = 0; i_agent < col.length; ++i_agent) {
         col[i_agent] = JSON.stringify(col[i_agent]);
       }
+    } else if (categories) {
+      for (var i_agent = 0; i_agent < col.length; ++i_agent) {
+        col[i_agent] = hash_util.category_to_key(
+          col[i_agent],
+          categories,
+          field.name,
+        );
+      }
+    } else if (field.type.typeId === arrow.Type.Int && field.type.bitWidth === 64) {
+      // Integers are loaded as numbers, but Arrow expects `BigInt`s
+      for (var i_agent = 0; i_agent < col.length; ++i_agent) {
+        if (typeof col[i_agent] === "number") col[i_agent] = BigInt(col[i_agent]);
+      }
+    } else if (field.type.typeId === arrow.Type.Timestamp) {
+      for (var i_agent = 0; i_agent < col.length; ++i_agent) {
+        if (col[i_agent] instanceof Date) col[i_agent] = col[i_agent].getTime();
+      }
     }
-    const array_data = array_data_from_col(col, field.type);
+    // Only the keys of dictionary-encoded columns are flushed
+    const type = categories ? field.type.indices : field.type;
+    const array_data = array_data_from_col(col, type);
     const data = ffi_data_from_array_data(array_data);
     changes.push({
       // Some fields might be skipped, so a field's index in `changes` might not be equal to `i_field`.
@@ -9,6 +9,88 @@ const deepfreeze = (x) => {
   return Object.freeze(x);
 };
 
+/// A raster layer, whose values are stored row by row in shared memory. Values are read and
+/// written in place, so writes are visible to all agents immediately.
+const RasterLayer = function (values, grid) {
+  this.__values = values;
+  this.__grid = grid;
+  this.width = grid.width;
+  this.height = grid.height;
+};
+
+/// Returns the `[x, y]` cell containing `position`, `null` if it lies outside the grid.
+RasterLayer.prototype.cell = function (position) {
+  const grid = this.__grid;
+  const x = Math.floor((position[0] - grid.origin[0]) / grid.cell_size);
+  const y = Math.floor((position[1] - grid.origin[1]) / grid.cell_size);
+  return x >= 0 && y >= 0 && x < grid.width && y < grid.height ? [x, y] : null;
+};
+
+/// Returns the index of the cell `(x, y)`, wrapping around continuous axes, or `null` if it lies
+/// outside the grid.
+RasterLayer.prototype.__index = function (x, y) {
+  const grid = this.__grid;
+  if (!Number.isInteger(x) || !Number.isInteger(y)) return null;
+  if (grid.wrap[0]) x = ((x % grid.width) + grid.width) % grid.width;
+  if (grid.wrap[1]) y = ((y % grid.height) + grid.height) % grid.height;
+  if (x < 0 || y < 0 || x >= grid.width || y >= grid.height) return null;
+  return y * grid.width + x;
+};
+
+/// Returns the value of the cell `(x, y)`, `null` if it lies outside the grid.
+RasterLayer.prototype.get = function (x, y) {
+  const index = this.__index(x, y);
+  return index === null ? null : this.__values[index];
+};
+
+RasterLayer.prototype.set = function (x, y, value) {
+  const index = this.__index(x, y);
+  if (index === null) {
+    throw new RangeError(
+      "Raster cell [" + x + ", " + y + "] is outside the grid",
+    );
+  }
+  this.__values[index] = value;
+};
+
+/// Returns the value of the cell containing `position`, `null` if it lies outside the grid.
+RasterLayer.prototype.get_at = function (position) {
+  const cell = this.cell(position);
+  return cell === null ? null : this.get(cell[0], cell[1]);
+};
+
+/// Sets the value of the cell containing `position`. Returns `false` without writing anything if
+/// `position` lies outside the grid.
+RasterLayer.prototype.set_at = function (position, value) {
+  const cell = this.cell(position);
+  if (cell === null) return false;
+  this.set(cell[0], cell[1], value);
+  return true;
+};
+
+/// Returns the raster layers by name. `raster_batch` must be synced with `raster_schema`, whose
+/// metadata describes the grid.
+export const load_raster = (raster_batch, raster_schema) => {
+  const grid = JSON.parse(raster_schema.metadata.get("raster_grid"));
+  const layers = {};
+  for (const field of raster_schema.fields) {
+    const values = raster_batch.vectors[field.name].data[0].values;
+    if (values.buffer !== raster_batch.mem) {
+      throw new Error(
+        "Raster layer " + field.name + " isn't backed by shared memory",
+      );
+    }
+    layers[field.name] = Object.freeze(new RasterLayer(values, grid));
+  }
+  return Object.freeze(layers);
+};
+
+const get_raster_layer = (raster, name) => {
+  const layer = raster.layers[name];
+  if (!layer) throw new ReferenceError("Unknown raster layer: " + name);
+  return layer;
+};
+
 const gen_agent_ctx_getter = (name, getter) => {
   return getter
     ? function () {
@@ -21,7 +103,7 @@ const gen_agent_ctx_getter = (name, getter) => {
 
 // TODO: agent view? (__sim_ctx field, this.__cols = sim_ctx.ctx_batch.cols,
 //       defineProperty(..., "state_snapshot", ... __sim_ctx.state_snapshot))
-const gen_agent_ctx = (ctx_schema, getters) => {
+const gen_agent_ctx = (ctx_schema, getters, raster) => {
   const AgentContext = function (
     ctx_batch,
     state_snapshot,
@@ -53,6 +135,10 @@ const gen_agent_ctx = (ctx_schema, getters) => {
     return this.__current_step;
   };
 
+  AgentContext.prototype.raster = function (name) {
+    return get_raster_layer(raster, name);
+  };
+
   for (var i_field = 0; i_field < ctx_schema.fields.length; ++i_field) {
     // `name` is the name of some context batch column
     const name = ctx_schema.fields[i_field].name;
@@ -63,7 +149,7 @@ const gen_agent_ctx = (ctx_schema, getters) => {
   return Object.seal(AgentContext);
 };
 
-const gen_group_ctx = (AgentContext) => {
+const gen_group_ctx = (AgentContext, raster) => {
   const GroupContext = function (
     ctx_batch,
     state_snapshot,
@@ -94,6 +180,10 @@ const gen_group_ctx = (AgentContext) => {
     return this.__current_step;
   };
 
+  GroupContext.prototype.raster = function (name) {
+    return get_raster_layer(raster, name);
+  };
+
   GroupContext.prototype.get_agent = function (
     i_agent_in_group,
     old_agent_ctx,
@@ -120,8 +210,10 @@ const gen_group_ctx = (AgentContext) => {
 };
 
 export const gen_sim_ctx = (ctx_schema, getters) => {
-  const AgentContext = gen_agent_ctx(ctx_schema, getters);
-  const GroupContext = gen_group_ctx(AgentContext);
+  // Raster layers of the simulation run, shared by all contexts.
+  const raster = { layers: {} };
+  const AgentContext = gen_agent_ctx(ctx_schema, getters, raster);
+  const GroupContext = gen_group_ctx(AgentContext, raster);
 
   const SimContext = function (experiment_ctx, globals) {
     this.__experiment_ctx = experiment_ctx;
@@ -144,6 +236,16 @@ export const gen_sim_ctx = (ctx_schema, getters) => {
     return this.__current_step;
   };
 
+  SimContext.prototype.raster = function (name) {
+    return get_raster_layer(raster, name);
+  };
+
+  /// The layers are stored in the same shared memory for the whole simulation run, so they only
+  /// need to be set once.
+  SimContext.prototype.set_raster = function (layers) {
+    raster.layers = layers;
+  };
+
   /// Invalidates existing `GroupContext` and `AgentContext` objects.
   SimContext.prototype.set_batch = function (
     ctx_batch,
@@ -155,6 +257,11 @@ export const gen_sim_ctx = (ctx_schema, getters) => {
     this.__current_step = current_step;
   };
 
+  /// Invalidates existing `GroupContext` and `AgentContext` objects.
+  SimContext.prototype.set_globals = function (globals) {
+    this.__globals = deepfreeze(globals);
+  };
+
   /// Invalidates existing `GroupContext` and `AgentContext` objects.
   SimContext.prototype.sync_snapshot = function (state_snapshot) {
     this.state_snapshot = state_snapshot;
@@ -175,17 +175,13 @@ pub(in crate::runner::javascript) fn batch_to_js<'s>(
 
 pub(in crate::runner::javascript) fn state_to_js<'s, 'a>(
     scope: &mut v8::HandleScope<'s>,
-    mut agent_batches: impl Iterator<Item = &'a AgentBatch>,
-    mut message_batches: impl Iterator<Item = &'a MessageBatch>,
+    agent_batches: impl Iterator<Item = &'a AgentBatch>,
+    message_batches: impl Iterator<Item = &'a MessageBatch>,
 ) -> JavaScriptResult<(Value<'s>, Value<'s>)> {
     let js_agent_batches = v8::Array::new(scope, 0);
     let js_message_batches = v8::Array::new(scope, 0);
 
-    for (i_batch, (agent_batch, message_batch)) in agent_batches
-        .by_ref()
-        .zip(message_batches.by_ref())
-        .enumerate()
-    {
+    for (i_batch, agent_batch) in agent_batches.enumerate() {
         let agent_batch = batch_to_js(scope, agent_batch.batch.segment())?;
         js_agent_batches
             .set_index(scope, i_batch as u32, agent_batch)
@@ -194,7 +190,11 @@ pub(in crate::runner::javascript) fn state_to_js<'s, 'a>(
                     "Couldn't set agent batch at index {i_batch} on batch array"
                 ))
             })?;
+    }
 
+    // The message pool of a state snapshot may contain additional batches with delayed messages,
+    // which don't belong to an agent batch.
+    for (i_batch, message_batch) in message_batches.enumerate() {
         let message_batch = batch_to_js(scope, message_batch.batch.segment())?;
         js_message_batches
             .set_index(scope, i_batch as u32, message_batch)
@@ -205,13 +205,6 @@ pub(in crate::runner::javascript) fn state_to_js<'s, 'a>(
             })?;
     }
 
-    // There is no stable way of ensuring the length of an iterator, `zip` will stop as soon as one
-    // iterator returns None, thus if both iterators has no elements left, they had the same length.
-    debug_assert!(
-        agent_batches.next().is_none() && message_batches.next().is_none(),
-        "Agent batches and message batches needs to have the same size"
-    );
-
     Ok((js_agent_batches.into(), js_message_batches.into()))
 }
 
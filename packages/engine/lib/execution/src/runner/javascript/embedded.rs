@@ -1,5 +1,9 @@
 use super::{
-    error::JavaScriptResult, eval_file, modules::import_module, utils::new_js_string, Function,
+    error::JavaScriptResult,
+    eval_file,
+    modules::{engine_file, import_module, is_imported, set_module_functions},
+    utils::new_js_string,
+    Function,
 };
 use crate::runner::JavaScriptError;
 
@@ -14,24 +18,43 @@ pub(in crate::runner::javascript) struct Embedded<'s> {
     pub(in crate::runner::javascript) state_snapshot_sync: Function<'s>,
 }
 
+/// The file imported by the runner, which imports all other modules of the runner.
+const RUNNER_PATH: &str = "./lib/execution/src/runner/javascript/runner.js";
+
 impl<'s> Embedded<'s> {
-    pub(in crate::runner::javascript) fn import_common_js_files(
-        scope: &mut v8::HandleScope<'s>,
-    ) -> JavaScriptResult<Self> {
+    /// Evaluates the JavaScript every runner needs before it can start an experiment.
+    ///
+    /// This is what the startup snapshot contains, see the [`snapshot`](super::snapshot) module.
+    pub(in crate::runner::javascript) fn load_baseline(
+        scope: &mut v8::HandleScope<'_>,
+    ) -> JavaScriptResult<()> {
         // `hash_stdlib` can't be imported as a module because it needs to be available globally for
         // behaviors.
         // TODO: stop evaluating the file and use proper import for behaviors. https://app.asana.com/0/1199548034582004/1202225025969133/f
-        let hash_stdlib = eval_file(
+        let hash_stdlib_path = engine_file(
             scope,
             "./lib/execution/src/runner/javascript/hash_stdlib.js",
-        )?;
+        );
+        let hash_stdlib = eval_file(scope, &hash_stdlib_path)?;
         let hash_stdlib_str = new_js_string(scope, "hash_stdlib");
         scope
             .get_current_context()
             .global(scope)
             .set(scope, hash_stdlib_str.into(), hash_stdlib);
+        set_module_functions(scope)?;
 
-        let runner = import_module(scope, "./lib/execution/src/runner/javascript/runner.js")?;
+        import_module(scope, RUNNER_PATH)?;
+        Ok(())
+    }
+
+    pub(in crate::runner::javascript) fn import_common_js_files(
+        scope: &mut v8::HandleScope<'s>,
+    ) -> JavaScriptResult<Self> {
+        // Contexts created from the startup snapshot already contain the baseline.
+        if !is_imported(scope, RUNNER_PATH) {
+            Self::load_baseline(scope)?;
+        }
+        let runner = import_module(scope, RUNNER_PATH)?;
 
         // Importing a module doesn't return the items it exports. To access the items it exports we
         // can use `get_module_namespace`. An example of that can be found at https://github.com/v8/v8/blob/25e3225286d08a49812b9728810b4777041a7dd5/test/unittests/objects/modules-unittest.cc#L659
@@ -1,4 +1,5 @@
 // noinspection BadExpressionStatementJS
+import { arrow } from "./lib/execution/src/runner/javascript/apache-arrow-bundle.js";
 
 // TODO: Add this file to hash_stdlib instead?
 
@@ -63,6 +64,29 @@ export const load_shallow = (vector, is_nullable, is_any) => {
   return shallow;
 };
 
+const _is_int64 = (type) =>
+  type.typeId === arrow.Type.Int && type.bitWidth === 64;
+
+/// Arrow loads 64-bit integers as `BigInt`. They are always converted to `Number`, so behaviors can
+/// use them like any other number. Values which can't be represented exactly as a `Number` are
+/// rejected instead of silently losing precision.
+const _int64_to_number = (value) => {
+  if (typeof value !== "bigint") return value;
+  if (
+    value > BigInt(Number.MAX_SAFE_INTEGER) ||
+    value < BigInt(Number.MIN_SAFE_INTEGER)
+  ) {
+    throw new RangeError(
+      "Integer " +
+        value +
+        " can't be represented exactly in JavaScript, integer fields are limited to " +
+        Number.MAX_SAFE_INTEGER +
+        " in magnitude",
+    );
+  }
+  return Number(value);
+};
+
 const _is_primitive_or_list = (children) => {
   // TODO: This is currently a flakey form of duck-typing, that only works because the only nested field we support
   // are structs (we do not support Union or Map types). We should be testing by the type of the object here,
@@ -102,7 +126,9 @@ const _vector_to_array = (vector) => {
     return obj;
   } else {
     const children = vector.type.children;
-    if (!children) return shallow;
+    if (!children) {
+      return _is_int64(vector.type) ? shallow.map(_int64_to_number) : shallow;
+    }
 
     if (_is_primitive_or_list(children)) {
       // Primitive (strings, numbers) or list
@@ -135,9 +161,31 @@ export const load_full = (vector, is_nullable, is_any) => {
     }
     return array;
   }
+  if (vector.type.categories) {
+    // Can only have top-level categories. They are dictionary-encoded, so `get` already returns
+    // the category of the stored key.
+    return load_shallow(vector);
+  }
   return _vector_to_array(vector);
 };
 
+/// Returns the dictionary key of `value` in `categories`. `null` and `undefined` are kept.
+export const category_to_key = (value, categories, field_name) => {
+  if (value === null || value === undefined) return value;
+  const key = categories.indexOf(value);
+  if (key < 0) {
+    throw new Error(
+      "Unknown category " +
+        JSON.stringify(value) +
+        " for field " +
+        field_name +
+        ", expected one of " +
+        JSON.stringify(categories),
+    );
+  }
+  return key;
+};
+
 /// `elem` should be an element of a shallow-loaded array.
 /// `type` should be the type corresponding to the whole array (not a single element).
 /// (Loading `elem` manually for a known field type
@@ -151,6 +199,7 @@ export const load_elem = (elem, type) => {
     //       Otherwise would need `return elem ? JSON.parse(elem) : null`;
     return JSON.parse(elem);
   }
+  if (_is_int64(type)) return _int64_to_number(elem);
 
   const children = type.children;
   if (!children) return elem;
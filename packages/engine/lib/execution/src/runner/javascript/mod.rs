@@ -19,8 +19,10 @@ pub(crate) mod error;
 mod embedded;
 mod modules;
 mod reporting;
+mod resolution;
 mod run;
 mod runner;
+mod snapshot;
 mod task;
 mod thread_local_runner;
 mod utils;
@@ -120,6 +122,17 @@ impl<'s> JsPackage<'s> {
     }
 }
 
+/// Initializes V8, which can only be done once per process.
+#[cfg(test)]
+fn initialize_v8() {
+    static INITIALIZE: std::sync::Once = std::sync::Once::new();
+    INITIALIZE.call_once(|| {
+        let platform = v8::new_default_platform(0, false).make_shared();
+        v8::V8::initialize_platform(platform);
+        v8::V8::initialize();
+    });
+}
+
 fn read_file(path: &str) -> JavaScriptResult<String> {
     fs::read_to_string(path).map_err(|err| JavaScriptError::IO(path.into(), err))
 }
@@ -1,12 +1,51 @@
-use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use lazy_static::lazy_static;
+use v8::MapFnTo;
 
 use super::{
     error::JavaScriptResult,
     read_file,
-    utils::{exception_as_error, new_js_string},
+    resolution::{ModuleFormat, Resolver},
+    utils::{call_js_function, exception_as_error, new_js_string},
+    Function,
 };
 use crate::runner::JavaScriptError;
 
+/// Global function called by `require` in CommonJS modules with the specifier and the path of the
+/// requiring module.
+const REQUIRE_FUNCTION: &str = "__hash_require";
+
+/// Global function which imports the source code of a behavior written as ES module and returns
+/// its namespace.
+const IMPORT_BEHAVIOR_FUNCTION: &str = "__hash_import_behavior";
+
+lazy_static! {
+    /// Native functions which are referenced from the heap. They have to be registered when
+    /// creating a startup snapshot and every isolate created from it.
+    pub(in crate::runner::javascript) static ref EXTERNAL_REFERENCES: v8::ExternalReferences =
+        v8::ExternalReferences::new(&[
+            v8::ExternalReference {
+                function: require_callback.map_fn_to(),
+            },
+            v8::ExternalReference {
+                function: import_behavior_callback.map_fn_to(),
+            },
+        ]);
+}
+
+/// A CommonJS module and the names it exports, determined after running it.
+struct CommonJsModule {
+    /// The `module` object, whose `exports` are exported.
+    module: v8::Global<v8::Object>,
+    export_names: Vec<String>,
+}
+
 /// Caches modules to avoid evaluating them twice which is against the [JavaScript
 /// Specifications].
 ///
@@ -15,109 +54,477 @@ use crate::runner::JavaScriptError;
 ///
 /// [JavaScript Specifications](https://tc39.es/ecma262/#sec-hostresolveimportedmodule)
 pub(in crate::runner::javascript) struct ModuleMap {
+    /// Directory the paths of the runner's own modules are relative to.
+    engine_dir: PathBuf,
+    resolver: Resolver,
     modules_by_path: HashMap<String, v8::Global<v8::Module>>,
+    /// Used to resolve imports relative to the importing module.
+    paths_by_module: HashMap<v8::Global<v8::Module>, String>,
+    /// CommonJS and JSON modules by path. They are registered before running the code of the
+    /// module, so cyclic `require`s receive the exports populated so far like in Node.js.
+    commonjs_modules: HashMap<String, CommonJsModule>,
 }
 
 impl ModuleMap {
-    pub(in crate::runner::javascript) fn new() -> Self {
+    /// Creates a module map resolving packages against `node_modules`.
+    ///
+    /// The runner's own modules are read relative to the working directory of the engine.
+    pub(in crate::runner::javascript) fn new(node_modules: Option<PathBuf>) -> Self {
+        Self::with_engine_dir(PathBuf::from("."), node_modules)
+    }
+
+    /// Creates a module map reading the runner's own modules relative to `engine_dir` instead of
+    /// the working directory.
+    pub(in crate::runner::javascript) fn with_engine_dir(
+        engine_dir: PathBuf,
+        node_modules: Option<PathBuf>,
+    ) -> Self {
         Self {
+            engine_dir,
+            resolver: Resolver::new(node_modules),
             modules_by_path: HashMap::new(),
+            paths_by_module: HashMap::new(),
+            commonjs_modules: HashMap::new(),
         }
     }
+
+    /// Adds the cached modules to the data of `context`, so they can be restored with
+    /// [`from_snapshot()`](Self::from_snapshot) in contexts created from a startup snapshot.
+    ///
+    /// Only ES modules are supported, which is all the runner imports itself.
+    pub(in crate::runner::javascript) fn add_to_snapshot(
+        &self,
+        scope: &mut v8::HandleScope<'_>,
+        context: v8::Local<'_, v8::Context>,
+    ) {
+        let paths: Vec<_> = self.modules_by_path.keys().collect();
+        let js_paths: Vec<v8::Local<'_, v8::Value>> = paths
+            .iter()
+            .map(|path| new_js_string(scope, path).into())
+            .collect();
+        let js_paths = v8::Array::new_with_elements(scope, &js_paths);
+        scope.add_context_data(context, js_paths);
+        for path in paths {
+            let module = v8::Local::new(scope, &self.modules_by_path[path]);
+            scope.add_context_data(context, module);
+        }
+    }
+
+    /// Restores the modules added by [`add_to_snapshot()`](Self::add_to_snapshot) in a context
+    /// created from a startup snapshot.
+    pub(in crate::runner::javascript) fn from_snapshot(
+        scope: &mut v8::HandleScope<'_>,
+        node_modules: Option<PathBuf>,
+    ) -> JavaScriptResult<Self> {
+        let missing_data =
+            || JavaScriptError::V8("Startup snapshot is missing modules".to_string());
+
+        let mut module_map = Self::new(node_modules);
+        let paths = scope
+            .get_context_data_from_snapshot_once::<v8::Value>(0)
+            .and_then(|paths| v8::Local::<v8::Array>::try_from(paths).ok())
+            .ok_or_else(missing_data)?;
+        for index in 0..paths.length() {
+            let path = paths
+                .get_index(scope, index)
+                .ok_or_else(missing_data)?
+                .to_rust_string_lossy(scope);
+            let module = scope
+                .get_context_data_from_snapshot_once::<v8::Module>(index as usize + 1)
+                .ok_or_else(missing_data)?;
+            let module = v8::Global::new(scope, module);
+            module_map
+                .modules_by_path
+                .insert(path.clone(), module.clone());
+            module_map.paths_by_module.insert(module, path);
+        }
+        Ok(module_map)
+    }
+}
+
+/// Returns the path of the file at `path`, which is relative to the working directory of the engine
+/// for the runner's own files.
+pub(in crate::runner::javascript) fn engine_file(
+    scope: &mut v8::HandleScope<'_>,
+    path: &str,
+) -> String {
+    module_map(scope)
+        .borrow()
+        .engine_dir
+        .join(path)
+        .to_string_lossy()
+        .into_owned()
 }
 
+/// Returns `true` if the module at `path` was already imported.
+pub(in crate::runner::javascript) fn is_imported(
+    scope: &mut v8::HandleScope<'_>,
+    path: &str,
+) -> bool {
+    module_map(scope)
+        .borrow()
+        .modules_by_path
+        .contains_key(path)
+}
+
+fn module_map(scope: &mut v8::HandleScope<'_>) -> Rc<RefCell<ModuleMap>> {
+    scope
+        .get_slot::<Rc<RefCell<ModuleMap>>>()
+        .expect("ModuleMap is not present in isolate slots")
+        .clone()
+}
+
+fn throw_error(scope: &mut v8::HandleScope<'_>, message: &str) {
+    let message = new_js_string(scope, message);
+    let exception = v8::Exception::error(scope, message);
+    scope.throw_exception(exception);
+}
+
+fn script_origin<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    path: &str,
+    line_offset: i32,
+    is_module: bool,
+) -> v8::ScriptOrigin<'s> {
+    let js_path = new_js_string(scope, path);
+    let source_map_url = v8::undefined(scope);
+    v8::ScriptOrigin::new(
+        scope,
+        js_path.into(),
+        line_offset,
+        0,
+        false,
+        // Unique identifier for scripts, source: https://chromedevtools.github.io/devtools-protocol/v8/Runtime/#type-ScriptId
+        0,
+        source_map_url.into(),
+        false,
+        false,
+        is_module,
+    )
+}
+
+/// Imports the module at `path`, which is relative to the working directory of the engine.
 pub(in crate::runner::javascript) fn import_module<'s>(
     scope: &mut v8::HandleScope<'s>,
     path: &str,
 ) -> JavaScriptResult<v8::Local<'s, v8::Module>> {
-    let module_map = scope
-        .get_slot::<Rc<RefCell<ModuleMap>>>()
-        .expect("ModuleMap is not present in isolate slots")
-        .clone();
+    let module = load_module(scope, path)?;
+    instantiate_and_evaluate(scope, path, module)?;
+    Ok(module)
+}
+
+/// Compiles the module at `path` without instantiating it. CommonJS and JSON files are run and
+/// wrapped into a synthetic module.
+///
+/// Modules are cached before they are instantiated, so modules importing each other are only
+/// loaded once.
+fn load_module<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    path: &str,
+) -> JavaScriptResult<v8::Local<'s, v8::Module>> {
+    let module_map = module_map(scope);
 
     if let Some(module) = module_map.borrow().modules_by_path.get(path) {
         return Ok(v8::Local::new(scope, module));
     }
 
-    let source_code = read_file(path).map_err(|err| {
+    let source_code = read_file(&engine_file(scope, path)).map_err(|err| {
         JavaScriptError::AccessJavascriptImport(path.to_string(), err.to_string())
     })?;
-    let js_source_code = new_js_string(scope, &source_code);
-    let js_path = new_js_string(scope, path);
-    let source_map_url = v8::undefined(scope);
-    let source = v8::script_compiler::Source::new(
-        js_source_code,
-        Some(&v8::ScriptOrigin::new(
-            scope,
-            js_path.into(),
-            0,
-            0,
-            false,
-            // Unique identifier for scripts, source: https://chromedevtools.github.io/devtools-protocol/v8/Runtime/#type-ScriptId
-            0,
-            source_map_url.into(),
-            false,
-            false,
-            true,
-        )),
-    );
+    let format = module_map.borrow().resolver.format(path);
+    let module = match format {
+        ModuleFormat::EcmaScript => compile_module(scope, path, &source_code)?,
+        ModuleFormat::CommonJs | ModuleFormat::Json => {
+            create_commonjs_module(scope, path, &source_code, format)?
+        }
+    };
+
+    let global_module = v8::Global::new(scope, module);
+    let mut module_map = module_map.borrow_mut();
+    module_map
+        .modules_by_path
+        .insert(path.to_string(), global_module.clone());
+    module_map
+        .paths_by_module
+        .insert(global_module, path.to_string());
+
+    Ok(module)
+}
+
+fn compile_module<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    path: &str,
+    source_code: &str,
+) -> JavaScriptResult<v8::Local<'s, v8::Module>> {
+    let js_source_code = new_js_string(scope, source_code);
+    let origin = script_origin(scope, path, 0, true);
+    let source = v8::script_compiler::Source::new(js_source_code, Some(&origin));
     let mut try_catch_scope = v8::TryCatch::new(scope);
-    let module = v8::script_compiler::compile_module(&mut try_catch_scope, source)
+    v8::script_compiler::compile_module(&mut try_catch_scope, source)
         .ok_or_else(|| exception_as_error(&mut try_catch_scope))
-        .map_err(|err| JavaScriptError::Eval(path.to_string(), format!("Compile error: {err}")))?;
+        .map_err(|err| JavaScriptError::Eval(path.to_string(), format!("Compile error: {err}")))
+}
 
-    module
-        .instantiate_module(&mut try_catch_scope, module_resolve_callback)
-        .ok_or_else(|| exception_as_error(&mut try_catch_scope))
-        .map_err(|err| {
-            JavaScriptError::PackageImport(
-                path.to_string(),
-                format!("Could not instantiate code for package: {err}"),
-            )
-        })?;
+fn instantiate_and_evaluate(
+    scope: &mut v8::HandleScope<'_>,
+    path: &str,
+    module: v8::Local<'_, v8::Module>,
+) -> JavaScriptResult<()> {
+    let mut try_catch_scope = v8::TryCatch::new(scope);
 
-    if module.get_status() != v8::ModuleStatus::Instantiated {
-        return Err(JavaScriptError::PackageImport(
-            path.to_string(),
-            "Could not instantiate code for package".to_string(),
-        ));
+    if module.get_status() == v8::ModuleStatus::Uninstantiated {
+        module
+            .instantiate_module(&mut try_catch_scope, module_resolve_callback)
+            .ok_or_else(|| exception_as_error(&mut try_catch_scope))
+            .map_err(|err| {
+                JavaScriptError::PackageImport(
+                    path.to_string(),
+                    format!("Could not instantiate code for package: {err}"),
+                )
+            })?;
     }
 
-    module
-        .evaluate(&mut try_catch_scope)
-        .ok_or_else(|| exception_as_error(&mut try_catch_scope))
-        .map_err(|err| {
-            JavaScriptError::PackageImport(
-                path.to_string(),
-                format!("Could not evaluate code for package: {err}"),
-            )
-        })?;
+    if module.get_status() == v8::ModuleStatus::Instantiated {
+        module
+            .evaluate(&mut try_catch_scope)
+            .ok_or_else(|| exception_as_error(&mut try_catch_scope))
+            .map_err(|err| {
+                JavaScriptError::PackageImport(
+                    path.to_string(),
+                    format!("Could not evaluate code for package: {err}"),
+                )
+            })?;
+    }
 
     // `v8::Module::evaluate` can return `Some` even though the evaluation didn't
-    // succeed
-    if module.get_status() != v8::ModuleStatus::Evaluated {
-        let exception = module.get_exception();
-        let exception_string = exception
-            .to_string(&mut try_catch_scope)
-            .unwrap()
-            .to_rust_string_lossy(&mut try_catch_scope);
+    // succeed. A module is still evaluating if it's required by a module it imports.
+    match module.get_status() {
+        v8::ModuleStatus::Evaluated | v8::ModuleStatus::Evaluating => Ok(()),
+        v8::ModuleStatus::Errored => {
+            let exception = module.get_exception();
+            let exception_string = exception
+                .to_string(&mut try_catch_scope)
+                .unwrap()
+                .to_rust_string_lossy(&mut try_catch_scope);
 
-        return Err(JavaScriptError::PackageImport(
+            Err(JavaScriptError::PackageImport(
+                path.to_string(),
+                format!("Could not evaluate code for package: {exception_string}"),
+            ))
+        }
+        _ => Err(JavaScriptError::PackageImport(
             path.to_string(),
-            format!("Could not evaluate code for package: {exception_string}"),
-        ));
+            "Could not instantiate code for package".to_string(),
+        )),
     }
+}
+
+/// Runs a CommonJS module, or parses a JSON file, and returns a synthetic module exporting
+/// `module.exports` as default export and each of its properties as named export.
+fn create_commonjs_module<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    path: &str,
+    source_code: &str,
+    format: ModuleFormat,
+) -> JavaScriptResult<v8::Local<'s, v8::Module>> {
+    let module_map = module_map(scope);
+    let module_object = v8::Object::new(scope);
+    let exports_key = new_js_string(scope, "exports");
 
-    module_map.borrow_mut().modules_by_path.insert(
-        path.to_string(),
-        v8::Global::new(&mut try_catch_scope, module),
+    let exports: v8::Local<'_, v8::Value> = if format == ModuleFormat::Json {
+        let json = new_js_string(scope, source_code);
+        let mut try_catch_scope = v8::TryCatch::new(scope);
+        v8::json::parse(&mut try_catch_scope, json)
+            .ok_or_else(|| exception_as_error(&mut try_catch_scope))
+            .map_err(|err| {
+                JavaScriptError::Eval(path.to_string(), format!("Invalid JSON: {err}"))
+            })?
+    } else {
+        v8::Object::new(scope).into()
+    };
+    module_object.set(scope, exports_key.into(), exports);
+    let global_module_object = v8::Global::new(scope, module_object);
+    module_map
+        .borrow_mut()
+        .commonjs_modules
+        .insert(path.to_string(), CommonJsModule {
+            module: global_module_object,
+            export_names: Vec::new(),
+        });
+
+    if format == ModuleFormat::CommonJs {
+        run_commonjs(scope, path, source_code, module_object, exports)?;
+    }
+
+    // `module.exports` may have been replaced by the module.
+    let exports = module_object
+        .get(scope, exports_key.into())
+        .ok_or_else(|| JavaScriptError::V8(format!("Could not get the exports of {path}")))?;
+    let export_names = export_names(scope, exports)?;
+    if let Some(module) = module_map.borrow_mut().commonjs_modules.get_mut(path) {
+        module.export_names = export_names.clone();
+    }
+
+    let js_path = new_js_string(scope, path);
+    let js_export_names: Vec<_> = std::iter::once("default")
+        .chain(export_names.iter().map(String::as_str))
+        .map(|name| new_js_string(scope, name))
+        .collect();
+    Ok(v8::Module::create_synthetic_module(
+        scope,
+        js_path,
+        &js_export_names,
+        evaluate_commonjs_module,
+    ))
+}
+
+/// Runs the source code of a CommonJS module with `exports`, `require`, `module`, `__filename` and
+/// `__dirname` in scope.
+fn run_commonjs<'s>(
+    scope: &mut v8::HandleScope<'s>,
+    path: &str,
+    source_code: &str,
+    module_object: v8::Local<'s, v8::Object>,
+    exports: v8::Local<'s, v8::Value>,
+) -> JavaScriptResult<()> {
+    // The code is wrapped in a second function, so `require` can pass the path of the module
+    // without it being visible to the module. The wrapper starts one line before the code.
+    let wrapped_source_code = format!(
+        "(function (exports, module, __filename, __dirname) {{ (function (exports, require, \
+         module, __filename, __dirname) {{\n{source_code}\n}}).call(exports, exports, (specifier) \
+         => {REQUIRE_FUNCTION}(specifier, __filename), module, __filename, __dirname); }})"
     );
+    let js_source_code = new_js_string(scope, wrapped_source_code);
+    let origin = script_origin(scope, path, -1, false);
 
-    Ok(module)
+    let wrapper: Function<'s> = {
+        let mut try_catch_scope = v8::TryCatch::new(scope);
+        v8::Script::compile(&mut try_catch_scope, js_source_code, Some(&origin))
+            .and_then(|script| script.run(&mut try_catch_scope))
+            .ok_or_else(|| exception_as_error(&mut try_catch_scope))
+            .map_err(|err| {
+                JavaScriptError::Eval(path.to_string(), format!("Compile error: {err}"))
+            })?
+            .try_into()
+            .map_err(|err| {
+                JavaScriptError::V8(format!("Could not wrap CommonJS module {path}: {err}"))
+            })?
+    };
+
+    let directory = Path::new(path)
+        .parent()
+        .map(|directory| directory.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let filename = new_js_string(scope, path);
+    let dirname = new_js_string(scope, directory);
+    let this = v8::undefined(scope);
+    call_js_function(scope, wrapper, this.into(), &[
+        exports,
+        module_object.into(),
+        filename.into(),
+        dirname.into(),
+    ])
+    .map_err(|err| {
+        JavaScriptError::PackageImport(
+            path.to_string(),
+            format!("Could not evaluate code for package: {err}"),
+        )
+    })?;
+    Ok(())
+}
+
+/// Returns the names of the properties of `exports` which can be imported by name.
+fn export_names(
+    scope: &mut v8::HandleScope<'_>,
+    exports: v8::Local<'_, v8::Value>,
+) -> JavaScriptResult<Vec<String>> {
+    if !exports.is_object() {
+        return Ok(Vec::new());
+    }
+
+    let global = scope.get_current_context().global(scope);
+    let object_str = new_js_string(scope, "Object");
+    let keys_str = new_js_string(scope, "keys");
+    let keys: Function<'_> = global
+        .get(scope, object_str.into())
+        .and_then(|object| object.to_object(scope))
+        .and_then(|object| object.get(scope, keys_str.into()))
+        .and_then(|keys| keys.try_into().ok())
+        .ok_or_else(|| JavaScriptError::V8("Could not get `Object.keys`".to_string()))?;
+    let this = v8::undefined(scope);
+    let keys: v8::Local<'_, v8::Array> = call_js_function(scope, keys, this.into(), &[exports])?
+        .try_into()
+        .map_err(|err| JavaScriptError::V8(format!("Could not get export names: {err}")))?;
+
+    let mut names = Vec::with_capacity(keys.length() as usize);
+    for index in 0..keys.length() {
+        if let Some(key) = keys.get_index(scope, index) {
+            let name = key.to_rust_string_lossy(scope);
+            if name != "default" && name != "__esModule" {
+                names.push(name);
+            }
+        }
+    }
+    Ok(names)
 }
 
-/// Callback called for each `import ...` in JS files. It reads the file, compiles the source
-/// code, and evaluates it. Modules are only compiled and evaluated once.
+/// Sets the exports of a synthetic module created by [`create_commonjs_module`].
+fn evaluate_commonjs_module<'s>(
+    context: v8::Local<'s, v8::Context>,
+    module: v8::Local<'s, v8::Module>,
+) -> Option<v8::Local<'s, v8::Value>> {
+    // SAFETY: we are in a callback
+    let mut scope = unsafe { v8::CallbackScope::new(context) };
+    let module_map = module_map(&mut scope);
+    let global_module = v8::Global::new(&mut scope, module);
+    let (module_object, export_names) = {
+        let module_map = module_map.borrow();
+        let path = module_map.paths_by_module.get(&global_module)?;
+        let commonjs_module = module_map.commonjs_modules.get(path)?;
+        (
+            v8::Local::new(&mut scope, &commonjs_module.module),
+            commonjs_module.export_names.clone(),
+        )
+    };
+
+    let exports_key = new_js_string(&mut scope, "exports");
+    let exports = module_object.get(&mut scope, exports_key.into())?;
+    // Modules transpiled from ES modules mark their exports with `__esModule`.
+    let default_export = match v8::Local::<v8::Object>::try_from(exports) {
+        Ok(exports_object) => {
+            let es_module_key = new_js_string(&mut scope, "__esModule");
+            let default_key = new_js_string(&mut scope, "default");
+            let is_es_module = exports_object
+                .get(&mut scope, es_module_key.into())
+                .map_or(false, |value| value.boolean_value(&mut scope));
+            if is_es_module {
+                exports_object.get(&mut scope, default_key.into())?
+            } else {
+                exports
+            }
+        }
+        Err(_) => exports,
+    };
+
+    let default_name = new_js_string(&mut scope, "default");
+    module.set_synthetic_module_export(&mut scope, default_name, default_export)?;
+    if let Ok(exports_object) = v8::Local::<v8::Object>::try_from(exports) {
+        for name in export_names {
+            let name = new_js_string(&mut scope, name);
+            let value = exports_object.get(&mut scope, name.into())?;
+            module.set_synthetic_module_export(&mut scope, name, value)?;
+        }
+    }
+
+    // Since top-level await is enabled, evaluation has to return a promise.
+    let resolver = v8::PromiseResolver::new(&mut scope)?;
+    let undefined = v8::undefined(&mut scope);
+    resolver.resolve(&mut scope, undefined.into());
+    Some(resolver.get_promise(&mut scope).into())
+}
+
+/// Callback called for each `import ...` in JS files. It resolves the specifier relative to the
+/// importing module and loads the module. Modules are only compiled and evaluated once.
 // Simple example without any caching: https://gist.github.com/surusek/4c05e4dcac6b82d18a1a28e6742fc23e
 // More elaborate example with caching and multiple types of imports: https://github.com/denoland/deno/blob/f7e7f548499eff8d2df0872d1340ddcdfa028c45/core/bindings.rs#L1344
 fn module_resolve_callback<'s>(
@@ -126,19 +533,258 @@ fn module_resolve_callback<'s>(
     specifier: v8::Local<'s, v8::String>,
     // Should be safe to ignore for now as an unsupported Javascript feature. https://v8.dev/features/import-assertions
     _import_assertions: v8::Local<'s, v8::FixedArray>,
-    // Not used for now, a description can be found at https://github.com/denoland/rusty_v8/blob/25dd770570b77dfaa5a27a7dcee6fa660781640f/src/isolate.rs#L109
-    _referrer: v8::Local<'s, v8::Module>,
+    // The module containing the `import`
+    referrer: v8::Local<'s, v8::Module>,
 ) -> Option<v8::Local<'s, v8::Module>> {
     // SAFETY: we are in a callback
     let mut scope = unsafe { v8::CallbackScope::new(context) };
     let specifier = specifier.to_rust_string_lossy(&mut scope);
+    let module_map = module_map(&mut scope);
+    let referrer = v8::Global::new(&mut scope, referrer);
 
-    match import_module(&mut scope, &specifier) {
+    let path = {
+        let module_map = module_map.borrow();
+        let referrer = module_map
+            .paths_by_module
+            .get(&referrer)
+            .map(String::as_str);
+        module_map.resolver.resolve(&specifier, referrer)
+    };
+    // Errors are thrown, so they are reported when instantiating the importing module.
+    match path.and_then(|path| load_module(&mut scope, &path)) {
         Ok(module) => Some(module),
         Err(err) => {
-            tracing::error!("Couldn't import {specifier}, {err}.");
-
+            throw_error(&mut scope, &format!("Couldn't import {specifier}: {err}"));
             None
         }
     }
 }
+
+/// Implements `require` for CommonJS modules: `__hash_require(specifier, referrer)`.
+///
+/// CommonJS and JSON modules return `module.exports`, ES modules return their namespace.
+fn require_callback(
+    scope: &mut v8::HandleScope<'_>,
+    args: v8::FunctionCallbackArguments<'_>,
+    mut return_value: v8::ReturnValue<'_>,
+) {
+    let specifier = args.get(0).to_rust_string_lossy(scope);
+    let referrer = args.get(1).to_rust_string_lossy(scope);
+
+    let module_map = module_map(scope);
+    let path = module_map
+        .borrow()
+        .resolver
+        .resolve(&specifier, Some(&referrer));
+    let exports = path.and_then(|path| {
+        // Modules which are still running are not imported again, so cyclic `require`s receive
+        // the exports populated so far.
+        if !module_map.borrow().commonjs_modules.contains_key(&path) {
+            let module = import_module(scope, &path)?;
+            if !module_map.borrow().commonjs_modules.contains_key(&path) {
+                return Ok(module.get_module_namespace());
+            }
+        }
+        let module_object =
+            v8::Local::new(scope, &module_map.borrow().commonjs_modules[&path].module);
+        let exports_key = new_js_string(scope, "exports");
+        module_object
+            .get(scope, exports_key.into())
+            .ok_or_else(|| JavaScriptError::V8(format!("Could not get the exports of {path}")))
+    });
+
+    match exports {
+        Ok(exports) => return_value.set(exports),
+        Err(err) => throw_error(scope, &format!("Couldn't require {specifier}: {err}")),
+    }
+}
+
+/// Implements `__hash_import_behavior(name, source_code)`, which evaluates the source code of a
+/// behavior as ES module and returns its namespace.
+///
+/// Behaviors aren't cached as they are only loaded once per experiment.
+fn import_behavior_callback(
+    scope: &mut v8::HandleScope<'_>,
+    args: v8::FunctionCallbackArguments<'_>,
+    mut return_value: v8::ReturnValue<'_>,
+) {
+    let name = args.get(0).to_rust_string_lossy(scope);
+    let source_code = args.get(1).to_rust_string_lossy(scope);
+
+    let module = match compile_module(scope, &name, &source_code) {
+        Ok(module) => module,
+        Err(err) => {
+            // Thrown as `SyntaxError`, so code which isn't a module can be told apart from modules
+            // failing to import.
+            let message = new_js_string(scope, &err.to_string());
+            let exception = v8::Exception::syntax_error(scope, message);
+            scope.throw_exception(exception);
+            return;
+        }
+    };
+    let namespace = {
+        let global_module = v8::Global::new(scope, module);
+        module_map(scope)
+            .borrow_mut()
+            .paths_by_module
+            .insert(global_module, name.clone());
+        instantiate_and_evaluate(scope, &name, module).map(|_| module.get_module_namespace())
+    };
+
+    match namespace {
+        Ok(namespace) => return_value.set(namespace),
+        Err(err) => throw_error(scope, &err.to_string()),
+    }
+}
+
+/// Makes the functions used by CommonJS modules and behaviors written as ES modules available
+/// globally.
+pub(in crate::runner::javascript) fn set_module_functions(
+    scope: &mut v8::HandleScope<'_>,
+) -> JavaScriptResult<()> {
+    let global = scope.get_current_context().global(scope);
+
+    let require = v8::Function::new(scope, require_callback)
+        .ok_or_else(|| JavaScriptError::V8(format!("Could not create {REQUIRE_FUNCTION}")))?;
+    let require_str = new_js_string(scope, REQUIRE_FUNCTION);
+    global.set(scope, require_str.into(), require.into());
+
+    let import_behavior = v8::Function::new(scope, import_behavior_callback).ok_or_else(|| {
+        JavaScriptError::V8(format!("Could not create {IMPORT_BEHAVIOR_FUNCTION}"))
+    })?;
+    let import_behavior_str = new_js_string(scope, IMPORT_BEHAVIOR_FUNCTION);
+    global.set(scope, import_behavior_str.into(), import_behavior.into());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::*;
+    use crate::runner::javascript::initialize_v8;
+
+    /// Writes `files` into a new temporary directory and returns its path.
+    fn write_files(files: &[(&str, &str)]) -> PathBuf {
+        let directory = std::env::temp_dir().join(format!("js-modules-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&directory).unwrap();
+        for (path, contents) in files {
+            let path = directory.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+        directory
+    }
+
+    /// Runs `code` as classic script in a new context with the module functions and returns the
+    /// result as string.
+    fn eval(node_modules: PathBuf, code: &str) -> String {
+        initialize_v8();
+        let mut isolate = v8::Isolate::new(
+            v8::Isolate::create_params().external_references(&**EXTERNAL_REFERENCES),
+        );
+        let mut handle_scope = v8::HandleScope::new(&mut isolate);
+        let context = v8::Context::new(&mut handle_scope);
+        let mut scope = v8::ContextScope::new(&mut handle_scope, context);
+        scope.set_slot(Rc::new(RefCell::new(ModuleMap::new(Some(node_modules)))));
+        set_module_functions(&mut scope).unwrap();
+
+        let code = new_js_string(&mut scope, code);
+        let mut try_catch_scope = v8::TryCatch::new(&mut scope);
+        v8::Script::compile(&mut try_catch_scope, code, None)
+            .and_then(|script| script.run(&mut try_catch_scope))
+            .ok_or_else(|| exception_as_error(&mut try_catch_scope))
+            .unwrap()
+            .to_rust_string_lossy(&mut try_catch_scope)
+    }
+
+    #[test]
+    fn import_commonjs_package() {
+        let directory = write_files(&[
+            ("node_modules/pkg/package.json", r#"{ "main": "index.js" }"#),
+            (
+                "node_modules/pkg/index.js",
+                r#"exports.early = true;
+                const helper = require("./helper");
+                exports.add = (a, b) => a + b;
+                exports.name = require("./name.json").name;
+                exports.sawEarly = helper.sawEarly;
+                exports.dirname = __dirname.endsWith("pkg");"#,
+            ),
+            (
+                "node_modules/pkg/helper.js",
+                r#"exports.sawEarly = require("./index.js").early === true;"#,
+            ),
+            ("node_modules/pkg/name.json", r#"{ "name": "pkg" }"#),
+        ]);
+
+        let result = eval(
+            directory.join("node_modules"),
+            r#"__hash_import_behavior(
+                "behavior.js",
+                `import pkg, { add, name, sawEarly, dirname } from "pkg";
+                export const result = [add(1, 2), name, sawEarly, dirname, pkg.add === add];`,
+            ).result.join()"#,
+        );
+        assert_eq!(result, "3,pkg,true,true,true");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn import_default_exports() {
+        let directory = write_files(&[
+            (
+                "node_modules/esm/package.json",
+                r#"{ "type": "module", "main": "index.js" }"#,
+            ),
+            (
+                "node_modules/esm/index.js",
+                "export default 42; export const double = (x) => x * 2;",
+            ),
+            (
+                "node_modules/transpiled/package.json",
+                r#"{ "main": "index.js" }"#,
+            ),
+            (
+                "node_modules/transpiled/index.js",
+                r#"exports.__esModule = true; exports.default = "transpiled";"#,
+            ),
+        ]);
+
+        let result = eval(
+            directory.join("node_modules"),
+            r#"__hash_import_behavior(
+                "behavior.js",
+                `import answer, { double } from "esm";
+                import transpiled from "transpiled";
+                export const result = [double(answer), transpiled];`,
+            ).result.join()"#,
+        );
+        assert_eq!(result, "84,transpiled");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    #[test]
+    fn import_errors() {
+        let directory = write_files(&[]);
+
+        let result = eval(
+            directory.join("node_modules"),
+            r#"const errors = [];
+            for (const code of ["export const = 1;", `import _ from "missing";`]) {
+                try {
+                    __hash_import_behavior("behavior.js", code);
+                } catch (error) {
+                    errors.push(error.name);
+                }
+            }
+            errors.join()"#,
+        );
+        assert_eq!(result, "SyntaxError,Error");
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,353 @@
+//! Resolution of import specifiers to files.
+//!
+//! The runner itself imports its modules by paths relative to the working directory of the engine.
+//! Bare specifiers (e.g. `"lodash"` or `"date-fns/addDays"`) are resolved against the
+//! `node_modules` folder of the project, following Node.js: a package's entry points are read from
+//! the `exports` or `main` field of its `package.json`, and files within packages import each other
+//! relative to their own location.
+
+use std::{
+    ffi::OsStr,
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+use serde_json::Value;
+
+use crate::runner::javascript::{JavaScriptError, JavaScriptResult};
+
+/// Conditions in the `exports` of a `package.json` which are supported, by priority.
+const EXPORT_CONDITIONS: [&str; 3] = ["import", "require", "default"];
+
+/// Extensions tried for specifiers without an extension outside of `exports`, in order.
+const EXTENSIONS: [&str; 4] = ["js", "mjs", "cjs", "json"];
+
+/// How the source code of a module is loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(in crate::runner::javascript) enum ModuleFormat {
+    EcmaScript,
+    /// Evaluated with `module`, `exports` and `require` in scope and exposed as a module with
+    /// `module.exports` as default export.
+    CommonJs,
+    /// Exposed as a module with the parsed value as default export.
+    Json,
+}
+
+pub(in crate::runner::javascript) struct Resolver {
+    node_modules: Option<PathBuf>,
+}
+
+impl Resolver {
+    pub(in crate::runner::javascript) fn new(node_modules: Option<PathBuf>) -> Self {
+        Self { node_modules }
+    }
+
+    /// Returns `true` if `path` is inside of the `node_modules` folder.
+    fn is_package_file(&self, path: &str) -> bool {
+        self.node_modules.as_ref().map_or(false, |node_modules| {
+            Path::new(path).starts_with(node_modules)
+        })
+    }
+
+    /// Resolves `specifier`, imported by the module at `referrer`, to the path of a module.
+    pub(in crate::runner::javascript) fn resolve(
+        &self,
+        specifier: &str,
+        referrer: Option<&str>,
+    ) -> JavaScriptResult<String> {
+        let is_path = specifier.starts_with("./")
+            || specifier.starts_with("../")
+            || specifier.starts_with('/');
+        if is_path {
+            return match referrer.filter(|referrer| self.is_package_file(referrer)) {
+                Some(referrer) => {
+                    let directory = Path::new(referrer)
+                        .parent()
+                        .unwrap_or_else(|| Path::new(""));
+                    let path = normalize(&directory.join(specifier));
+                    resolve_file(&path)
+                        .map(path_to_string)
+                        .ok_or_else(|| access_error(specifier, format!("Could not find {path:?}")))
+                }
+                None => Ok(specifier.to_string()),
+            };
+        }
+
+        let node_modules = self.node_modules.as_ref().ok_or_else(|| {
+            access_error(
+                specifier,
+                "Packages are imported from the `node_modules` folder of the project, which \
+                 doesn't exist",
+            )
+        })?;
+        if specifier.starts_with("node:") {
+            return Err(access_error(
+                specifier,
+                "Built-in modules of Node.js are not available",
+            ));
+        }
+        let (name, subpath) = split_package_specifier(specifier)
+            .ok_or_else(|| access_error(specifier, "Invalid package name"))?;
+        // Packages may have their own `node_modules` with dependencies in versions different from
+        // the ones of the project.
+        let package_dir = referrer
+            .filter(|referrer| self.is_package_file(referrer))
+            .into_iter()
+            .flat_map(|referrer| Path::new(referrer).ancestors().skip(1))
+            .take_while(|directory| directory.starts_with(node_modules))
+            .filter(|directory| directory.file_name() != Some(OsStr::new("node_modules")))
+            .map(|directory| directory.join("node_modules").join(name))
+            .chain(std::iter::once(node_modules.join(name)))
+            .find(|package_dir| package_dir.is_dir())
+            .ok_or_else(|| {
+                access_error(
+                    specifier,
+                    format!("Package `{name}` is not installed in {node_modules:?}"),
+                )
+            })?;
+
+        let path = match read_package_json(&package_dir)?
+            .as_ref()
+            .and_then(|package_json| package_json.get("exports"))
+        {
+            Some(exports) => {
+                let target = resolve_exports(exports, &subpath).ok_or_else(|| {
+                    access_error(
+                        specifier,
+                        format!("Package `{name}` doesn't export `{subpath}`"),
+                    )
+                })?;
+                let path = normalize(&package_dir.join(target));
+                path.is_file().then_some(path)
+            }
+            None if subpath == "." => resolve_directory(&package_dir),
+            None => resolve_file(&package_dir.join(&subpath[2..])),
+        };
+        path.map(path_to_string).ok_or_else(|| {
+            access_error(
+                specifier,
+                format!("Could not find `{subpath}` in package `{name}` at {package_dir:?}"),
+            )
+        })
+    }
+
+    /// Returns how the module at the resolved `path` has to be loaded.
+    ///
+    /// Files of the runner are always ES modules. Within packages, the format is determined by the
+    /// extension and the `type` field of the closest `package.json` like in Node.js.
+    pub(in crate::runner::javascript) fn format(&self, path: &str) -> ModuleFormat {
+        if !self.is_package_file(path) {
+            return ModuleFormat::EcmaScript;
+        }
+        let path = Path::new(path);
+        match path.extension().and_then(OsStr::to_str) {
+            Some("mjs") => ModuleFormat::EcmaScript,
+            Some("cjs") => ModuleFormat::CommonJs,
+            Some("json") => ModuleFormat::Json,
+            _ => {
+                let package_type = path
+                    .ancestors()
+                    .skip(1)
+                    .take_while(|directory| {
+                        directory.file_name() != Some(OsStr::new("node_modules"))
+                    })
+                    .find_map(|directory| read_package_json(directory).ok().flatten())
+                    .and_then(|package_json| package_json.get("type").cloned());
+                match package_type {
+                    Some(Value::String(package_type)) if package_type == "module" => {
+                        ModuleFormat::EcmaScript
+                    }
+                    _ => ModuleFormat::CommonJs,
+                }
+            }
+        }
+    }
+}
+
+fn access_error(specifier: &str, reason: impl Into<String>) -> JavaScriptError {
+    JavaScriptError::AccessJavascriptImport(specifier.to_string(), reason.into())
+}
+
+fn path_to_string(path: PathBuf) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Removes `.` and `..` components without resolving symbolic links, so a module has the same path
+/// independent of how it was imported.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Splits a bare specifier into the package name and the subpath within the package, e.g.
+/// `"@scope/name/sub"` into `("@scope/name", "./sub")` and `"name"` into `("name", ".")`.
+fn split_package_specifier(specifier: &str) -> Option<(&str, String)> {
+    let name_len = if specifier.starts_with('@') {
+        let scope_len = specifier.find('/')?;
+        specifier[scope_len + 1..]
+            .find('/')
+            .map_or(specifier.len(), |len| scope_len + 1 + len)
+    } else {
+        specifier.find('/').unwrap_or(specifier.len())
+    };
+    let (name, subpath) = specifier.split_at(name_len);
+    if name.is_empty() || name.ends_with('/') {
+        return None;
+    }
+    Some((name, format!(".{subpath}")))
+}
+
+fn read_package_json(directory: &Path) -> JavaScriptResult<Option<Value>> {
+    let path = directory.join("package.json");
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let package_json = fs::read_to_string(&path)
+        .map_err(|err| JavaScriptError::IO(path_to_string(path.clone()), err))?;
+    serde_json::from_str(&package_json)
+        .map(Some)
+        .map_err(|err| {
+            JavaScriptError::AccessJavascriptImport(path_to_string(path), err.to_string())
+        })
+}
+
+/// Returns `path` if it is a file, otherwise the first file with one of the [`EXTENSIONS`]
+/// appended.
+fn file_with_extension(path: &Path) -> Option<PathBuf> {
+    if path.is_file() {
+        return Some(path.to_path_buf());
+    }
+    EXTENSIONS.into_iter().find_map(|extension| {
+        let mut candidate = path.as_os_str().to_owned();
+        candidate.push(".");
+        candidate.push(extension);
+        let candidate = PathBuf::from(candidate);
+        candidate.is_file().then_some(candidate)
+    })
+}
+
+/// Resolves a file path as in CommonJS, i.e. with optional extension or as a directory.
+fn resolve_file(path: &Path) -> Option<PathBuf> {
+    file_with_extension(path).or_else(|| path.is_dir().then(|| resolve_directory(path)).flatten())
+}
+
+/// Resolves a directory to the file in the `main` field of its `package.json` or to its index.
+fn resolve_directory(directory: &Path) -> Option<PathBuf> {
+    let main = read_package_json(directory)
+        .ok()
+        .flatten()
+        .and_then(|package_json| package_json.get("main")?.as_str().map(str::to_owned))
+        .and_then(|main| {
+            let main = normalize(&directory.join(main));
+            file_with_extension(&main).or_else(|| file_with_extension(&main.join("index")))
+        });
+    main.or_else(|| file_with_extension(&directory.join("index")))
+}
+
+/// Looks up `subpath` (`"."` or `"./<path>"`) in the `exports` of a `package.json` and returns the
+/// target relative to the package.
+fn resolve_exports(exports: &Value, subpath: &str) -> Option<String> {
+    let subpaths = match exports {
+        Value::Object(map) if map.keys().any(|key| key.starts_with('.')) => map,
+        // `exports` only declares the main entry point.
+        _ => {
+            return if subpath == "." {
+                resolve_export_target(exports, None)
+            } else {
+                None
+            };
+        }
+    };
+    if let Some(target) = subpaths.get(subpath) {
+        return resolve_export_target(target, None);
+    }
+    // Subpath patterns, e.g. `"./features/*": "./src/features/*.js"`. The longest prefix wins.
+    subpaths
+        .iter()
+        .filter_map(|(key, target)| {
+            let (prefix, suffix) = key.split_once('*')?;
+            let matched = subpath.strip_prefix(prefix)?.strip_suffix(suffix)?;
+            Some((prefix.len(), target, matched))
+        })
+        .max_by_key(|(prefix_len, ..)| *prefix_len)
+        .and_then(|(_, target, matched)| resolve_export_target(target, Some(matched)))
+}
+
+fn resolve_export_target(target: &Value, matched: Option<&str>) -> Option<String> {
+    match target {
+        Value::String(target) => Some(match matched {
+            Some(matched) => target.replace('*', matched),
+            None => target.clone(),
+        }),
+        Value::Array(targets) => targets
+            .iter()
+            .find_map(|target| resolve_export_target(target, matched)),
+        Value::Object(conditions) => EXPORT_CONDITIONS
+            .into_iter()
+            .find_map(|condition| resolve_export_target(conditions.get(condition)?, matched)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_split_package_specifier() {
+        assert_eq!(
+            split_package_specifier("lodash"),
+            Some(("lodash", ".".to_owned()))
+        );
+        assert_eq!(
+            split_package_specifier("date-fns/addDays"),
+            Some(("date-fns", "./addDays".to_owned()))
+        );
+        assert_eq!(
+            split_package_specifier("@turf/helpers/dist/js"),
+            Some(("@turf/helpers", "./dist/js".to_owned()))
+        );
+        assert_eq!(split_package_specifier("@turf"), None);
+    }
+
+    #[test]
+    fn test_resolve_exports() {
+        let exports = json!({
+            ".": { "require": "./index.cjs", "import": "./index.mjs" },
+            "./fp": [{ "browser": "./fp.browser.js" }, "./fp.js"],
+            "./features/*": "./src/features/*.js",
+            "./features/private/*": null,
+        });
+        assert_eq!(
+            resolve_exports(&exports, "."),
+            Some("./index.mjs".to_owned())
+        );
+        assert_eq!(
+            resolve_exports(&exports, "./fp"),
+            Some("./fp.js".to_owned())
+        );
+        assert_eq!(
+            resolve_exports(&exports, "./features/map"),
+            Some("./src/features/map.js".to_owned())
+        );
+        assert_eq!(resolve_exports(&exports, "./features/private/map"), None);
+        assert_eq!(resolve_exports(&exports, "./package.json"), None);
+
+        assert_eq!(
+            resolve_exports(&json!("./main.js"), "."),
+            Some("./main.js".to_owned())
+        );
+        assert_eq!(resolve_exports(&json!("./main.js"), "./other"), None);
+    }
+}
@@ -8,7 +8,9 @@ use crate::{
     runner::{
         comms::{ExperimentInitRunnerMsg, InboundToRunnerMsgPayload, OutboundFromRunnerMsg},
         javascript::{
-            modules::ModuleMap, near_heap_limit_callback, thread_local_runner::ThreadLocalRunner,
+            modules::{ModuleMap, EXTERNAL_REFERENCES},
+            near_heap_limit_callback, snapshot,
+            thread_local_runner::ThreadLocalRunner,
             MB,
         },
         JavaScriptError,
@@ -41,10 +43,20 @@ pub(in crate::runner::javascript) fn run_experiment(
             // 0 makes V8 use its default value
             let js_runner_max_heap_size = init_msg.runner_config.js_runner_max_heap_size.unwrap_or(0);
 
-            let create_params = v8::Isolate::create_params().heap_limits(
-                js_runner_initial_heap_constraint * MB,
-                js_runner_max_heap_size * MB,
-            );
+            let mut create_params = v8::Isolate::create_params()
+                .heap_limits(
+                    js_runner_initial_heap_constraint * MB,
+                    js_runner_max_heap_size * MB,
+                )
+                .external_references(&**EXTERNAL_REFERENCES);
+            let startup_snapshot = if init_msg.runner_config.js_runner_disable_snapshot {
+                None
+            } else {
+                snapshot::startup_snapshot()
+            };
+            if let Some(startup_snapshot) = startup_snapshot {
+                create_params = create_params.snapshot_blob(startup_snapshot);
+            }
 
             let mut isolate = v8::Isolate::new(create_params);
 
@@ -63,7 +75,12 @@ pub(in crate::runner::javascript) fn run_experiment(
             // `scope.get_slot::<ModuleMap>().import_module(&mut scope)`
             // `scope` would be borrowed twice which is not possible. By using `Rc<RefCell>` we can
             // clone the `Rc` ending the first borrow.
-            let module_map = Rc::new(RefCell::new(ModuleMap::new()));
+            let node_modules = init_msg.runner_config.js_node_modules.clone();
+            let module_map = match startup_snapshot {
+                Some(_) => ModuleMap::from_snapshot(&mut context_scope, node_modules)?,
+                None => ModuleMap::new(node_modules),
+            };
+            let module_map = Rc::new(RefCell::new(module_map));
 
             context_scope.set_slot(module_map);
 
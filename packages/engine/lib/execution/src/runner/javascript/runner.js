@@ -1,10 +1,14 @@
 // noinspection BadExpressionStatementJS
 import { arrow } from "./lib/execution/src/runner/javascript/apache-arrow-bundle.js";
-import { Batches } from "./lib/execution/src/runner/javascript/batch.js";
+import {
+  Batches,
+  load_marked_schema_bytes,
+} from "./lib/execution/src/runner/javascript/batch.js";
 import {
   ExperimentContext,
   SimInitContext,
   gen_sim_ctx,
+  load_raster,
 } from "./lib/execution/src/runner/javascript/context.js";
 import { gen_group_state } from "./lib/execution/src/runner/javascript/state.js";
 
@@ -228,11 +232,15 @@ Stack: ${JSON.stringify(e.stack)}
 /// Invalidates existing `GroupContext` and `AgentContext` objects.
 /// (NB: Any `GroupContext` or `AgentContext` objects must be forgotten at
 /// the end of a `run_task` call.)
+/// `globals` is only passed if the globals changed at the beginning of the step.
+/// `raster_batch` is only passed if raster layers are declared in the globals.
 export function ctx_batch_sync(
   sim_id,
   ctx_batch,
   state_group_start_idxs,
   current_step,
+  globals,
+  raster_batch,
 ) {
   const sim = this.sims[sim_id];
 
@@ -240,15 +248,28 @@ export function ctx_batch_sync(
   ctx_batch.load_missing_cols(sim.schema.ctx, sim.context_loaders);
 
   sim.ctx.set_batch(ctx_batch, state_group_start_idxs, current_step);
+  if (globals !== undefined) {
+    sim.ctx.set_globals(JSON.parse(globals));
+  }
+
+  // The raster layers never move, so they only have to be loaded on the first sync.
+  if (raster_batch !== undefined && !sim.schema.raster) {
+    sim.schema.raster = load_schema(load_marked_schema_bytes(raster_batch.mem));
+    raster_batch = this.batches.sync(raster_batch, sim.schema.raster);
+    sim.ctx.set_raster(load_raster(raster_batch, sim.schema.raster));
+  }
 }
 
 const _sync_pools = (sim, batches, agent_pool, message_pool) => {
   for (var i_group = 0; i_group < agent_pool.length; ++i_group) {
     agent_pool[i_group] = batches.sync(agent_pool[i_group], sim.schema.agent);
     agent_pool[i_group].load_missing_cols(sim.schema.agent, sim.state_loaders);
+  }
 
-    message_pool[i_group] = batches.sync(message_pool[i_group], sim.schema.msg);
-    message_pool[i_group].load_missing_cols(sim.schema.msg, {});
+  // The message pool of a state snapshot can have an additional batch with delayed messages.
+  for (var i_batch = 0; i_batch < message_pool.length; ++i_batch) {
+    message_pool[i_batch] = batches.sync(message_pool[i_batch], sim.schema.msg);
+    message_pool[i_batch].load_missing_cols(sim.schema.msg, {});
   }
 };
 
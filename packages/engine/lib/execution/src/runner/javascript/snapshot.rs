@@ -0,0 +1,269 @@
+//! Startup snapshots of the JavaScript runner.
+//!
+//! Before it can start an experiment, every runner evaluates the same JavaScript: the Apache Arrow
+//! bundle, `hash_stdlib.js`, `hash_util.js` and `runner.js` with the other modules it imports (see
+//! [`Embedded::load_baseline`]). Instead, the first runner of an engine process evaluates it once
+//! and serializes the resulting heap into a snapshot, which every runner then creates its isolate
+//! from.
+//!
+//! Snapshots are cached in `hash_engine/snapshots` in `XDG_CACHE_HOME`, or next to the engine
+//! binary if it's not set, keyed by a SHA-256 hash of the version of the engine, the version of V8,
+//! and the content of the JavaScript files of the runner, so they are only created again after one
+//! of them changed. V8 doesn't validate snapshots, so the [cache directory] may only be accessible
+//! by the current user and every snapshot is stored with a checksum, which is verified before using
+//! it. If a snapshot can't be created, runners evaluate the JavaScript themselves.
+//!
+//! [cache directory]: crate::runner::cache
+
+use std::{
+    cell::RefCell,
+    fs, io,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+
+use crate::runner::{
+    cache,
+    javascript::{
+        embedded::Embedded,
+        modules::{ModuleMap, EXTERNAL_REFERENCES},
+        JavaScriptError, JavaScriptResult,
+    },
+};
+
+const RUNNER_DIR: &str = "./lib/execution/src/runner/javascript";
+
+lazy_static! {
+    static ref STARTUP_SNAPSHOT: Option<Vec<u8>> = match load_or_create_snapshot() {
+        Ok(snapshot) => Some(snapshot),
+        Err(err) => {
+            tracing::warn!("Could not use a startup snapshot for the JavaScript runner: {err}");
+            None
+        }
+    };
+}
+
+/// Returns the startup snapshot, creating it on first use.
+///
+/// V8 has to be initialized before calling this.
+pub(in crate::runner::javascript) fn startup_snapshot() -> Option<&'static [u8]> {
+    STARTUP_SNAPSHOT.as_deref()
+}
+
+/// Length of the SHA-256 checksum stored in front of a cached snapshot.
+const CHECKSUM_LEN: usize = 32;
+
+/// Returns the directory snapshots are cached in, creating it if necessary.
+fn cache_directory() -> JavaScriptResult<PathBuf> {
+    cache::cache_directory("snapshots")
+        .map_err(|err| JavaScriptError::IO("snapshot cache directory".to_string(), err))
+}
+
+/// Returns the path the snapshot of the current engine is cached at.
+fn snapshot_path() -> JavaScriptResult<PathBuf> {
+    let io_error = |err| JavaScriptError::IO(RUNNER_DIR.to_string(), err);
+
+    let mut files = fs::read_dir(RUNNER_DIR)
+        .map_err(io_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(io_error)?;
+    files.retain(|path| {
+        path.extension()
+            .map_or(false, |extension| extension == "js")
+    });
+    files.sort();
+
+    // Every part is prefixed with its length, so different inputs can't produce the same bytes.
+    let mut hasher = Sha256::new();
+    let mut update = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    };
+    update(env!("CARGO_PKG_VERSION").as_bytes());
+    update(v8::V8::get_version().as_bytes());
+    for path in files {
+        update(path.to_string_lossy().as_bytes());
+        update(
+            &fs::read(&path)
+                .map_err(|err| JavaScriptError::IO(path.to_string_lossy().into_owned(), err))?,
+        );
+    }
+
+    Ok(cache_directory()?.join(format!(
+        "js-runner-{}-{:x}.bin",
+        env!("CARGO_PKG_VERSION"),
+        hasher.finalize()
+    )))
+}
+
+fn load_or_create_snapshot() -> JavaScriptResult<Vec<u8>> {
+    let path = snapshot_path()?;
+    match read_snapshot(&path) {
+        Ok(Some(snapshot)) => {
+            tracing::debug!("Using startup snapshot at {path:?}");
+            return Ok(snapshot);
+        }
+        Ok(None) => {}
+        Err(err) => tracing::warn!("Could not read startup snapshot at {path:?}: {err}"),
+    }
+
+    let snapshot = create_snapshot(Path::new("."))?;
+    if let Err(err) = write_snapshot(&path, &snapshot) {
+        tracing::warn!("Could not cache startup snapshot at {path:?}: {err}");
+    } else {
+        tracing::debug!("Created startup snapshot at {path:?}");
+    }
+    Ok(snapshot)
+}
+
+/// Reads a snapshot written by [`write_snapshot`], returning `None` if there is none.
+///
+/// Returns an error if the checksum of the snapshot doesn't match.
+fn read_snapshot(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    let mut contents = match fs::read(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    if contents.len() < CHECKSUM_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The snapshot is truncated",
+        ));
+    }
+    let snapshot = contents.split_off(CHECKSUM_LEN);
+    if Sha256::digest(&snapshot).as_slice() != contents {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "The checksum of the snapshot doesn't match",
+        ));
+    }
+    Ok(Some(snapshot))
+}
+
+/// Writes the snapshot prefixed with its checksum.
+///
+/// Writes to a temporary file first, so other engine processes never read a partial snapshot.
+fn write_snapshot(path: &Path, snapshot: &[u8]) -> io::Result<()> {
+    let temporary_path = path.with_extension(format!("{}.tmp", std::process::id()));
+    let mut contents = Sha256::digest(snapshot).to_vec();
+    contents.extend_from_slice(snapshot);
+    fs::write(&temporary_path, contents)?;
+    fs::rename(&temporary_path, path)
+}
+
+/// Creates a startup snapshot of the runner, reading its modules relative to `engine_dir`.
+fn create_snapshot(engine_dir: &Path) -> JavaScriptResult<Vec<u8>> {
+    let mut snapshot_creator = v8::SnapshotCreator::new(Some(&*EXTERNAL_REFERENCES));
+    // SAFETY: The isolate is owned by the snapshot creator, it's only used until the snapshot is
+    //   created and not dropped.
+    let mut isolate = unsafe { snapshot_creator.get_owned_isolate() };
+
+    let module_map = Rc::new(RefCell::new(ModuleMap::with_engine_dir(
+        engine_dir.to_path_buf(),
+        None,
+    )));
+    isolate.set_slot(Rc::clone(&module_map));
+    let result = {
+        let mut handle_scope = v8::HandleScope::new(&mut isolate);
+        let context = v8::Context::new(&mut handle_scope);
+        let mut context_scope = v8::ContextScope::new(&mut handle_scope, context);
+
+        let result = Embedded::load_baseline(&mut context_scope);
+        if result.is_ok() {
+            module_map
+                .borrow()
+                .add_to_snapshot(&mut context_scope, context);
+            snapshot_creator.set_default_context(context);
+        }
+        result
+    };
+
+    // A snapshot can't be created while there are global handles.
+    isolate.remove_slot::<Rc<RefCell<ModuleMap>>>();
+    drop(module_map);
+    // The isolate is disposed by the snapshot creator.
+    std::mem::forget(isolate);
+    result?;
+
+    snapshot_creator
+        .create_blob(v8::FunctionCodeHandling::Keep)
+        .map(|snapshot| snapshot.to_vec())
+        .ok_or_else(|| JavaScriptError::V8("Could not create startup snapshot".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::runner::javascript::initialize_v8;
+
+    #[test]
+    fn verify_checksum() {
+        let directory = std::env::temp_dir().join(format!("js-snapshots-{}", uuid::Uuid::new_v4()));
+        create_private_directory(&directory).unwrap();
+        let path = directory.join("snapshot.bin");
+
+        assert!(read_snapshot(&path).unwrap().is_none());
+        write_snapshot(&path, b"snapshot").unwrap();
+        assert_eq!(read_snapshot(&path).unwrap().unwrap(), b"snapshot");
+
+        let mut contents = fs::read(&path).unwrap();
+        *contents.last_mut().unwrap() ^= 1;
+        fs::write(&path, contents).unwrap();
+        assert!(read_snapshot(&path).is_err());
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+
+    /// Returns the source code of the functions of [`Embedded`] in a new isolate, which is created
+    /// from `snapshot` if provided.
+    /// The directory the runner imports its JavaScript from, the working directory of the engine.
+    fn engine_dir() -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+    }
+
+    fn embedded_functions(snapshot: Option<&'static [u8]>) -> Vec<String> {
+        let mut create_params =
+            v8::Isolate::create_params().external_references(&**EXTERNAL_REFERENCES);
+        if let Some(snapshot) = snapshot {
+            create_params = create_params.snapshot_blob(snapshot);
+        }
+        let mut isolate = v8::Isolate::new(create_params);
+        let mut handle_scope = v8::HandleScope::new(&mut isolate);
+        let context = v8::Context::new(&mut handle_scope);
+        let mut scope = v8::ContextScope::new(&mut handle_scope, context);
+        let module_map = match snapshot {
+            Some(_) => ModuleMap::from_snapshot(&mut scope, None).unwrap(),
+            None => ModuleMap::with_engine_dir(engine_dir(), None),
+        };
+        scope.set_slot(Rc::new(RefCell::new(module_map)));
+
+        let embedded = Embedded::import_common_js_files(&mut scope).unwrap();
+        [
+            embedded.start_experiment,
+            embedded.start_sim,
+            embedded.run_task,
+            embedded.ctx_batch_sync,
+            embedded.state_sync,
+            embedded.state_interim_sync,
+            embedded.state_snapshot_sync,
+        ]
+        .into_iter()
+        .map(|function| function.to_rust_string_lossy(&mut scope))
+        .collect()
+    }
+
+    #[test]
+    fn embedded_functions_from_snapshot() {
+        initialize_v8();
+
+        let snapshot: &'static [u8] =
+            Box::leak(create_snapshot(&engine_dir()).unwrap().into_boxed_slice());
+        let from_snapshot = embedded_functions(Some(snapshot));
+        assert_eq!(from_snapshot, embedded_functions(None));
+    }
+}
@@ -140,13 +140,19 @@ const gen_agent_state = (agent_schema, getters) => {
   /// converted to an array if it's not one already.
 
   /// `data` is an optional argument. `data` must be JSON-serializable.
-  AgentState.prototype.addMessage = function (to, msg_type, data) {
+
+  /// `delay` is an optional number of steps until the message is
+  /// delivered. By default, messages are delivered in the next step.
+  AgentState.prototype.addMessage = function (to, msg_type, data, delay) {
     // Keeps native messages native and JSON messages as JSON.
     let new_message = {
       to: typeof to === "string" ? [to] : to.slice(),
       type: msg_type, // `msg_type` is a string, so don't need to deepcopy it.
       data: hash_util.json_deepcopy(data),
     };
+    if (delay !== undefined) {
+      new_message.delay = delay;
+    }
     // because arrow2 serializes empty arrays as `null`, if there are no messages, then we
     // need to set the field (because we can't push to null)
     if (!this.__msgs[this.__idx_in_group]) {
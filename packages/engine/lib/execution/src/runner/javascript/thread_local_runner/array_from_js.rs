@@ -233,6 +233,42 @@ impl<'s> ThreadLocalRunner<'s> {
                 )
                 .boxed()
             },
+            DataType::Int32 => unsafe {
+                // SAFETY: `data` is provided by arrow and the type is `i32`
+                debug_assert!(
+                    !data.buffer_ptrs[0].is_null(),
+                    "Required pointer for `Int32` (`buffers[0]`) is null"
+                );
+                PrimitiveArray::<i32>::from_data(
+                    DataType::Int32,
+                    self.read_primitive_buffer(
+                        NonNull::new_unchecked(data.buffer_ptrs[0] as *mut u8).cast::<i32>(),
+                        data.len,
+                        data.buffer_capacities[0],
+                        target_len,
+                    ),
+                    validity,
+                )
+                .boxed()
+            },
+            DataType::Int64 | DataType::Timestamp(..) => unsafe {
+                // SAFETY: `data` is provided by arrow and the type is `i64`
+                debug_assert!(
+                    !data.buffer_ptrs[0].is_null(),
+                    "Required pointer for `{data_type:?}` (`buffers[0]`) is null"
+                );
+                PrimitiveArray::<i64>::from_data(
+                    data_type.clone(),
+                    self.read_primitive_buffer(
+                        NonNull::new_unchecked(data.buffer_ptrs[0] as *mut u8).cast::<i64>(),
+                        data.len,
+                        data.buffer_capacities[0],
+                        target_len,
+                    ),
+                    validity,
+                )
+                .boxed()
+            },
             DataType::Float64 => unsafe {
                 debug_assert!(
                     !data.buffer_ptrs[0].is_null(),
@@ -1,13 +1,17 @@
-use std::sync::Arc;
+use std::{collections::HashMap, sync::Arc};
 
-use arrow2::datatypes::Schema;
-use memory::arrow::{ArrowBatch, ColumnChange};
+use arrow2::{
+    array::{Array, PrimitiveArray},
+    datatypes::{DataType, Field, IntegerType, Schema},
+};
+use memory::arrow::{keys_to_dictionary, ArrowBatch, ColumnChange};
 use stateful::state::StateWriteProxy;
 
 use super::ThreadLocalRunner;
 use crate::{
     package::simulation::SimulationId,
     runner::{
+        comms::UserError,
         javascript::{
             error::JavaScriptResult as Result, utils::new_js_string, Array, Object, Value,
         },
@@ -16,12 +20,41 @@ use crate::{
     task::TaskSharedStore,
 };
 impl<'s> ThreadLocalRunner<'s> {
+    /// Converts the `data` of a column flushed by the runner into an array of `field`.
+    ///
+    /// The runner only writes the keys of dictionary-encoded columns, so their values are taken
+    /// from the `dictionary_values` of the schema.
+    fn column_from_js(
+        &mut self,
+        scope: &mut v8::HandleScope<'s>,
+        data: Value<'s>,
+        field: &Field,
+        dictionary_values: &HashMap<String, Vec<String>>,
+    ) -> Result<Box<dyn Array>> {
+        match field.data_type() {
+            DataType::Dictionary(IntegerType::Int32, ..) => {
+                let keys = self.array_data_from_js(scope, data, &DataType::Int32, None)?;
+                let keys = keys
+                    .as_any()
+                    .downcast_ref::<PrimitiveArray<i32>>()
+                    .ok_or_else(|| Error::FlushType(field.data_type().clone()))?
+                    .clone();
+                let values = dictionary_values
+                    .get(&field.name)
+                    .ok_or_else(|| Error::FlushType(field.data_type().clone()))?;
+                Ok(keys_to_dictionary(keys, values)?.boxed())
+            }
+            data_type => self.array_data_from_js(scope, data, data_type, None),
+        }
+    }
+
     fn flush_batch(
         &mut self,
         scope: &mut v8::HandleScope<'s>,
         changes: Array<'s>,
         batch: &mut ArrowBatch,
         schema: &Schema,
+        dictionary_values: &HashMap<String, Vec<String>>,
     ) -> Result<()> {
         for change_idx in 0..changes.length() {
             let change = changes.get_index(scope, change_idx as u32).ok_or_else(|| {
@@ -51,7 +84,17 @@ impl<'s> ThreadLocalRunner<'s> {
             let data = change
                 .get(scope, data.into())
                 .ok_or_else(|| Error::V8("Could not get data property on change".to_string()))?;
-            let data = self.array_data_from_js(scope, data, field.data_type(), None)?;
+            // Values not matching the schema are written by user code, so report them as user
+            // errors naming the field instead of failing the runner.
+            let data = self
+                .column_from_js(scope, data, field, dictionary_values)
+                .map_err(|err| {
+                    Error::User(vec![UserError(format!(
+                        "Could not write field `{}` of type {:?}: {err}",
+                        field.name,
+                        field.data_type()
+                    ))])
+                })?;
 
             batch.queue_change(ColumnChange {
                 data,
@@ -73,6 +116,7 @@ impl<'s> ThreadLocalRunner<'s> {
         &mut self,
         scope: &mut v8::HandleScope<'s>,
         agent_schema: &Arc<Schema>,
+        agent_dictionary_values: &HashMap<String, Vec<String>>,
         msg_schema: &Arc<Schema>,
         state_proxy: &mut StateWriteProxy,
         i_proxy: usize,
@@ -101,6 +145,7 @@ impl<'s> ThreadLocalRunner<'s> {
                 .ok_or_else(|| format!("Could not access batch at index {i_proxy}"))?
                 .batch,
             agent_schema,
+            agent_dictionary_values,
         )?;
 
         let msg = new_js_string(scope, "msg");
@@ -123,6 +168,7 @@ impl<'s> ThreadLocalRunner<'s> {
                 .ok_or_else(|| format!("Could not access batch at index {i_proxy}"))?
                 .batch,
             msg_schema,
+            &HashMap::new(),
         )?;
 
         Ok(())
@@ -151,6 +197,7 @@ impl<'s> ThreadLocalRunner<'s> {
         // Assuming cloning an Arc once is faster than looking up `state` in
         // the `sims_state` HashMap in every `flush_group` call.
         let agent_schema = state.agent_schema.clone();
+        let agent_dictionary_values = state.agent_dictionary_values.clone();
         let msg_schema = state.msg_schema.clone();
 
         let changes = new_js_string(scope, "changes");
@@ -160,7 +207,15 @@ impl<'s> ThreadLocalRunner<'s> {
             .ok_or_else(|| Error::V8("Could not get changes property on return_val".to_string()))?;
 
         if group_indices.len() == 1 {
-            self.flush_group(scope, &agent_schema, &msg_schema, proxy, 0, changes)?;
+            self.flush_group(
+                scope,
+                &agent_schema,
+                &agent_dictionary_values,
+                &msg_schema,
+                proxy,
+                0,
+                changes,
+            )?;
         } else {
             let changes: Array<'s> = changes.try_into().unwrap();
             for i_proxy in 0..group_indices.len() {
@@ -172,6 +227,7 @@ impl<'s> ThreadLocalRunner<'s> {
                 self.flush_group(
                     scope,
                     &agent_schema,
+                    &agent_dictionary_values,
                     &msg_schema,
                     proxy,
                     i_proxy,
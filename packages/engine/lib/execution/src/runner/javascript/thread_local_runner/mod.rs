@@ -1,6 +1,7 @@
 use std::{collections::HashMap, sync::Arc};
 
 use arrow2::datatypes::Schema;
+use memory::arrow::dictionary_values;
 use stateful::{
     field::PackageId,
     global::{Globals, SharedStore},
@@ -42,6 +43,8 @@ mod sync;
 /// Due to flushing, need batches and schemas in both Rust and JS.
 struct SimState {
     agent_schema: Arc<Schema>,
+    /// Values of the dictionary-encoded agent fields, parsed once from the schema metadata.
+    agent_dictionary_values: Arc<HashMap<String, Vec<String>>>,
     msg_schema: Arc<Schema>,
 }
 
@@ -201,6 +204,9 @@ impl<'s> ThreadLocalRunner<'s> {
         // Initialize Rust.
         let state = SimState {
             agent_schema: Arc::clone(&run.datastore.agent_batch_schema.arrow),
+            agent_dictionary_values: Arc::new(dictionary_values(
+                &run.datastore.agent_batch_schema.arrow,
+            )?),
             msg_schema: Arc::clone(&run.datastore.message_batch_schema),
         };
         self.sims_state
@@ -8,7 +8,7 @@ use crate::{
                 new_js_array_from_usizes, sim_id_to_js, state_to_js,
             },
             error::JavaScriptResult,
-            utils::call_js_function,
+            utils::{call_js_function, new_js_string},
         },
         JavaScriptError,
     },
@@ -27,17 +27,30 @@ impl<'s> ThreadLocalRunner<'s> {
             context_batch,
             current_step,
             state_group_start_indices,
+            globals,
+            raster_batch,
         } = ctx_batch_sync;
 
         let js_sim_id = sim_id_to_js(scope, sim_run_id);
         let js_batch_id = batch_to_js(scope, context_batch.segment())?;
         let js_idxs = new_js_array_from_usizes(scope, &state_group_start_indices)?;
         let js_current_step = current_step_to_js(scope, current_step);
+        // Globals are only passed if they changed
+        let js_globals = match globals {
+            Some(globals) => new_js_string(scope, &serde_json::to_string(&*globals)?).into(),
+            None => v8::undefined(scope).into(),
+        };
+        let js_raster_batch = match raster_batch {
+            Some(raster_batch) => batch_to_js(scope, raster_batch.read().segment())?,
+            None => v8::undefined(scope).into(),
+        };
         call_js_function(scope, self.embedded.ctx_batch_sync, self.this, &[
             js_sim_id,
             js_batch_id,
             js_idxs,
             js_current_step,
+            js_globals,
+            js_raster_batch,
         ])
         .map_err(|err| format!("Could not run ctx_batch_sync function: {err}"))?;
 
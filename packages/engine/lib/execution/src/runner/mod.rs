@@ -6,6 +6,7 @@
 //!
 //! [`package`]: crate::package
 
+pub mod cache;
 pub mod comms;
 
 mod javascript;
@@ -79,6 +79,40 @@ def parse_any_type_fields(metadata):
     return any_type_fields
 
 
+def parse_category_fields(metadata):
+    """Returns a dict from the names of category fields to their list of categories."""
+    if metadata is None:
+        return {}
+
+    category_fields = metadata.get("category_fields")
+    return json.loads(category_fields) if category_fields else {}
+
+
+def read_dictionary_encoded_batch(buf, schema, category_fields):
+    """Reads a record batch with the dictionary-encoded columns in `category_fields`.
+
+    The batches only contain the keys of dictionary-encoded columns, as their values are stored in
+    the schema metadata instead of dictionary batches. The keys are read as integer columns and
+    combined with the values afterwards, neither of which copies the column data.
+    """
+    key_fields = [
+        pa.field(field.name, field.type.index_type, field.nullable, field.metadata)
+        if pa.types.is_dictionary(field.type)
+        else field
+        for field in schema
+    ]
+    keys = pa.ipc.read_record_batch(buf, pa.schema(key_fields, schema.metadata))
+    columns = [
+        pa.DictionaryArray.from_arrays(
+            column, pa.array(category_fields[field.name], type=pa.string())
+        )
+        if pa.types.is_dictionary(field.type)
+        else column
+        for field, column in zip(schema, keys.columns)
+    ]
+    return pa.RecordBatch.from_arrays(columns, schema=schema)
+
+
 def load_record_batch(mem, schema=None):
     (
         schema_offset,
@@ -99,7 +133,13 @@ def load_record_batch(mem, schema=None):
         schema = pa.ipc.read_schema(schema_buf)
 
     record_batch_buf = mem[meta_offset: data_offset + data_size]
-    record_batch = pa.ipc.read_record_batch(record_batch_buf, schema)
+    category_fields = parse_category_fields(schema.metadata)
+    if category_fields:
+        record_batch = read_dictionary_encoded_batch(
+            record_batch_buf, schema, category_fields
+        )
+    else:
+        record_batch = pa.ipc.read_record_batch(record_batch_buf, schema)
 
     any_type_fields = parse_any_type_fields(schema.metadata)
     return record_batch, any_type_fields
@@ -227,6 +267,7 @@ class Batch:
 
     def flush_changes(self, schema, skip):
         any_type_fields = parse_any_type_fields(schema.metadata)
+        category_fields = parse_category_fields(schema.metadata)
 
         # Dynamically accessed columns (if any) were added to `cols` by `state`.
         changes = []
@@ -245,6 +286,13 @@ class Batch:
                 # Convert `any`-type array of JSON values to array of JSON strings
                 # for Arrow serialization as a string column.
                 py_col = [json.dumps(elem) for elem in col]
+            elif field.name in category_fields:
+                # Convert categories back to their dictionary keys
+                categories = category_fields[field.name]
+                py_col = [
+                    hash_util.category_to_key(elem, categories, field.name)
+                    for elem in col
+                ]
             elif isinstance(col[0], pa.Scalar):
                 # Shallow-loaded column; can be modified in place
                 continue
@@ -253,9 +301,20 @@ class Batch:
                 #       (These currently result in an exception from `pa.array`.)
                 py_col = col
 
-            changes.append(
-                {"i_field": i_field, "data": pa.array(py_col, type=field.type)}
-            )
+            try:
+                if field.name in category_fields:
+                    # Only the keys of dictionary-encoded columns are flushed
+                    data = pa.DictionaryArray.from_arrays(
+                        pa.array(py_col, type=field.type.index_type),
+                        pa.array(category_fields[field.name], type=pa.string()),
+                    )
+                else:
+                    data = pa.array(py_col, type=field.type)
+            except (pa.ArrowInvalid, pa.ArrowTypeError, TypeError, OverflowError):
+                agent_names = self.cols.get("agent_name")
+                raise hash_util.invalid_value_error(py_col, field, agent_names) from None
+
+            changes.append({"i_field": i_field, "data": data})
 
         if len(changes) == 0:
             return
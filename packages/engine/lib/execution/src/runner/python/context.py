@@ -1,5 +1,97 @@
+import json
+import math
 from copy import deepcopy
 
+from wrappers import np_force_writable
+
+
+class RasterLayer:
+    """
+    A raster layer, whose values are stored row by row in shared memory.
+    Values are read and written in place, so writes are visible to all
+    agents immediately.
+    """
+
+    def __init__(self, values, grid):
+        self.__values = values
+        self.__grid = grid
+        self.width = grid["width"]
+        self.height = grid["height"]
+
+    def cell(self, position):
+        """
+        Returns the `(x, y)` cell containing `position`, `None` if it lies
+        outside the grid.
+        """
+        grid = self.__grid
+        x = math.floor((position[0] - grid["origin"][0]) / grid["cell_size"])
+        y = math.floor((position[1] - grid["origin"][1]) / grid["cell_size"])
+        if 0 <= x < self.width and 0 <= y < self.height:
+            return x, y
+        return None
+
+    def __index(self, x, y):
+        # Wraps around continuous axes, `None` if the cell lies outside the grid
+        if not (float(x).is_integer() and float(y).is_integer()):
+            return None
+        x, y = int(x), int(y)
+        wrap = self.__grid["wrap"]
+        if wrap[0]:
+            x %= self.width
+        if wrap[1]:
+            y %= self.height
+        if 0 <= x < self.width and 0 <= y < self.height:
+            return y * self.width + x
+        return None
+
+    def get(self, x, y):
+        """
+        Returns the value of the cell `(x, y)`, `None` if it lies outside the
+        grid.
+        """
+        index = self.__index(x, y)
+        return None if index is None else float(self.__values[index])
+
+    def set(self, x, y, value):
+        index = self.__index(x, y)
+        if index is None:
+            raise IndexError(f"Raster cell ({x}, {y}) is outside the grid")
+        self.__values[index] = value
+
+    def get_at(self, position):
+        """
+        Returns the value of the cell containing `position`, `None` if it lies
+        outside the grid.
+        """
+        cell = self.cell(position)
+        return None if cell is None else self.get(*cell)
+
+    def set_at(self, position, value):
+        """
+        Sets the value of the cell containing `position`. Returns `False`
+        without writing anything if `position` lies outside the grid.
+        """
+        cell = self.cell(position)
+        if cell is None:
+            return False
+        self.set(*cell, value)
+        return True
+
+
+def load_raster(raster_batch):
+    """
+    Returns the raster layers by name. The schema metadata of `raster_batch`
+    describes the grid.
+    """
+    record_batch = raster_batch.record_batch
+    grid = json.loads(record_batch.schema.metadata[b"raster_grid"])
+    layers = {}
+    for field, column in zip(record_batch.schema, record_batch.columns):
+        values = column.to_numpy(zero_copy_only=True)
+        np_force_writable(values)
+        layers[field.name] = RasterLayer(values, grid)
+    return layers
+
 
 class AgentContext:
     def __init__(self, sim_ctx, ctx_batch, state_snapshot, i_agent_in_sim):
@@ -21,6 +113,9 @@ class AgentContext:
     def step(self):
         return self.__sim_ctx.step()
 
+    def raster(self, name):
+        return self.__sim_ctx.raster(name)
+
     def __getattr__(self, field_name):
         # Prefixes are because class field names get mangled
         column = self.__dict__["_AgentContext__cols"][field_name]
@@ -60,6 +155,9 @@ class GroupContext:
     def step(self):
         return self.__sim_ctx.step()
 
+    def raster(self, name):
+        return self.__sim_ctx.raster(name)
+
 
 class Snapshot:
     def __init__(self, agent_pool, message_pool):
@@ -74,6 +172,7 @@ class SimContext:
         self.__experiment_ctx = experiment_ctx
         self.__globals = sim_globals
         self.__ctx_batch = None
+        self.__raster = {}
         self.state_snapshot = Snapshot(None, None)
 
     # Invalidates existing `GroupContext` and `AgentContext` objects.
@@ -89,6 +188,24 @@ class SimContext:
     def set_step(self, cur_step):
         self.__step = cur_step
 
+    # Invalidates existing `GroupContext` and `AgentContext` objects.
+    def set_globals(self, sim_globals):
+        self.__globals = sim_globals
+
+    # The layers are stored in the same shared memory for the whole simulation
+    # run, so they only need to be set once.
+    def set_raster(self, layers):
+        self.__raster = layers
+
+    def has_raster(self):
+        return bool(self.__raster)
+
+    def raster(self, name):
+        layer = self.__raster.get(name)
+        if layer is None:
+            raise KeyError(f"Unknown raster layer: {name}")
+        return layer
+
     # TODO: step getter method
 
     def get_group(self, i_group):
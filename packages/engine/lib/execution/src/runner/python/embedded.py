@@ -0,0 +1,73 @@
+"""
+Entry point of a Python runner embedded into the engine process (see
+`embedded.rs`). This replaces `run.sh` and `main.py`, which are used when
+the runner is started as a child process.
+"""
+import ctypes
+import logging
+import os
+import site
+import sys
+
+SCRIPT_DIR = os.path.dirname(os.path.abspath(__file__))
+
+
+def add_venv_site_packages():
+    """
+    The embedded interpreter is the one the engine is linked against, so
+    instead of activating `runner_venv` (see `setup.sh`) or the project's
+    environment, its packages are added to the module search path.
+    """
+    venv = os.getenv("HASH_PYTHON_ENVIRONMENT") or os.path.join(
+        SCRIPT_DIR, "runner_venv"
+    )
+    version = f"python{sys.version_info.major}.{sys.version_info.minor}"
+    site_packages = os.path.join(venv, "lib", version, "site-packages")
+    if os.path.isdir(site_packages):
+        site.addsitedir(site_packages)
+    else:
+        logging.warning(
+            "No virtual environment for %s found at %s", version, site_packages
+        )
+
+
+def setup(memory_library):
+    """
+    Prepares the interpreter for the runner, which is only done once per
+    process.
+
+    `wrappers` is linked against the `memory` library, which `run.sh` makes
+    available through `LD_LIBRARY_PATH`. That variable is only read when a
+    process starts, so instead the library next to the engine binary is
+    loaded globally before `wrappers` is imported.
+    """
+    if SCRIPT_DIR + "/fbs" in sys.path:
+        return
+    sys.path.append(SCRIPT_DIR + "/fbs")
+    add_venv_site_packages()
+    ctypes.CDLL(memory_library, mode=ctypes.RTLD_GLOBAL)
+
+    # pylint: disable=import-outside-toplevel
+    from main import logging_setup
+
+    logging_setup()
+
+
+def run(experiment_id, worker_index, channel, memory_library):
+    """
+    Runs the runner of one worker until the engine terminates it.
+
+    :param channel: Passes flatbuffers messages between the runner and the
+                    engine, with `recv_init`, `recv` and `send` methods.
+    :param memory_library: Path of the `memory` library
+    """
+    setup(memory_library)
+    # pylint: disable=import-outside-toplevel
+    from runner import Runner
+
+    logging.info(
+        "Running embedded Python runner for experiment id %s and worker index %s",
+        experiment_id,
+        worker_index,
+    )
+    Runner(experiment_id, worker_index, channel).run()
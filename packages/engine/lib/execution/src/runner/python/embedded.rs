@@ -0,0 +1,243 @@
+//! Runs the Python runner in an interpreter embedded into the engine process.
+//!
+//! The runner exchanges the same flatbuffers messages as the runner in a child process, but they
+//! are passed through a [`RunnerChannel`], which Python calls directly, instead of nng sockets.
+//! Agent and message batches are in shared memory in both cases, so Python reads them without
+//! copying.
+//!
+//! An interpreter has a single global interpreter lock, so only the runner of the first worker is
+//! embedded (see [`PythonRunner`]). The lock is released while the runner waits for messages.
+//!
+//! [`PythonRunner`]: super::PythonRunner
+
+use std::{
+    env::consts::{DLL_PREFIX, DLL_SUFFIX},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use pyo3::{exceptions::PyConnectionError, prelude::*, types::PyBytes};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+use crate::{
+    package::{experiment::ExperimentId, simulation::SimulationId},
+    runner::{
+        comms::{ExperimentInitRunnerMsg, InboundToRunnerMsgPayload, OutboundFromRunnerMsg},
+        python::{
+            receiver::experiment_init_to_fbs, sender::inbound_to_fbs, split_task_payload,
+            PendingMessages, PythonError, PYTHON_ENVIRONMENT_VAR, RUNNER_DIR,
+        },
+    },
+    worker_pool::WorkerIndex,
+    Error, Result,
+};
+
+/// Passes flatbuffers messages between the engine and an embedded runner, used by
+/// `EmbeddedMessenger` in `message.py`.
+#[pyclass]
+struct RunnerChannel {
+    init_msg: Option<Vec<u8>>,
+    from_engine: UnboundedReceiver<Vec<u8>>,
+    to_engine: UnboundedSender<Vec<u8>>,
+}
+
+#[pymethods]
+impl RunnerChannel {
+    /// Returns the `Init` message, which can only be received once.
+    fn recv_init(&mut self, py: Python<'_>) -> PyResult<Py<PyBytes>> {
+        let init_msg = self
+            .init_msg
+            .take()
+            .ok_or_else(|| PyConnectionError::new_err("The init message was already received"))?;
+        Ok(PyBytes::new(py, &init_msg).into())
+    }
+
+    /// Blocks until the next `RunnerInboundMsg` is available.
+    fn recv(&mut self, py: Python<'_>) -> PyResult<Py<PyBytes>> {
+        let msg = py
+            .allow_threads(|| self.from_engine.blocking_recv())
+            .ok_or_else(|| PyConnectionError::new_err("The engine stopped sending messages"))?;
+        Ok(PyBytes::new(py, &msg).into())
+    }
+
+    /// Sends a `RunnerOutboundMsg` to the engine.
+    fn send(&self, msg: &[u8]) -> PyResult<()> {
+        self.to_engine
+            .send(msg.to_vec())
+            .map_err(|_| PyConnectionError::new_err("The engine stopped receiving messages"))
+    }
+}
+
+/// Returns the path of the `memory` library, which is built next to the engine binary.
+///
+/// The `wrappers` Python module is linked against it, but the embedded interpreter can't find it
+/// through `LD_LIBRARY_PATH` like the runner in a child process does (see `run.sh`).
+fn memory_library() -> Result<PathBuf> {
+    let engine = std::env::current_exe()
+        .map_err(|err| PythonError::from(format!("Couldn't locate the engine binary: {err}")))?;
+    let library = engine.with_file_name(format!("{DLL_PREFIX}memory{DLL_SUFFIX}"));
+    if library.is_file() {
+        Ok(library)
+    } else {
+        Err(PythonError::from(format!(
+            "Couldn't find the memory library at {}",
+            library.display()
+        ))
+        .into())
+    }
+}
+
+fn run_interpreter(
+    experiment_id: ExperimentId,
+    worker_index: WorkerIndex,
+    channel: RunnerChannel,
+    python_environment: Option<PathBuf>,
+    memory_library: PathBuf,
+) -> PyResult<()> {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        if let Some(python_environment) = python_environment {
+            py.import("os")?.getattr("environ")?.set_item(
+                PYTHON_ENVIRONMENT_VAR,
+                python_environment.to_string_lossy().as_ref(),
+            )?;
+        }
+        let path = py.import("sys")?.getattr("path")?;
+        if !path.contains(RUNNER_DIR)? {
+            path.call_method1("insert", (0, RUNNER_DIR))?;
+        }
+        py.import("embedded")?.call_method1(
+            "run",
+            (
+                experiment_id.to_string(),
+                worker_index.index(),
+                Py::new(py, channel)?,
+                memory_library.to_string_lossy().as_ref(),
+            ),
+        )?;
+        Ok(())
+    })
+}
+
+pub(super) async fn run(
+    init_msg: Arc<ExperimentInitRunnerMsg>,
+    mut inbound_receiver: UnboundedReceiver<(Option<SimulationId>, InboundToRunnerMsgPayload)>,
+    outbound_sender: UnboundedSender<OutboundFromRunnerMsg>,
+) -> Result<()> {
+    let (to_python, from_engine) = unbounded_channel();
+    let (to_engine, mut from_python) = unbounded_channel();
+    let channel = RunnerChannel {
+        init_msg: Some(experiment_init_to_fbs(&init_msg)?.finished_data().to_vec()),
+        from_engine,
+        to_engine,
+    };
+
+    let experiment_id = init_msg.experiment_id;
+    let worker_index = init_msg.worker_index;
+    let python_environment = init_msg.runner_config.python_environment.clone();
+    let memory_library = memory_library()?;
+    let interpreter = std::thread::Builder::new()
+        .name(format!("python-runner-{worker_index}"))
+        .spawn(move || {
+            if let Err(err) = run_interpreter(
+                experiment_id,
+                worker_index,
+                channel,
+                python_environment,
+                memory_library,
+            ) {
+                tracing::error!("Embedded Python runner {worker_index} failed: {err}");
+            }
+        })
+        .map_err(PythonError::Spawn)?;
+    tracing::debug!("Started embedded Python runner {worker_index}");
+
+    let mut pending = PendingMessages::default();
+    'select_loop: loop {
+        tokio::select! {
+            Some((sim_id, inbound)) = inbound_receiver.recv() => {
+                let (task_payload_json, task_wrapper) = split_task_payload(&inbound)?;
+                let msg = inbound_to_fbs(sim_id, &inbound, &task_payload_json)?;
+                to_python.send(msg.finished_data().to_vec()).map_err(|_| {
+                    PythonError::from("Embedded Python runner stopped receiving messages")
+                })?;
+
+                if pending.register_sent(sim_id, inbound, task_wrapper)? {
+                    break 'select_loop;
+                }
+            }
+            outbound = from_python.recv() => {
+                let outbound = outbound.ok_or(PythonError::OutboundReceive)?;
+                pending.handle_outbound(&outbound, &outbound_sender)?;
+            }
+        }
+    }
+
+    // Python returns after handling the terminate message and freeing its batches.
+    tokio::task::spawn_blocking(move || interpreter.join())
+        .await
+        .map_err(|err| Error::from(format!("Couldn't join embedded Python runner: {err}")))?
+        .map_err(|_| Error::from("Embedded Python runner panicked"))
+}
+
+#[cfg(test)]
+mod tests {
+    use pyo3::types::PyDict;
+
+    use super::*;
+
+    /// Runs `code` with the channel bound to `channel`.
+    fn run_python(channel: RunnerChannel, code: &str) -> PyResult<()> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let locals = PyDict::new(py);
+            locals.set_item("channel", Py::new(py, channel)?)?;
+            py.run(code, None, Some(locals))
+        })
+    }
+
+    #[test]
+    fn receive_init_once() {
+        let (_to_python, from_engine) = unbounded_channel();
+        let (to_engine, _from_python) = unbounded_channel();
+        let channel = RunnerChannel {
+            init_msg: Some(b"init".to_vec()),
+            from_engine,
+            to_engine,
+        };
+
+        run_python(
+            channel,
+            "assert channel.recv_init() == b'init'\ntry:\n    channel.recv_init()\n    assert \
+             False\nexcept ConnectionError:\n    pass\n",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn pass_messages() {
+        let (to_python, from_engine) = unbounded_channel();
+        let (to_engine, mut from_python) = unbounded_channel();
+        let channel = RunnerChannel {
+            init_msg: None,
+            from_engine,
+            to_engine,
+        };
+        to_python.send(b"first".to_vec()).unwrap();
+        to_python.send(b"second".to_vec()).unwrap();
+        drop(to_python);
+
+        // Python echoes the messages in reverse order until the engine stops sending.
+        run_python(
+            channel,
+            "messages = []\ntry:\n    while True:\n        \
+             messages.append(channel.recv())\nexcept ConnectionError:\n    pass\nfor message in \
+             reversed(messages):\n    channel.send(message)\n",
+        )
+        .unwrap();
+
+        assert_eq!(from_python.try_recv().unwrap(), b"second");
+        assert_eq!(from_python.try_recv().unwrap(), b"first");
+        assert!(from_python.try_recv().is_err());
+    }
+}
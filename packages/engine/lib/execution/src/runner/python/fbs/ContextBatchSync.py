@@ -42,7 +42,25 @@ class ContextBatchSync(object):
             return self._tab.Get(flatbuffers.number_types.Int64Flags, o + self._tab.Pos)
         return 0
 
-def Start(builder): builder.StartObject(2)
+    # ContextBatchSync
+    def Globals(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(8))
+        if o != 0:
+            return self._tab.String(o + self._tab.Pos)
+        return None
+
+    # ContextBatchSync
+    def RasterBatch(self):
+        o = flatbuffers.number_types.UOffsetTFlags.py_type(self._tab.Offset(10))
+        if o != 0:
+            x = self._tab.Indirect(o + self._tab.Pos)
+            from Batch import Batch
+            obj = Batch()
+            obj.Init(self._tab.Bytes, x)
+            return obj
+        return None
+
+def Start(builder): builder.StartObject(4)
 def ContextBatchSyncStart(builder):
     """This method is deprecated. Please switch to Start."""
     return Start(builder)
@@ -54,6 +72,14 @@ def AddCurrentStep(builder, currentStep): builder.PrependInt64Slot(1, currentSte
 def ContextBatchSyncAddCurrentStep(builder, currentStep):
     """This method is deprecated. Please switch to AddCurrentStep."""
     return AddCurrentStep(builder, currentStep)
+def AddGlobals(builder, globals): builder.PrependUOffsetTRelativeSlot(2, flatbuffers.number_types.UOffsetTFlags.py_type(globals), 0)
+def ContextBatchSyncAddGlobals(builder, globals):
+    """This method is deprecated. Please switch to AddGlobals."""
+    return AddGlobals(builder, globals)
+def AddRasterBatch(builder, rasterBatch): builder.PrependUOffsetTRelativeSlot(3, flatbuffers.number_types.UOffsetTFlags.py_type(rasterBatch), 0)
+def ContextBatchSyncAddRasterBatch(builder, rasterBatch):
+    """This method is deprecated. Please switch to AddRasterBatch."""
+    return AddRasterBatch(builder, rasterBatch)
 def End(builder): return builder.EndObject()
 def ContextBatchSyncEnd(builder):
     """This method is deprecated. Please switch to End."""
@@ -34,4 +34,38 @@ def load_full(
     np_force_writable(col_np)
     return col_np
 
+
+class FieldWriteError(ValueError):
+    """A value written by user code doesn't match the type of its field."""
+
+
+def invalid_value_error(col, field, agent_names=None):
+    # Find the first value that can't be converted to name it in the error
+    for i, value in enumerate(col):
+        try:
+            pa.array([value], type=field.type)
+        except (pa.ArrowInvalid, pa.ArrowTypeError, TypeError, OverflowError):
+            name = agent_names[i] if agent_names and i < len(agent_names) else None
+            if name is not None:
+                row = f"Agent `{name}`"
+            else:
+                row = f"Row {i}"
+            return FieldWriteError(
+                f"{row} has an invalid value for field `{field.name}`: "
+                f"expected {field.type}, got {value!r}"
+            )
+    return FieldWriteError(f"Could not write field `{field.name}` of type {field.type}")
+
+
+def category_to_key(value, categories, field_name):
+    if value is None:
+        return None
+    try:
+        return categories.index(value)
+    except ValueError:
+        raise FieldWriteError(
+            f"Unknown category {value!r} for field {field_name}, expected one of {categories}"
+        ) from None
+
+
 # TODO: `load_elem` like in `hash_util.js` if a use case for it comes up in a package.
@@ -2,6 +2,13 @@ import logging
 import os
 import sys
 
+# A project's virtual environment contains its own build of `wrappers`, which
+# is imported instead of the one next to this file.
+PYTHON_ENVIRONMENT = os.getenv("HASH_PYTHON_ENVIRONMENT")
+if PYTHON_ENVIRONMENT:
+    sys.path.insert(0, os.path.join(PYTHON_ENVIRONMENT, "hash_runner"))
+
+# pylint: disable=wrong-import-position
 from runner import Runner
 
 
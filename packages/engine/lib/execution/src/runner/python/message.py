@@ -131,6 +131,12 @@ class PyContextBatchSync:
         self.sim_id = sim_id
         self.batch = PyBatchMsg(context_batch_sync_fbs.ContextBatch())
         self.cur_step = context_batch_sync_fbs.CurrentStep()
+        globals = context_batch_sync_fbs.Globals()
+        # Only set if the globals changed at the beginning of the step
+        self.globals = None if globals is None else json.loads(globals.decode("utf-8"))
+        raster_batch = context_batch_sync_fbs.RasterBatch()
+        # Only set if raster layers are declared
+        self.raster_batch = None if raster_batch is None else PyBatchMsg(raster_batch)
 
 
 class PyStateSync:
@@ -324,6 +330,27 @@ class Messenger:
         self.to_rust.send(fbs_bytes)
 
 
+class EmbeddedMessenger(Messenger):
+    """
+    Messenger of a runner embedded into the engine process (see `embedded.py`).
+    The same flatbuffers are exchanged as with `Messenger`, but through direct
+    calls on a channel object provided by the engine instead of nng sockets.
+    """
+
+    # pylint: disable=super-init-not-called
+    def __init__(self, channel):
+        self.to_rust = channel
+        self.from_rust = channel
+
+    def __del__(self):
+        pass
+
+    def recv_init(self):
+        fbs_bytes = self.from_rust.recv_init()
+        logging.debug("Received init message")
+        return PyInit(fbs_bytes)
+
+
 def runner_error_to_fbs(builder, error):
     msg_offset = builder.CreateString(error)
 
@@ -1,3 +1,5 @@
+#[cfg(feature = "embedded-python")]
+mod embedded;
 mod error;
 mod fbs;
 mod receiver;
@@ -30,9 +32,17 @@ use crate::{
         Language,
     },
     task::TaskId,
+    worker::SyncCompletionSender,
     Error, Result,
 };
 
+/// Directory of the Python runner sources, relative to the engine's working directory.
+const RUNNER_DIR: &str = "./lib/execution/src/runner/python";
+
+/// Environment variable with the path of the virtual environment the runner uses instead of
+/// `runner_venv`, read by `run.sh`, `main.py` and `embedded.py`.
+const PYTHON_ENVIRONMENT_VAR: &str = "HASH_PYTHON_ENVIRONMENT";
+
 pub struct PythonRunner {
     // Args to RunnerImpl::new
     init_msg: Arc<ExperimentInitRunnerMsg>,
@@ -42,12 +52,19 @@ pub struct PythonRunner {
     outbound_sender: Option<UnboundedSender<OutboundFromRunnerMsg>>,
     outbound_receiver: UnboundedReceiver<OutboundFromRunnerMsg>,
     spawn: bool,
+    /// Whether to run Python in an interpreter embedded into the engine process instead of a
+    /// child process.
+    ///
+    /// All embedded runners would share the interpreter and its global interpreter lock, so only
+    /// the runner of the first worker is embedded, the others run in child processes.
+    embedded: bool,
 }
 
 impl PythonRunner {
-    pub fn new(spawn: bool, init_msg: ExperimentInitRunnerMsg) -> Result<Self> {
+    pub fn new(spawn: bool, embedded: bool, init_msg: ExperimentInitRunnerMsg) -> Result<Self> {
         let (inbound_sender, inbound_receiver) = unbounded_channel();
         let (outbound_sender, outbound_receiver) = unbounded_channel();
+        let embedded = embedded && init_msg.worker_index.index() == 0;
         Ok(Self {
             init_msg: Arc::new(init_msg),
             inbound_sender,
@@ -55,6 +72,7 @@ impl PythonRunner {
             outbound_sender: Some(outbound_sender),
             outbound_receiver,
             spawn,
+            embedded,
         })
     }
 
@@ -119,7 +137,14 @@ impl PythonRunner {
             .take()
             .ok_or(PythonError::AlreadyRunning)?;
 
-        let f = async move { _run(init_msg, inbound_receiver, outbound_sender).await };
+        let embedded = self.embedded;
+        let f = async move {
+            if embedded {
+                run_embedded(init_msg, inbound_receiver, outbound_sender).await
+            } else {
+                _run(init_msg, inbound_receiver, outbound_sender).await
+            }
+        };
         Ok(Box::pin(tokio::task::spawn(f)))
     }
 
@@ -173,9 +198,12 @@ async fn _run(
 
     // Spawn Python process.
     let mut cmd = Command::new("sh");
-    cmd.arg("./lib/execution/src/runner/python/run.sh")
+    cmd.arg(format!("{RUNNER_DIR}/run.sh"))
         .arg(&init_msg.experiment_id.to_string())
         .arg(&init_msg.worker_index.to_string());
+    if let Some(python_environment) = &init_msg.runner_config.python_environment {
+        cmd.env(PYTHON_ENVIRONMENT_VAR, python_environment);
+    }
     let _process = cmd.spawn().map_err(PythonError::Spawn)?;
     tracing::debug!("Started Python process {}", init_msg.worker_index);
 
@@ -186,8 +214,7 @@ async fn _run(
     nng_sender.init()?;
 
     tracing::debug!("Waiting for messages to Python runner");
-    let mut sent_tasks: HashMap<TaskId, SentTask> = HashMap::new();
-    let mut sync_completion_senders = HashMap::new();
+    let mut pending = PendingMessages::default();
     'select_loop: loop {
         // TODO: Send errors instead of immediately stopping?
         tokio::select! {
@@ -196,89 +223,19 @@ async fn _run(
             }
             Some((sim_id, inbound)) = inbound_receiver.recv() => {
                 // Need to get payload before sending nng message.
-                let (task_payload_json, task_wrapper) = match &inbound {
-                    InboundToRunnerMsgPayload::TaskMsg(msg) => {
-                        // TODO: Error message duplication with JS runner
-                        let (payload, wrapper) = msg.payload
-                            .clone()
-                            .extract_inner_msg_with_wrapper()
-                            .map_err(|err| {
-                                Error::from(format!(
-                                    "Failed to extract the inner task message: {err}"
-                                ))
-                            })?;
-                        (Some(payload), Some(wrapper))
-                    }
-                    InboundToRunnerMsgPayload::CancelTask(_) => {
-                        todo!("Cancel messages are not implemented yet");
-                        // see https://app.asana.com/0/1199548034582004/1202011714603653/f
-                    }
-                    _ => (None, None)
-                };
+                let (task_payload_json, task_wrapper) = split_task_payload(&inbound)?;
 
                 // Send nng first, because need inbound by reference for nng,
                 // but by value for saving sent task.
                 nng_sender.send(sim_id, &inbound, &task_payload_json)?;
 
-                // Do Rust part of message handling, if any.
-                match inbound {
-                    InboundToRunnerMsgPayload::TerminateRunner => break 'select_loop,
-                    InboundToRunnerMsgPayload::StateSync(sync) => {
-                        let sim_id = sim_id.ok_or_else(|| Error::from("Missing simulation id"))?;
-                        if let Entry::Vacant(entry) = sync_completion_senders.entry(sim_id) {
-                            entry.insert(sync.completion_sender);
-                        } else {
-                            return Err(PythonError::AlreadyAwaiting.into());
-                        }
-                    }
-                    InboundToRunnerMsgPayload::TaskMsg(RunnerTaskMessage {
-                        task_id,
-                        shared_store,
-                        ..
-                    }) => {
-                        tracing::trace!("Sent task with id {task_id:?}");
-                        // unwrap: TaskMsg variant, so must have serialized payload earlier.
-                        let sent = SentTask {
-                            task_wrapper: task_wrapper.unwrap(),
-                            shared_store
-                        };
-                        sent_tasks
-                            .try_insert(task_id, sent)
-                            .map_err(|_| Error::from(format!(
-                                "Inbound message with duplicate sent task id {:?}", task_id
-                            )))?;
-                    }
-                    _ => {}
+                if pending.register_sent(sim_id, inbound, task_wrapper)? {
+                    break 'select_loop;
                 }
             }
             outbound = nng_receiver.get_recv_result() => {
                 let outbound = outbound.map_err(Error::from)?;
-                let outbound = OutboundFromRunnerMsg::try_from_nng(
-                    outbound,
-                    Language::Python,
-                    &mut sent_tasks,
-                );
-                let outbound = outbound.map_err(|err| {
-                    let err = Error::from(format!(
-                        "Failed to convert nng message to OutboundFromRunnerMsg: {err}"
-                    ));
-                    // TODO: Investigate why `err` sometimes doesn't get logged at all
-                    //       (higher in the call stack) unless we log it here and avoid
-                    //       logging `err` more than once.
-                    tracing::error!("{err}");
-                    err
-                })?;
-                if let OutboundFromRunnerMsgPayload::SyncCompletion = &outbound.payload {
-                    sync_completion_senders
-                        .remove(&outbound.sim_id)
-                        .ok_or(PythonError::NotAwaiting)?
-                        .send(Ok(()))
-                        .map_err(|error| Error::from(format!(
-                            "Couldn't send state sync completion from Python: {error:?}"
-                        )))?;
-                } else {
-                    outbound_sender.send(outbound)?;
-                }
+                pending.handle_outbound(outbound.as_slice(), &outbound_sender)?;
             }
         }
     }
@@ -298,3 +255,140 @@ async fn _run(
     // }
     Ok(())
 }
+
+#[cfg(feature = "embedded-python")]
+async fn run_embedded(
+    init_msg: Arc<ExperimentInitRunnerMsg>,
+    inbound_receiver: UnboundedReceiver<(Option<SimulationId>, InboundToRunnerMsgPayload)>,
+    outbound_sender: UnboundedSender<OutboundFromRunnerMsg>,
+) -> Result<()> {
+    embedded::run(init_msg, inbound_receiver, outbound_sender).await
+}
+
+#[cfg(not(feature = "embedded-python"))]
+async fn run_embedded(
+    _init_msg: Arc<ExperimentInitRunnerMsg>,
+    _inbound_receiver: UnboundedReceiver<(Option<SimulationId>, InboundToRunnerMsgPayload)>,
+    _outbound_sender: UnboundedSender<OutboundFromRunnerMsg>,
+) -> Result<()> {
+    Err(PythonError::from(
+        "The embedded Python runner requires the engine to be built with the `embedded-python` \
+         feature",
+    )
+    .into())
+}
+
+/// Splits the payload of a task message into the inner message, which is sent to Python, and the
+/// wrapper around it, which is kept until the task returns.
+fn split_task_payload(
+    inbound: &InboundToRunnerMsgPayload,
+) -> Result<(Option<serde_json::Value>, Option<serde_json::Value>)> {
+    match inbound {
+        InboundToRunnerMsgPayload::TaskMsg(msg) => {
+            // TODO: Error message duplication with JS runner
+            let (payload, wrapper) = msg
+                .payload
+                .clone()
+                .extract_inner_msg_with_wrapper()
+                .map_err(|err| {
+                    Error::from(format!("Failed to extract the inner task message: {err}"))
+                })?;
+            Ok((Some(payload), Some(wrapper)))
+        }
+        InboundToRunnerMsgPayload::CancelTask(_) => {
+            todo!("Cancel messages are not implemented yet");
+            // see https://app.asana.com/0/1199548034582004/1202011714603653/f
+        }
+        _ => Ok((None, None)),
+    }
+}
+
+/// Messages sent to Python which are still waiting for a response, independent of how the
+/// messages are transported.
+#[derive(Default)]
+struct PendingMessages {
+    sent_tasks: HashMap<TaskId, SentTask>,
+    sync_completion_senders: HashMap<SimulationId, SyncCompletionSender>,
+}
+
+impl PendingMessages {
+    /// Does the Rust part of handling an inbound message after it was sent to Python.
+    ///
+    /// Returns `true` if the runner was asked to terminate.
+    fn register_sent(
+        &mut self,
+        sim_id: Option<SimulationId>,
+        inbound: InboundToRunnerMsgPayload,
+        task_wrapper: Option<serde_json::Value>,
+    ) -> Result<bool> {
+        match inbound {
+            InboundToRunnerMsgPayload::TerminateRunner => return Ok(true),
+            InboundToRunnerMsgPayload::StateSync(sync) => {
+                let sim_id = sim_id.ok_or_else(|| Error::from("Missing simulation id"))?;
+                if let Entry::Vacant(entry) = self.sync_completion_senders.entry(sim_id) {
+                    entry.insert(sync.completion_sender);
+                } else {
+                    return Err(PythonError::AlreadyAwaiting.into());
+                }
+            }
+            InboundToRunnerMsgPayload::TaskMsg(RunnerTaskMessage {
+                task_id,
+                shared_store,
+                ..
+            }) => {
+                tracing::trace!("Sent task with id {task_id:?}");
+                // unwrap: TaskMsg variant, so must have serialized payload earlier.
+                let sent = SentTask {
+                    task_wrapper: task_wrapper.unwrap(),
+                    shared_store,
+                };
+                self.sent_tasks.try_insert(task_id, sent).map_err(|_| {
+                    Error::from(format!(
+                        "Inbound message with duplicate sent task id {:?}",
+                        task_id
+                    ))
+                })?;
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Parses a `RunnerOutboundMsg` flatbuffer received from Python. State sync completions are
+    /// resolved here, all other messages are forwarded to `outbound_sender`.
+    fn handle_outbound(
+        &mut self,
+        outbound: &[u8],
+        outbound_sender: &UnboundedSender<OutboundFromRunnerMsg>,
+    ) -> Result<()> {
+        let outbound = OutboundFromRunnerMsg::try_from_fbs_bytes(
+            outbound,
+            Language::Python,
+            &mut self.sent_tasks,
+        );
+        let outbound = outbound.map_err(|err| {
+            let err = Error::from(format!(
+                "Failed to convert Python message to OutboundFromRunnerMsg: {err}"
+            ));
+            // TODO: Investigate why `err` sometimes doesn't get logged at all
+            //       (higher in the call stack) unless we log it here and avoid
+            //       logging `err` more than once.
+            tracing::error!("{err}");
+            err
+        })?;
+        if let OutboundFromRunnerMsgPayload::SyncCompletion = &outbound.payload {
+            self.sync_completion_senders
+                .remove(&outbound.sim_id)
+                .ok_or(PythonError::NotAwaiting)?
+                .send(Ok(()))
+                .map_err(|error| {
+                    Error::from(format!(
+                        "Couldn't send state sync completion from Python: {error:?}"
+                    ))
+                })?;
+        } else {
+            outbound_sender.send(outbound)?;
+        }
+        Ok(())
+    }
+}
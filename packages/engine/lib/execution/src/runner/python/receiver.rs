@@ -1,3 +1,4 @@
+use flatbuffers::FlatBufferBuilder;
 use nng::{Aio, Socket};
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 
@@ -12,6 +13,19 @@ use crate::{
 };
 
 fn experiment_init_to_nng(init: &ExperimentInitRunnerMsg) -> PythonResult<nng::Message> {
+    let fbb = experiment_init_to_fbs(init)?;
+    let bytes = fbb.finished_data();
+
+    let mut nanomsg = nng::Message::with_capacity(bytes.len());
+    nanomsg.push_front(bytes);
+
+    Ok(nanomsg)
+}
+
+/// Builds the `Init` flatbuffer for `init` and returns the finished builder.
+pub(super) fn experiment_init_to_fbs(
+    init: &ExperimentInitRunnerMsg,
+) -> PythonResult<FlatBufferBuilder<'static>> {
     // TODO: initial buffer size
     let mut fbb = flatbuffers::FlatBufferBuilder::new();
     let experiment_id =
@@ -36,12 +50,7 @@ fn experiment_init_to_nng(init: &ExperimentInitRunnerMsg) -> PythonResult<nng::M
     );
 
     fbb.finish(msg, None);
-    let bytes = fbb.finished_data();
-
-    let mut nanomsg = nng::Message::with_capacity(bytes.len());
-    nanomsg.push_front(bytes);
-
-    Ok(nanomsg)
+    Ok(fbb)
 }
 
 /// Only used for receiving messages from the Python process,
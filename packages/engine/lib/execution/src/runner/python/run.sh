@@ -3,7 +3,8 @@
 # Also may have to run `dos2unix ./run.sh` for 
 # `/bin/sh^M: bad interpreter` or bad line endings
 SCRIPT_DIR=$(dirname "$0") 
-VENV_ACTIVATE_PATH="$SCRIPT_DIR/runner_venv/bin/activate"
+# A project's own virtual environment, if the engine provides one
+VENV_ACTIVATE_PATH="${HASH_PYTHON_ENVIRONMENT:-$SCRIPT_DIR/runner_venv}/bin/activate"
 
 . $VENV_ACTIVATE_PATH
 
@@ -3,11 +3,12 @@ import sys
 import time
 
 from batch import Batches
-from context import SimInitContext
+from context import SimInitContext, load_raster
+from hash_util import FieldWriteError
 from fbs.RunnerInboundMsgPayload import RunnerInboundMsgPayload
 from package import Package
 from sim import Sim
-from message import Messenger
+from message import EmbeddedMessenger, Messenger
 from util import format_exc_info
 
 """
@@ -19,9 +20,16 @@ SLEEP_BEFORE_FREE = 2
 # We want to catch everything
 # pylint: disable=broad-except
 class Runner:
-    def __init__(self, experiment_id, worker_index):
+    def __init__(self, experiment_id, worker_index, channel=None):
+        """
+        :param channel: Channel to the engine if the runner is embedded into
+                        the engine process, otherwise nng sockets are opened.
+        """
         try:
-            self.messenger = Messenger(experiment_id, worker_index)
+            if channel is None:
+                self.messenger = Messenger(experiment_id, worker_index)
+            else:
+                self.messenger = EmbeddedMessenger(channel)
         except Exception as error:
             # Can't do much if messenger init fails.
             logging.error("Messenger init failed: %s", error)
@@ -309,7 +317,15 @@ class Runner:
             self.sims.pop(sim_id)
             return
 
-        changes = state.flush_changes(sim.schema)
+        try:
+            changes = state.flush_changes(sim.schema)
+        except FieldWriteError as error:
+            # Values not matching the agent schema were written by the package's user code
+            message = f"Package `{pkg.name}` run_task: {error}"
+            self.messenger.send_user_errors((message,), sim_id)
+            self.sims.pop(sim_id)
+            return
+
         if group_idx is not None:
             changes["i_group"] = group_idx
             changes = [changes]
@@ -319,17 +335,24 @@ class Runner:
         )
         # TODO: OPTIM chaining if `continuation.target == "Python"`
 
-    def ctx_batch_sync(self, sim_id, ctx_batch, cur_step):
+    def ctx_batch_sync(
+        self, sim_id, ctx_batch, cur_step, sim_globals=None, raster_batch=None
+    ):
         """
         Load one simulation run's context batch's shared memory segment
         (if necessary) and native columns from Arrow. Also update the
-        simulation run's current step.
+        simulation run's current step and, if they changed, its globals.
+        The raster layers are loaded on the first sync.
 
         :param sim_id: ID of the simulation run whose context batch to sync
         :param ctx_batch: Object describing how to sync the context batch, with
                           the batch's id, batch version and memory version.
         :param cur_step: Current step of the simulation run -- synced along
                          with the batch because it's also part of context
+        :param sim_globals: New globals of the simulation run, `None` if they
+                            didn't change
+        :param raster_batch: Object describing the batch with the raster
+                             layers, `None` if no layers are declared
         """
         sim = self.sims[sim_id]
 
@@ -338,6 +361,15 @@ class Runner:
 
         sim.context.set_batch(ctx_batch)
         sim.context.set_step(cur_step)
+        if sim_globals is not None:
+            sim.globals = sim_globals
+            sim.context.set_globals(sim_globals)
+
+        # The raster layers never move, so they only have to be loaded once.
+        if raster_batch is not None and not sim.context.has_raster():
+            # The schema of the raster layers is stored in the batch itself.
+            raster_batch = self.batches.sync(raster_batch)
+            sim.context.set_raster(load_raster(raster_batch))
 
     def _load_pools(self, sim, agent_pool, message_pool):
         """
@@ -348,8 +380,7 @@ class Runner:
         :param agent_pool: List of agent batch objects (i.e. objects with the agent schema)
         :param message_pool: List of message batch objects (i.e. objects with the message schema)
         """
-        # `group_index` is used for `agent_pool` and `message_pool`. Using `range(len(..))` is more
-        # readable as `enumerate(zip(...))`
+        # Using `range(len(..))` is more readable as `enumerate(...)`
         # pylint: disable=consider-using-enumerate
         for group_index in range(len(agent_pool)):
             agent_pool[group_index] = self.batches.sync(
@@ -359,10 +390,12 @@ class Runner:
                 sim.schema.agent, sim.state_loaders
             )
 
-            message_pool[group_index] = self.batches.sync(
-                message_pool[group_index], sim.schema.message
+        # The message pool of a state snapshot can have an additional batch with delayed messages.
+        for batch_index in range(len(message_pool)):
+            message_pool[batch_index] = self.batches.sync(
+                message_pool[batch_index], sim.schema.message
             )
-            message_pool[group_index].load_missing_cols(
+            message_pool[batch_index].load_missing_cols(
                 sim.schema.message, sim.state_loaders
             )
 
@@ -436,7 +469,13 @@ class Runner:
 
                 elif msg_type == RunnerInboundMsgPayload.ContextBatchSync:
                     logging.debug("Handling context batch sync")
-                    self.ctx_batch_sync(msg.sim_id, msg.batch, msg.cur_step)
+                    self.ctx_batch_sync(
+                        msg.sim_id,
+                        msg.batch,
+                        msg.cur_step,
+                        msg.globals,
+                        msg.raster_batch,
+                    )
 
                 elif msg_type == RunnerInboundMsgPayload.StateSync:
                     logging.debug("Handling state sync")
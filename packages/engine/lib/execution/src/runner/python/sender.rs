@@ -112,14 +112,28 @@ impl NngSender {
     }
 }
 
-// TODO: Make this function shorter.
 fn inbound_to_nng(
     sim_id: Option<SimulationId>,
     msg: &InboundToRunnerMsgPayload,
     task_payload_json: &Option<serde_json::Value>,
 ) -> PythonResult<nng::Message> {
-    let mut fbb = flatbuffers::FlatBufferBuilder::new();
-    let fbb = &mut fbb;
+    let fbb = inbound_to_fbs(sim_id, msg, task_payload_json)?;
+    let bytes = fbb.finished_data();
+
+    let mut nanomsg = nng::Message::with_capacity(bytes.len());
+    nanomsg.push_front(bytes);
+    Ok(nanomsg)
+}
+
+/// Builds the `RunnerInboundMsg` flatbuffer for `msg` and returns the finished builder.
+// TODO: Make this function shorter.
+pub(super) fn inbound_to_fbs(
+    sim_id: Option<SimulationId>,
+    msg: &InboundToRunnerMsgPayload,
+    task_payload_json: &Option<serde_json::Value>,
+) -> PythonResult<FlatBufferBuilder<'static>> {
+    let mut builder = flatbuffers::FlatBufferBuilder::new();
+    let fbb = &mut builder;
 
     let (msg, msg_type) = match msg {
         InboundToRunnerMsgPayload::TaskMsg(msg) => {
@@ -188,11 +202,22 @@ fn inbound_to_nng(
         }
         InboundToRunnerMsgPayload::ContextBatchSync(msg) => {
             let batch = batch_to_fbs(fbb, msg.context_batch.segment());
+            let globals = msg.globals.as_ref().map(|globals| {
+                let globals =
+                    serde_json::to_string(&globals.0).expect("Can serialize serde_json::Value");
+                fbb.create_string(&globals)
+            });
+            let raster_batch = msg
+                .raster_batch
+                .as_ref()
+                .map(|raster_batch| batch_to_fbs(fbb, raster_batch.read().segment()));
             let msg = flatbuffers_gen::sync_context_batch_generated::ContextBatchSync::create(
                 fbb,
                 &flatbuffers_gen::sync_context_batch_generated::ContextBatchSyncArgs {
                     context_batch: Some(batch),
                     current_step: msg.current_step as i64,
+                    globals,
+                    raster_batch,
                 },
             );
             (
@@ -284,11 +309,7 @@ fn inbound_to_nng(
         },
     );
     fbb.finish(msg, None);
-    let bytes = fbb.finished_data();
-
-    let mut nanomsg = nng::Message::with_capacity(bytes.len());
-    nanomsg.push_front(bytes);
-    Ok(nanomsg)
+    Ok(builder)
 }
 
 #[allow(clippy::type_complexity)]
@@ -97,7 +97,11 @@ class AgentState:
     # converted to a list if it's not one already.
 
     # `data` is an optional argument. `data` must be JSON-serializable.
-    def add_message(self, to, msg_type, data=None):
+    def add_message(self, to, msg_type, data=None, delay=None):
+        """
+        Send a message to `to`. If `delay` is given, the message is delivered after `delay`
+        steps instead of in the next step.
+        """
         idx = self.__dict__["__idx_in_group"]
         new_message = {
             "to": [to] if isinstance(to, str) else to,
@@ -106,6 +110,8 @@ class AgentState:
             if self.__dict__["__msgs_native"][idx]
             else json.dumps(data),
         }
+        if delay is not None:
+            new_message["delay"] = delay
         if not self.__dict__["__msgs"][idx]:
             self.__dict__["__msgs"][idx] = [new_message]
         else:
@@ -607,6 +607,15 @@ pub fn field_or_property<T: for<'de> Deserialize<'de> + Clone>(
     Ok(default)
 }
 
+/// Whether the `topology` global declares a geographic topology, i.e. positions are `[lon, lat]`
+/// in degrees and movement is in metres along the sphere.
+pub fn is_geographic(topology: Option<&serde_json::Value>) -> bool {
+    topology
+        .and_then(|topology| topology.get("geographic"))
+        .and_then(serde_json::Value::as_bool)
+        .unwrap_or(false)
+}
+
 #[derive(ThisError, Debug)]
 pub enum OptionNativeColumnExtError {
     #[error("Reference Unwrap failed on type: {0}")]
@@ -1,14 +1,26 @@
-use super::{error::SimulationError, Context, Result, SharedBehavior, State};
+use super::{
+    accessors::is_geographic, error::SimulationError, Context, Result, SharedBehavior, State,
+};
+use crate::package::simulation::state::topology::geo;
 
-pub fn behavior(state: &mut State<'_>, _context: &Context<'_>) -> Result<()> {
+pub fn behavior(state: &mut State<'_>, context: &Context<'_>) -> Result<()> {
+    // In a geographic topology, the direction is the displacement in metres towards east and north.
+    let geographic = is_geographic(context.globals.get("topology"));
     let mut position = state.take_position()?;
 
     for (i, direction) in state.direction()?.iter().enumerate() {
         if let Some(dir) = direction {
             let (dx, dy) = (dir.x(), dir.y());
             if let Some(pos) = &mut position[i] {
-                pos[0] += dx;
-                pos[1] += dy;
+                if geographic {
+                    let mut lon_lat = [pos[0], pos[1], pos[2]];
+                    geo::displace(&mut lon_lat, dx, dy);
+                    pos[0] = lon_lat[0];
+                    pos[1] = lon_lat[1];
+                } else {
+                    pos[0] += dx;
+                    pos[1] += dy;
+                }
             } else {
                 Err(SimulationError::from("Expected position to exist on agent"))?;
             }
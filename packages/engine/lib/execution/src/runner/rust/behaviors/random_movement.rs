@@ -1,8 +1,11 @@
 use rand::Rng;
 
 use super::{
-    accessors::field_or_property, error::SimulationError, Context, Result, SharedBehavior, State,
+    accessors::{field_or_property, is_geographic},
+    error::SimulationError,
+    Context, Result, SharedBehavior, State,
 };
+use crate::package::simulation::state::topology::geo;
 
 pub fn behavior(state: &mut State<'_>, context: &Context<'_>) -> Result<()> {
     fn get_satisfaction(neighbor_count: i64, min_neighbors: i64, max_neighbors: i64) -> bool {
@@ -49,6 +52,8 @@ pub fn behavior(state: &mut State<'_>, context: &Context<'_>) -> Result<()> {
     let random_movement_seek_max_neighbors_property =
         globals.get("random_movement_seek_max_neighbors").cloned();
     let random_movement_step_size_property = globals.get("random_movement_step_size").cloned();
+    // In a geographic topology, the step size is in metres along the sphere.
+    let geographic = is_geographic(globals.get("topology"));
 
     let mut position = state.take_position()?;
 
@@ -80,8 +85,15 @@ pub fn behavior(state: &mut State<'_>, context: &Context<'_>) -> Result<()> {
         )?;
 
         if let Some(pos) = &mut position[i] {
-            pos["x"] += step(step_size);
-            pos["y"] += step(step_size);
+            if geographic {
+                let mut lon_lat = [pos[0], pos[1], pos[2]];
+                geo::displace(&mut lon_lat, step(step_size), step(step_size));
+                pos[0] = lon_lat[0];
+                pos[1] = lon_lat[1];
+            } else {
+                pos["x"] += step(step_size);
+                pos["y"] += step(step_size);
+            }
         } else {
             Err(SimulationError::from("Expected position to exist on agent"))?;
         }
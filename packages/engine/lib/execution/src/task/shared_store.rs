@@ -245,13 +245,21 @@ pub struct DistributedBatch<A, M> {
     group_indices: Vec<usize>,
 }
 
-/// TODO: DOC
+/// Distributes the batches to the workers in `worker_list`.
+///
+/// If `assigned_workers` is set, every batch is sent to the worker it was assigned to by the load
+/// balancing of the simulation engine (see [`AgentBatch::worker_index`]), so batches only move
+/// between workers when the engine migrates them. Otherwise, the batches are distributed
+/// round-robin by their position in the pool.
+///
+/// [`AgentBatch::worker_index`]: stateful::agent::AgentBatch::worker_index
 fn distribute_batches<A, M>(
     worker_list: &WorkerAllocation,
     agent_batches: Vec<A>,
     msg_batches: Vec<M>,
     group_indices: Vec<usize>,
     group_sizes: Vec<usize>, // Number of agents in each group
+    assigned_workers: Option<Vec<usize>>,
 ) -> (Vec<DistributedBatch<A, M>>, SplitConfig) {
     // Initialize with empty distribution.
     let num_workers = worker_list.len();
@@ -273,7 +281,10 @@ fn distribute_batches<A, M>(
         .zip(msg_batches.into_iter())
         .enumerate();
     for (i_group, (agent_batch, msg_batch)) in iter {
-        let i_worker = i_group % num_workers;
+        let i_worker = assigned_workers
+            .as_ref()
+            .map_or(i_group, |workers| workers[i_group])
+            % num_workers;
         agent_distribution[i_worker] += group_sizes[i_group];
 
         let store = &mut stores[i_worker];
@@ -304,11 +315,15 @@ impl TaskSharedStore {
         }
     }
 
-    /// TODO: DOC
+    /// Splits the store into one store per worker in `worker_list`.
+    ///
+    /// If `balance_workers` is set, batches are distributed as assigned by the load balancing of
+    /// the simulation engine, see [`distribute_batches()`].
     pub fn distribute(
         self,
         distribution: &StateBatchDistribution,
         worker_list: &WorkerAllocation,
+        balance_workers: bool,
     ) -> Result<(Vec<(WorkerIndex, Self)>, SplitConfig)> {
         let reads_state = self.state.is_readonly();
         let writes_state = self.state.is_readwrite();
@@ -335,12 +350,19 @@ impl TaskSharedStore {
                 .iter()
                 .map(|proxy| proxy.num_agents())
                 .collect();
+            let assigned_workers = balance_workers.then(|| {
+                agent_batches
+                    .iter()
+                    .map(|proxy| proxy.worker_index)
+                    .collect()
+            });
             let (stores, split_config) = distribute_batches(
                 worker_list,
                 agent_batches,
                 msg_batches,
                 group_indices,
                 group_sizes,
+                assigned_workers,
             );
             let stores: Vec<_> = stores
                 .into_iter()
@@ -381,12 +403,19 @@ impl TaskSharedStore {
                 .iter()
                 .map(|batch| batch.num_agents())
                 .collect();
+            let assigned_workers = balance_workers.then(|| {
+                agent_batches
+                    .iter()
+                    .map(|batch| batch.worker_index)
+                    .collect()
+            });
             let (stores, split_config) = distribute_batches(
                 worker_list,
                 agent_batches,
                 msg_batches,
                 group_indices,
                 group_sizes,
+                assigned_workers,
             );
             let stores: Vec<_> = stores
                 .into_iter()
@@ -454,3 +483,39 @@ impl TaskSharedStore {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn distributed_groups(assigned_workers: Option<Vec<usize>>) -> (Vec<Vec<usize>>, SplitConfig) {
+        let (stores, split_config) = distribute_batches(
+            &vec![0, 1],
+            vec![(); 4],
+            vec![(); 4],
+            vec![0, 1, 2, 3],
+            vec![10, 20, 30, 40],
+            assigned_workers,
+        );
+        let groups = stores
+            .into_iter()
+            .map(|store| store.group_indices)
+            .collect();
+        (groups, split_config)
+    }
+
+    #[test]
+    fn distribute_round_robin() {
+        let (groups, split_config) = distributed_groups(None);
+        assert_eq!(groups, [vec![0, 2], vec![1, 3]]);
+        assert_eq!(split_config.num_workers, 2);
+        assert_eq!(split_config.agent_distribution, Some(vec![40, 60]));
+    }
+
+    #[test]
+    fn distribute_assigned_workers() {
+        let (groups, split_config) = distributed_groups(Some(vec![1, 1, 0, 1]));
+        assert_eq!(groups, [vec![2], vec![0, 1, 3]]);
+        assert_eq!(split_config.agent_distribution, Some(vec![30, 70]));
+    }
+}
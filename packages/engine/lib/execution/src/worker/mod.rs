@@ -11,7 +11,12 @@ mod pending;
 mod sync;
 mod task;
 
-use std::{collections::hash_map::Entry, future::Future, pin::Pin, time::Duration};
+use std::{
+    collections::hash_map::Entry,
+    future::Future,
+    pin::Pin,
+    time::{Duration, Instant},
+};
 
 use futures::{
     future::OptionFuture,
@@ -27,7 +32,10 @@ pub use self::{
     config::{RunnerSpawnConfig, WorkerConfig},
     handler::WorkerHandler,
     init::PackageInitMsgForWorker,
-    sync::{ContextBatchSync, StateSync, SyncCompletionReceiver, SyncPayload, WaitableStateSync},
+    sync::{
+        ContextBatchSync, StateSync, SyncCompletionReceiver, SyncCompletionSender, SyncPayload,
+        WaitableStateSync,
+    },
 };
 use crate::{
     package::{experiment::ExperimentId, simulation::SimulationId},
@@ -38,7 +46,10 @@ use crate::{
         },
         JavaScriptRunner, Language, MessageTarget, PythonRunner, RunnerConfig, RustRunner,
     },
-    task::{SharedState, TaskId, TaskMessage, TaskResultOrCancelled, TaskSharedStore},
+    task::{
+        PartialSharedState, SharedState, TaskId, TaskMessage, TaskResultOrCancelled,
+        TaskSharedStore,
+    },
     worker_pool::comms::{
         WorkerCommsWithWorkerPool, WorkerPoolToWorkerMsg, WorkerPoolToWorkerMsgPayload,
         WorkerToWorkerPoolMsg,
@@ -85,7 +96,11 @@ impl Worker {
         } = worker_config.spawn;
         // TODO: Rust, JS
         Ok(Self {
-            py: PythonRunner::new(python, exp_init.clone())?,
+            py: PythonRunner::new(
+                python,
+                worker_config.runner_config.python_runner_embedded,
+                exp_init.clone(),
+            )?,
             js: JavaScriptRunner::new(javascript, exp_init.clone())?,
             rs: RustRunner::new(rust, exp_init)?,
             _runner_config: worker_config.runner_config,
@@ -410,6 +425,8 @@ impl Worker {
     /// Handles the terminating message of a sub-task associated with a group (i.e. the last one in
     /// its execution chain, which will be sent to "Main")
     ///
+    /// - Records the execution time of the group on its batch, if the sub-task had write access to
+    /// a single group.
     /// - Drops the [`TaskSharedStore`] associated with the sub-task.
     /// - Removes the [`PendingGroup`] from the [`PendingWorkerTask`], and if there are no more
     /// [`PendingGroup`]s then the Task has finished and sends a message to runners to cancel any
@@ -421,8 +438,21 @@ impl Worker {
         group_index: Option<usize>,
         _source: Language,
         message: TaskMessage,
-        shared_store: TaskSharedStore,
+        mut shared_store: TaskSharedStore,
     ) -> Result<()> {
+        // The simulation engine uses the execution times to balance the batches across workers.
+        if let Some(task) = self.tasks.inner.get_mut(&task_id) {
+            let finished = Instant::now();
+            if let SharedState::Partial(PartialSharedState::Write(partial)) =
+                &mut shared_store.state
+            {
+                for batch in partial.state_proxy.agent_pool_mut().batches_iter_mut() {
+                    batch.set_execution_time(finished - task.last_completion);
+                }
+            }
+            task.last_completion = finished;
+        }
+
         // `shared_store` metaversioning should have been kept updated
         // by the runners, so it doesn't need to be updated at this point.
         // It's important to drop here since we then lose the access to the shared store,
@@ -626,6 +656,7 @@ impl Worker {
     /// [`PartialSharedState::split_into_individual_per_group()`]: crate::task::PartialSharedState::split_into_individual_per_group
     async fn spawn_task(&mut self, sim_id: SimulationId, task: WorkerTask) -> Result<()> {
         let task_id = task.task_id;
+        let started = Instant::now();
         let msg = WorkerHandler::start_message(&task.task)?;
 
         let context = task.shared_store.context().clone();
@@ -694,6 +725,7 @@ impl Worker {
                 pending_groups,
                 final_task_messages: Vec::new(),
                 cancelling: CancelState::None,
+                last_completion: started,
             })
             .is_some()
         {
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Instant};
 
 use crate::{
     package::simulation::PackageTask,
@@ -29,6 +29,12 @@ pub struct PendingWorkerTask {
     pub final_task_messages: Vec<TaskMessage>,
     // TODO: UNUSED: Needs triage
     pub cancelling: CancelState,
+    /// When the last group of the task finished, or when the task was spawned.
+    ///
+    /// The runners execute the groups one after another, so the time elapsed since then is the
+    /// execution time of the group finishing next. This is only an approximation if the groups
+    /// are executed by different runners.
+    pub last_completion: Instant,
 }
 
 impl PendingWorkerTask {
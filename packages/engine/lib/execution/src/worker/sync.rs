@@ -1,7 +1,12 @@
 use std::{fmt, sync::Arc};
 
 use futures::future::join_all;
-use stateful::{context::ContextBatch, state::StateReadProxy};
+use parking_lot::RwLock;
+use stateful::{
+    context::{ContextBatch, RasterBatch},
+    global::Globals,
+    state::StateReadProxy,
+};
 
 use crate::{Error, Result};
 
@@ -86,6 +91,12 @@ pub struct ContextBatchSync {
     pub context_batch: Arc<ContextBatch>,
     pub current_step: usize,
     pub state_group_start_indices: Arc<Vec<usize>>,
+    /// The globals of the simulation run, if they changed at the beginning of this step.
+    pub globals: Option<Arc<Globals>>,
+    /// The raster layers of the simulation run, if any are declared.
+    ///
+    /// The layers stay in the same shared memory segment, so runners only need to load it once.
+    pub raster_batch: Option<Arc<RwLock<RasterBatch>>>,
 }
 
 impl fmt::Debug for ContextBatchSync {
@@ -291,8 +291,11 @@ impl WorkerPool {
     async fn handle_exp_msg(&mut self, msg: ExperimentToWorkerPoolMsg) -> Result<()> {
         match msg {
             ExperimentToWorkerPoolMsg::NewSimulationRun(payload) => {
-                self.simulation_runs
-                    .push(payload.short_id, payload.worker_allocation.as_ref().clone())?;
+                self.simulation_runs.push(
+                    payload.short_id,
+                    payload.worker_allocation.as_ref().clone(),
+                    payload.balance_workers,
+                )?;
                 self.register_simulation(payload).await?
             }
         }
@@ -418,8 +421,9 @@ impl WorkerPool {
 
         let (triples, original_task) =
             if let TaskDistributionConfig::Distributed(distribution) = task.distribution() {
+                let balance_workers = self.simulation_runs.balances_workers(sim_id)?;
                 let (distributed_tables, split_config) =
-                    shared_store.distribute(&distribution, worker_list)?;
+                    shared_store.distribute(&distribution, worker_list, balance_workers)?;
                 let tasks: Vec<PackageTask> = task.split_task(&split_config)?;
                 (
                     tasks
@@ -2,10 +2,15 @@ use std::collections::HashMap;
 
 use crate::{package::simulation::SimulationId, worker_pool::WorkerAllocation, Error, Result};
 
+struct SimulationRun {
+    worker_allocation: WorkerAllocation,
+    balance_workers: bool,
+}
+
 #[derive(Default)]
 pub struct SimulationRuns {
     // Associates a simulation run with the workers available to it
-    worker_allocations: HashMap<SimulationId, WorkerAllocation>,
+    runs: HashMap<SimulationId, SimulationRun>,
 }
 
 impl SimulationRuns {
@@ -13,16 +18,28 @@ impl SimulationRuns {
         &mut self,
         sim_id: SimulationId,
         worker_allocation: WorkerAllocation,
+        balance_workers: bool,
     ) -> Result<()> {
-        self.worker_allocations
-            .try_insert(sim_id, worker_allocation)
+        self.runs
+            .try_insert(sim_id, SimulationRun {
+                worker_allocation,
+                balance_workers,
+            })
             .map_err(|_| Error::from("Occupied hashmap key"))?;
         Ok(())
     }
 
+    fn get(&self, id: SimulationId) -> Result<&SimulationRun> {
+        self.runs.get(&id).ok_or(Error::MissingSimulationWithId(id))
+    }
+
     pub fn get_worker_allocation(&self, id: SimulationId) -> Result<&WorkerAllocation> {
-        self.worker_allocations
-            .get(&id)
-            .ok_or(Error::MissingSimulationWithId(id))
+        Ok(&self.get(id)?.worker_allocation)
+    }
+
+    /// Returns if the batches of the simulation run are balanced across the workers by the
+    /// simulation engine.
+    pub fn balances_workers(&self, id: SimulationId) -> Result<bool> {
+        Ok(self.get(id)?.balance_workers)
     }
 }
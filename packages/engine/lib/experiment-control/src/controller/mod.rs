@@ -25,7 +25,7 @@ use simulation_control::{
         Comms,
     },
     controller::{Packages, SimControl, SimulationController, SimulationRuns},
-    EngineStatus, SimStatus,
+    EngineStatus, SimStatus, LOAD_BALANCING_GLOBAL,
 };
 use stateful::global::{Globals, SharedStore};
 use tracing::{Instrument, Span};
@@ -249,6 +249,7 @@ impl<P: OutputPersistenceCreator> ExperimentController<P> {
                     packages: sim_start_msgs,
                     datastore: datastore_payload,
                     globals: globals.clone(),
+                    balance_workers: globals.get(LOAD_BALANCING_GLOBAL).is_some(),
                 },
             ))
             .await?;
@@ -352,6 +352,12 @@ fn worker_pool_exit_logic(
     false
 }
 
+/// Reuse the shared-memory segments of dropped batches for new batches of the experiment, see
+/// [`shared_memory::pool`].
+pub fn enable_segment_pool(experiment_id: ExperimentId) {
+    shared_memory::pool::enable(experiment_id.as_uuid());
+}
+
 /// Forcefully clean-up resources created by the experiment
 pub fn cleanup_experiment(experiment_id: ExperimentId) {
     if let Err(err) = shared_memory::cleanup_by_base_id(experiment_id.as_uuid()) {
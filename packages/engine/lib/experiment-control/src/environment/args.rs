@@ -89,6 +89,22 @@ pub struct Args {
     /// Defaults to V8's `max_heap_size` default.
     #[cfg_attr(feature = "clap", clap(long))]
     pub js_runner_max_heap_size: Option<usize>,
+
+    /// Don't start the JavaScript runners from a cached V8 startup snapshot, each runner evaluates
+    /// its JavaScript itself instead.
+    #[cfg_attr(feature = "clap", clap(long))]
+    pub js_runner_disable_snapshot: bool,
+
+    /// Virtual environment to run the Python runner in instead of the runner's own `runner_venv`.
+    ///
+    /// The environment has to contain the runner's requirements and its Cython extension built in
+    /// the `hash_runner` folder of the environment.
+    #[cfg_attr(feature = "clap", clap(long))]
+    pub python_environment: Option<PathBuf>,
+
+    /// Reuse the shared-memory segments of dropped batches for new batches of the experiment.
+    #[cfg_attr(feature = "clap", clap(long))]
+    pub shared_memory_pool: bool,
 }
 
 impl Args {
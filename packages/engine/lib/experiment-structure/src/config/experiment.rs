@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use error_stack::{IntoReport, ResultExt};
+use error_stack::{IntoReport, Report, ResultExt};
 use execution::{
     package::simulation::init::{InitPackageName, InitialStateName},
     runner::RunnerConfig,
@@ -14,6 +14,25 @@ use crate::{
     ExperimentRun, PackageConfig, PackageConfigBuilder,
 };
 
+/// Global choosing where the Python runners run: `"process"` (the default) runs them in a child
+/// process per worker, `"embedded"` runs the runner of the first worker in an interpreter embedded
+/// into the engine process.
+///
+/// The runners are shared by all simulation runs, so only the globals of the project are read, not
+/// the globals changed by the experiment.
+const PYTHON_RUNNER_GLOBAL: &str = "python_runner";
+
+fn python_runner_embedded(globals: &Globals) -> Result<bool> {
+    match globals.get(PYTHON_RUNNER_GLOBAL) {
+        None => Ok(false),
+        Some(serde_json::Value::String(runner)) if runner == "process" => Ok(false),
+        Some(serde_json::Value::String(runner)) if runner == "embedded" => Ok(true),
+        Some(runner) => Err(Report::new(ConfigError).attach_printable(format!(
+            "`{PYTHON_RUNNER_GLOBAL}` in globals must be \"process\" or \"embedded\", got {runner}"
+        ))),
+    }
+}
+
 #[derive(Clone)]
 /// Experiment level configuration
 pub struct ExperimentConfig {
@@ -47,7 +66,10 @@ impl ExperimentConfig {
 
         let worker_config = WorkerConfig {
             spawn: experiment_run.create_runner_spawn_config(),
-            runner_config,
+            runner_config: RunnerConfig {
+                python_runner_embedded: python_runner_embedded(&base_globals)?,
+                ..runner_config
+            },
         };
         let worker_pool = Arc::new(WorkerPoolConfig::new(worker_config, num_workers));
 
@@ -60,3 +82,17 @@ impl ExperimentConfig {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn python_runner_global() {
+        let globals = |src: &str| Globals::from_json(serde_json::from_str(src).unwrap()).unwrap();
+        assert!(!python_runner_embedded(&globals("{}")).unwrap());
+        assert!(!python_runner_embedded(&globals(r#"{"python_runner": "process"}"#)).unwrap());
+        assert!(python_runner_embedded(&globals(r#"{"python_runner": "embedded"}"#)).unwrap());
+        assert!(python_runner_embedded(&globals(r#"{"python_runner": true}"#)).is_err());
+    }
+}
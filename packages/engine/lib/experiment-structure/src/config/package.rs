@@ -37,6 +37,7 @@ impl PackageConfig {
         vec![
             StatePackageName::BehaviorExecution,
             StatePackageName::Topology,
+            StatePackageName::Raster,
         ]
     }
 
@@ -1,7 +1,9 @@
 use execution::package::experiment::ExperimentName;
+use serde::{Deserialize, Serialize};
 
 /// Specific configuration needed for either Experiments or single runs of Simulations.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
 #[cfg_attr(feature = "clap", derive(clap::Parser))]
 pub enum ExperimentType {
     /// Run a single simulation without an experiment.
@@ -1,5 +1,12 @@
 mod experiment_type;
 mod plan;
 mod run;
+mod sampling;
+mod sensitivity;
 
-pub use self::{experiment_type::ExperimentType, run::ExperimentRun};
+pub use self::{
+    experiment_type::ExperimentType,
+    run::ExperimentRun,
+    sampling::inverse_normal_cdf,
+    sensitivity::{MorrisIndices, SensitivityDesign, SensitivityIndices, SobolIndices},
+};
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use error_stack::{bail, IntoReport, Report, ResultExt};
 use execution::package::experiment::{
@@ -6,13 +6,15 @@ use execution::package::experiment::{
     ExperimentName, ExperimentPackageConfig,
 };
 use json_comments::StripComments;
-use rand::{distributions::Distribution, Rng, RngCore};
+use rand::{distributions::Distribution, rngs::StdRng, Rng, RngCore, SeedableRng};
 use rand_distr::{Beta, Gamma, LogNormal, Normal, Poisson};
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 use thiserror::Error;
 
-use crate::{experiment::ExperimentType, SimulationSource};
+use crate::{
+    experiment::{sampling, ExperimentType, SensitivityDesign},
+    SimulationSource,
+};
 
 #[derive(Debug, Error)]
 #[error("Could not read experiment plan")]
@@ -20,6 +22,10 @@ pub struct ExperimentPlanError;
 
 pub type Result<T, E = ExperimentPlanError> = error_stack::Result<T, E>;
 
+/// Fields of an experiment, which are passed as globals of the same name to the simulation runs:
+/// the stop conditions and the interventions.
+const ENGINE_FIELDS: [&str; 2] = ["stop_conditions", "interventions"];
+
 impl ExperimentType {
     /// Creates an experiment config from `ExperimentType`.
     ///
@@ -29,23 +35,35 @@ impl ExperimentType {
         self,
         simulation: &SimulationSource,
     ) -> Result<ExperimentPackageConfig> {
-        let basic = match self {
-            ExperimentType::SingleRun { num_steps } => {
-                BasicExperimentConfig::SingleRun(SingleRunExperimentConfig { num_steps })
-            }
-            ExperimentType::Simple { name } => BasicExperimentConfig::Simple(
-                get_simple_experiment_config(simulation, name)
-                    .attach_printable("Could not read simple experiment config")?,
+        self.get_package_config_and_design(simulation)
+            .map(|(config, _)| config)
+    }
+
+    /// Like [`get_package_config()`](Self::get_package_config), but also returns the design of
+    /// the runs for `sensitivity` experiments.
+    pub(crate) fn get_package_config_and_design(
+        self,
+        simulation: &SimulationSource,
+    ) -> Result<(ExperimentPackageConfig, Option<SensitivityDesign>)> {
+        let (basic, sensitivity) = match self {
+            ExperimentType::SingleRun { num_steps } => (
+                BasicExperimentConfig::SingleRun(SingleRunExperimentConfig { num_steps }),
+                None,
             ),
+            ExperimentType::Simple { name } => {
+                let (config, sensitivity) = get_simple_experiment_config(simulation, name)
+                    .attach_printable("Could not read simple experiment config")?;
+                (BasicExperimentConfig::Simple(config), sensitivity)
+            }
         };
-        Ok(ExperimentPackageConfig::Basic(basic))
+        Ok((ExperimentPackageConfig::Basic(basic), sensitivity))
     }
 }
 
 fn get_simple_experiment_config(
     simulation: &SimulationSource,
     experiment_name: ExperimentName,
-) -> Result<SimpleExperimentConfig> {
+) -> Result<(SimpleExperimentConfig, Option<SensitivityDesign>)> {
     let experiments_manifest = simulation
         .experiments_src
         .as_ref()
@@ -73,19 +91,18 @@ fn get_simple_experiment_config(
 
     let config = SimpleExperimentConfig {
         experiment_name,
+        // Every entry of the plan is one simulation run, which changes all of its fields, e.g. both
+        // the `x_field` and the `y_field` of a `meshgrid` experiment
         changed_globals: plan
             .inner
             .into_iter()
-            .flat_map(|v| {
-                v.fields
-                    .into_iter()
-                    .map(|(property_path, changed_value)| json!({ property_path: changed_value }))
-            })
+            .map(|v| serde_json::Value::Object(v.fields.into_iter().collect()))
             .collect(),
         num_steps: plan.num_steps,
         max_sims_in_parallel,
+        engine_globals: plan.engine_globals,
     };
-    Ok(config)
+    Ok((config, plan.sensitivity))
 }
 
 fn create_experiment_plan(
@@ -108,16 +125,26 @@ fn create_experiment_plan(
         .as_str()
         .ok_or_else(|| Report::new(ExperimentPlanError))
         .attach_printable("Expected experiment definition type to have a string value")?;
-    match experiment_type {
+    let mut plan = match experiment_type {
         "group" => create_group_variant(selected_experiment, experiments),
         "multiparameter" => create_multiparameter_variant(selected_experiment, experiments),
+        "sensitivity" => create_space_filling_variant_plan(selected_experiment, "sensitivity"),
         "optimization" => bail!(
             Report::new(ExperimentPlanError)
                 .attach_printable("Not implemented for optimization experiment types")
         ),
         _ => create_basic_variant(selected_experiment, experiment_type)
             .attach_printable("Could not parse basic variant"),
+    }?;
+
+    // Stop conditions and interventions of the experiment are read by the engine from the globals,
+    // they are not changed by the experiment
+    for field in ENGINE_FIELDS {
+        if let Some(value) = selected_experiment.get(field) {
+            plan.engine_globals.insert(field.to_string(), value.clone());
+        }
     }
+    Ok(plan)
 }
 
 fn create_multiparameter_variant(
@@ -198,6 +225,17 @@ fn create_group_variant(
         |mut acc, name| {
             let variants = create_experiment_plan(experiments, name)
                 .attach_printable("Could not read experiment plan")?;
+            if variants.sensitivity.is_some() {
+                bail!(Report::new(ExperimentPlanError).attach_printable(format!(
+                    "Sensitivity experiment {name} can't be part of a group"
+                )));
+            }
+            if !variants.engine_globals.is_empty() {
+                bail!(Report::new(ExperimentPlanError).attach_printable(format!(
+                    "Experiment {name} is part of a group, so {ENGINE_FIELDS:?} have to be \
+                     declared by the group"
+                )));
+            }
             variants.inner.into_iter().for_each(|v| {
                 acc.push(v);
             });
@@ -216,6 +254,9 @@ fn create_basic_variant(
         "linspace" => create_linspace_variant_plan(selected_experiment),
         "arange" => create_arange_variant_plan(selected_experiment),
         "meshgrid" => create_meshgrid_variant_plan(selected_experiment),
+        "latin-hypercube" | "sobol" => {
+            create_space_filling_variant_plan(selected_experiment, experiment_type)
+        }
         _ => bail!(
             Report::new(ExperimentPlanError)
                 .attach_printable(format!("Unknown experiment type: {experiment_type}"))
@@ -477,6 +518,233 @@ fn create_meshgrid_variant_plan(
     Ok(plan)
 }
 
+/// Creates a plan for the `latin-hypercube`, `sobol` and `sensitivity` experiment types, which
+/// sample several parameters at once, e.g.
+///
+/// ```json
+/// {
+///     "type": "latin-hypercube",
+///     "steps": 100,
+///     "samples": 64,
+///     "seed": 42,
+///     "parameters": {
+///         "infection_rate": { "min": 0.01, "max": 0.2 },
+///         "recovery_days": { "distribution": "normal", "mean": 14, "std": 3 },
+///         "contacts": { "distribution": "log-normal", "mu": 1.5, "sigma": 0.5 },
+///         "policy": { "distribution": "values", "values": ["none", "masks", "lockdown"] }
+///     }
+/// }
+/// ```
+///
+/// The points of the design are mapped to the parameters through the inverse of their cumulative
+/// distribution function. The design of `sobol` is deterministic, `seed` is only used by
+/// `latin-hypercube` and `sensitivity`.
+///
+/// `sensitivity` experiments additionally compute the sensitivity of an analysis output to the
+/// parameters after all runs have finished, e.g.
+///
+/// ```json
+/// {
+///     "type": "sensitivity",
+///     "method": "morris",
+///     "steps": 100,
+///     "output": "infected",
+///     "trajectories": 20,
+///     "levels": 4,
+///     "parameters": {
+///         "infection_rate": { "min": 0.01, "max": 0.2 },
+///         "recovery_days": { "distribution": "normal", "mean": 14, "std": 3 }
+///     }
+/// }
+/// ```
+///
+/// The `morris` method requires `trajectories * (parameters + 1)` runs, the `sobol` method
+/// requires `samples * (parameters + 2)` runs.
+fn create_space_filling_variant_plan(
+    selected_experiment: &serde_json::Value,
+    experiment_type: &str,
+) -> Result<SimpleExperimentPlan> {
+    #[derive(Serialize, Deserialize)]
+    struct SpaceFillingVariant {
+        #[serde(rename = "type")]
+        _type: String,
+        steps: f64,
+        samples: Option<f64>,
+        seed: Option<u64>,
+        // Sorted by name, so the parameters are assigned to the same dimensions for every run
+        parameters: BTreeMap<String, SampledParameter>,
+        // Only used by `sensitivity` experiments
+        method: Option<String>,
+        output: Option<String>,
+        trajectories: Option<f64>,
+        levels: Option<f64>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct SampledParameter {
+        distribution: Option<String>,
+        min: Option<f64>,
+        max: Option<f64>,
+        mean: Option<f64>,
+        std: Option<f64>,
+        mu: Option<f64>,
+        sigma: Option<f64>,
+        values: Option<Vec<serde_json::Value>>,
+    }
+
+    type Quantile = Box<dyn Fn(f64) -> serde_json::Value>;
+
+    impl SampledParameter {
+        /// Returns the function mapping a coordinate in `(0, 1)` to the value of the parameter.
+        fn quantile_fn(&self, field: &str) -> Result<Quantile> {
+            let require = |value: Option<f64>, name: &str| {
+                value
+                    .ok_or_else(|| Report::new(ExperimentPlanError))
+                    .attach_printable_lazy(|| {
+                        format!("Expected parameter `{field}` to specify `{name}`")
+                    })
+            };
+            let distribution = self.distribution.as_deref().unwrap_or("uniform");
+            Ok(match distribution {
+                "uniform" => {
+                    let min = require(self.min, "min")?;
+                    let max = require(self.max, "max")?;
+                    Box::new(move |u| (min + u * (max - min)).into()) as Quantile
+                }
+                "normal" => {
+                    let mean = require(self.mean, "mean")?;
+                    let std = require(self.std, "std")?;
+                    Box::new(move |u| (mean + std * sampling::inverse_normal_cdf(u)).into())
+                }
+                "log-normal" => {
+                    let mu = require(self.mu, "mu")?;
+                    let sigma = require(self.sigma, "sigma")?;
+                    Box::new(move |u| (mu + sigma * sampling::inverse_normal_cdf(u)).exp().into())
+                }
+                "values" => {
+                    let values = match &self.values {
+                        Some(values) if !values.is_empty() => values.clone(),
+                        _ => bail!(Report::new(ExperimentPlanError).attach_printable(format!(
+                            "Expected parameter `{field}` to specify a non-empty list of `values`"
+                        ))),
+                    };
+                    Box::new(move |u| {
+                        let index = (u * values.len() as f64) as usize;
+                        values[index.min(values.len() - 1)].clone()
+                    })
+                }
+                _ => bail!(Report::new(ExperimentPlanError).attach_printable(format!(
+                    "Unknown distribution of parameter `{field}`: {distribution}"
+                ))),
+            })
+        }
+    }
+
+    let var: SpaceFillingVariant = serde_json::from_value(selected_experiment.clone())
+        .into_report()
+        .change_context(ExperimentPlanError)
+        .attach_printable_lazy(|| format!("Could not create {experiment_type} variant"))?;
+    if var.parameters.is_empty() {
+        bail!(Report::new(ExperimentPlanError).attach_printable(format!(
+            "Expected {experiment_type} variant to have parameters"
+        )));
+    }
+    let quantiles = var
+        .parameters
+        .iter()
+        .map(|(field, parameter)| Ok((field, parameter.quantile_fn(field)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let samples = || {
+        let samples = var
+            .samples
+            .ok_or_else(|| Report::new(ExperimentPlanError))
+            .attach_printable_lazy(|| {
+                format!("Expected {experiment_type} variant to specify `samples`")
+            })?;
+        sample_count(samples, experiment_type)
+    };
+    let mut rng = var
+        .seed
+        .map_or_else(StdRng::from_entropy, StdRng::seed_from_u64);
+    let (points, sensitivity) = match experiment_type {
+        "sobol" => {
+            let samples = samples()?;
+            if samples > u32::MAX as usize {
+                bail!(Report::new(ExperimentPlanError).attach_printable(format!(
+                    "A sobol variant supports at most {} samples",
+                    u32::MAX
+                )));
+            }
+            let points = sampling::sobol(samples, quantiles.len())
+                .ok_or_else(|| Report::new(ExperimentPlanError))
+                .attach_printable_lazy(|| {
+                    format!(
+                        "A sobol variant supports at most {} parameters",
+                        sampling::SOBOL_MAX_DIMENSIONS
+                    )
+                })?;
+            (points, None)
+        }
+        "sensitivity" => {
+            let output = var
+                .output
+                .clone()
+                .ok_or_else(|| Report::new(ExperimentPlanError))
+                .attach_printable("Expected sensitivity variant to specify an `output`")?;
+            let parameters = var.parameters.keys().cloned().collect();
+            let (design, points) = match var.method.as_deref() {
+                Some("morris") => {
+                    let levels = var.levels.unwrap_or(4.0) as usize;
+                    if levels < 2 || levels % 2 != 0 {
+                        bail!(
+                            Report::new(ExperimentPlanError).attach_printable(
+                                "Expected the number of Morris levels to be even"
+                            )
+                        );
+                    }
+                    let trajectories = var.trajectories.unwrap_or(10.0) as usize;
+                    SensitivityDesign::morris(output, parameters, trajectories, levels, &mut rng)
+                }
+                Some("sobol") => SensitivityDesign::sobol(output, parameters, samples()?, &mut rng),
+                method => bail!(Report::new(ExperimentPlanError).attach_printable(format!(
+                    "Unknown sensitivity method: {}",
+                    method.unwrap_or_default()
+                ))),
+            };
+            (points, Some(design))
+        }
+        _ => {
+            let points = sampling::latin_hypercube(samples()?, quantiles.len(), &mut rng);
+            (points, None)
+        }
+    };
+
+    let mut plan = SimpleExperimentPlan::new(var.steps as usize);
+    for point in points {
+        let entry = quantiles
+            .iter()
+            .zip(point)
+            .map(|((field, quantile), u)| ((*field).clone(), quantile(u)))
+            .collect::<HashMap<_, _>>()
+            .into();
+        plan.push(entry);
+    }
+    plan.sensitivity = sensitivity;
+    Ok(plan)
+}
+
+/// Returns `samples` as number of samples, if it's a positive integer.
+fn sample_count(samples: f64, experiment_type: &str) -> Result<usize> {
+    if samples < 1.0 || samples.fract() != 0.0 || samples > usize::MAX as f64 {
+        bail!(Report::new(ExperimentPlanError).attach_printable(format!(
+            "Expected `samples` of {experiment_type} variant to be a positive integer, got \
+             {samples}"
+        )));
+    }
+    Ok(samples as usize)
+}
+
 fn linspace(start: f64, stop: f64, num_samples: usize) -> Vec<f64> {
     let mut samples = vec![];
     let length = (stop - start) / (num_samples - 1) as f64;
@@ -503,6 +771,10 @@ impl From<HashMap<String, serde_json::Value>> for ExperimentPlanEntry {
 struct SimpleExperimentPlan {
     inner: Vec<ExperimentPlanEntry>,
     num_steps: usize,
+    /// Set by `sensitivity` experiments, describes how the entries relate to each other.
+    sensitivity: Option<SensitivityDesign>,
+    /// The [`ENGINE_FIELDS`] declared by the experiment.
+    engine_globals: serde_json::Map<String, serde_json::Value>,
 }
 
 impl SimpleExperimentPlan {
@@ -510,6 +782,8 @@ impl SimpleExperimentPlan {
         SimpleExperimentPlan {
             inner: Vec::new(),
             num_steps,
+            sensitivity: None,
+            engine_globals: serde_json::Map::new(),
         }
     }
 
@@ -517,3 +791,127 @@ impl SimpleExperimentPlan {
         self.inner.push(value);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use execution::package::simulation::{
+        init::{InitialState, InitialStateName},
+        PackageInitConfig,
+    };
+    use serde_json::json;
+
+    use super::*;
+
+    fn simple_config(experiments: serde_json::Value) -> Result<SimpleExperimentConfig> {
+        let simulation = SimulationSource {
+            name: "test".to_string(),
+            globals_src: "{}".to_string(),
+            experiments_src: Some(experiments.to_string()),
+            datasets: Vec::new(),
+            node_modules: None,
+            python_requirements: None,
+            package_init: PackageInitConfig {
+                packages: Vec::new(),
+                initial_state: InitialState {
+                    name: InitialStateName::InitJson,
+                    src: "[]".to_string(),
+                },
+                behaviors: Vec::new(),
+            },
+        };
+        let (config, _) =
+            get_simple_experiment_config(&simulation, "experiment".to_string().into())?;
+        Ok(config)
+    }
+
+    fn changed_globals(experiment: serde_json::Value) -> Result<Vec<serde_json::Value>> {
+        simple_config(json!({ "experiment": experiment })).map(|config| config.changed_globals)
+    }
+
+    #[test]
+    fn meshgrid_changes_both_fields_per_run() {
+        let changed_globals = changed_globals(json!({
+            "type": "meshgrid",
+            "steps": 1,
+            "x_field": "x",
+            "y_field": "y",
+            "x": [0, 1, 2],
+            "y": [0, 1, 2],
+        }))
+        .unwrap();
+        assert_eq!(changed_globals, vec![
+            json!({ "x": 0.0, "y": 0.0 }),
+            json!({ "x": 0.0, "y": 1.0 }),
+            json!({ "x": 1.0, "y": 0.0 }),
+            json!({ "x": 1.0, "y": 1.0 }),
+        ]);
+    }
+
+    #[test]
+    fn latin_hypercube_changes_all_parameters_per_run() {
+        let experiment = json!({
+            "type": "latin-hypercube",
+            "steps": 1,
+            "samples": 8,
+            "seed": 42,
+            "parameters": {
+                "a": { "min": 0, "max": 1 },
+                "b": { "distribution": "values", "values": ["x", "y"] },
+            },
+        });
+        let changed_globals = changed_globals(experiment.clone()).unwrap();
+        assert_eq!(changed_globals.len(), 8);
+        for globals in &changed_globals {
+            let globals = globals.as_object().unwrap();
+            assert_eq!(globals.len(), 2);
+            assert!((0.0..1.0).contains(&globals["a"].as_f64().unwrap()));
+            assert!(globals["b"] == "x" || globals["b"] == "y");
+        }
+        assert_eq!(changed_globals, self::changed_globals(experiment).unwrap());
+    }
+
+    #[test]
+    fn rejects_fractional_samples() {
+        for samples in [json!(2.5), json!(0), json!(-4)] {
+            let result = changed_globals(json!({
+                "type": "sobol",
+                "steps": 1,
+                "samples": samples,
+                "parameters": { "a": { "min": 0, "max": 1 } },
+            }));
+            assert!(result.is_err(), "{samples} samples were accepted");
+        }
+    }
+
+    #[test]
+    fn engine_fields_are_not_changed_globals() {
+        let stop_conditions = json!([{ "type": "no-agents" }]);
+        let config = simple_config(json!({
+            "experiment": {
+                "type": "values",
+                "steps": 1,
+                "field": "a",
+                "values": [1, 2],
+                "stop_conditions": stop_conditions,
+            }
+        }))
+        .unwrap();
+        assert_eq!(config.changed_globals, vec![
+            json!({ "a": 1 }),
+            json!({ "a": 2 })
+        ]);
+        assert_eq!(config.engine_globals["stop_conditions"], stop_conditions);
+
+        let result = simple_config(json!({
+            "experiment": { "type": "group", "steps": 1, "runs": ["sweep"] },
+            "sweep": {
+                "type": "values",
+                "steps": 1,
+                "field": "a",
+                "values": [1, 2],
+                "stop_conditions": stop_conditions,
+            },
+        }));
+        assert!(result.is_err());
+    }
+}
@@ -8,7 +8,7 @@ use execution::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::SimulationSource;
+use crate::{experiment::SensitivityDesign, SimulationSource};
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct ExperimentRun {
@@ -16,6 +16,8 @@ pub struct ExperimentRun {
     id: ExperimentId,
     config: ExperimentPackageConfig,
     simulation: SimulationSource,
+    #[serde(default)]
+    sensitivity_design: Option<SensitivityDesign>,
 }
 
 impl ExperimentRun {
@@ -29,9 +31,17 @@ impl ExperimentRun {
             id: ExperimentId::generate(),
             config,
             simulation,
+            sensitivity_design: None,
         }
     }
 
+    /// Attaches the design of a `sensitivity` experiment, which is used to compute the
+    /// sensitivity indices after the experiment has finished.
+    pub fn with_sensitivity_design(mut self, design: SensitivityDesign) -> Self {
+        self.sensitivity_design = Some(design);
+        self
+    }
+
     pub fn id(&self) -> ExperimentId {
         self.id
     }
@@ -44,6 +54,10 @@ impl ExperimentRun {
         &self.config
     }
 
+    pub fn sensitivity_design(&self) -> Option<&SensitivityDesign> {
+        self.sensitivity_design.as_ref()
+    }
+
     pub fn simulation(&self) -> &SimulationSource {
         &self.simulation
     }
@@ -64,7 +78,7 @@ impl ExperimentRun {
 
     /// Returns `true` if the experiment uses the language's init or has any behavior of the
     /// language.
-    fn requires_runner(&self, language: Language) -> bool {
+    pub fn requires_runner(&self, language: Language) -> bool {
         #[allow(clippy::match_like_matches_macro)]
         let requires_init = match (language, &self.simulation.package_init.initial_state.name) {
             (Language::JavaScript, InitialStateName::InitJs) => true,
@@ -0,0 +1,211 @@
+//! Space-filling designs in the unit hypercube used by the `latin-hypercube` and `sobol`
+//! experiment types.
+
+use rand::{distributions::Open01, seq::SliceRandom, Rng};
+
+/// Number of bits of the Sobol points, this also limits the number of points to `2^32 - 1`.
+const SOBOL_BITS: usize = 32;
+
+/// Degree `s`, coefficients `a` and initial direction numbers `m` of the primitive polynomials
+/// used for the second and following dimensions of the Sobol sequence.
+///
+/// Taken from the `new-joe-kuo-6.21201` table by S. Joe and F. Y. Kuo, "Constructing Sobol
+/// sequences with better two-dimensional projections", SIAM J. Sci. Comput. 30, 2635-2654 (2008).
+const SOBOL_DIRECTIONS: [(u32, u32, &[u32]); 20] = [
+    (1, 0, &[1]),
+    (2, 1, &[1, 3]),
+    (3, 1, &[1, 3, 1]),
+    (3, 2, &[1, 1, 1]),
+    (4, 1, &[1, 1, 3, 3]),
+    (4, 4, &[1, 3, 5, 13]),
+    (5, 2, &[1, 1, 5, 5, 17]),
+    (5, 4, &[1, 1, 5, 5, 5]),
+    (5, 7, &[1, 1, 7, 11, 19]),
+    (5, 11, &[1, 1, 5, 1, 1]),
+    (5, 13, &[1, 1, 1, 3, 11]),
+    (5, 14, &[1, 3, 5, 5, 31]),
+    (6, 1, &[1, 3, 3, 9, 7, 49]),
+    (6, 13, &[1, 1, 1, 15, 21, 21]),
+    (6, 16, &[1, 3, 1, 13, 27, 49]),
+    (6, 19, &[1, 1, 1, 15, 7, 5]),
+    (6, 22, &[1, 3, 1, 15, 13, 25]),
+    (6, 25, &[1, 1, 5, 5, 19, 61]),
+    (7, 1, &[1, 3, 7, 11, 23, 15, 103]),
+    (7, 4, &[1, 3, 7, 13, 13, 15, 69]),
+];
+
+/// The maximum number of dimensions supported by [`sobol()`].
+pub const SOBOL_MAX_DIMENSIONS: usize = SOBOL_DIRECTIONS.len() + 1;
+
+/// Returns `samples` points of a Latin hypercube design with `dimensions` dimensions.
+///
+/// Every dimension is divided into `samples` intervals of equal size, and every interval contains
+/// exactly one point. All coordinates are in the open interval `(0, 1)`.
+pub fn latin_hypercube<R: Rng + ?Sized>(
+    samples: usize,
+    dimensions: usize,
+    rng: &mut R,
+) -> Vec<Vec<f64>> {
+    let mut points = vec![Vec::with_capacity(dimensions); samples];
+    let mut strata: Vec<usize> = (0..samples).collect();
+    for _ in 0..dimensions {
+        strata.shuffle(rng);
+        for (point, stratum) in points.iter_mut().zip(&strata) {
+            let offset: f64 = rng.sample(Open01);
+            point.push((*stratum as f64 + offset) / samples as f64);
+        }
+    }
+    points
+}
+
+/// Returns the first `samples` points of the Sobol sequence with `dimensions` dimensions.
+///
+/// The first point of the sequence, the origin, is skipped, so all coordinates are in the open
+/// interval `(0, 1)`. Returns `None` if more than [`SOBOL_MAX_DIMENSIONS`] dimensions are
+/// requested.
+pub fn sobol(samples: usize, dimensions: usize) -> Option<Vec<Vec<f64>>> {
+    if dimensions > SOBOL_MAX_DIMENSIONS {
+        return None;
+    }
+    let directions: Vec<[u32; SOBOL_BITS]> = (0..dimensions).map(direction_numbers).collect();
+
+    let mut current = vec![0_u32; dimensions];
+    let points = (0..samples as u32)
+        .map(|index| {
+            // Gray code construction: the next point flips the direction numbers belonging to the
+            // rightmost zero bit of the index
+            let bit = index.trailing_ones() as usize;
+            current
+                .iter_mut()
+                .zip(&directions)
+                .map(|(value, directions)| {
+                    *value ^= directions[bit];
+                    f64::from(*value) / 2_f64.powi(SOBOL_BITS as i32)
+                })
+                .collect()
+        })
+        .collect();
+    Some(points)
+}
+
+/// Returns the direction numbers of the `dimension`-th dimension (starting at zero), scaled to
+/// [`SOBOL_BITS`] bits.
+fn direction_numbers(dimension: usize) -> [u32; SOBOL_BITS] {
+    let mut directions = [0_u32; SOBOL_BITS];
+    if dimension == 0 {
+        for (i, direction) in directions.iter_mut().enumerate() {
+            *direction = 1 << (SOBOL_BITS - 1 - i);
+        }
+        return directions;
+    }
+
+    let (degree, coefficients, initial) = SOBOL_DIRECTIONS[dimension - 1];
+    let degree = degree as usize;
+    for i in 0..SOBOL_BITS {
+        directions[i] = if i < degree {
+            initial[i] << (SOBOL_BITS - 1 - i)
+        } else {
+            let mut direction = directions[i - degree] ^ (directions[i - degree] >> degree);
+            for k in 1..degree {
+                if (coefficients >> (degree - 1 - k)) & 1 == 1 {
+                    direction ^= directions[i - k];
+                }
+            }
+            direction
+        };
+    }
+    directions
+}
+
+/// Returns the inverse of the cumulative distribution function of the standard normal
+/// distribution for `p` in `(0, 1)`.
+///
+/// Uses the rational approximation by P. J. Acklam with a relative error below `1.15e-9`.
+pub fn inverse_normal_cdf(p: f64) -> f64 {
+    const A: [f64; 6] = [
+        -3.969683028665376e+01,
+        2.209460984245205e+02,
+        -2.759285104469687e+02,
+        1.38357751867269e+02,
+        -3.066479806614716e+01,
+        2.506628277459239e+00,
+    ];
+    const B: [f64; 5] = [
+        -5.447609879822406e+01,
+        1.615858368580409e+02,
+        -1.556989798598866e+02,
+        6.680131188771972e+01,
+        -1.328068155288572e+01,
+    ];
+    const C: [f64; 6] = [
+        -7.784894002430293e-03,
+        -3.223964580411365e-01,
+        -2.400758277161838e+00,
+        -2.549732539343734e+00,
+        4.374664141464968e+00,
+        2.938163982698783e+00,
+    ];
+    const D: [f64; 4] = [
+        7.784695709041462e-03,
+        3.224671290700398e-01,
+        2.445134137142996e+00,
+        3.754408661907416e+00,
+    ];
+    const P_LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p <= 1.0 - P_LOW {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    } else {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn sobol_points() {
+        let points = sobol(5, 2).unwrap();
+        assert_eq!(points, vec![
+            vec![0.5, 0.5],
+            vec![0.75, 0.25],
+            vec![0.25, 0.75],
+            vec![0.375, 0.375],
+            vec![0.875, 0.875],
+        ]);
+        assert!(sobol(1, SOBOL_MAX_DIMENSIONS + 1).is_none());
+    }
+
+    #[test]
+    fn latin_hypercube_strata() {
+        let samples = 10;
+        let points = latin_hypercube(samples, 3, &mut StdRng::seed_from_u64(0));
+        for dimension in 0..3 {
+            let mut strata: Vec<_> = points
+                .iter()
+                .map(|point| (point[dimension] * samples as f64) as usize)
+                .collect();
+            strata.sort_unstable();
+            assert_eq!(strata, (0..samples).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn inverse_normal() {
+        assert!(inverse_normal_cdf(0.5).abs() < 1e-9);
+        assert!((inverse_normal_cdf(0.975) - 1.959963984540054).abs() < 1e-8);
+        assert!((inverse_normal_cdf(0.01) + 2.326347874040841).abs() < 1e-8);
+    }
+}
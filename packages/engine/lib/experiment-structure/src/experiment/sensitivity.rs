@@ -0,0 +1,359 @@
+//! Run designs and indices of the `sensitivity` experiment type.
+//!
+//! The design is created together with the experiment plan, and determines which globals are
+//! changed by every simulation run. After the experiment has finished, the chosen analysis output
+//! of every run is passed to [`SensitivityDesign::analyze()`] to compute the indices.
+
+use rand::{seq::SliceRandom, Rng};
+use serde::{Deserialize, Serialize};
+
+use crate::experiment::sampling;
+
+/// A change of a single parameter between two consecutive runs of a Morris trajectory.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MorrisMove {
+    /// Index into [`SensitivityDesign::parameters()`].
+    pub parameter: usize,
+    /// Change of the parameter in the unit hypercube.
+    pub delta: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "method", rename_all = "kebab-case")]
+enum SensitivityMethod {
+    /// Elementary effects, every trajectory consists of one run per parameter plus the starting
+    /// run.
+    Morris { trajectories: Vec<Vec<MorrisMove>> },
+    /// Sobol indices using the estimators by Saltelli et al. (2010), every sample consists of the
+    /// runs `A`, `B` and one run `AB_i` for every parameter `i`.
+    Sobol { samples: usize },
+}
+
+/// Describes how the runs of a `sensitivity` experiment relate to each other.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SensitivityDesign {
+    output: String,
+    parameters: Vec<String>,
+    #[serde(flatten)]
+    method: SensitivityMethod,
+}
+
+impl SensitivityDesign {
+    /// Creates a Morris design with `trajectories` trajectories on a grid with `levels` levels.
+    ///
+    /// Returns the design and the points of every run in the unit hypercube. `levels` has to be
+    /// even, so every parameter changes by half of the unit interval in each move.
+    pub(crate) fn morris<R: Rng + ?Sized>(
+        output: String,
+        parameters: Vec<String>,
+        trajectories: usize,
+        levels: usize,
+        rng: &mut R,
+    ) -> (Self, Vec<Vec<f64>>) {
+        let num_parameters = parameters.len();
+        let half = levels / 2;
+        // Points are placed in the center of their level to stay inside of `(0, 1)`
+        let to_point = |grid: &[usize]| -> Vec<f64> {
+            grid.iter()
+                .map(|&level| (level as f64 + 0.5) / levels as f64)
+                .collect()
+        };
+
+        let mut points = Vec::with_capacity(trajectories * (num_parameters + 1));
+        let mut moves = Vec::with_capacity(trajectories);
+        for _ in 0..trajectories {
+            let mut order: Vec<usize> = (0..num_parameters).collect();
+            order.shuffle(rng);
+            let upwards: Vec<bool> = (0..num_parameters).map(|_| rng.gen()).collect();
+            let mut grid: Vec<usize> = upwards
+                .iter()
+                .map(|&up| rng.gen_range(0..half) + if up { 0 } else { half })
+                .collect();
+
+            points.push(to_point(&grid));
+            let mut trajectory = Vec::with_capacity(num_parameters);
+            for parameter in order {
+                let delta = if upwards[parameter] {
+                    grid[parameter] += half;
+                    half as f64 / levels as f64
+                } else {
+                    grid[parameter] -= half;
+                    -(half as f64) / levels as f64
+                };
+                points.push(to_point(&grid));
+                trajectory.push(MorrisMove { parameter, delta });
+            }
+            moves.push(trajectory);
+        }
+
+        let design = Self {
+            output,
+            parameters,
+            method: SensitivityMethod::Morris {
+                trajectories: moves,
+            },
+        };
+        (design, points)
+    }
+
+    /// Creates a design for Sobol indices with `samples` base samples.
+    ///
+    /// Returns the design and the points of every run in the unit hypercube. The matrices `A` and
+    /// `B` are independent Latin hypercubes, so the number of parameters is not limited.
+    pub(crate) fn sobol<R: Rng + ?Sized>(
+        output: String,
+        parameters: Vec<String>,
+        samples: usize,
+        rng: &mut R,
+    ) -> (Self, Vec<Vec<f64>>) {
+        let num_parameters = parameters.len();
+        let a = sampling::latin_hypercube(samples, num_parameters, rng);
+        let b = sampling::latin_hypercube(samples, num_parameters, rng);
+
+        let mut points = Vec::with_capacity(samples * (num_parameters + 2));
+        for (a, b) in a.into_iter().zip(b) {
+            for parameter in 0..num_parameters {
+                let mut ab = a.clone();
+                ab[parameter] = b[parameter];
+                points.push(ab);
+            }
+            // Insert `A` and `B` in front of the `AB_i` runs of this sample
+            let start = points.len() - num_parameters;
+            points.insert(start, b);
+            points.insert(start, a);
+        }
+
+        let design = Self {
+            output,
+            parameters,
+            method: SensitivityMethod::Sobol { samples },
+        };
+        (design, points)
+    }
+
+    /// The name of the analysis output the indices are computed for.
+    pub fn output(&self) -> &str {
+        &self.output
+    }
+
+    /// The names of the globals varied by the design.
+    pub fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
+
+    /// The number of simulation runs required by the design.
+    pub fn num_runs(&self) -> usize {
+        match &self.method {
+            SensitivityMethod::Morris { trajectories } => {
+                trajectories.len() * (self.parameters.len() + 1)
+            }
+            SensitivityMethod::Sobol { samples } => samples * (self.parameters.len() + 2),
+        }
+    }
+
+    /// Computes the sensitivity indices from the output of every run, in the order of the runs.
+    ///
+    /// Runs without an output (e.g. because the simulation failed) are skipped, together with the
+    /// runs depending on them.
+    pub fn analyze(&self, outputs: &[Option<f64>]) -> SensitivityIndices {
+        let output = |run: usize| outputs.get(run).copied().flatten();
+        let num_parameters = self.parameters.len();
+        match &self.method {
+            SensitivityMethod::Morris { trajectories } => {
+                let mut effects = vec![Vec::new(); num_parameters];
+                for (index, trajectory) in trajectories.iter().enumerate() {
+                    let first_run = index * (num_parameters + 1);
+                    for (step, change) in trajectory.iter().enumerate() {
+                        if let (Some(before), Some(after)) =
+                            (output(first_run + step), output(first_run + step + 1))
+                        {
+                            effects[change.parameter].push((after - before) / change.delta);
+                        }
+                    }
+                }
+                SensitivityIndices::Morris {
+                    output: self.output.clone(),
+                    parameters: self
+                        .parameters
+                        .iter()
+                        .zip(effects)
+                        .map(|(name, effects)| MorrisIndices::new(name.clone(), &effects))
+                        .collect(),
+                }
+            }
+            SensitivityMethod::Sobol { samples } => {
+                let group = num_parameters + 2;
+                let complete: Vec<Vec<f64>> = (0..*samples)
+                    .filter_map(|sample| {
+                        (sample * group..(sample + 1) * group)
+                            .map(output)
+                            .collect::<Option<Vec<_>>>()
+                    })
+                    .collect();
+                let n = complete.len() as f64;
+
+                let a_and_b = complete.iter().flat_map(|runs| [runs[0], runs[1]]);
+                let mean = a_and_b.clone().sum::<f64>() / (2.0 * n);
+                let variance = a_and_b.map(|y| (y - mean).powi(2)).sum::<f64>() / (2.0 * n);
+                let valid = !complete.is_empty() && variance > 0.0;
+
+                let parameters = self
+                    .parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(parameter, name)| {
+                        let (mut first, mut total) = (0.0, 0.0);
+                        for runs in &complete {
+                            let (a, b, ab) = (runs[0], runs[1], runs[parameter + 2]);
+                            first += b * (ab - a);
+                            total += (a - ab).powi(2);
+                        }
+                        SobolIndices {
+                            name: name.clone(),
+                            first_order: valid.then(|| first / n / variance),
+                            total_order: valid.then(|| total / (2.0 * n) / variance),
+                        }
+                    })
+                    .collect();
+                SensitivityIndices::Sobol {
+                    output: self.output.clone(),
+                    samples: complete.len(),
+                    variance: valid.then_some(variance),
+                    parameters,
+                }
+            }
+        }
+    }
+}
+
+/// Elementary effects of a single parameter.
+#[derive(Debug, Clone, Serialize)]
+pub struct MorrisIndices {
+    pub name: String,
+    /// Number of elementary effects computed.
+    pub effects: usize,
+    /// Mean of the elementary effects.
+    pub mu: Option<f64>,
+    /// Mean of the absolute elementary effects, used to rank the parameters.
+    pub mu_star: Option<f64>,
+    /// Standard deviation of the elementary effects, indicating interactions and non-linearity.
+    pub sigma: Option<f64>,
+}
+
+impl MorrisIndices {
+    fn new(name: String, effects: &[f64]) -> Self {
+        let n = effects.len() as f64;
+        let mu = (!effects.is_empty()).then(|| effects.iter().sum::<f64>() / n);
+        let mu_star =
+            (!effects.is_empty()).then(|| effects.iter().map(|e| e.abs()).sum::<f64>() / n);
+        let sigma = mu
+            .filter(|_| effects.len() > 1)
+            .map(|mu| (effects.iter().map(|e| (e - mu).powi(2)).sum::<f64>() / (n - 1.0)).sqrt());
+        Self {
+            name,
+            effects: effects.len(),
+            mu,
+            mu_star,
+            sigma,
+        }
+    }
+}
+
+/// Sobol indices of a single parameter.
+#[derive(Debug, Clone, Serialize)]
+pub struct SobolIndices {
+    pub name: String,
+    /// Share of the output variance caused by the parameter alone.
+    pub first_order: Option<f64>,
+    /// Share of the output variance caused by the parameter including all interactions.
+    pub total_order: Option<f64>,
+}
+
+/// Result of [`SensitivityDesign::analyze()`].
+///
+/// Indices are `None` if they couldn't be computed because too many runs were missing.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "method", rename_all = "kebab-case")]
+pub enum SensitivityIndices {
+    Morris {
+        output: String,
+        parameters: Vec<MorrisIndices>,
+    },
+    Sobol {
+        output: String,
+        /// Number of samples where all runs produced the output.
+        samples: usize,
+        /// Variance of the output.
+        variance: Option<f64>,
+        parameters: Vec<SobolIndices>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use super::*;
+
+    // y = 4 * x0 + x1 + 0 * x2
+    fn linear_model(point: &[f64]) -> Option<f64> {
+        Some(4.0 * point[0] + point[1])
+    }
+
+    fn parameters() -> Vec<String> {
+        vec!["a".to_string(), "b".to_string(), "c".to_string()]
+    }
+
+    #[test]
+    fn morris() {
+        let (design, points) = SensitivityDesign::morris(
+            "output".to_string(),
+            parameters(),
+            10,
+            4,
+            &mut StdRng::seed_from_u64(0),
+        );
+        assert_eq!(points.len(), design.num_runs());
+        let outputs: Vec<_> = points.iter().map(|point| linear_model(point)).collect();
+
+        let indices = match design.analyze(&outputs) {
+            SensitivityIndices::Morris { parameters, .. } => parameters,
+            _ => unreachable!(),
+        };
+        let mu_star: Vec<_> = indices.iter().map(|index| index.mu_star.unwrap()).collect();
+        assert!((mu_star[0] - 4.0).abs() < 1e-9);
+        assert!((mu_star[1] - 1.0).abs() < 1e-9);
+        assert!(mu_star[2].abs() < 1e-9);
+        assert!(indices.iter().all(|index| index.effects == 10));
+    }
+
+    #[test]
+    fn sobol() {
+        let (design, points) = SensitivityDesign::sobol(
+            "output".to_string(),
+            parameters(),
+            2000,
+            &mut StdRng::seed_from_u64(0),
+        );
+        assert_eq!(points.len(), design.num_runs());
+        let mut outputs: Vec<_> = points.iter().map(|point| linear_model(point)).collect();
+        // A failed run drops its sample
+        outputs[3] = None;
+
+        let (samples, indices) = match design.analyze(&outputs) {
+            SensitivityIndices::Sobol {
+                samples,
+                parameters,
+                ..
+            } => (samples, parameters),
+            _ => unreachable!(),
+        };
+        assert_eq!(samples, 1999);
+        // The variances of the terms are 16/12 and 1/12
+        let expected = [16.0 / 17.0, 1.0 / 17.0, 0.0];
+        for (index, expected) in indices.iter().zip(expected) {
+            assert!((index.first_order.unwrap() - expected).abs() < 0.05);
+            assert!((index.total_order.unwrap() - expected).abs() < 0.05);
+        }
+    }
+}
@@ -11,12 +11,20 @@ mod error;
 mod experiment;
 mod manifest;
 mod simulation;
+mod validation;
 
 pub use self::{
     config::{ExperimentConfig, PackageConfig, PackageConfigBuilder},
     dependencies::FetchDependencies,
     error::{Error, Result},
-    experiment::{ExperimentRun, ExperimentType},
+    experiment::{
+        inverse_normal_cdf, ExperimentRun, ExperimentType, MorrisIndices, SensitivityDesign,
+        SensitivityIndices, SobolIndices,
+    },
     manifest::Manifest,
-    simulation::{PackageCreators, SimulationConfig, SimulationRunConfig, SimulationSource},
+    simulation::{
+        PackageCreators, PythonRequirements, SimulationConfig, SimulationRunConfig,
+        SimulationSource,
+    },
+    validation::ProjectProblem,
 };
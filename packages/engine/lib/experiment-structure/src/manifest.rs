@@ -20,7 +20,7 @@ use thiserror::Error;
 
 use crate::{
     dependencies::parse_raw_csv_into_json, experiment::ExperimentType, ExperimentRun,
-    SimulationSource,
+    PythonRequirements, SimulationSource,
 };
 
 #[derive(Debug, Error)]
@@ -51,10 +51,20 @@ pub struct Manifest {
     /// JSON string describing the analysis that's calculated by the
     /// [analysis output package](execution::package::simulation::output::analysis).
     pub analysis_json: Option<String>,
+    /// JSON string configuring the output packages, e.g. the
+    /// [JSON state output package](execution::package::simulation::output::json_state).
+    pub output_json: Option<String>,
+    /// JSON string declaring the types, nullability and defaults of agent fields, see
+    /// [`AgentSchemaDeclaration`](execution::package::simulation::init::AgentSchemaDeclaration).
+    pub schema_json: Option<String>,
     /// JSON string describing the structure of available experiments for this project.
     pub experiments_json: Option<String>,
     /// A list of all dependencies identified by its name.
     pub dependencies: HashMap<String, serde_json::Value>,
+    /// The `node_modules` folder JavaScript behaviors import packages from.
+    pub node_modules: Option<PathBuf>,
+    /// The Python packages the Python runner has to be able to import.
+    pub python_requirements: Option<PythonRequirements>,
 }
 
 impl Manifest {
@@ -165,6 +175,26 @@ impl Manifest {
         Ok(())
     }
 
+    /// Reads the content from the file at the provided `path` configuring the output packages.
+    ///
+    /// # Errors
+    ///
+    /// - if the file referred by `path` could not be read
+    pub fn set_output_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.output_json.replace(file_contents(path)?);
+        Ok(())
+    }
+
+    /// Reads the content from the file at the provided `path` declaring the agent fields.
+    ///
+    /// # Errors
+    ///
+    /// - if the file referred by `path` could not be read
+    pub fn set_schema_from_file<P: AsRef<Path>>(&mut self, path: P) -> Result<()> {
+        self.schema_json.replace(file_contents(path)?);
+        Ok(())
+    }
+
     /// Reads the content from the file at the provided `path` describing the structure of available
     /// experiments for this project.
     ///
@@ -176,6 +206,38 @@ impl Manifest {
         Ok(())
     }
 
+    /// Reads the Python packages required by the project at `project_path` from its
+    /// `requirements.txt` or, if it doesn't exist, from the `dependencies` in the `[project]` table
+    /// of its `pyproject.toml`.
+    ///
+    /// # Errors
+    ///
+    /// - if the file could not be read
+    /// - if the `pyproject.toml` could not be parsed
+    pub fn set_python_requirements_from_directory<P: AsRef<Path>>(
+        &mut self,
+        project_path: P,
+    ) -> Result<()> {
+        let project_path = project_path.as_ref();
+        let requirements_txt = project_path.join("requirements.txt");
+        let pyproject_toml = project_path.join("pyproject.toml");
+        self.python_requirements =
+            if let Some(requirements_txt) = file_contents_opt(requirements_txt)? {
+                Some(PythonRequirements {
+                    project_path: project_path.to_path_buf(),
+                    requirements_txt,
+                })
+            } else if let Some(content) = file_contents_opt(&pyproject_toml)? {
+                PythonRequirements::from_pyproject_toml(project_path.to_path_buf(), &content)
+                    .into_report()
+                    .attach_printable_lazy(|| format!("Could not parse {pyproject_toml:?}"))
+                    .change_context(ManifestError)?
+            } else {
+                None
+            };
+        Ok(())
+    }
+
     /// Reads the content from the file at the provided `path` describing the dependencies for this
     /// project.
     ///
@@ -419,8 +481,15 @@ impl Manifest {
     ///   [`set_experiments_from_file("experiments.json")`](Self::set_experiments_from_file)
     /// - Analysis JSON as specified in
     ///   [`set_analysis_from_file("views/analysis.json")`](Self::set_analysis_from_file)
+    /// - Output JSON as specified in
+    ///   [`set_output_from_file("output.json")`](Self::set_output_from_file)
+    /// - Schema JSON as specified in
+    ///   [`set_schema_from_file("schema.json")`](Self::set_schema_from_file)
     /// - Dependencies recursively as provided by
     ///   [`set_dependencies_from_file("dependencies.json")`](Self::set_dependencies_from_file)
+    /// - The [`node_modules`](Self::node_modules) folder, if it exists
+    /// - Python requirements of the project directory as specified in
+    ///   [`set_python_requirements_from_directory()`](Self::set_python_requirements_from_directory)
     pub fn from_local<P: AsRef<Path>>(project_path: P) -> Result<Self> {
         Self::from_local_impl(project_path, false)
     }
@@ -455,6 +524,8 @@ impl Manifest {
             .to_string_lossy()
             .to_string();
         let experiments_json = project_path.join("experiments.json");
+        let output_json = project_path.join("output.json");
+        let schema_json = project_path.join("schema.json");
         let dependencies_json = project_path.join("dependencies.json");
         let src_folder = project_path.join("src");
         let behaviors_folder = src_folder.join("behaviors");
@@ -463,12 +534,20 @@ impl Manifest {
         let analysis_json = views_folder.join("analysis.json");
         let data_folder = project_path.join("data");
         let dependencies_folder = project_path.join("dependencies");
+        let node_modules_folder = project_path.join("node_modules");
 
         let mut project = Manifest::new();
 
         if !is_dependency {
             project.project_name = project_name;
 
+            if node_modules_folder.is_dir() {
+                project.node_modules = Some(node_modules_folder);
+            }
+            project
+                .set_python_requirements_from_directory(project_path)
+                .attach_printable("Could not read Python requirements")?;
+
             project
                 .set_initial_state_from_directory(src_folder)
                 .attach_printable("Could not read initial state")?;
@@ -483,6 +562,16 @@ impl Manifest {
                     .set_analysis_from_file(analysis_json)
                     .attach_printable("Could not read analysis view")?;
             }
+            if output_json.exists() {
+                project
+                    .set_output_from_file(output_json)
+                    .attach_printable("Could not read output config")?;
+            }
+            if schema_json.exists() {
+                project
+                    .set_schema_from_file(schema_json)
+                    .attach_printable("Could not read agent schema")?;
+            }
 
             if experiments_json.exists() {
                 project
@@ -532,13 +621,25 @@ impl Manifest {
             globals_src: self.globals_json.unwrap_or_else(|| "{}".to_string()),
             experiments_src: self.experiments_json,
             datasets: self.datasets,
+            node_modules: self.node_modules,
+            python_requirements: self.python_requirements,
             // TODO: allow packages themselves to implement resolvers for local projects to build
             // this   field
             package_init: PackageInitConfig {
-                packages: vec![SimPackageArgs {
-                    name: "analysis".into(),
-                    data: serde_json::Value::String(self.analysis_json.unwrap_or_default()),
-                }],
+                packages: vec![
+                    SimPackageArgs {
+                        name: "analysis".into(),
+                        data: serde_json::Value::String(self.analysis_json.unwrap_or_default()),
+                    },
+                    SimPackageArgs {
+                        name: "json_state".into(),
+                        data: serde_json::Value::String(self.output_json.unwrap_or_default()),
+                    },
+                    SimPackageArgs {
+                        name: "schema".into(),
+                        data: serde_json::Value::String(self.schema_json.unwrap_or_default()),
+                    },
+                ],
                 behaviors: self.behaviors,
                 initial_state: self
                     .initial_state
@@ -552,11 +653,15 @@ impl Manifest {
             ExperimentType::Simple { name } => name.clone(),
         };
 
-        let config = experiment_type
-            .get_package_config(&simulation)
+        let (config, sensitivity) = experiment_type
+            .get_package_config_and_design(&simulation)
             .attach_printable("Could not read package config")
             .change_context(ManifestError)?;
-        Ok(ExperimentRun::new(name, simulation, config))
+        let experiment_run = ExperimentRun::new(name, simulation, config);
+        Ok(match sensitivity {
+            Some(design) => experiment_run.with_sensitivity_design(design),
+            None => experiment_run,
+        })
     }
 }
 
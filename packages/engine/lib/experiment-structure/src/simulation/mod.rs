@@ -1,9 +1,11 @@
 mod config;
 mod package_creators;
+mod python;
 mod source;
 
 pub use self::{
     config::{SimulationConfig, SimulationRunConfig},
     package_creators::PackageCreators,
+    python::PythonRequirements,
     source::SimulationSource,
 };
@@ -0,0 +1,131 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// The Python packages required by the behaviors of a project, read from its `requirements.txt` or
+/// `pyproject.toml`.
+///
+/// The Python runner is started in a virtual environment with these packages installed.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct PythonRequirements {
+    /// The directory of the project. Relative paths in the requirements are relative to it.
+    pub project_path: PathBuf,
+    /// The requirements in the format of a requirements file, as passed to `pip install -r`.
+    pub requirements_txt: String,
+}
+
+impl PythonRequirements {
+    /// Converts the `dependencies` in the `[project]` table of a `pyproject.toml` into
+    /// requirements.
+    ///
+    /// Returns `None` if the `pyproject.toml` doesn't declare any dependencies.
+    pub fn from_pyproject_toml(
+        project_path: PathBuf,
+        pyproject_toml: &str,
+    ) -> Result<Option<Self>, toml::de::Error> {
+        let pyproject: toml::Value = toml::from_str(pyproject_toml)?;
+        let dependencies: Vec<_> = pyproject
+            .get("project")
+            .and_then(|project| project.get("dependencies"))
+            .and_then(toml::Value::as_array)
+            .into_iter()
+            .flatten()
+            .filter_map(toml::Value::as_str)
+            .collect();
+        if dependencies.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            project_path,
+            requirements_txt: dependencies.join("\n") + "\n",
+        }))
+    }
+
+    /// Returns the normalized names of all required packages.
+    ///
+    /// Lines which don't start with a package name, e.g. options or paths, are ignored.
+    pub fn package_names(&self) -> HashSet<String> {
+        self.requirements_txt
+            .lines()
+            .filter_map(Self::package_name)
+            .collect()
+    }
+
+    /// Returns the name of the package `requirement` refers to, normalized as described in
+    /// [PEP 503](https://peps.python.org/pep-0503/#normalized-names), e.g. `scikit-learn` for
+    /// `"Scikit_Learn >= 1.0 ; python_version > '3.7'"`.
+    pub fn package_name(requirement: &str) -> Option<String> {
+        let requirement = requirement.trim_start();
+        if !requirement.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        let name_len = requirement
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.')))
+            .unwrap_or(requirement.len());
+        let name = &requirement[..name_len];
+        // Paths and URLs, e.g. `wheels/package.whl`, start like a name as well.
+        if requirement[name_len..].starts_with(['/', '\\', ':']) {
+            return None;
+        }
+
+        let mut normalized = String::with_capacity(name.len());
+        for c in name.chars() {
+            if matches!(c, '-' | '_' | '.') {
+                if !normalized.ends_with('-') {
+                    normalized.push('-');
+                }
+            } else {
+                normalized.push(c.to_ascii_lowercase());
+            }
+        }
+        Some(normalized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_package_name() {
+        let package_name = PythonRequirements::package_name;
+        assert_eq!(package_name("numpy==1.21.6"), Some("numpy".to_owned()));
+        assert_eq!(
+            package_name("Scikit_Learn >= 1.0 ; python_version > '3.7'"),
+            Some("scikit-learn".to_owned())
+        );
+        assert_eq!(
+            package_name("zope.interface[test]"),
+            Some("zope-interface".to_owned())
+        );
+        assert_eq!(package_name("-r base.txt"), None);
+        assert_eq!(package_name("# numpy"), None);
+        assert_eq!(package_name("wheels/numpy.whl"), None);
+        assert_eq!(package_name("https://example.com/numpy.whl"), None);
+    }
+
+    #[test]
+    fn test_from_pyproject_toml() {
+        let pyproject_toml = r#"
+            [project]
+            name = "model"
+            dependencies = ["numpy<1.22", "scipy==1.7.3"]
+        "#;
+        let requirements =
+            PythonRequirements::from_pyproject_toml(PathBuf::from("model"), pyproject_toml)
+                .unwrap()
+                .unwrap();
+        assert_eq!(requirements.requirements_txt, "numpy<1.22\nscipy==1.7.3\n");
+        assert_eq!(
+            requirements.package_names(),
+            HashSet::from(["numpy".to_owned(), "scipy".to_owned()])
+        );
+
+        let pyproject_toml = "[tool.black]\nline-length = 100\n";
+        assert_eq!(
+            PythonRequirements::from_pyproject_toml(PathBuf::from("model"), pyproject_toml)
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -1,7 +1,11 @@
+use std::path::PathBuf;
+
 use execution::package::simulation::PackageInitConfig;
 use serde::{Deserialize, Serialize};
 use stateful::global::Dataset;
 
+use crate::simulation::PythonRequirements;
+
 /// This contains all the source code for a specific simulation.
 ///
 /// This includes initial state source, analysis source, experiment source, globals source
@@ -12,5 +16,12 @@ pub struct SimulationSource {
     pub globals_src: String,
     pub experiments_src: Option<String>,
     pub datasets: Vec<Dataset>,
+    /// The `node_modules` folder of the project, used to resolve packages imported by JavaScript
+    /// behaviors.
+    #[serde(default)]
+    pub node_modules: Option<PathBuf>,
+    /// The Python packages required by the project.
+    #[serde(default)]
+    pub python_requirements: Option<PythonRequirements>,
     pub package_init: PackageInitConfig,
 }
@@ -0,0 +1,531 @@
+//! Static checks of a project, which don't require starting an engine.
+
+use std::{collections::HashMap, fmt};
+
+use execution::package::{
+    experiment::ExperimentName,
+    simulation::{
+        init::{AgentSchemaDeclaration, InitPackageName, InitialStateName},
+        output::{analysis::validate_analysis_source, json_state::JsonStateOutputConfig},
+        state::behavior_execution::{Behavior, BehaviorKeys},
+    },
+};
+use json_comments::StripComments;
+use stateful::{
+    agent::Agent,
+    field::{FieldSource, FieldType, RootFieldSpecCreator},
+    global::{Dataset, Globals},
+};
+
+use crate::{ExperimentType, Manifest, PackageConfigBuilder, PackageCreators};
+
+/// A problem found by [`Manifest::validate()`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectProblem {
+    /// The file containing the problem, relative to the project root, if it's caused by a single
+    /// file.
+    pub file: Option<String>,
+    pub message: String,
+}
+
+impl ProjectProblem {
+    fn new(file: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            file: Some(file.into()),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ProjectProblem {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.file {
+            Some(file) => write!(fmt, "{file}: {}", self.message),
+            None => fmt.write_str(&self.message),
+        }
+    }
+}
+
+impl Manifest {
+    /// Checks the project for problems, which would otherwise only be reported after the engine
+    /// has been started.
+    ///
+    /// In contrast to [`read()`](Self::read), this doesn't stop at the first problem but returns
+    /// every problem found:
+    /// - files which are not valid JSON (globals, datasets, ...)
+    /// - experiments in _experiments.json_ which can't be turned into an experiment plan
+    /// - invalid analysis definitions in _views/analysis.json_
+    /// - invalid behavior keys, and behavior keys which disagree with each other or _schema.json_
+    /// - behaviors used by agents in _init.json_ which don't exist, and agents not matching
+    ///   _schema.json_
+    ///
+    /// If no problem was found, the agent schema is built like it's done by the engine to detect
+    /// clashes with the fields of packages.
+    pub fn validate(&self) -> Vec<ProjectProblem> {
+        let mut problems = Vec::new();
+
+        let globals = match &self.globals_json {
+            Some(src) => match serde_json::from_str::<Globals>(src) {
+                Ok(globals) => Some(globals),
+                Err(err) => {
+                    problems.push(ProjectProblem::new("src/globals.json", err.to_string()));
+                    None
+                }
+            },
+            None => Some(Globals::default()),
+        };
+
+        let schema_declaration = match &self.schema_json {
+            Some(src) if !src.trim().is_empty() => {
+                match AgentSchemaDeclaration::from_json_str(src) {
+                    Ok(declaration) => declaration,
+                    Err(err) => {
+                        problems.push(ProjectProblem::new("schema.json", err.to_string()));
+                        AgentSchemaDeclaration::default()
+                    }
+                }
+            }
+            _ => AgentSchemaDeclaration::default(),
+        };
+
+        self.validate_behaviors(&schema_declaration, &mut problems);
+        self.validate_initial_state(&schema_declaration, &mut problems);
+
+        for dataset in &self.datasets {
+            if let (Some(data), false) = (&dataset.data, dataset.raw_csv) {
+                if let Err(err) = serde_json::from_str::<serde_json::Value>(data) {
+                    problems.push(ProjectProblem::new(dataset_file(dataset), err.to_string()));
+                }
+            }
+        }
+
+        if let Some(src) = &self.analysis_json {
+            if let Err(err) = validate_analysis_source(src) {
+                problems.push(ProjectProblem::new("views/analysis.json", err.to_string()));
+            }
+        }
+        if let Some(src) = &self.output_json {
+            if !src.trim().is_empty() {
+                if let Err(err) = JsonStateOutputConfig::from_json_str(src) {
+                    problems.push(ProjectProblem::new("output.json", err.to_string()));
+                }
+            }
+        }
+
+        self.validate_experiments(&mut problems);
+
+        if problems.is_empty() {
+            if let Some(globals) = globals {
+                self.validate_agent_schema(&globals, &mut problems);
+            }
+        }
+
+        problems
+    }
+
+    fn validate_behaviors(
+        &self,
+        schema_declaration: &AgentSchemaDeclaration,
+        problems: &mut Vec<ProjectProblem>,
+    ) {
+        // The first declaration of every key and the file it was declared in
+        let mut declared_keys: HashMap<String, (FieldType, String)> = schema_declaration
+            .fields()
+            .iter()
+            .map(|field| {
+                (
+                    field.name.clone(),
+                    (field.field_type.clone(), "schema.json".to_string()),
+                )
+            })
+            .collect();
+        let field_spec_creator = RootFieldSpecCreator::new(FieldSource::Engine);
+
+        for behavior in &self.behaviors {
+            let file = behavior_file(behavior);
+            if let Err(err) = behavior.language() {
+                problems.push(ProjectProblem::new(file.clone(), err.to_string()));
+            }
+
+            let keys_file = format!("{file}.json");
+            let keys = match &behavior.behavior_keys_src {
+                Some(src) => match BehaviorKeys::from_json_str(src, &field_spec_creator) {
+                    Ok(keys) => keys,
+                    Err(err) => {
+                        problems.push(ProjectProblem::new(keys_file, err.to_string()));
+                        continue;
+                    }
+                },
+                None => continue,
+            };
+            for field_spec in keys.inner.field_specs() {
+                let name = &field_spec.inner.name;
+                let field_type = &field_spec.inner.field_type;
+                match declared_keys.get(name) {
+                    Some((declared_type, declared_in)) if declared_type != field_type => {
+                        problems.push(ProjectProblem::new(
+                            keys_file.clone(),
+                            format!(
+                                "Key `{name}` is declared as {:?} (nullable: {}), but as {:?} \
+                                 (nullable: {}) in {declared_in}",
+                                field_type.variant,
+                                field_type.nullable,
+                                declared_type.variant,
+                                declared_type.nullable
+                            ),
+                        ));
+                    }
+                    Some(_) => {}
+                    None => {
+                        declared_keys.insert(name.clone(), (field_type.clone(), keys_file.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    fn validate_initial_state(
+        &self,
+        schema_declaration: &AgentSchemaDeclaration,
+        problems: &mut Vec<ProjectProblem>,
+    ) {
+        // Only JSON initial state can be checked without running it
+        let src = match &self.initial_state {
+            Some(initial_state) if initial_state.name == InitialStateName::InitJson => {
+                &initial_state.src
+            }
+            Some(_) => return,
+            None => {
+                problems.push(ProjectProblem {
+                    file: None,
+                    message: "The project doesn't provide an initial state".to_string(),
+                });
+                return;
+            }
+        };
+        let mut agents: Vec<Agent> = match serde_json::from_str(src) {
+            Ok(agents) => agents,
+            Err(err) => {
+                problems.push(ProjectProblem::new("src/init.json", err.to_string()));
+                return;
+            }
+        };
+
+        let behavior_names: Vec<&str> = self
+            .behaviors
+            .iter()
+            .flat_map(|behavior| {
+                std::iter::once(behavior.name.as_str())
+                    .chain(behavior.shortnames.iter().map(String::as_str))
+            })
+            .collect();
+        for (index, agent) in agents.iter().enumerate() {
+            let agent_description = match &agent.agent_name {
+                Some(name) => format!("Agent `{}`", name.0),
+                None => format!("Agent at index {index}"),
+            };
+            let behaviors = match agent.custom.get("behaviors") {
+                Some(serde_json::Value::Array(behaviors)) => behaviors,
+                Some(serde_json::Value::Null) | None => continue,
+                Some(_) => {
+                    problems.push(ProjectProblem::new(
+                        "src/init.json",
+                        format!("{agent_description} has a `behaviors` field, which is not a list"),
+                    ));
+                    continue;
+                }
+            };
+            for behavior in behaviors {
+                match behavior.as_str() {
+                    Some(name) if behavior_names.contains(&name) => {}
+                    Some(name) => problems.push(ProjectProblem::new(
+                        "src/init.json",
+                        format!(
+                            "{agent_description} uses the behavior `{name}`, which doesn't exist"
+                        ),
+                    )),
+                    None => problems.push(ProjectProblem::new(
+                        "src/init.json",
+                        format!(
+                            "{agent_description} uses the behavior {behavior}, which is not a \
+                             string"
+                        ),
+                    )),
+                }
+            }
+        }
+
+        problems.extend(
+            schema_declaration
+                .validate(&mut agents)
+                .into_iter()
+                .map(|error| ProjectProblem::new("src/init.json", error.0)),
+        );
+    }
+
+    fn validate_experiments(&self, problems: &mut Vec<ProjectProblem>) {
+        let src = match &self.experiments_json {
+            Some(src) => src,
+            None => return,
+        };
+        let experiments: HashMap<String, serde_json::Value> =
+            match serde_json::from_reader(StripComments::new(src.as_bytes())) {
+                Ok(experiments) => experiments,
+                Err(err) => {
+                    problems.push(ProjectProblem::new("experiments.json", err.to_string()));
+                    return;
+                }
+            };
+
+        let mut names: Vec<_> = experiments
+            .iter()
+            .filter(|(_, experiment)| experiment.is_object())
+            .map(|(name, _)| name.clone())
+            .collect();
+        names.sort();
+        for name in names {
+            let experiment_type = ExperimentType::Simple {
+                name: ExperimentName::from(name.clone()),
+            };
+            if let Err(report) = self.clone().read(experiment_type) {
+                problems.push(ProjectProblem::new(
+                    "experiments.json",
+                    format!("Experiment `{name}`: {report:#}"),
+                ));
+            }
+        }
+    }
+
+    fn validate_agent_schema(&self, globals: &Globals, problems: &mut Vec<ProjectProblem>) {
+        let experiment_run = match self
+            .clone()
+            .read(ExperimentType::SingleRun { num_steps: 1 })
+        {
+            Ok(experiment_run) => experiment_run,
+            Err(report) => {
+                problems.push(ProjectProblem {
+                    file: None,
+                    message: format!("{report:#}"),
+                });
+                return;
+            }
+        };
+        let package_init = &experiment_run.simulation().package_init;
+        let package_config = match PackageConfigBuilder::new()
+            .add_init_package(match package_init.initial_state.name {
+                InitialStateName::InitJson => InitPackageName::Json,
+                InitialStateName::InitPy | InitialStateName::InitJs => InitPackageName::JsPy,
+            })
+            .build()
+        {
+            Ok(package_config) => package_config,
+            Err(report) => {
+                problems.push(ProjectProblem {
+                    file: None,
+                    message: format!("{report:#}"),
+                });
+                return;
+            }
+        };
+        let result = PackageCreators::from_config(&package_config, package_init)
+            .and_then(|package_creators| package_creators.create_schema(package_init, globals));
+        if let Err(err) = result {
+            problems.push(ProjectProblem {
+                file: None,
+                message: format!("Could not build the agent schema: {err}"),
+            });
+        }
+    }
+}
+
+/// Returns the location of the behavior, dependencies are referred to by their name.
+fn behavior_file(behavior: &Behavior) -> String {
+    if behavior.name.starts_with('@') {
+        behavior.name.clone()
+    } else {
+        format!("src/behaviors/{}", behavior.name)
+    }
+}
+
+/// Returns the location of the dataset, dependencies are referred to by their name.
+fn dataset_file(dataset: &Dataset) -> String {
+    match &dataset.name {
+        Some(name) if name.starts_with('@') => name.clone(),
+        _ => format!("data/{}", dataset.filename),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use execution::package::simulation::init::InitialState;
+
+    use super::*;
+
+    fn behavior(name: &str, keys: Option<&str>) -> Behavior {
+        Behavior {
+            id: name.to_string(),
+            name: name.to_string(),
+            shortnames: Vec::new(),
+            behavior_src: Some(String::new()),
+            behavior_keys_src: keys.map(str::to_string),
+        }
+    }
+
+    fn project(init_json: &str) -> Manifest {
+        let mut manifest = Manifest::new();
+        manifest.initial_state = Some(InitialState {
+            name: InitialStateName::InitJson,
+            src: init_json.to_string(),
+        });
+        manifest.add_behavior(behavior(
+            "move.js",
+            Some(r#"{ "keys": { "speed": { "type": "number", "nullable": true } } }"#),
+        ));
+        manifest
+    }
+
+    /// Returns the files of the problems found, asserting that every message contains the
+    /// corresponding fragment.
+    fn problems(manifest: &Manifest, fragments: &[&str]) -> Vec<Option<String>> {
+        let problems = manifest.validate();
+        assert_eq!(problems.len(), fragments.len(), "{problems:?}");
+        for (problem, fragment) in problems.iter().zip(fragments) {
+            assert!(problem.message.contains(fragment), "{problem}");
+        }
+        problems.into_iter().map(|problem| problem.file).collect()
+    }
+
+    #[test]
+    fn valid_project() {
+        let manifest = project(r#"[{ "agent_name": "a", "behaviors": ["move.js"], "speed": 1 }]"#);
+        assert_eq!(manifest.validate(), Vec::new());
+    }
+
+    #[test]
+    fn missing_initial_state() {
+        let mut manifest = project("[]");
+        manifest.initial_state = None;
+        assert_eq!(problems(&manifest, &["initial state"]), vec![None]);
+    }
+
+    #[test]
+    fn invalid_json_files() {
+        let mut manifest = project("[");
+        manifest.globals_json = Some("{".to_string());
+        manifest.analysis_json = Some("{".to_string());
+        manifest.output_json = Some("{".to_string());
+        manifest.add_dataset(Dataset {
+            name: None,
+            shortname: "data".to_string(),
+            filename: "data.json".to_string(),
+            url: None,
+            raw_csv: false,
+            data: Some("{".to_string()),
+        });
+
+        let mut files = problems(&manifest, &["", "", "", "", ""]);
+        files.sort();
+        assert_eq!(files, vec![
+            Some("data/data.json".to_string()),
+            Some("output.json".to_string()),
+            Some("src/globals.json".to_string()),
+            Some("src/init.json".to_string()),
+            Some("views/analysis.json".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn invalid_behaviors() {
+        let mut manifest = project("[]");
+        manifest.add_behavior(behavior("move.txt", None));
+        manifest.add_behavior(behavior("broken.js", Some(r#"{ "keys": [] }"#)));
+        assert_eq!(problems(&manifest, &["", ""]), vec![
+            Some("src/behaviors/move.txt".to_string()),
+            Some("src/behaviors/broken.js.json".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn conflicting_behavior_keys() {
+        let mut manifest = project("[]");
+        manifest.add_behavior(behavior(
+            "stop.js",
+            Some(r#"{ "keys": { "speed": { "type": "string", "nullable": true } } }"#),
+        ));
+        assert_eq!(problems(&manifest, &["src/behaviors/move.js.json"]), vec![
+            Some("src/behaviors/stop.js.json".to_string())
+        ]);
+
+        manifest.schema_json =
+            Some(r#"{ "keys": { "speed": { "type": "number", "nullable": false } } }"#.to_string());
+        assert_eq!(problems(&manifest, &["schema.json", "schema.json"]), vec![
+            Some("src/behaviors/move.js.json".to_string()),
+            Some("src/behaviors/stop.js.json".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn invalid_schema() {
+        let mut manifest = project("[]");
+        manifest.schema_json =
+            Some(r#"{ "keys": { "agent_id": { "type": "string" } } }"#.to_string());
+        assert_eq!(problems(&manifest, &["built-in field"]), vec![Some(
+            "schema.json".to_string()
+        )]);
+    }
+
+    #[test]
+    fn invalid_agent_behaviors() {
+        let manifest = project(
+            r#"[
+                { "agent_name": "a", "behaviors": ["move.js", "missing.js"] },
+                { "behaviors": "move.js" },
+                { "behaviors": [1] }
+            ]"#,
+        );
+        let files = problems(&manifest, &[
+            "Agent `a` uses the behavior `missing.js`",
+            "Agent at index 1 has a `behaviors` field",
+            "Agent at index 2 uses the behavior 1",
+        ]);
+        assert!(
+            files
+                .iter()
+                .all(|file| file.as_deref() == Some("src/init.json"))
+        );
+    }
+
+    #[test]
+    fn agents_not_matching_schema() {
+        let mut manifest = project(r#"[{ "agent_name": "a" }, { "speed": "fast" }]"#);
+        manifest.schema_json =
+            Some(r#"{ "keys": { "speed": { "type": "number", "nullable": false } } }"#.to_string());
+        let files = problems(&manifest, &["non-nullable field `speed`", "speed"]);
+        assert!(
+            files
+                .iter()
+                .all(|file| file.as_deref() == Some("src/init.json"))
+        );
+    }
+
+    #[test]
+    fn invalid_experiments() {
+        let mut manifest = project("[]");
+        manifest.experiments_json = Some(
+            r#"{
+                // Comments are allowed
+                "valid": { "type": "values", "field": "speed", "values": [1, 2], "steps": 1 },
+                "untyped": { "field": "speed", "values": [1, 2], "steps": 1 }
+            }"#
+            .to_string(),
+        );
+        assert_eq!(problems(&manifest, &["Experiment `untyped`"]), vec![Some(
+            "experiments.json".to_string()
+        )]);
+
+        manifest.experiments_json = Some("{".to_string());
+        assert_eq!(problems(&manifest, &[""]), vec![Some(
+            "experiments.json".to_string()
+        )]);
+    }
+}
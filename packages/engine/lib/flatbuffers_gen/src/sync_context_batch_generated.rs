@@ -38,6 +38,8 @@ impl<'a> flatbuffers::Follow<'a> for ContextBatchSync<'a> {
 impl<'a> ContextBatchSync<'a> {
     pub const VT_CONTEXT_BATCH: flatbuffers::VOffsetT = 4;
     pub const VT_CURRENT_STEP: flatbuffers::VOffsetT = 6;
+    pub const VT_GLOBALS: flatbuffers::VOffsetT = 8;
+    pub const VT_RASTER_BATCH: flatbuffers::VOffsetT = 10;
 
     #[inline]
     pub fn init_from_table(table: flatbuffers::Table<'a>) -> Self {
@@ -51,6 +53,12 @@ impl<'a> ContextBatchSync<'a> {
     ) -> flatbuffers::WIPOffset<ContextBatchSync<'bldr>> {
         let mut builder = ContextBatchSyncBuilder::new(_fbb);
         builder.add_current_step(args.current_step);
+        if let Some(x) = args.raster_batch {
+            builder.add_raster_batch(x);
+        }
+        if let Some(x) = args.globals {
+            builder.add_globals(x);
+        }
         if let Some(x) = args.context_batch {
             builder.add_context_batch(x);
         }
@@ -70,6 +78,18 @@ impl<'a> ContextBatchSync<'a> {
             .get::<i64>(ContextBatchSync::VT_CURRENT_STEP, Some(0))
             .unwrap()
     }
+
+    #[inline]
+    pub fn globals(&self) -> Option<&'a str> {
+        self._tab
+            .get::<flatbuffers::ForwardsUOffset<&str>>(ContextBatchSync::VT_GLOBALS, None)
+    }
+
+    #[inline]
+    pub fn raster_batch(&self) -> Option<Batch<'a>> {
+        self._tab
+            .get::<flatbuffers::ForwardsUOffset<Batch>>(ContextBatchSync::VT_RASTER_BATCH, None)
+    }
 }
 
 impl flatbuffers::Verifiable for ContextBatchSync<'_> {
@@ -86,6 +106,12 @@ impl flatbuffers::Verifiable for ContextBatchSync<'_> {
                 true,
             )?
             .visit_field::<i64>(&"current_step", Self::VT_CURRENT_STEP, false)?
+            .visit_field::<flatbuffers::ForwardsUOffset<&str>>(&"globals", Self::VT_GLOBALS, false)?
+            .visit_field::<flatbuffers::ForwardsUOffset<Batch>>(
+                &"raster_batch",
+                Self::VT_RASTER_BATCH,
+                false,
+            )?
             .finish();
         Ok(())
     }
@@ -93,6 +119,8 @@ impl flatbuffers::Verifiable for ContextBatchSync<'_> {
 pub struct ContextBatchSyncArgs<'a> {
     pub context_batch: Option<flatbuffers::WIPOffset<Batch<'a>>>,
     pub current_step: i64,
+    pub globals: Option<flatbuffers::WIPOffset<&'a str>>,
+    pub raster_batch: Option<flatbuffers::WIPOffset<Batch<'a>>>,
 }
 impl<'a> Default for ContextBatchSyncArgs<'a> {
     #[inline]
@@ -100,6 +128,8 @@ impl<'a> Default for ContextBatchSyncArgs<'a> {
         ContextBatchSyncArgs {
             context_batch: None, // required field
             current_step: 0,
+            globals: None,
+            raster_batch: None,
         }
     }
 }
@@ -122,6 +152,20 @@ impl<'a: 'b, 'b> ContextBatchSyncBuilder<'a, 'b> {
             .push_slot::<i64>(ContextBatchSync::VT_CURRENT_STEP, current_step, 0);
     }
 
+    #[inline]
+    pub fn add_globals(&mut self, globals: flatbuffers::WIPOffset<&'b str>) {
+        self.fbb_
+            .push_slot_always::<flatbuffers::WIPOffset<_>>(ContextBatchSync::VT_GLOBALS, globals);
+    }
+
+    #[inline]
+    pub fn add_raster_batch(&mut self, raster_batch: flatbuffers::WIPOffset<Batch<'b>>) {
+        self.fbb_.push_slot_always::<flatbuffers::WIPOffset<Batch>>(
+            ContextBatchSync::VT_RASTER_BATCH,
+            raster_batch,
+        );
+    }
+
     #[inline]
     pub fn new(
         _fbb: &'b mut flatbuffers::FlatBufferBuilder<'a>,
@@ -147,6 +191,8 @@ impl std::fmt::Debug for ContextBatchSync<'_> {
         let mut ds = f.debug_struct("ContextBatchSync");
         ds.field("context_batch", &self.context_batch());
         ds.field("current_step", &self.current_step());
+        ds.field("globals", &self.globals());
+        ds.field("raster_batch", &self.raster_batch());
         ds.finish()
     }
 }
@@ -1,12 +1,12 @@
 use arrow2::{
     array,
     array::{
-        Array, BooleanArray, FixedSizeListArray, ListArray, MutableArray, MutablePrimitiveArray,
-        MutableUtf8Array, PrimitiveArray, StructArray, Utf8Array,
+        Array, BooleanArray, DictionaryArray, FixedSizeListArray, ListArray, MutableArray,
+        MutablePrimitiveArray, MutableUtf8Array, PrimitiveArray, StructArray, Utf8Array,
     },
     bitmap::{Bitmap, MutableBitmap},
     buffer::Buffer,
-    datatypes::{DataType, Field},
+    datatypes::{DataType, Field, IntegerType},
     types::NativeType,
 };
 use num::Num;
@@ -15,7 +15,7 @@ use serde_json::Value;
 
 use super::new_zero_bits;
 use crate::{
-    arrow::{buffer::new_offsets_buffer, util::bit_util},
+    arrow::{buffer::new_offsets_buffer, dictionary::keys_to_dictionary, util::bit_util},
     error::{Error, Result, SupportedType},
 };
 
@@ -47,6 +47,38 @@ pub fn json_vals_to_primitive<T: NativeType + DeserializeOwned>(
     Ok(builder.into())
 }
 
+/// Converts string values into a dictionary-encoded column with the provided `categories` as
+/// dictionary values.
+pub fn json_vals_to_category(
+    vals: Vec<Value>,
+    categories: &[String],
+    nullable: bool,
+) -> Result<DictionaryArray<i32>> {
+    let mut keys = MutablePrimitiveArray::<i32>::with_capacity(vals.len());
+    for val in vals {
+        match val {
+            Value::String(value) => {
+                let key = categories
+                    .iter()
+                    .position(|category| category == &value)
+                    .ok_or_else(|| Error::UnknownCategory {
+                        value,
+                        categories: categories.to_vec(),
+                    })?;
+                keys.push(Some(key as i32));
+            }
+            Value::Null if nullable => keys.push_null(),
+            value => {
+                return Err(Error::UnknownCategory {
+                    value: value.to_string(),
+                    categories: categories.to_vec(),
+                });
+            }
+        }
+    }
+    keys_to_dictionary(keys.into(), categories)
+}
+
 pub fn json_vals_to_utf8(vals: Vec<Value>, nullable: bool) -> Result<Utf8Array<i32>> {
     // TODO: some better heuristics for capacity estimation?
     let mut builder = MutableUtf8Array::with_capacity(vals.len() * 64);
@@ -214,6 +246,10 @@ pub fn json_vals_to_col(vals: Vec<Value>, field: &Field, nullable: bool) -> Resu
         DataType::Float64 => Ok(Box::new(json_vals_to_primitive::<f64>(vals, nullable)?)),
         DataType::Float32 => Ok(Box::new(json_vals_to_primitive::<f32>(vals, nullable)?)),
         DataType::Int64 => Ok(Box::new(json_vals_to_primitive::<i64>(vals, nullable)?)),
+        // Timestamps are represented as milliseconds since the Unix epoch
+        DataType::Timestamp(..) => Ok(Box::new(
+            json_vals_to_primitive::<i64>(vals, nullable)?.to(field.data_type().clone()),
+        )),
         DataType::Int32 => Ok(Box::new(json_vals_to_primitive::<i32>(vals, nullable)?)),
         DataType::Int16 => Ok(Box::new(json_vals_to_primitive::<i16>(vals, nullable)?)),
         DataType::Int8 => Ok(Box::new(json_vals_to_primitive::<i8>(vals, nullable)?)),
@@ -300,6 +336,39 @@ fn bool_to_json_vals(col: &dyn Array) -> Result<Vec<Value>> {
     Ok(json_vals)
 }
 
+fn dictionary_to_json_vals(col: &dyn Array) -> Result<Vec<Value>> {
+    let array =
+        col.as_any()
+            .downcast_ref::<DictionaryArray<i32>>()
+            .ok_or(Error::InvalidArrowDowncast {
+                name: "[dictionary]".into(),
+            })?;
+    let values = array
+        .values()
+        .as_any()
+        .downcast_ref::<Utf8Array<i32>>()
+        .ok_or(Error::InvalidArrowDowncast {
+            name: "[dictionary values]".into(),
+        })?;
+    array
+        .keys()
+        .iter()
+        .map(|key| match key {
+            Some(key) => usize::try_from(*key)
+                .ok()
+                .filter(|key| *key < values.len())
+                .map(|key| Value::String(values.value(key).to_string()))
+                .ok_or_else(|| {
+                    Error::Memory(format!(
+                        "Dictionary key {key} is out of bounds for {} values",
+                        values.len()
+                    ))
+                }),
+            None => Ok(Value::Null),
+        })
+        .collect()
+}
+
 fn utf8_to_json_vals(col: &dyn Array) -> Result<Vec<Value>> {
     let array = col.as_any().downcast_ref::<Utf8Array<i32>>().unwrap();
     let mut json_vals: Vec<Value> = Vec::with_capacity(array.len());
@@ -420,13 +489,16 @@ pub fn col_to_json_vals(col: &dyn Array, dt: &DataType) -> Result<Vec<Value>> {
         DataType::Int8 => numeric_to_json_vals::<i8>(col),
         DataType::Int16 => numeric_to_json_vals::<i16>(col),
         DataType::Int32 => numeric_to_json_vals::<i32>(col),
-        DataType::Int64 => numeric_to_json_vals::<i64>(col),
+        DataType::Int64 | DataType::Timestamp(..) => numeric_to_json_vals::<i64>(col),
         DataType::UInt8 => numeric_to_json_vals::<u8>(col),
         DataType::UInt16 => numeric_to_json_vals::<u16>(col),
         DataType::UInt32 => numeric_to_json_vals::<u32>(col),
         DataType::UInt64 => numeric_to_json_vals::<u64>(col),
         DataType::Boolean => bool_to_json_vals(col),
         DataType::Utf8 => utf8_to_json_vals(col),
+        DataType::Dictionary(IntegerType::Int32, values, _) if **values == DataType::Utf8 => {
+            dictionary_to_json_vals(col)
+        }
         DataType::List(inner_field) => list_to_json_vals(col, inner_field.data_type()),
         DataType::FixedSizeList(inner_field, _) => {
             fixed_size_list_to_json_vals(col, inner_field.data_type())
@@ -437,3 +509,33 @@ pub fn col_to_json_vals(col: &dyn Array, dt: &DataType) -> Result<Vec<Value>> {
         ))),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use arrow2::datatypes::TimeUnit;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn category_round_trip() -> Result<()> {
+        let categories = vec!["susceptible".to_string(), "infected".to_string()];
+        let vals = vec![json!("infected"), Value::Null, json!("susceptible")];
+        let array = json_vals_to_category(vals.clone(), &categories, true)?;
+        assert_eq!(array.keys().values().as_slice(), &[1, 0, 0]);
+        assert_eq!(col_to_json_vals(&array, array.data_type())?, vals);
+
+        assert!(json_vals_to_category(vec![json!("recovered")], &categories, false).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn timestamp_round_trip() -> Result<()> {
+        let field = Field::new("t", DataType::Timestamp(TimeUnit::Millisecond, None), false);
+        let vals = vec![json!(1_660_000_000_000_i64), json!(0)];
+        let col = json_vals_to_col(vals.clone(), &field, false)?;
+        assert_eq!(col.data_type(), field.data_type());
+        assert_eq!(col_to_json_vals(col.as_ref(), field.data_type())?, vals);
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+//! Dictionary-encoded columns.
+//!
+//! A shared-memory batch only holds a single record batch message, so the values of
+//! dictionary-encoded columns can't be sent as dictionary batches like in the Arrow IPC stream
+//! format. Instead, the values of every dictionary-encoded field are stored as JSON in the schema
+//! metadata under [`DICTIONARY_VALUES_METADATA_KEY`], and the columns in shared memory only hold
+//! the keys. The language runners attach the values again when loading the columns.
+//!
+//! Only dictionaries with `Int32` keys and `Utf8` values are supported, see
+//! [`dictionary_data_type`].
+
+use std::collections::HashMap;
+
+use arrow2::{
+    array::{Array, DictionaryArray, PrimitiveArray, Utf8Array},
+    datatypes::{DataType, IntegerType, Schema},
+    io::ipc::{read::Dictionaries, IpcField},
+};
+
+use crate::error::{Error, Result};
+
+/// Key of the schema metadata mapping the names of dictionary-encoded fields to a JSON list of
+/// their values.
+pub const DICTIONARY_VALUES_METADATA_KEY: &str = "category_fields";
+
+/// Returns the data type of a dictionary-encoded string column.
+pub fn dictionary_data_type() -> DataType {
+    DataType::Dictionary(IntegerType::Int32, Box::new(DataType::Utf8), false)
+}
+
+/// Returns the values of all dictionary-encoded fields in `schema`, identified by the field name.
+pub fn dictionary_values(schema: &Schema) -> Result<HashMap<String, Vec<String>>> {
+    match schema.metadata.get(DICTIONARY_VALUES_METADATA_KEY) {
+        Some(values) => Ok(serde_json::from_str(values)?),
+        None => Ok(HashMap::new()),
+    }
+}
+
+/// Creates a dictionary-encoded column from its `keys` and `values`.
+///
+/// Returns an error if a key is out of bounds of `values`.
+pub fn keys_to_dictionary(
+    keys: PrimitiveArray<i32>,
+    values: &[String],
+) -> Result<DictionaryArray<i32>> {
+    if let Some(key) = keys
+        .iter()
+        .flatten()
+        .find(|key| usize::try_from(**key).map_or(true, |key| key >= values.len()))
+    {
+        return Err(Error::Memory(format!(
+            "Dictionary key {key} is out of bounds for {} values",
+            values.len()
+        )));
+    }
+    Ok(DictionaryArray::from_data(
+        keys,
+        Utf8Array::<i32>::from_slice(values).boxed(),
+    ))
+}
+
+/// Returns the dictionaries of the dictionary-encoded fields in `schema`, which are required to
+/// read a record batch with the provided `ipc_fields`.
+pub(crate) fn read_dictionaries(schema: &Schema, ipc_fields: &[IpcField]) -> Result<Dictionaries> {
+    let mut dictionaries = Dictionaries::default();
+    if !schema
+        .fields
+        .iter()
+        .any(|field| matches!(field.data_type(), DataType::Dictionary(..)))
+    {
+        return Ok(dictionaries);
+    }
+
+    let values = dictionary_values(schema)?;
+    for (field, ipc_field) in schema.fields.iter().zip(ipc_fields) {
+        if let (DataType::Dictionary(..), Some(id)) = (field.data_type(), ipc_field.dictionary_id) {
+            let values = values.get(&field.name).ok_or_else(|| {
+                Error::Memory(format!(
+                    "Missing values of dictionary-encoded field {}",
+                    field.name
+                ))
+            })?;
+            dictionaries.insert(id, Utf8Array::<i32>::from_slice(values).boxed());
+        }
+    }
+    Ok(dictionaries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_out_of_bounds_keys() {
+        let values = ["a".to_string(), "b".to_string()];
+        let dictionary =
+            keys_to_dictionary(PrimitiveArray::from([Some(1), None, Some(0)]), &values).unwrap();
+        assert_eq!(dictionary.data_type(), &dictionary_data_type());
+
+        assert!(keys_to_dictionary(PrimitiveArray::from_slice([2]), &values).is_err());
+        assert!(keys_to_dictionary(PrimitiveArray::from_slice([-1]), &values).is_err());
+    }
+}
@@ -5,10 +5,10 @@
 use arrow2::{
     self,
     array::{
-        Array, BinaryArray, BooleanArray, FixedSizeBinaryArray, FixedSizeListArray, ListArray,
-        PrimitiveArray, StructArray, Utf8Array,
+        Array, BinaryArray, BooleanArray, DictionaryArray, FixedSizeBinaryArray,
+        FixedSizeListArray, ListArray, PrimitiveArray, StructArray, Utf8Array,
     },
-    datatypes::{PhysicalType, PrimitiveType},
+    datatypes::{IntegerType, PhysicalType, PrimitiveType},
 };
 use bytemuck::cast_slice;
 
@@ -176,6 +176,16 @@ impl GrowableArrayData for Box<dyn Array> {
             arrow2::datatypes::PhysicalType::Struct => unimplemented!(),
             arrow2::datatypes::PhysicalType::Union => todo!(),
             arrow2::datatypes::PhysicalType::Map => todo!(),
+            // only the keys of dictionary-encoded arrays are stored
+            arrow2::datatypes::PhysicalType::Dictionary(IntegerType::Int32) => {
+                debug_assert_eq!(index, 0);
+
+                let dictionary = self
+                    .as_any()
+                    .downcast_ref::<DictionaryArray<i32>>()
+                    .unwrap();
+                cast_slice(dictionary.keys().values().as_slice())
+            }
             arrow2::datatypes::PhysicalType::Dictionary(_) => todo!(),
             _ => {
                 panic!("The provided buffer index was out of range");
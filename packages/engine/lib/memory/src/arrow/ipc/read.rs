@@ -10,7 +10,7 @@ use arrow_format::ipc::planus::ReadAsRoot;
 use tracing::trace;
 
 use super::RecordBatch;
-use crate::shared_memory::Segment;
+use crate::{arrow::dictionary::read_dictionaries, shared_memory::Segment};
 
 /// Reads the [`RecordBatch`] stored in the given [`Segment`].
 ///
@@ -26,16 +26,19 @@ pub fn read_record_batch(segment: &Segment, schema: Arc<Schema>) -> crate::Resul
 
     let mut scratch = Vec::new();
 
+    let ipc_fields = default_ipc_fields(&schema.fields);
+    let dictionaries = read_dictionaries(&schema, &ipc_fields)?;
+
     let columns = arrow2::io::ipc::read::read_record_batch(
         batch,
         &schema.fields,
         &IpcSchema {
-            fields: default_ipc_fields(&schema.fields),
+            fields: ipc_fields,
             is_little_endian: cfg!(target_endian = "little"),
         },
         None,
         None,
-        &Default::default(),
+        &dictionaries,
         arrow_format::ipc::MetadataVersion::V4,
         &mut reader,
         0,
@@ -15,10 +15,13 @@ use uuid::Uuid;
 use super::read_record_batch;
 use crate::{
     arrow::{
+        dictionary_data_type,
         ipc::{
             calculate_ipc_header_data, read_record_batch_message, write_record_batch_to_segment,
         },
+        keys_to_dictionary,
         record_batch::RecordBatch,
+        DICTIONARY_VALUES_METADATA_KEY,
     },
     shared_memory::MemoryId,
 };
@@ -109,6 +112,37 @@ fn integer_roundtrip() {
     round_trip(schema, record_batch);
 }
 
+#[test]
+#[cfg_attr(miri, ignore)]
+fn dictionary_roundtrip() {
+    let schema = Arc::new(Schema {
+        fields: vec![
+            Field::new("field1", dictionary_data_type(), true),
+            Field::new("field2", DataType::UInt32, false),
+        ],
+        metadata: BTreeMap::from([(
+            DICTIONARY_VALUES_METADATA_KEY.to_string(),
+            r#"{"field1": ["susceptible", "infected", "recovered"]}"#.to_string(),
+        )]),
+    });
+    let dictionary =
+        keys_to_dictionary(PrimitiveArray::from([Some(2), None, Some(0), Some(1)]), &[
+            "susceptible".to_string(),
+            "infected".to_string(),
+            "recovered".to_string(),
+        ])
+        .unwrap();
+    let record_batch = RecordBatch::new(
+        schema.clone(),
+        Chunk::new(vec![
+            dictionary.boxed(),
+            PrimitiveArray::<u32>::from_slice(&[1, 2, 1, 2]).boxed(),
+        ]),
+    );
+
+    round_trip(schema, record_batch);
+}
+
 #[test]
 #[cfg_attr(miri, ignore)]
 fn fixed_sized_list_roundtrip() {
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use arrow2::datatypes::{DataType, Field, IntervalUnit, Schema, TimeUnit};
+use arrow2::datatypes::{DataType, Field, IntegerType, IntervalUnit, Schema, TimeUnit};
 
 use crate::{
     arrow::meta::{self, BufferType, NodeMapping},
@@ -40,6 +40,10 @@ enum SupportedDataTypes {
     List(Box<SupportedDataTypes>),
     FixedSizeList(Box<SupportedDataTypes>, usize),
     Struct(Vec<Field>),
+
+    /// A dictionary-encoded column, of which only the keys are stored in the batch (see
+    /// [`dictionary`](crate::arrow::dictionary)).
+    Dictionary(IntegerType),
 }
 
 impl TryFrom<DataType> for SupportedDataTypes {
@@ -83,6 +87,7 @@ impl TryFrom<DataType> for SupportedDataTypes {
                 size,
             )),
             DataType::Struct(fields) => Ok(Self::Struct(fields)),
+            DataType::Dictionary(key_type, ..) => Ok(Self::Dictionary(key_type)),
             d_type => Err(Error::UnsupportedArrowDataType { d_type }),
         }
     }
@@ -328,6 +333,19 @@ fn data_type_to_metadata(
 
         D::Duration(_) => data_type_metadata(is_parent_growable, multiplier, 8),
 
+        D::Dictionary(IntegerType::Int8 | IntegerType::UInt8) => {
+            data_type_metadata(is_parent_growable, multiplier, 1)
+        }
+        D::Dictionary(IntegerType::Int16 | IntegerType::UInt16) => {
+            data_type_metadata(is_parent_growable, multiplier, 2)
+        }
+        D::Dictionary(IntegerType::Int32 | IntegerType::UInt32) => {
+            data_type_metadata(is_parent_growable, multiplier, 4)
+        }
+        D::Dictionary(IntegerType::Int64 | IntegerType::UInt64) => {
+            data_type_metadata(is_parent_growable, multiplier, 8)
+        }
+
         D::Struct(ref fields) => {
             let mut node_count = 1;
             let mut buffer_counts = vec![1];
@@ -24,6 +24,7 @@ mod batch;
 mod buffer;
 mod change;
 mod conversion;
+mod dictionary;
 mod ffi;
 
 pub use self::{
@@ -32,6 +33,9 @@ pub use self::{
     change::{ColumnChange, IntoArrowChange},
     conversion::{
         col_to_json_vals, json_utf8_json_vals, json_vals_to_any_type_col, json_vals_to_bool,
-        json_vals_to_col, json_vals_to_primitive, json_vals_to_utf8,
+        json_vals_to_category, json_vals_to_col, json_vals_to_primitive, json_vals_to_utf8,
+    },
+    dictionary::{
+        dictionary_data_type, dictionary_values, keys_to_dictionary, DICTIONARY_VALUES_METADATA_KEY,
     },
 };
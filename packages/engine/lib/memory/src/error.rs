@@ -65,6 +65,12 @@ pub enum Error {
     #[error("Object is missing field with name: {0}")]
     MissingFieldInObject(String),
 
+    #[error("Unknown category {value}, expected one of {categories:?}")]
+    UnknownCategory {
+        value: String,
+        categories: Vec<String>,
+    },
+
     #[error("Expected boolean value in `serde_json::Value`")]
     BooleanSerdeValueExpected,
 
@@ -2,7 +2,8 @@
 //!
 //! This module mainly provides [`Segment`], which holds a shared-memory segment. See its
 //! documentation for further information. [`MemoryId`] is used to identify a [`Segment`] using a
-//! UUID and a random number appended to it.
+//! UUID and a random number appended to it. Segments left behind by crashed engine processes can
+//! be found with [`list_segments`] and removed with [`reap_orphaned_segments`].
 //!
 //! This module provides an FFI interface containing `CSegment` as a representation of `Segment`
 //! and the `load_shmem` and `free_memory` functions (in `ffi`).
@@ -24,5 +25,9 @@ pub use self::{
     continuation::arrow_continuation,
     markers::Markers,
     metaversion::Metaversion,
-    segment::{cleanup_by_base_id, MemoryId, Segment},
+    segment::{
+        cleanup_by_base_id,
+        inspect::{list_segments, reap_orphaned_segments, SegmentInfo},
+        pool, MemoryId, Segment,
+    },
 };
@@ -15,11 +15,16 @@ use crate::{shared_memory::MemoryId, Error, Result};
 
 /// Clean up generated shared memory segments associated with a given `MemoryId`.
 ///
+/// This also removes the segments kept in the [`pool`](super::pool) for this `MemoryId` and the
+/// record of the current process owning them (see [`inspect`](super::inspect)).
+///
 /// If debug assertions are enabled, this function will panic if there are any shared-memory
 /// segments left to clear up. This is because ideally the engine would clean them all up promptly
 /// (i.e. when the batch the segment corresponds to is no longer needed).
 #[allow(clippy::significant_drop_in_scrutinee)]
 pub fn cleanup_by_base_id(id: Uuid) -> Result<()> {
+    super::pool::release(id);
+
     let segments_not_removed_by_engine: Vec<String> = {
         let mut segments_list_lock = IN_USE_SHM_SEGMENTS.lock().unwrap();
 
@@ -69,6 +74,8 @@ pub fn cleanup_by_base_id(id: Uuid) -> Result<()> {
          {segments_not_removed_by_engine:?}"
     );
 
+    super::inspect::remove_owner(id);
+
     #[cfg(debug_assertions)]
     check_all_deallocated_linux(id)?;
 
@@ -0,0 +1,425 @@
+//! Inspection and removal of shared-memory segments left behind by engine processes.
+//!
+//! [`cleanup_by_base_id`] can only remove segments which are tracked in the current process. If an
+//! engine process is killed, e.g. by `SIGKILL`, its segments remain in */dev/shm* until they are
+//! removed explicitly. [`list_segments`] lists all segments created by the engine, the processes
+//! which still have them mapped and the processes owning them.
+//!
+//! A process creating segments of an experiment records itself as their owner in */dev/shm* (see
+//! [`record_owner`]). A segment is considered orphaned once all its owners have exited and no
+//! process maps it anymore, and can then be removed with [`reap_orphaned_segments`]. Segments of a
+//! running experiment, including the ones in its [`pool`], are never orphaned. If the mappings of a
+//! process of the user owning a segment can't be read, it's unknown whether the process uses the
+//! segment, so the segment isn't considered orphaned either.
+//!
+//! Listing segments is only supported on Linux, as other platforms don't expose their shared
+//! memory in the file system.
+//!
+//! [`cleanup_by_base_id`]: super::cleanup_by_base_id
+//! [`pool`]: super::pool
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    sync::{LazyLock, Mutex},
+};
+
+use uuid::Uuid;
+
+use crate::{Error, Result};
+
+const SHM_DIRECTORY: &str = "/dev/shm";
+const SEGMENT_PREFIX: &str = "shm_";
+const OWNER_PREFIX: &str = "hash_owner_";
+
+/// Number of segments alive in this process by the base id of the experiments this process has
+/// recorded itself as owner of.
+static OWNED_SEGMENTS: LazyLock<Mutex<HashMap<Uuid, usize>>> = LazyLock::new(Mutex::default);
+
+/// A shared-memory segment created by the engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SegmentInfo {
+    /// The operating system ID of the segment.
+    pub os_id: String,
+    /// The base id of the [`MemoryId`] of the segment, i.e. the ID of the experiment owning it.
+    ///
+    /// [`MemoryId`]: super::MemoryId
+    pub base_id: Uuid,
+    /// Size of the segment in bytes.
+    pub size: u64,
+    /// IDs of the processes which have the segment mapped into their memory.
+    pub pids: Vec<u32>,
+    /// IDs of the running processes which created segments of the experiment.
+    pub owners: Vec<u32>,
+    /// IDs of the processes of the user owning the segment, whose mappings could not be read.
+    pub unknown_pids: Vec<u32>,
+}
+
+impl SegmentInfo {
+    /// Returns the path of the segment in the file system.
+    pub fn path(&self) -> PathBuf {
+        Path::new(SHM_DIRECTORY).join(&self.os_id)
+    }
+
+    /// Returns `true` if the processes owning the segment have exited and no process has it mapped
+    /// anymore.
+    pub fn is_orphaned(&self) -> bool {
+        self.owners.is_empty() && self.pids.is_empty() && self.unknown_pids.is_empty()
+    }
+
+    /// Removes the segment.
+    ///
+    /// Processes which have the segment mapped keep their mapping, but it can't be opened again.
+    pub fn remove(&self) -> Result<()> {
+        fs::remove_file(self.path()).map_err(|err| {
+            Error::Memory(format!(
+                "Could not remove shared-memory segment {}: {err}",
+                self.os_id
+            ))
+        })
+    }
+}
+
+/// Parses the base id from the operating system ID of a segment, e.g. `shm_<uuid>_<suffix>`.
+pub(super) fn parse_base_id(os_id: &str) -> Option<Uuid> {
+    let (id, suffix) = os_id.strip_prefix(SEGMENT_PREFIX)?.rsplit_once('_')?;
+    suffix.parse::<u16>().ok()?;
+    Uuid::try_parse(id).ok()
+}
+
+/// Returns the path of the record of `pid` owning the segments of the experiment `base_id`.
+fn owner_path(base_id: Uuid, pid: u32) -> PathBuf {
+    Path::new(SHM_DIRECTORY).join(format!("{OWNER_PREFIX}{}_{pid}", base_id.as_simple()))
+}
+
+/// Parses the base id and the process ID from the file name of an owner record, e.g.
+/// `hash_owner_<uuid>_<pid>`.
+fn parse_owner(file_name: &str) -> Option<(Uuid, u32)> {
+    let (id, pid) = file_name.strip_prefix(OWNER_PREFIX)?.rsplit_once('_')?;
+    Some((Uuid::try_parse(id).ok()?, pid.parse().ok()?))
+}
+
+/// Returns the start time of the process `pid` in clock ticks after boot, or `None` if it's not
+/// running.
+///
+/// Together with the process ID, this identifies a process, as process IDs are reused.
+fn process_start_time(pid: u32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // The command name in the second field may contain spaces, the start time is the 22nd field
+    let (_, fields) = stat.rsplit_once(')')?;
+    fields.split_whitespace().nth(19)?.parse().ok()
+}
+
+/// Records the current process as owner of the segments of the experiment `base_id`, when it
+/// creates a segment of the experiment.
+///
+/// The record contains the start time of the process, so it isn't mistaken for a running process
+/// when the process ID is reused.
+pub(super) fn record_owner(base_id: Uuid) {
+    if !cfg!(target_os = "linux") {
+        return;
+    }
+    let mut owned_segments = OWNED_SEGMENTS.lock().unwrap();
+    if let Some(count) = owned_segments.get_mut(&base_id) {
+        *count += 1;
+        return;
+    }
+    owned_segments.insert(base_id, 1);
+    let pid = std::process::id();
+    let start_time = process_start_time(pid).unwrap_or_default();
+    if let Err(err) = fs::write(owner_path(base_id, pid), start_time.to_string()) {
+        tracing::warn!("Could not record the owner of the segments of {base_id}: {err}");
+    }
+}
+
+/// Notes that a segment of the experiment `base_id` created by the current process was dropped.
+///
+/// Once the last one is dropped, the record of the current process owning the segments is removed,
+/// unless the experiment keeps dropped segments in its [`pool`](super::pool).
+pub(super) fn release_owner(base_id: Uuid) {
+    let mut owned_segments = OWNED_SEGMENTS.lock().unwrap();
+    let count = match owned_segments.get_mut(&base_id) {
+        Some(count) => count,
+        None => return,
+    };
+    *count = count.saturating_sub(1);
+    if *count == 0 && !super::pool::is_enabled(base_id) {
+        owned_segments.remove(&base_id);
+        remove_owner_record(base_id);
+    }
+}
+
+/// Removes the record of the current process owning the segments of the experiment `base_id`.
+pub(super) fn remove_owner(base_id: Uuid) {
+    if OWNED_SEGMENTS.lock().unwrap().remove(&base_id).is_some() {
+        remove_owner_record(base_id);
+    }
+}
+
+fn remove_owner_record(base_id: Uuid) {
+    let path = owner_path(base_id, std::process::id());
+    if let Err(err) = fs::remove_file(&path) {
+        tracing::warn!("Could not remove {path:?}: {err}");
+    }
+}
+
+/// A process which recorded itself as owner of the segments of an experiment.
+struct OwnerRecord {
+    pid: u32,
+    path: PathBuf,
+    running: bool,
+}
+
+/// Returns the owner records in */dev/shm* by the base id of their experiment.
+fn owner_records() -> Result<HashMap<Uuid, Vec<OwnerRecord>>> {
+    let mut owners: HashMap<Uuid, Vec<OwnerRecord>> = HashMap::new();
+    let entries = fs::read_dir(SHM_DIRECTORY)
+        .map_err(|err| Error::Memory(format!("Could not list {SHM_DIRECTORY}: {err}")))?;
+    for entry in entries.flatten() {
+        let (base_id, pid) = match entry.file_name().to_str().and_then(parse_owner) {
+            Some(owner) => owner,
+            None => continue,
+        };
+        let path = entry.path();
+        let running = match fs::read_to_string(&path) {
+            Ok(start_time) => {
+                let start_time = start_time.trim().parse().ok();
+                let current_start_time = process_start_time(pid);
+                // A start time of 0 couldn't be recorded, so only the process has to exist
+                current_start_time.is_some()
+                    && (start_time == Some(0) || start_time == current_start_time)
+            }
+            // The record may have been removed in the meantime
+            Err(_) => continue,
+        };
+        owners
+            .entry(base_id)
+            .or_default()
+            .push(OwnerRecord { pid, path, running });
+    }
+    Ok(owners)
+}
+
+/// The memory mappings of all processes.
+pub(super) struct Mappings {
+    /// IDs of the processes which map each segment in */dev/shm*, by the segment's operating
+    /// system ID.
+    pub(super) segments: HashMap<String, Vec<u32>>,
+    /// IDs of the processes whose mappings could not be read, by the ID of their user.
+    pub(super) unreadable: HashMap<u32, Vec<u32>>,
+}
+
+/// Returns the IDs of all processes which map each segment in */dev/shm*.
+///
+/// Processes whose mappings we are not allowed to read are returned separately.
+pub(super) fn segment_mappings() -> Result<Mappings> {
+    let mut mappings = Mappings {
+        segments: HashMap::new(),
+        unreadable: HashMap::new(),
+    };
+    let processes = fs::read_dir("/proc")
+        .map_err(|err| Error::Memory(format!("Could not list processes: {err}")))?;
+    for process in processes.flatten() {
+        let pid = match process
+            .file_name()
+            .to_str()
+            .and_then(|pid| pid.parse().ok())
+        {
+            Some(pid) => pid,
+            None => continue,
+        };
+        let maps = match fs::read_to_string(process.path().join("maps")) {
+            Ok(maps) => maps,
+            Err(_) => {
+                // The process may have exited in the meantime
+                if let Ok(metadata) = process.metadata() {
+                    mappings
+                        .unreadable
+                        .entry(metadata.uid())
+                        .or_default()
+                        .push(pid);
+                }
+                continue;
+            }
+        };
+        for line in maps.lines() {
+            let os_id = match line
+                .split_once(SHM_DIRECTORY)
+                .and_then(|(_, path)| path.trim_start_matches('/').split_whitespace().next())
+            {
+                Some(os_id) if os_id.starts_with(SEGMENT_PREFIX) => os_id,
+                _ => continue,
+            };
+            let pids = mappings.segments.entry(os_id.to_owned()).or_default();
+            if !pids.contains(&pid) {
+                pids.push(pid);
+            }
+        }
+    }
+    Ok(mappings)
+}
+
+/// Lists all shared-memory segments created by the engine, optionally only the ones belonging to
+/// the experiment with the provided `base_id`.
+///
+/// Returns an empty list on platforms other than Linux.
+pub fn list_segments(base_id: Option<Uuid>) -> Result<Vec<SegmentInfo>> {
+    if !cfg!(target_os = "linux") {
+        return Ok(Vec::new());
+    }
+
+    let mappings = segment_mappings()?;
+    let owners = owner_records()?;
+    let entries = fs::read_dir(SHM_DIRECTORY)
+        .map_err(|err| Error::Memory(format!("Could not list {SHM_DIRECTORY}: {err}")))?;
+
+    let mut segments = Vec::new();
+    for entry in entries.flatten() {
+        let os_id = match entry.file_name().into_string() {
+            Ok(os_id) => os_id,
+            Err(_) => continue,
+        };
+        let segment_base_id = match parse_base_id(&os_id) {
+            Some(segment_base_id) => segment_base_id,
+            None => continue,
+        };
+        if base_id.map_or(false, |base_id| base_id != segment_base_id) {
+            continue;
+        }
+        // The segment may have been removed in the meantime
+        let metadata = match entry.metadata() {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        let pids = mappings.segments.get(&os_id).cloned().unwrap_or_default();
+        let owners = owners
+            .get(&segment_base_id)
+            .into_iter()
+            .flatten()
+            .filter(|owner| owner.running)
+            .map(|owner| owner.pid)
+            .collect();
+        let unknown_pids = mappings
+            .unreadable
+            .get(&metadata.uid())
+            .cloned()
+            .unwrap_or_default();
+        segments.push(SegmentInfo {
+            os_id,
+            base_id: segment_base_id,
+            size: metadata.len(),
+            pids,
+            owners,
+            unknown_pids,
+        });
+    }
+    segments.sort_by(|a, b| (a.base_id, &a.os_id).cmp(&(b.base_id, &b.os_id)));
+    Ok(segments)
+}
+
+/// Removes all orphaned segments, optionally only the ones belonging to the experiment with the
+/// provided `base_id`.
+///
+/// The records of exited owners are removed as well once their experiment has no segments left.
+///
+/// Returns the removed segments. Segments which could not be removed are logged and skipped.
+pub fn reap_orphaned_segments(base_id: Option<Uuid>) -> Result<Vec<SegmentInfo>> {
+    let mut removed = Vec::new();
+    let mut remaining = HashSet::new();
+    for segment in list_segments(base_id)? {
+        if !segment.is_orphaned() {
+            remaining.insert(segment.base_id);
+            continue;
+        }
+        match segment.remove() {
+            Ok(()) => {
+                tracing::debug!("Removed orphaned shared-memory segment {}", segment.os_id);
+                removed.push(segment);
+            }
+            Err(err) => {
+                tracing::warn!("{err}");
+                remaining.insert(segment.base_id);
+            }
+        }
+    }
+
+    if cfg!(target_os = "linux") {
+        let exited_owners = owner_records()?
+            .into_iter()
+            .filter(|(owner_base_id, _)| {
+                base_id.map_or(true, |base_id| base_id == *owner_base_id)
+                    && !remaining.contains(owner_base_id)
+            })
+            .flat_map(|(_, owners)| owners)
+            .filter(|owner| !owner.running);
+        for owner in exited_owners {
+            if let Err(err) = fs::remove_file(&owner.path) {
+                tracing::warn!("Could not remove {:?}: {err}", owner.path);
+            }
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use shared_memory::ShmemConf;
+
+    use super::*;
+    use crate::shared_memory::{MemoryId, Segment};
+
+    #[test]
+    fn parse_memory_id() {
+        let base_id = Uuid::new_v4();
+        let memory_id = MemoryId::new(base_id);
+        if cfg!(target_os = "linux") {
+            assert_eq!(parse_base_id(&memory_id.to_string()), Some(base_id));
+        }
+        assert_eq!(parse_base_id("shm_not-a-uuid_1"), None);
+        assert_eq!(parse_base_id("other_segment"), None);
+    }
+
+    #[test]
+    fn reap_segments_of_exited_owners() -> Result<()> {
+        if !cfg!(target_os = "linux") {
+            return Ok(());
+        }
+        let base_id = Uuid::new_v4();
+        let pid = std::process::id();
+
+        let segment = Segment::new(MemoryId::new(base_id), 4096, true, false)?;
+        let segments = list_segments(Some(base_id))?;
+        assert_eq!(segments.len(), 1);
+        assert_eq!(segments[0].owners, [pid]);
+        assert!(!segments[0].is_orphaned());
+        assert!(reap_orphaned_segments(Some(base_id))?.is_empty());
+        drop(segment);
+        assert!(!owner_path(base_id, pid).exists());
+
+        // Leave a segment behind like a killed engine, whose process ID was reused afterwards
+        let mut data = ShmemConf::new(true)
+            .os_id(&MemoryId::new(base_id).to_string())
+            .size(4096)
+            .create()?;
+        data.set_owner(false);
+        drop(data);
+        fs::write(owner_path(base_id, pid), "1").unwrap();
+
+        let segments = list_segments(Some(base_id))?;
+        assert_eq!(segments.len(), 1);
+        assert!(segments[0].owners.is_empty());
+        assert!(segments[0].pids.is_empty());
+        if segments[0].unknown_pids.is_empty() {
+            assert_eq!(reap_orphaned_segments(Some(base_id))?, segments);
+            assert!(!owner_path(base_id, pid).exists());
+        } else {
+            // Another process of this user may map the segment, so it must not be reaped
+            assert!(reap_orphaned_segments(Some(base_id))?.is_empty());
+            segments[0].remove()?;
+            fs::remove_file(owner_path(base_id, pid)).unwrap();
+        }
+        Ok(())
+    }
+}
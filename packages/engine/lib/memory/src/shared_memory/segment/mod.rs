@@ -15,7 +15,9 @@ use crate::{
 
 pub mod buffers;
 pub mod cleanup;
+pub mod inspect;
 pub mod memory_id;
+pub mod pool;
 
 pub use buffers::Buffers;
 pub use cleanup::cleanup_by_base_id;
@@ -63,6 +65,11 @@ use self::cleanup::IN_USE_SHM_SEGMENTS;
 /// ------------------------------------------------------------------------------------------------
 ///
 /// Note column data will not be densely packed as it will leave space for array size fluctuations.
+///
+/// ## Pooling
+///
+/// If the [`pool`] is enabled for an experiment, dropped segments are reused for new segments of
+/// the same experiment once no runner maps them anymore.
 pub struct Segment {
     pub data: Shmem,
     pub size: usize,
@@ -101,10 +108,18 @@ impl Drop for Segment {
                 }
             );
 
-            trace!(
-                "unlinking shared memory segment {} (as `is_owner`=true)",
-                self.id()
-            );
+            let base_id = inspect::parse_base_id(self.id());
+            if pool::recycle(&mut self.data) {
+                trace!("returning shared memory segment {} to the pool", self.id());
+            } else {
+                trace!(
+                    "unlinking shared memory segment {} (as `is_owner`=true)",
+                    self.id()
+                );
+            }
+            if let Some(base_id) = base_id {
+                inspect::release_owner(base_id);
+            }
         } else {
             trace!(
                 "dropping shared memory segment {} (as `is_owner=false`)",
@@ -123,10 +138,7 @@ impl Segment {
         include_terminal_padding: bool,
     ) -> Result<Segment> {
         Self::validate_size(size)?;
-        let data = ShmemConf::new(droppable)
-            .os_id(&memory_id.to_string())
-            .size(size)
-            .create()?;
+        let data = Self::create_shmem(&memory_id, size, droppable)?;
         IN_USE_SHM_SEGMENTS
             .lock()
             .unwrap()
@@ -193,10 +205,7 @@ impl Segment {
         debug_assert_ne!(memory.id(), os_id);
 
         let shmem = &memory.data;
-        let data = ShmemConf::new(true)
-            .os_id(&os_id)
-            .size(memory.size)
-            .create()?;
+        let data = Self::create_shmem(&memory_id, memory.size, true)?;
         unsafe { std::ptr::copy_nonoverlapping(shmem.as_ptr(), data.as_ptr(), memory.size) };
 
         // make a note that we created this segment
@@ -211,6 +220,23 @@ impl Segment {
         })
     }
 
+    /// Takes a shared-memory object of `size` bytes from the [`pool`] or creates a new one, and
+    /// records the current process as owner of the experiment's segments.
+    fn create_shmem(memory_id: &MemoryId, size: usize, droppable: bool) -> Result<Shmem> {
+        let data = match pool::take(memory_id, size, droppable) {
+            Some(data) => data,
+            None => {
+                pool::record_allocation();
+                ShmemConf::new(droppable)
+                    .os_id(&memory_id.to_string())
+                    .size(size)
+                    .create()?
+            }
+        };
+        inspect::record_owner(memory_id.base_id());
+        Ok(data)
+    }
+
     /// Get the ID of the shared memory segment
     pub fn id(&self) -> &str {
         self.data.get_os_id()
@@ -0,0 +1,394 @@
+//! Recycling of shared-memory segments between batches.
+//!
+//! Creating a [`Segment`] creates, truncates and maps a new shared-memory object, and dropping it
+//! unlinks the object again. When batches are recreated frequently, e.g. because agents are created
+//! or removed every step, this causes a lot of system calls and page faults. If the pool is
+//! [enabled](enable) for an experiment, a dropped [`Segment`] is not unlinked but returned to the
+//! pool of its experiment and handed out again when a new [`Segment`] of a suitable size is
+//! requested for the same experiment.
+//!
+//! Language runners map the batches they have loaded and cache them by the ID of their segment, so
+//! a dropped segment is only reused once no process maps it anymore, i.e. once every runner has
+//! released it. When a segment is taken from the pool, its shared-memory object is renamed to the
+//! newly requested [`MemoryId`], truncated to the requested size and zeroed, so it's
+//! indistinguishable from a newly created segment. As this requires shared memory to be exposed in
+//! the file system, the pool is only used on Linux.
+//!
+//! Pooled segments are removed by [`cleanup_by_base_id`] at the end of the experiment.
+//!
+//! [`Segment`]: super::Segment
+//! [`cleanup_by_base_id`]: super::cleanup_by_base_id
+
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        LazyLock, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use shared_memory::{Shmem, ShmemConf};
+use uuid::Uuid;
+
+use crate::shared_memory::{
+    segment::inspect::{parse_base_id, segment_mappings},
+    MemoryId,
+};
+
+/// Maximum number of segments kept in the pool of a single experiment.
+const MAX_POOLED_SEGMENTS: usize = 64;
+
+/// A pooled segment is only reused for requests of at least `1 / MAX_HEADROOM_FACTOR` of its
+/// capacity, so small batches don't occupy large segments.
+const MAX_HEADROOM_FACTOR: usize = 4;
+
+/// Minimum time between two checks whether dropped segments are still mapped by any process, as
+/// this has to read the memory mappings of all processes.
+const MAPPING_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+
+struct PooledSegment {
+    os_id: String,
+    capacity: usize,
+}
+
+/// The pool of a single experiment.
+#[derive(Default)]
+struct ExperimentPool {
+    /// Segments which are not mapped by any process anymore and can be reused.
+    available: Vec<PooledSegment>,
+    /// Dropped segments which may still be mapped by a runner.
+    dropped: Vec<PooledSegment>,
+    last_mapping_check: Option<Instant>,
+}
+
+impl ExperimentPool {
+    /// Makes the dropped segments, which are not mapped by any process anymore, available.
+    fn release_unmapped(&mut self) {
+        if self.dropped.is_empty()
+            || self.last_mapping_check.map_or(false, |last_check| {
+                last_check.elapsed() < MAPPING_CHECK_INTERVAL
+            })
+        {
+            return;
+        }
+        self.last_mapping_check = Some(Instant::now());
+        let mappings = match segment_mappings() {
+            Ok(mappings) => mappings,
+            Err(err) => {
+                tracing::debug!("Could not check mappings of pooled segments: {err}");
+                return;
+            }
+        };
+        let (mapped, unmapped): (Vec<_>, Vec<_>) = std::mem::take(&mut self.dropped)
+            .into_iter()
+            .partition(|segment| mappings.segments.contains_key(&segment.os_id));
+        self.dropped = mapped;
+        self.available.extend(unmapped);
+    }
+
+    fn len(&self) -> usize {
+        self.available.len() + self.dropped.len()
+    }
+}
+
+/// Experiments with an enabled pool, by their base id.
+static POOL: LazyLock<Mutex<HashMap<Uuid, ExperimentPool>>> = LazyLock::new(Mutex::default);
+
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static REUSED: AtomicU64 = AtomicU64::new(0);
+static REUSED_BYTES: AtomicU64 = AtomicU64::new(0);
+static RECYCLED: AtomicU64 = AtomicU64::new(0);
+static RELEASED: AtomicU64 = AtomicU64::new(0);
+
+/// Counters of the segment pool since the start of the process.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// Number of segments created as no suitable segment was pooled.
+    pub allocations: u64,
+    /// Number of segments taken from the pool, i.e. allocations avoided.
+    pub reused: u64,
+    /// Total size in bytes of the segments taken from the pool.
+    pub reused_bytes: u64,
+    /// Number of dropped segments which were returned to the pool.
+    pub recycled: u64,
+    /// Number of pooled segments which were removed without being reused.
+    pub released: u64,
+}
+
+/// Returns the current [`PoolMetrics`].
+pub fn metrics() -> PoolMetrics {
+    PoolMetrics {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        reused: REUSED.load(Ordering::Relaxed),
+        reused_bytes: REUSED_BYTES.load(Ordering::Relaxed),
+        recycled: RECYCLED.load(Ordering::Relaxed),
+        released: RELEASED.load(Ordering::Relaxed),
+    }
+}
+
+/// Enables the pool for the segments of the experiment with the provided `base_id`.
+///
+/// The pool is disabled again by [`cleanup_by_base_id`](super::cleanup_by_base_id).
+pub fn enable(base_id: Uuid) {
+    if cfg!(target_os = "linux") {
+        POOL.lock().unwrap().entry(base_id).or_default();
+    }
+}
+
+/// Returns `true` if the pool is enabled for the experiment with the provided `base_id`.
+pub fn is_enabled(base_id: Uuid) -> bool {
+    POOL.lock().unwrap().contains_key(&base_id)
+}
+
+fn shm_path(os_id: &str) -> String {
+    format!("/dev/shm/{os_id}")
+}
+
+/// Takes a segment from the pool, truncates it to `size` bytes and makes it available as
+/// `memory_id`.
+///
+/// Returns `None` if no suitable segment is pooled, in which case a new segment has to be created.
+pub(super) fn take(memory_id: &MemoryId, size: usize, droppable: bool) -> Option<Shmem> {
+    loop {
+        let pooled = {
+            let mut pool = POOL.lock().unwrap();
+            let experiment_pool = pool.get_mut(&memory_id.base_id())?;
+            experiment_pool.release_unmapped();
+            let segments = &mut experiment_pool.available;
+            // Prefer the smallest suitable segment to keep large ones for large batches
+            let index = segments
+                .iter()
+                .enumerate()
+                .filter(|(_, segment)| {
+                    segment.capacity >= size
+                        && segment.capacity <= size.saturating_mul(MAX_HEADROOM_FACTOR)
+                })
+                .min_by_key(|(_, segment)| segment.capacity)
+                .map(|(index, _)| index)?;
+            segments.swap_remove(index)
+        };
+
+        // The pooled segment might have been removed externally, in which case we try the next one
+        let os_id = memory_id.to_string();
+        if let Err(err) = fs::rename(shm_path(&pooled.os_id), shm_path(&os_id)) {
+            tracing::debug!("Could not reuse pooled segment {}: {err}", pooled.os_id);
+            continue;
+        }
+        match open_with_size(&os_id, size, droppable) {
+            Ok(data) => {
+                REUSED.fetch_add(1, Ordering::Relaxed);
+                REUSED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+                return Some(data);
+            }
+            Err(err) => {
+                tracing::warn!("Could not open pooled segment {os_id}: {err}");
+                if let Err(err) = fs::remove_file(shm_path(&os_id)) {
+                    tracing::warn!("Could not remove pooled segment {os_id}: {err}");
+                }
+            }
+        }
+    }
+}
+
+/// Opens the pooled segment `os_id` and truncates it to `size` bytes, so it has the same size as a
+/// newly created segment for every process opening it.
+fn open_with_size(
+    os_id: &str,
+    size: usize,
+    droppable: bool,
+) -> Result<Shmem, shared_memory::ShmemError> {
+    let mut data = ShmemConf::new(droppable).os_id(os_id).open()?;
+    data.set_owner(true);
+    if data.len() != size {
+        data.resize(size)?;
+    }
+    // Newly created segments are zeroed, so recycled segments have to be as well
+    unsafe { std::ptr::write_bytes(data.as_ptr(), 0, size) };
+    Ok(data)
+}
+
+/// Records that a new segment had to be created.
+pub(super) fn record_allocation() {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Returns the segment backing `data` to the pool if it's enabled for the segment's experiment.
+///
+/// Returns `false` if the segment is not pooled, in which case it has to be unlinked as usual. If
+/// it returns `true`, `data` is not owning the shared-memory object anymore, so dropping it only
+/// unmaps it.
+pub(super) fn recycle(data: &mut Shmem) -> bool {
+    let os_id = data.get_os_id().to_owned();
+    let base_id = match parse_base_id(&os_id) {
+        Some(base_id) => base_id,
+        None => return false,
+    };
+
+    let mut pool = POOL.lock().unwrap();
+    let experiment_pool = match pool.get_mut(&base_id) {
+        Some(experiment_pool) => experiment_pool,
+        None => return false,
+    };
+    if experiment_pool.len() >= MAX_POOLED_SEGMENTS || !Path::new(&shm_path(&os_id)).exists() {
+        return false;
+    }
+    data.set_owner(false);
+    experiment_pool.dropped.push(PooledSegment {
+        os_id,
+        capacity: data.len(),
+    });
+    RECYCLED.fetch_add(1, Ordering::Relaxed);
+    true
+}
+
+/// Removes all pooled segments of the experiment with the provided `base_id` and disables its pool.
+pub(super) fn release(base_id: Uuid) {
+    let experiment_pool = match POOL.lock().unwrap().remove(&base_id) {
+        Some(experiment_pool) => experiment_pool,
+        None => return,
+    };
+    let segments = experiment_pool
+        .available
+        .iter()
+        .chain(&experiment_pool.dropped);
+    for segment in segments.clone() {
+        if let Err(err) = fs::remove_file(shm_path(&segment.os_id)) {
+            tracing::warn!("Could not remove pooled segment {}: {err}", segment.os_id);
+        }
+    }
+    RELEASED.fetch_add(segments.count() as u64, Ordering::Relaxed);
+    tracing::debug!("Shared-memory segment pool: {:?}", metrics());
+}
+
+#[cfg(all(test, not(miri), target_os = "linux"))]
+mod tests {
+    use super::*;
+    use crate::shared_memory::Segment;
+
+    /// Drops `segment` and returns its ID once it's available in the pool.
+    fn recycle_segment(segment: Segment) -> String {
+        let os_id = segment.id().to_owned();
+        drop(segment);
+        assert!(Path::new(&shm_path(&os_id)).exists());
+        os_id
+    }
+
+    #[test]
+    fn disabled_by_default() -> crate::Result<()> {
+        let base_id = Uuid::new_v4();
+        let segment = Segment::new(MemoryId::new(base_id), 4096, true, false)?;
+        let os_id = segment.id().to_owned();
+        drop(segment);
+        assert!(!is_enabled(base_id));
+        assert!(!Path::new(&shm_path(&os_id)).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn reuses_dropped_segment() -> crate::Result<()> {
+        let base_id = Uuid::new_v4();
+        enable(base_id);
+        let old_id = recycle_segment(Segment::new(MemoryId::new(base_id), 4096, true, false)?);
+
+        let reused_before = metrics().reused;
+        let memory_id = MemoryId::new(base_id);
+        let new_id = memory_id.to_string();
+        let segment = Segment::new(memory_id, 2048, true, false)?;
+        assert!(metrics().reused > reused_before);
+        assert_eq!(segment.id(), new_id);
+        // The segment has the requested size for every process opening it
+        assert_eq!(segment.size, 2048);
+        assert_eq!(Segment::open(&new_id, false, false)?.size, 2048);
+        assert!(!Path::new(&shm_path(&old_id)).exists());
+        drop(segment);
+
+        crate::shared_memory::cleanup_by_base_id(base_id)?;
+        assert!(!is_enabled(base_id));
+        assert!(!Path::new(&shm_path(&new_id)).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_mapped_segment() -> crate::Result<()> {
+        let base_id = Uuid::new_v4();
+        enable(base_id);
+        let segment = Segment::new(MemoryId::new(base_id), 4096, true, false)?;
+        // A runner which still has the batch loaded
+        let runner_view = Segment::open_unchecked(segment.id(), false, false)?;
+        let old_id = recycle_segment(segment);
+
+        let segment = Segment::new(MemoryId::new(base_id), 4096, true, false)?;
+        assert_ne!(segment.id(), old_id);
+        assert!(Path::new(&shm_path(&old_id)).exists());
+        drop(runner_view);
+        drop(segment);
+
+        crate::shared_memory::cleanup_by_base_id(base_id)?;
+        assert!(!Path::new(&shm_path(&old_id)).exists());
+        Ok(())
+    }
+
+    #[test]
+    fn duplicates_into_recycled_segment() -> crate::Result<()> {
+        let base_id = Uuid::new_v4();
+        enable(base_id);
+        let original = Segment::from_batch_buffers(
+            MemoryId::new(base_id),
+            &[1; 100],
+            &[2; 50],
+            &[3; 20],
+            &[4; 400],
+            false,
+        )?;
+        let old_id = recycle_segment(Segment::from_batch_buffers(
+            MemoryId::new(base_id),
+            &[9; 200],
+            &[9; 100],
+            &[9; 50],
+            &[9; 800],
+            false,
+        )?);
+
+        let duplicate = Segment::duplicate(&original, MemoryId::new(base_id))?;
+        assert!(!Path::new(&shm_path(&old_id)).exists());
+        assert_eq!(duplicate.size, original.size);
+        duplicate.validate_markers()?;
+        let buffers = duplicate.get_batch_buffers()?;
+        assert_eq!(buffers.schema, [1; 100]);
+        assert_eq!(buffers.header, [2; 50]);
+        assert_eq!(buffers.meta, [3; 20]);
+        assert_eq!(buffers.data, [4; 400]);
+        drop(duplicate);
+        drop(original);
+
+        crate::shared_memory::cleanup_by_base_id(base_id)?;
+        Ok(())
+    }
+
+    #[test]
+    fn validates_markers_of_recycled_segment() -> crate::Result<()> {
+        let base_id = Uuid::new_v4();
+        enable(base_id);
+        let old_id = recycle_segment(Segment::from_sizes(
+            MemoryId::new(base_id),
+            1000,
+            500,
+            500,
+            2000,
+            false,
+        )?);
+
+        let segment = Segment::from_sizes(MemoryId::new(base_id), 400, 200, 200, 1000, false)?;
+        assert!(!Path::new(&shm_path(&old_id)).exists());
+        segment.validate_markers()?;
+        // Buffers of the previous batch are not visible
+        assert!(segment.get_data_buffer()?.iter().all(|byte| *byte == 0));
+        drop(segment);
+
+        crate::shared_memory::cleanup_by_base_id(base_id)?;
+        Ok(())
+    }
+}
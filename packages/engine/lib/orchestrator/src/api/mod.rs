@@ -0,0 +1,326 @@
+//! A local HTTP/JSON API to submit and monitor experiments.
+//!
+//! The [`ApiServer`] runs each submitted experiment on its own `hash_engine` subprocess using
+//! [`Experiment::run_with_monitor()`]. The following routes are provided:
+//!
+//! - `GET /experiments`: Lists all submitted experiments
+//! - `POST /experiments`: Submits a new experiment, the body is a [`SubmitRequest`]
+//! - `GET /experiments/:id`: Returns the [`RunInfo`] of an experiment
+//! - `DELETE /experiments/:id`: Cancels a running experiment
+//! - `GET /experiments/:id/events`: Streams the [`EngineStatus`] messages of a running experiment
+//!   as server-sent events, the event name is the [`kind`](EngineStatus::kind) of the message
+//! - `GET /experiments/:id/outputs`: Lists the output folders of the finished simulations
+//!
+//! [`EngineStatus`]: simulation_control::EngineStatus
+
+mod registry;
+
+use std::{net::SocketAddr, path::PathBuf, sync::Arc};
+
+use axum::{
+    extract::{Extension, Path as UrlPath},
+    http::StatusCode,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    routing::get,
+    Json, Router,
+};
+use error_stack::{IntoReport, ResultExt};
+use execution::package::experiment::ExperimentId;
+use experiment_structure::{ExperimentRun, ExperimentType, Manifest};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::{broadcast, oneshot};
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamExt,
+};
+
+pub use self::registry::{Registry, RunInfo, RunState};
+use self::registry::Subscription;
+use crate::{
+    experiment::STATUS_CHANNEL_CAPACITY, Experiment, ExperimentConfig, ExperimentMonitor, Handler,
+    OrchestratorError, Result,
+};
+
+/// Body of a `POST /experiments` request.
+#[derive(Debug, Deserialize)]
+pub struct SubmitRequest {
+    /// Path to the project to be run.
+    pub project: PathBuf,
+    /// Experiment type to be run, e.g. `{ "single-run": { "num_steps": 10 } }`.
+    pub experiment: ExperimentType,
+}
+
+/// Serves the HTTP API and runs the experiments submitted to it.
+#[derive(Clone)]
+pub struct ApiServer {
+    config: ExperimentConfig,
+    handler: Handler,
+    registry: Registry,
+}
+
+impl ApiServer {
+    /// Creates an API server which runs experiments with the provided `config`.
+    ///
+    /// The `handler` has to be connected to a running [`Server`](crate::Server).
+    pub fn new(config: ExperimentConfig, handler: Handler) -> Self {
+        Self {
+            config,
+            handler,
+            registry: Registry::default(),
+        }
+    }
+
+    /// Returns the registry of all experiments submitted to this server.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// Reads the project at `project` and starts running the experiment in the background.
+    ///
+    /// # Errors
+    ///
+    /// - if the project could not be read
+    /// - if the experiment could not be created from the project
+    pub async fn submit(
+        &self,
+        project: PathBuf,
+        experiment_type: ExperimentType,
+    ) -> Result<RunInfo> {
+        // Reading the project accesses the file system, which would block the API server.
+        let (project, experiment_run) =
+            tokio::task::spawn_blocking(move || read_project(project, experiment_type))
+                .await
+                .into_report()
+                .change_context(OrchestratorError::from("Could not read project"))??;
+
+        let id = experiment_run.id();
+        let info = RunInfo {
+            id,
+            name: experiment_run.name().clone(),
+            project,
+            state: RunState::Running,
+            output_paths: Vec::new(),
+            error: None,
+        };
+        let (status_tx, mut status_rx) = broadcast::channel(STATUS_CHANNEL_CAPACITY);
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        self.registry
+            .insert(info.clone(), status_tx.clone(), cancel_tx);
+
+        let registry = self.registry.clone();
+        tokio::spawn(async move {
+            loop {
+                match status_rx.recv().await {
+                    Ok(status) => registry.record_status(id, &status),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!("Experiment {id} skipped {skipped} status messages");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let experiment = Experiment::new(self.config.clone());
+        let handler = self.handler.clone();
+        let registry = self.registry.clone();
+        tokio::spawn(async move {
+            let monitor = ExperimentMonitor {
+                status_tx: Some(status_tx),
+                cancel_rx: Some(cancel_rx),
+            };
+            let result = experiment
+                .run_with_monitor(experiment_run, handler, None, monitor)
+                .await;
+            if let Err(report) = &result {
+                error!("Experiment {id} failed: {report:?}");
+            }
+            registry.finish(id, result);
+        });
+
+        info!("Submitted experiment {id}");
+        Ok(info)
+    }
+
+    /// Serves the HTTP API on `address` until the server fails.
+    ///
+    /// # Errors
+    ///
+    /// - if the server could not bind to `address`
+    pub async fn serve(self, address: SocketAddr) -> Result<()> {
+        let app = Router::new()
+            .route("/experiments", get(list_experiments).post(submit_experiment))
+            .route(
+                "/experiments/:id",
+                get(get_experiment).delete(cancel_experiment),
+            )
+            .route("/experiments/:id/events", get(experiment_events))
+            .route("/experiments/:id/outputs", get(experiment_outputs))
+            .layer(Extension(Arc::new(self)));
+
+        info!("Listening for API requests on http://{address}");
+        axum::Server::try_bind(&address)
+            .into_report()
+            .change_context_lazy(|| {
+                OrchestratorError::from(format!("Could not bind API server to {address}"))
+            })?
+            .serve(app.into_make_service())
+            .await
+            .into_report()
+            .change_context(OrchestratorError::from("API server failed"))
+    }
+}
+
+fn read_project(
+    project: PathBuf,
+    experiment_type: ExperimentType,
+) -> Result<(PathBuf, ExperimentRun)> {
+    let project = project
+        .canonicalize()
+        .into_report()
+        .change_context_lazy(|| {
+            OrchestratorError::from(format!("Could not canonicalize project path: {project:?}"))
+        })?;
+    let experiment_run = Manifest::from_local(&project)
+        .change_context_lazy(|| {
+            OrchestratorError::from(format!("Could not read local project {project:?}"))
+        })?
+        .read(experiment_type)
+        .change_context(OrchestratorError::from("Could not read manifest"))?;
+    Ok((project, experiment_run))
+}
+
+/// Errors returned to API clients.
+enum ApiError {
+    InvalidId(String),
+    NotFound(ExperimentId),
+    NotRunning(ExperimentId),
+    Submit(error_stack::Report<OrchestratorError>),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            Self::InvalidId(id) => (
+                StatusCode::BAD_REQUEST,
+                format!("Not a valid experiment id: {id:?}"),
+            ),
+            Self::NotFound(id) => (StatusCode::NOT_FOUND, format!("Unknown experiment {id}")),
+            Self::NotRunning(id) => (
+                StatusCode::CONFLICT,
+                format!("Experiment {id} is not running"),
+            ),
+            Self::Submit(report) => (StatusCode::UNPROCESSABLE_ENTITY, format!("{report:#}")),
+        };
+        (status, Json(json!({ "error": message }))).into_response()
+    }
+}
+
+fn parse_id(id: &str) -> core::result::Result<ExperimentId, ApiError> {
+    id.parse().map_err(|_| ApiError::InvalidId(id.to_owned()))
+}
+
+async fn list_experiments(Extension(api): Extension<Arc<ApiServer>>) -> Json<Vec<RunInfo>> {
+    Json(api.registry.list())
+}
+
+async fn submit_experiment(
+    Extension(api): Extension<Arc<ApiServer>>,
+    Json(request): Json<SubmitRequest>,
+) -> core::result::Result<(StatusCode, Json<RunInfo>), ApiError> {
+    let info = api
+        .submit(request.project, request.experiment)
+        .await
+        .map_err(ApiError::Submit)?;
+    Ok((StatusCode::CREATED, Json(info)))
+}
+
+async fn get_experiment(
+    Extension(api): Extension<Arc<ApiServer>>,
+    UrlPath(id): UrlPath<String>,
+) -> core::result::Result<Json<RunInfo>, ApiError> {
+    let id = parse_id(&id)?;
+    api.registry
+        .get(id)
+        .map(Json)
+        .ok_or(ApiError::NotFound(id))
+}
+
+async fn cancel_experiment(
+    Extension(api): Extension<Arc<ApiServer>>,
+    UrlPath(id): UrlPath<String>,
+) -> core::result::Result<StatusCode, ApiError> {
+    let id = parse_id(&id)?;
+    match api.registry.cancel(id) {
+        Some(true) => Ok(StatusCode::ACCEPTED),
+        Some(false) => Err(ApiError::NotRunning(id)),
+        None => Err(ApiError::NotFound(id)),
+    }
+}
+
+async fn experiment_events(
+    Extension(api): Extension<Arc<ApiServer>>,
+    UrlPath(id): UrlPath<String>,
+) -> core::result::Result<impl IntoResponse, ApiError> {
+    let id = parse_id(&id)?;
+    let status_rx = match api.registry.subscribe(id) {
+        Subscription::Receiver(status_rx) => status_rx,
+        Subscription::Finished => return Err(ApiError::NotRunning(id)),
+        Subscription::NotFound => return Err(ApiError::NotFound(id)),
+    };
+
+    let events = BroadcastStream::new(status_rx).filter_map(move |status| match status {
+        Ok(status) => Some(Event::default().event(status.kind()).json_data(&*status)),
+        Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+            warn!("Event stream for experiment {id} skipped {skipped} messages");
+            None
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+async fn experiment_outputs(
+    Extension(api): Extension<Arc<ApiServer>>,
+    UrlPath(id): UrlPath<String>,
+) -> core::result::Result<Json<Vec<PathBuf>>, ApiError> {
+    let id = parse_id(&id)?;
+    api.registry
+        .get(id)
+        .map(|info| Json(info.output_paths))
+        .ok_or(ApiError::NotFound(id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(error: ApiError) -> StatusCode {
+        error.into_response().status()
+    }
+
+    #[test]
+    fn test_parse_id() {
+        let id = ExperimentId::generate();
+        assert!(matches!(parse_id(&id.to_string()), Ok(parsed) if parsed == id));
+        match parse_id("not-an-id") {
+            Err(error) => assert_eq!(status(error), StatusCode::BAD_REQUEST),
+            Ok(id) => panic!("Parsed invalid id as {id}"),
+        }
+    }
+
+    #[test]
+    fn test_error_status() {
+        let id = ExperimentId::generate();
+        assert_eq!(status(ApiError::NotFound(id)), StatusCode::NOT_FOUND);
+        assert_eq!(status(ApiError::NotRunning(id)), StatusCode::CONFLICT);
+        assert_eq!(
+            status(ApiError::Submit(error_stack::report!(
+                OrchestratorError::from("invalid project")
+            ))),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+    }
+}
@@ -0,0 +1,231 @@
+//! Bookkeeping of the experiments submitted through the [HTTP API](super).
+
+use std::{
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use execution::package::experiment::{ExperimentId, ExperimentName};
+use serde::Serialize;
+use simulation_control::{EngineStatus, SimStatus};
+use tokio::sync::{broadcast, oneshot};
+
+use crate::Result;
+
+/// The lifecycle state of a submitted experiment.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunState {
+    /// The engine process is running the experiment.
+    Running,
+    /// The experiment finished successfully.
+    Finished,
+    /// The experiment finished with an error.
+    Failed,
+    /// The experiment was cancelled before it finished.
+    Cancelled,
+}
+
+/// Public information about a submitted experiment.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunInfo {
+    pub id: ExperimentId,
+    pub name: ExperimentName,
+    pub project: PathBuf,
+    pub state: RunState,
+    /// Output folders of the simulations which have finished so far.
+    pub output_paths: Vec<PathBuf>,
+    /// The error message if the experiment [failed](RunState::Failed).
+    pub error: Option<String>,
+}
+
+struct RunEntry {
+    info: RunInfo,
+    status_tx: Option<broadcast::Sender<Arc<EngineStatus>>>,
+    cancel_tx: Option<oneshot::Sender<()>>,
+}
+
+/// Outcome of a [`Registry::subscribe`] call.
+pub(super) enum Subscription {
+    Receiver(broadcast::Receiver<Arc<EngineStatus>>),
+    Finished,
+    NotFound,
+}
+
+/// Shared list of all experiments submitted to the API, in order of submission.
+#[derive(Clone, Default)]
+pub struct Registry {
+    runs: Arc<Mutex<Vec<RunEntry>>>,
+}
+
+impl Registry {
+    fn with_entry<T>(&self, id: ExperimentId, f: impl FnOnce(&mut RunEntry) -> T) -> Option<T> {
+        self.runs
+            .lock()
+            .expect("experiment registry lock is poisoned")
+            .iter_mut()
+            .find(|entry| entry.info.id == id)
+            .map(f)
+    }
+
+    pub(super) fn insert(
+        &self,
+        info: RunInfo,
+        status_tx: broadcast::Sender<Arc<EngineStatus>>,
+        cancel_tx: oneshot::Sender<()>,
+    ) {
+        self.runs
+            .lock()
+            .expect("experiment registry lock is poisoned")
+            .push(RunEntry {
+                info,
+                status_tx: Some(status_tx),
+                cancel_tx: Some(cancel_tx),
+            });
+    }
+
+    /// Returns information about all submitted experiments.
+    pub fn list(&self) -> Vec<RunInfo> {
+        self.runs
+            .lock()
+            .expect("experiment registry lock is poisoned")
+            .iter()
+            .map(|entry| entry.info.clone())
+            .collect()
+    }
+
+    /// Returns information about the experiment identified by `id`.
+    pub fn get(&self, id: ExperimentId) -> Option<RunInfo> {
+        self.with_entry(id, |entry| entry.info.clone())
+    }
+
+    /// Subscribes to the [`EngineStatus`] messages of a running experiment.
+    pub(super) fn subscribe(&self, id: ExperimentId) -> Subscription {
+        self.with_entry(id, |entry| match &entry.status_tx {
+            Some(status_tx) => Subscription::Receiver(status_tx.subscribe()),
+            None => Subscription::Finished,
+        })
+        .unwrap_or(Subscription::NotFound)
+    }
+
+    /// Requests the cancellation of the experiment identified by `id`.
+    ///
+    /// Returns `None` if no such experiment exists and `false` if it is not running anymore.
+    pub fn cancel(&self, id: ExperimentId) -> Option<bool> {
+        self.with_entry(id, |entry| match entry.cancel_tx.take() {
+            Some(cancel_tx) if entry.info.state == RunState::Running => {
+                // If the experiment is already shutting down, it isn't cancelled but its result is
+                // recorded by `finish`.
+                let cancelled = cancel_tx.send(()).is_ok();
+                if cancelled {
+                    entry.info.state = RunState::Cancelled;
+                }
+                cancelled
+            }
+            _ => false,
+        })
+    }
+
+    /// Updates the experiment identified by `id` with information from an [`EngineStatus`].
+    pub(super) fn record_status(&self, id: ExperimentId, status: &EngineStatus) {
+        if let EngineStatus::SimStatus(SimStatus {
+            persistence_result: Some((_, serde_json::Value::String(path))),
+            ..
+        }) = status
+        {
+            self.with_entry(id, |entry| entry.info.output_paths.push(PathBuf::from(path)));
+        }
+    }
+
+    /// Marks the experiment identified by `id` as finished with the provided `result`.
+    pub(super) fn finish(&self, id: ExperimentId, result: Result<()>) {
+        self.with_entry(id, |entry| {
+            entry.status_tx = None;
+            entry.cancel_tx = None;
+            match result {
+                Ok(()) => entry.info.state = RunState::Finished,
+                // Cancelled experiments always return an error, which is expected
+                Err(_) if entry.info.state == RunState::Cancelled => {}
+                Err(report) => {
+                    entry.info.state = RunState::Failed;
+                    entry.info.error = Some(format!("{report:#}"));
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use error_stack::report;
+
+    use super::*;
+    use crate::OrchestratorError;
+
+    fn insert_run(registry: &Registry) -> (ExperimentId, oneshot::Receiver<()>) {
+        let id = ExperimentId::generate();
+        let (status_tx, _) = broadcast::channel(1);
+        let (cancel_tx, cancel_rx) = oneshot::channel();
+        registry.insert(
+            RunInfo {
+                id,
+                name: ExperimentName::from("test".to_owned()),
+                project: PathBuf::from("project"),
+                state: RunState::Running,
+                output_paths: Vec::new(),
+                error: None,
+            },
+            status_tx,
+            cancel_tx,
+        );
+        (id, cancel_rx)
+    }
+
+    #[test]
+    fn test_finish() {
+        let registry = Registry::default();
+        let (succeeded, _cancel_rx) = insert_run(&registry);
+        let (failed, _cancel_rx) = insert_run(&registry);
+        registry.finish(succeeded, Ok(()));
+        registry.finish(failed, Err(report!(OrchestratorError::from("failed"))));
+
+        assert_eq!(registry.list().len(), 2);
+        assert_eq!(registry.get(succeeded).unwrap().state, RunState::Finished);
+        let failed = registry.get(failed).unwrap();
+        assert_eq!(failed.state, RunState::Failed);
+        assert!(failed.error.unwrap().contains("failed"));
+        assert!(matches!(
+            registry.subscribe(succeeded),
+            Subscription::Finished
+        ));
+        assert!(matches!(
+            registry.subscribe(ExperimentId::generate()),
+            Subscription::NotFound
+        ));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let registry = Registry::default();
+        let (id, mut cancel_rx) = insert_run(&registry);
+        assert_eq!(registry.cancel(id), Some(true));
+        assert!(cancel_rx.try_recv().is_ok());
+        assert_eq!(registry.get(id).unwrap().state, RunState::Cancelled);
+        // The error of a cancelled experiment is expected.
+        registry.finish(id, Err(report!(OrchestratorError::from("cancelled"))));
+        assert_eq!(registry.get(id).unwrap().state, RunState::Cancelled);
+        assert_eq!(registry.cancel(id), Some(false));
+        assert_eq!(registry.cancel(ExperimentId::generate()), None);
+    }
+
+    #[test]
+    fn test_cancel_after_shutdown() {
+        let registry = Registry::default();
+        let (id, cancel_rx) = insert_run(&registry);
+        drop(cancel_rx);
+        assert_eq!(registry.cancel(id), Some(false));
+        assert_eq!(registry.get(id).unwrap().state, RunState::Running);
+        registry.finish(id, Ok(()));
+        assert_eq!(registry.get(id).unwrap().state, RunState::Finished);
+    }
+}
@@ -2,11 +2,14 @@
 //!
 //! [`Process`]: crate::process::Process
 
-use std::{path::PathBuf, time::Duration};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use error_stack::{bail, ensure, IntoReport, ResultExt};
-use execution::package::{
-    experiment::ExperimentId, simulation::output::persistence::local::LocalPersistenceConfig,
+use execution::{
+    package::{
+        experiment::ExperimentId, simulation::output::persistence::local::LocalPersistenceConfig,
+    },
+    runner::Language,
 };
 use experiment_control::{
     comms::{EngineMsg, InitMessage},
@@ -14,11 +17,18 @@ use experiment_control::{
     environment::{ExecutionEnvironment, LogFormat, LogLevel, OutputLocation},
 };
 use experiment_structure::ExperimentRun;
+use memory::shared_memory;
 use serde_json::json;
 use simulation_control::{command::StopStatus, EngineStatus};
-use tokio::time::{sleep, timeout};
+use tokio::{
+    sync::{broadcast, oneshot},
+    time::{sleep, timeout},
+};
 
-use crate::{experiment_server::Handler, process, OrchestratorError, Result};
+use crate::{
+    experiment_analysis, experiment_server::Handler, process, python_environment, sensitivity,
+    summary::ExperimentSummary, OrchestratorError, Result,
+};
 
 /// Configuration values used when starting a `hash_engine` subprocess.
 ///
@@ -118,6 +128,42 @@ pub struct ExperimentConfig {
     /// Defaults to V8's `max_heap_size` default.
     #[cfg_attr(feature = "clap", clap(global = true, long))]
     pub js_runner_max_heap_size: Option<usize>,
+
+    /// Don't start the JavaScript runners from a cached V8 startup snapshot, each runner evaluates
+    /// its JavaScript itself instead.
+    #[cfg_attr(feature = "clap", clap(global = true, long))]
+    pub js_runner_disable_snapshot: bool,
+
+    /// Folder the virtual environments for projects with a `requirements.txt` or `pyproject.toml`
+    /// are cached in.
+    ///
+    /// Defaults to `hash_engine/python_environments` in `XDG_CACHE_HOME`, or `python_environments`
+    /// next to the binary if it's not set. The folder may only be accessible by the current user.
+    #[cfg_attr(
+        feature = "clap",
+        clap(global = true, long, env = "HASH_PYTHON_ENVIRONMENTS")
+    )]
+    pub python_environments_folder: Option<PathBuf>,
+
+    /// Directory containing wheels to install the Python requirements of projects from, can be
+    /// passed multiple times.
+    ///
+    /// If provided, packages are only installed from these directories, so virtual environments
+    /// can be created without network access.
+    #[cfg_attr(feature = "clap", clap(global = true, long = "python-wheel-dir"))]
+    pub python_wheel_dirs: Vec<PathBuf>,
+
+    /// Reuse the shared memory of dropped batches for new batches instead of allocating new
+    /// shared-memory segments.
+    ///
+    /// This reduces the allocation overhead for simulations which frequently create or remove
+    /// agents. A segment is only reused once no runner has it loaded anymore. Only supported on
+    /// Linux.
+    #[cfg_attr(
+        feature = "clap",
+        clap(global = true, long, env = "HASH_SHARED_MEMORY_POOL")
+    )]
+    pub shared_memory_pool: bool,
 }
 
 #[cfg(feature = "clap")]
@@ -130,6 +176,19 @@ fn at_least_one(v: &str) -> core::result::Result<(), String> {
     }
 }
 
+/// Number of [`EngineStatus`] messages buffered for slow receivers of an [`ExperimentMonitor`].
+pub(crate) const STATUS_CHANNEL_CAPACITY: usize = 1024;
+
+/// Optional hooks to observe and control an experiment started with
+/// [`Experiment::run_with_monitor()`].
+#[derive(Default)]
+pub struct ExperimentMonitor {
+    /// Every [`EngineStatus`] received from the engine is forwarded to this channel.
+    pub status_tx: Option<broadcast::Sender<Arc<EngineStatus>>>,
+    /// The experiment is cancelled as soon as a message is received on this channel.
+    pub cancel_rx: Option<oneshot::Receiver<()>>,
+}
+
 /// A fully specified and configured experiment
 pub struct Experiment {
     /// Configuration for the experiment.
@@ -148,6 +207,7 @@ impl Experiment {
     /// `controller_url`.
     ///
     /// [`Command`]: crate::process::Command
+    #[allow(clippy::too_many_arguments)]
     fn create_engine_command(
         &self,
         experiment_id: ExperimentId,
@@ -155,6 +215,9 @@ impl Experiment {
         target_max_group_size: Option<usize>,
         js_runner_initial_heap_constraint: Option<usize>,
         js_runner_max_heap_size: Option<usize>,
+        js_runner_disable_snapshot: bool,
+        python_environment: Option<PathBuf>,
+        shared_memory_pool: bool,
     ) -> Box<dyn process::Command + Send> {
         Box::new(process::LocalCommand::new(
             experiment_id,
@@ -167,6 +230,9 @@ impl Experiment {
             target_max_group_size,
             js_runner_initial_heap_constraint,
             js_runner_max_heap_size,
+            js_runner_disable_snapshot,
+            python_environment,
+            shared_memory_pool,
         ))
     }
 
@@ -174,17 +240,61 @@ impl Experiment {
     ///
     /// The `experiment_run` is registered at the server with the provided `handler`, and started
     /// using [`Process`]. After startup it listens to the messages sent from `hash_engine` and
-    /// returns once the experiment has finished.
+    /// returns once the experiment has finished. An [`ExperimentSummary`] is written to the output
+    /// folder of the experiment afterwards.
     ///
     /// [`Process`]: crate::process::Process
-    #[instrument(skip_all, fields(experiment_name = %experiment_run.name(), experiment_id = %experiment_run.id()))]
     pub async fn run(
+        &self,
+        experiment_run: ExperimentRun,
+        handler: Handler,
+        target_max_group_size: Option<usize>,
+    ) -> Result<(), OrchestratorError> {
+        self.run_with_monitor(
+            experiment_run,
+            handler,
+            target_max_group_size,
+            ExperimentMonitor::default(),
+        )
+        .await
+    }
+
+    /// Starts an Engine process and runs the experiment on it as described in [`run()`], while
+    /// reporting to and listening on the provided [`ExperimentMonitor`].
+    ///
+    /// If the experiment is cancelled through the monitor, the engine process is shut down and an
+    /// error is returned.
+    ///
+    /// [`run()`]: Self::run
+    #[instrument(skip_all, fields(experiment_name = %experiment_run.name(), experiment_id = %experiment_run.id()))]
+    pub async fn run_with_monitor(
         &self,
         experiment_run: ExperimentRun,
         mut handler: Handler,
         target_max_group_size: Option<usize>,
+        monitor: ExperimentMonitor,
     ) -> Result<(), OrchestratorError> {
+        let ExperimentMonitor {
+            status_tx,
+            mut cancel_rx,
+        } = monitor;
         let experiment_name = experiment_run.name();
+
+        // Creating an environment may take a while, so it's done before the engine is started.
+        let python_environment = match &experiment_run.simulation().python_requirements {
+            Some(requirements) if experiment_run.requires_runner(Language::Python) => Some(
+                python_environment::prepare_environment(
+                    requirements,
+                    self.config.python_environments_folder.as_deref(),
+                    &self.config.python_wheel_dirs,
+                )
+                .await
+                .attach_printable("Could not prepare the Python environment of the project")?,
+            ),
+            _ => None,
+        };
+
+        let mut summary = ExperimentSummary::new(&experiment_run, &self.config.output_folder);
         let mut engine_handle = handler
             .register_experiment(experiment_run.id())
             .await
@@ -199,6 +309,9 @@ impl Experiment {
             target_max_group_size,
             self.config.js_runner_initial_heap_constraint,
             self.config.js_runner_max_heap_size,
+            self.config.js_runner_disable_snapshot,
+            python_environment,
+            self.config.shared_memory_pool,
         );
         let mut engine_process = cmd
             .run()
@@ -268,9 +381,21 @@ impl Experiment {
         debug!("Sent init message to \"{experiment_name}\"");
 
         let mut graceful_finish = true;
+        let mut cancelled = false;
         loop {
             let msg: Option<EngineStatus>;
             tokio::select! {
+                cancel = async { cancel_rx.as_mut().expect("must be some").await }, if cancel_rx.is_some() => {
+                    if cancel.is_err() {
+                        // The monitor was dropped without requesting a cancellation
+                        cancel_rx = None;
+                        continue;
+                    }
+                    warn!("Cancelling experiment \"{experiment_name}\"");
+                    cancelled = true;
+                    graceful_finish = false;
+                    break;
+                }
                 _ = sleep(Duration::from_secs_f64(self.config.wait_timeout)) => {
                     error!(
                         "Did not receive status from experiment \"{experiment_name}\" for over {}s. \
@@ -291,10 +416,15 @@ impl Experiment {
                 }
                 m = engine_handle.recv() => { msg = Some(m) },
             }
-            let msg = msg.unwrap();
+            let msg = Arc::new(msg.unwrap());
             debug!("Got message from experiment run with type: {}", msg.kind());
+            if let Some(status_tx) = &status_tx {
+                // Sending only fails if there is currently no receiver, which is fine
+                let _ = status_tx.send(Arc::clone(&msg));
+            }
+            summary.record(&msg);
 
-            match msg {
+            match &*msg {
                 EngineStatus::Stopping => {
                     debug!("Stopping experiment \"{experiment_name}\"");
                 }
@@ -303,7 +433,7 @@ impl Experiment {
                 }
                 EngineStatus::SimStatus(status) => {
                     debug!("Got simulation run status: {status:?}");
-                    for stop_command in status.stop_msg {
+                    for stop_command in &status.stop_msg {
                         let reason = if let Some(reason) = stop_command.message.reason.as_ref() {
                             format!(": {reason}")
                         } else {
@@ -329,6 +459,21 @@ impl Experiment {
                             }
                         }
                     }
+                    if let Some(stop_condition) = &status.stop_condition {
+                        let reason = stop_condition.reason.as_deref().unwrap_or_default();
+                        match stop_condition.status {
+                            StopStatus::Success => {
+                                tracing::info!("Simulation stopped by stop condition: {reason}");
+                            }
+                            StopStatus::Warning => {
+                                tracing::warn!("Simulation stopped by stop condition: {reason}");
+                            }
+                            StopStatus::Error => {
+                                graceful_finish = false;
+                                tracing::error!("Simulation stopped by stop condition: {reason}");
+                            }
+                        }
+                    }
                     // TODO: OS - handle more status fields
                 }
                 EngineStatus::SimStop(sim_id) => {
@@ -392,6 +537,23 @@ impl Experiment {
             }
         }
 
+        summary.finish(graceful_finish && !cancelled);
+        match summary.write() {
+            Ok(()) => debug!("Wrote experiment summary to {:?}", summary.path()),
+            Err(err) => error!("Could not write experiment summary: {err:?}"),
+        }
+        match experiment_analysis::write_experiment_analysis(&summary) {
+            Ok(Some(path)) => debug!("Wrote experiment analysis to {path:?}"),
+            Ok(None) => {}
+            Err(err) => error!("Could not write experiment analysis: {err:?}"),
+        }
+        if let Some(design) = experiment_run.sensitivity_design() {
+            match sensitivity::write_sensitivity_indices(design, &summary) {
+                Ok(path) => debug!("Wrote sensitivity indices to {path:?}"),
+                Err(err) => error!("Could not write sensitivity indices: {err:?}"),
+            }
+        }
+
         if !graceful_finish {
             // TODO: Wait for threads to finish before starting a forced cleanup
             warn!("Engine didn't exit gracefully, waiting for subprocesses to finish.");
@@ -402,10 +564,15 @@ impl Experiment {
         // we run this in a separate task because it might panic (in debug builds), and we would
         // still like the debug output from tracing in that case
         let join_handle = tokio::task::spawn(async move {
-            engine_process
+            let result = engine_process
                 .exit_and_cleanup(experiment_run.id())
                 .await
-                .attach_printable("Could not cleanup after finish")
+                .attach_printable("Could not cleanup after finish");
+            if !graceful_finish {
+                // A crashed engine can't clean up its shared memory
+                reap_shared_memory(experiment_run.id());
+            }
+            result
         });
         match join_handle.await {
             Ok(inner) => inner?,
@@ -417,6 +584,10 @@ impl Experiment {
             }
         }
 
+        ensure!(
+            !cancelled,
+            OrchestratorError::from("Experiment was cancelled.")
+        );
         ensure!(
             graceful_finish,
             OrchestratorError::from("Engine didn't exit gracefully.")
@@ -426,4 +597,21 @@ impl Experiment {
     }
 }
 
+/// Removes the shared-memory segments of the experiment `experiment_id`, which are not used by any
+/// process anymore.
+fn reap_shared_memory(experiment_id: ExperimentId) {
+    match shared_memory::reap_orphaned_segments(Some(experiment_id.as_uuid())) {
+        Ok(removed) if removed.is_empty() => {}
+        Ok(removed) => {
+            let size: u64 = removed.iter().map(|segment| segment.size).sum();
+            warn!(
+                "Removed {} leaked shared-memory segments ({size} bytes) of experiment \
+                 {experiment_id}",
+                removed.len()
+            );
+        }
+        Err(err) => warn!("Could not remove leaked shared-memory segments: {err}"),
+    }
+}
+
 // TODO: cleanup section below
@@ -0,0 +1,313 @@
+//! Statistics of the analysis outputs across the simulation runs of an experiment.
+//!
+//! Every simulation writes its own `analysis_outputs.json`. After the experiment has finished, the
+//! numeric outputs of all runs are aggregated per step, once for every group of runs sharing the
+//! same changed globals and once for all runs. The result is written as `experiment_analysis.json`
+//! next to the [`summary`]:
+//!
+//! ```text
+//! <output>/<project>/<experiment name>/<experiment id>/experiment_analysis.json
+//! ```
+//!
+//! [`summary`]: crate::summary
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, ResultExt};
+use execution::package::simulation::{output::analysis::AnalysisSingleOutput, SimulationId};
+use experiment_structure::inverse_normal_cdf;
+use serde::{Deserialize, Serialize};
+
+use crate::{summary::ExperimentSummary, OrchestratorError, Result};
+
+/// Name of the file the aggregated analysis outputs are written to.
+pub const EXPERIMENT_ANALYSIS_FILE_NAME: &str = "experiment_analysis.json";
+
+/// Name of the file the analysis outputs of a simulation are persisted to.
+const ANALYSIS_FILE_NAME: &str = "analysis_outputs.json";
+
+/// The quantiles computed for every step.
+const QUANTILES: [f64; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// The confidence level of the confidence interval of the mean.
+const CONFIDENCE_LEVEL: f64 = 0.95;
+
+/// Numeric analysis outputs of a single simulation run, by output name and step.
+///
+/// Outputs which are not numbers (e.g. vectors) are not included.
+pub(crate) type RunOutputs = HashMap<String, Vec<Option<f64>>>;
+
+/// Reads the numeric analysis outputs of the simulation persisted in `output_path`.
+pub(crate) fn read_analysis_outputs(output_path: &Path) -> Option<RunOutputs> {
+    #[derive(Deserialize)]
+    struct AnalysisFile {
+        buffers: HashMap<String, Vec<AnalysisSingleOutput>>,
+    }
+
+    let path = output_path.join(ANALYSIS_FILE_NAME);
+    let analysis: AnalysisFile = match fs::read_to_string(&path)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|err| err.to_string()))
+    {
+        Ok(analysis) => analysis,
+        Err(err) => {
+            warn!("Could not read analysis outputs from {path:?}: {err}");
+            return None;
+        }
+    };
+    Some(
+        analysis
+            .buffers
+            .into_iter()
+            .filter_map(|(name, steps)| {
+                steps
+                    .into_iter()
+                    .map(|step| match step {
+                        AnalysisSingleOutput::Number(value) => Some(value),
+                        AnalysisSingleOutput::Vec(_) => None,
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .map(|steps| (name, steps))
+            })
+            .collect(),
+    )
+}
+
+/// Statistics of an analysis output at a single step across the runs of a group.
+#[derive(Debug, Clone, Serialize)]
+pub struct StepStatistics {
+    /// Number of runs with a value at this step.
+    pub count: usize,
+    pub mean: Option<f64>,
+    /// Sample standard deviation.
+    pub std: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// The values at [`ExperimentAnalysis::quantiles`], linearly interpolated.
+    pub quantiles: Vec<f64>,
+    /// Confidence interval of the mean at [`ExperimentAnalysis::confidence_level`], based on the
+    /// Student's t-distribution.
+    pub confidence_interval: Option<[f64; 2]>,
+}
+
+impl StepStatistics {
+    fn new(mut values: Vec<f64>) -> Self {
+        values.sort_by(f64::total_cmp);
+        let count = values.len();
+        let n = count as f64;
+        let mean = (count > 0).then(|| values.iter().sum::<f64>() / n);
+        let std = mean.filter(|_| count > 1).map(|mean| {
+            (values
+                .iter()
+                .map(|value| (value - mean).powi(2))
+                .sum::<f64>()
+                / (n - 1.0))
+                .sqrt()
+        });
+        let confidence_interval = mean.zip(std).map(|(mean, std)| {
+            let half_width = t_quantile(0.5 + CONFIDENCE_LEVEL / 2.0, n - 1.0) * std / n.sqrt();
+            [mean - half_width, mean + half_width]
+        });
+        let quantiles = if count > 0 {
+            QUANTILES
+                .iter()
+                .map(|quantile| {
+                    let position = quantile * (n - 1.0);
+                    let lower = position.floor() as usize;
+                    let upper = position.ceil() as usize;
+                    values[lower] + (values[upper] - values[lower]) * (position - lower as f64)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        Self {
+            count,
+            mean,
+            std,
+            min: values.first().copied(),
+            max: values.last().copied(),
+            quantiles,
+            confidence_interval,
+        }
+    }
+}
+
+/// Aggregated outputs of a group of simulation runs.
+#[derive(Debug, Clone, Serialize)]
+pub struct GroupAnalysis {
+    /// The globals changed by the experiment for the runs of the group, `None` for the group of
+    /// all runs.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub changed_globals: Option<serde_json::Value>,
+    pub runs: Vec<SimulationId>,
+    /// Statistics of every numeric analysis output, by output name and step.
+    pub outputs: BTreeMap<String, Vec<StepStatistics>>,
+}
+
+impl GroupAnalysis {
+    fn new(
+        changed_globals: Option<serde_json::Value>,
+        runs: &[(SimulationId, RunOutputs)],
+    ) -> Self {
+        let mut steps: BTreeMap<&str, Vec<Vec<f64>>> = BTreeMap::new();
+        for (_, outputs) in runs {
+            for (name, values) in outputs {
+                let steps = steps.entry(name.as_str()).or_default();
+                if steps.len() < values.len() {
+                    steps.resize(values.len(), Vec::new());
+                }
+                for (step, value) in values.iter().enumerate() {
+                    if let Some(value) = value {
+                        steps[step].push(*value);
+                    }
+                }
+            }
+        }
+        Self {
+            changed_globals,
+            runs: runs.iter().map(|(sim_id, _)| *sim_id).collect(),
+            outputs: steps
+                .into_iter()
+                .map(|(name, steps)| {
+                    let statistics = steps.into_iter().map(StepStatistics::new).collect();
+                    (name.to_string(), statistics)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Content of [`EXPERIMENT_ANALYSIS_FILE_NAME`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentAnalysis {
+    pub quantiles: Vec<f64>,
+    pub confidence_level: f64,
+    /// One group for every distinct set of changed globals, in the order of the first run.
+    pub groups: Vec<GroupAnalysis>,
+    pub all_runs: GroupAnalysis,
+}
+
+/// Aggregates the analysis outputs of the simulations recorded in `summary` and writes them next to
+/// the summary.
+///
+/// Returns `None` if no simulation has persisted analysis outputs.
+///
+/// # Errors
+///
+/// - if the aggregated outputs could not be serialized
+/// - if the aggregated outputs could not be written
+pub fn write_experiment_analysis(summary: &ExperimentSummary) -> Result<Option<PathBuf>> {
+    let mut groups: Vec<(&serde_json::Value, Vec<(SimulationId, RunOutputs)>)> = Vec::new();
+    for simulation in &summary.simulations {
+        let outputs = match simulation
+            .output_path
+            .as_deref()
+            .and_then(read_analysis_outputs)
+        {
+            Some(outputs) if !outputs.is_empty() => outputs,
+            _ => continue,
+        };
+        let run = (simulation.sim_id, outputs);
+        match groups
+            .iter_mut()
+            .find(|(changed_globals, _)| **changed_globals == simulation.changed_globals)
+        {
+            Some((_, runs)) => runs.push(run),
+            None => groups.push((&simulation.changed_globals, vec![run])),
+        }
+    }
+    if groups.is_empty() {
+        return Ok(None);
+    }
+
+    let all_runs: Vec<_> = groups
+        .iter()
+        .flat_map(|(_, runs)| runs.iter().cloned())
+        .collect();
+    let analysis = ExperimentAnalysis {
+        quantiles: QUANTILES.to_vec(),
+        confidence_level: CONFIDENCE_LEVEL,
+        groups: groups
+            .iter()
+            .map(|(changed_globals, runs)| {
+                GroupAnalysis::new(Some((*changed_globals).clone()), runs)
+            })
+            .collect(),
+        all_runs: GroupAnalysis::new(None, &all_runs),
+    };
+
+    let path = summary.path().with_file_name(EXPERIMENT_ANALYSIS_FILE_NAME);
+    let contents = serde_json::to_string_pretty(&analysis)
+        .into_report()
+        .change_context(OrchestratorError::from(
+            "Could not serialize experiment analysis",
+        ))?;
+    fs::write(&path, contents)
+        .into_report()
+        .change_context_lazy(|| {
+            OrchestratorError::from(format!("Could not write experiment analysis to {path:?}"))
+        })?;
+    Ok(Some(path))
+}
+
+/// Returns the `p`-quantile of the Student's t-distribution with `dof` degrees of freedom.
+///
+/// Exact for one and two degrees of freedom, otherwise the Cornish-Fisher expansion from
+/// Abramowitz and Stegun (26.7.5) is used, which is accurate to about `1e-3` for three degrees of
+/// freedom and better for more.
+fn t_quantile(p: f64, dof: f64) -> f64 {
+    if dof <= 1.0 {
+        return (std::f64::consts::PI * (p - 0.5)).tan();
+    }
+    if dof <= 2.0 {
+        return (2.0 * p - 1.0) / (2.0 * p * (1.0 - p)).sqrt();
+    }
+    let z = inverse_normal_cdf(p);
+    let z3 = z.powi(3);
+    let z5 = z.powi(5);
+    let z7 = z.powi(7);
+    let z9 = z.powi(9);
+    let g1 = (z3 + z) / 4.0;
+    let g2 = (5.0 * z5 + 16.0 * z3 + 3.0 * z) / 96.0;
+    let g3 = (3.0 * z7 + 19.0 * z5 + 17.0 * z3 - 15.0 * z) / 384.0;
+    let g4 = (79.0 * z9 + 776.0 * z7 + 1482.0 * z5 - 1920.0 * z3 - 945.0 * z) / 92160.0;
+    z + g1 / dof + g2 / dof.powi(2) + g3 / dof.powi(3) + g4 / dof.powi(4)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_statistics() {
+        let statistics = StepStatistics::new(vec![4.0, 1.0, 3.0, 2.0, 5.0]);
+        assert_eq!(statistics.count, 5);
+        assert_eq!(statistics.mean, Some(3.0));
+        assert!((statistics.std.unwrap() - 2.5_f64.sqrt()).abs() < 1e-12);
+        for (quantile, expected) in statistics.quantiles.iter().zip([1.2, 2.0, 3.0, 4.0, 4.8]) {
+            assert!((quantile - expected).abs() < 1e-12);
+        }
+        // t(0.975, 4) = 2.776
+        let [low, high] = statistics.confidence_interval.unwrap();
+        assert!((high - 3.0 - 2.776 * (0.5_f64).sqrt()).abs() < 1e-2);
+        assert!((3.0 - low - (high - 3.0)).abs() < 1e-12);
+
+        let empty = StepStatistics::new(Vec::new());
+        assert_eq!(empty.mean, None);
+        assert!(empty.quantiles.is_empty());
+    }
+
+    #[test]
+    fn t_quantiles() {
+        assert!((t_quantile(0.975, 1.0) - 12.706).abs() < 1e-3);
+        assert!((t_quantile(0.975, 2.0) - 4.303).abs() < 1e-3);
+        assert!((t_quantile(0.975, 3.0) - 3.182).abs() < 1e-2);
+        assert!((t_quantile(0.975, 10.0) - 2.228).abs() < 1e-3);
+        assert!((t_quantile(0.975, 1000.0) - 1.962).abs() < 1e-3);
+    }
+}
@@ -1,20 +1,31 @@
 //! Library for running an experiment on a `hash_engine` process.
 //!
 //! This crate is used for parsing a project manifest file [`Manifest`] into an experiment
-//! configuration, which then can be run on a `hash_engine` subprocess.
+//! configuration, which then can be run on a `hash_engine` subprocess. Experiments can also be
+//! submitted and monitored through a local HTTP API provided by the [`api`] module, or run in
+//! bulk using the persistent [`queue`]. Every finished experiment writes a machine-readable
+//! [`summary`] into its output folder, together with [statistics of the analysis outputs across
+//! runs](experiment_analysis). `sensitivity` experiments additionally write their [`sensitivity`]
+//! indices.
 //!
 //! [`Manifest`]: experiment_structure::Manifest
 
 #[macro_use]
 extern crate tracing;
 
+pub mod api;
 pub mod error;
 mod experiment;
+pub mod experiment_analysis;
 mod experiment_server;
 pub mod process;
+mod python_environment;
+pub mod queue;
+pub mod sensitivity;
+pub mod summary;
 
 pub use self::{
     error::{OrchestratorError, Result},
-    experiment::{Experiment, ExperimentConfig},
+    experiment::{Experiment, ExperimentConfig, ExperimentMonitor},
     experiment_server::{Handler, Server},
 };
@@ -122,6 +122,9 @@ pub struct LocalCommand {
     target_max_group_size: Option<usize>,
     js_runner_initial_heap_constraint: Option<usize>,
     js_runner_max_heap_size: Option<usize>,
+    js_runner_disable_snapshot: bool,
+    python_environment: Option<PathBuf>,
+    shared_memory_pool: bool,
 }
 
 impl LocalCommand {
@@ -138,6 +141,9 @@ impl LocalCommand {
         target_max_group_size: Option<usize>,
         js_runner_initial_heap_constraint: Option<usize>,
         js_runner_max_heap_size: Option<usize>,
+        js_runner_disable_snapshot: bool,
+        python_environment: Option<PathBuf>,
+        shared_memory_pool: bool,
     ) -> Self {
         // The NNG URL that the engine process will listen on
         let engine_url = format!("ipc://run-{experiment_id}");
@@ -154,6 +160,9 @@ impl LocalCommand {
             target_max_group_size,
             js_runner_initial_heap_constraint,
             js_runner_max_heap_size,
+            js_runner_disable_snapshot,
+            python_environment,
+            shared_memory_pool,
         }
     }
 }
@@ -207,6 +216,15 @@ impl process::Command for LocalCommand {
             cmd.arg("--js-runner-max-heap-size")
                 .arg(js_runner_max_heap_size.to_string());
         }
+        if self.js_runner_disable_snapshot {
+            cmd.arg("--js-runner-disable-snapshot");
+        }
+        if let Some(python_environment) = self.python_environment {
+            cmd.arg("--python-environment").arg(python_environment);
+        }
+        if self.shared_memory_pool {
+            cmd.arg("--shared-memory-pool");
+        }
         debug!("Running `{cmd:?}`");
 
         let child = cmd.spawn().into_report().change_context_lazy(|| {
@@ -0,0 +1,442 @@
+//! Virtual environments for the Python runner of projects with their own Python requirements.
+//!
+//! By default, the Python runner uses `runner_venv` created by `setup.sh`. If a project has a
+//! `requirements.txt` or `pyproject.toml` (see [`PythonRequirements`]), a virtual environment is
+//! created with the requirements of the runner and of the project, where the project's version of a
+//! package takes precedence over the runner's. The Cython extension of the runner is built in the
+//! environment against its packages, so projects can use different versions of e.g. numpy.
+//!
+//! Environments are cached in a directory only accessible by the current user (see
+//! [`cache_directory`]), as the engine runs the Python of an environment it finds there. They are
+//! keyed by a SHA-256 hash of the requirements, the files they reference, the Python version and
+//! the wheel directories, so projects with the same requirements share an environment. Engines
+//! creating the same environment, or building the Cython extension of the runner, at the same time
+//! wait for each other through file locks.
+
+use std::{
+    collections::HashSet,
+    fs, io,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use error_stack::{ensure, IntoReport, ResultExt};
+use execution::runner::cache::{cache_directory, create_private_directory};
+use experiment_structure::PythonRequirements;
+use sha2::{Digest, Sha256};
+use tokio::{process::Command, time::sleep};
+
+use crate::{OrchestratorError, Result};
+
+/// The Python used to create environments.
+const PYTHON: &str = "python3";
+
+/// Directory of the Python runner sources, relative to the working directory.
+const RUNNER_DIR: &str = "./lib/execution/src/runner/python";
+
+/// The requirements of the runner itself.
+#[cfg(not(all(target_os = "macos", target_arch = "aarch64")))]
+const RUNNER_REQUIREMENTS: &str = "requirements.txt";
+#[cfg(all(target_os = "macos", target_arch = "aarch64"))]
+const RUNNER_REQUIREMENTS: &str = "m1-requirements.txt";
+
+/// Directory in an environment containing the Cython extension of the runner, which is imported
+/// instead of the one next to the runner sources (see `main.py`).
+const EXTENSION_DIR: &str = "hash_runner";
+
+/// File created in an environment once it's completely set up.
+const COMPLETE_MARKER: &str = ".complete";
+
+/// Lock file held while building the Cython extension, as Cython writes `wrappers.c` into
+/// [`RUNNER_DIR`], which all environments share.
+const BUILD_LOCK: &str = ".build_ext.lock";
+
+/// Files describing a project installed with `-e`, whose other files are used in place.
+const EDITABLE_PROJECT_FILES: [&str; 3] = ["pyproject.toml", "setup.py", "setup.cfg"];
+
+/// Returns the path of a virtual environment in `environments_folder` with the runner's and the
+/// project's `requirements` installed, creating it if it doesn't exist yet.
+///
+/// Without an `environments_folder`, environments are cached in `python_environments` in the cache
+/// directory of the current user.
+///
+/// If `wheel_dirs` are provided, packages are only installed from these directories instead of the
+/// package index.
+///
+/// # Errors
+///
+/// - if `environments_folder` could not be created or is accessible by other users
+/// - if Python or one of the steps to create the environment failed
+pub(crate) async fn prepare_environment(
+    requirements: &PythonRequirements,
+    environments_folder: Option<&Path>,
+    wheel_dirs: &[PathBuf],
+) -> Result<PathBuf> {
+    let environments_folder = match environments_folder {
+        Some(environments_folder) => create_private_directory(environments_folder)
+            .map(|()| environments_folder.to_path_buf()),
+        None => cache_directory("python_environments"),
+    }
+    .into_report()
+    .change_context(OrchestratorError::from(
+        "Could not create the folder of Python environments",
+    ))?;
+    let environments_folder = canonicalize(&environments_folder)?;
+    let wheel_dirs = wheel_dirs
+        .iter()
+        .map(|wheel_dir| canonicalize(wheel_dir))
+        .collect::<Result<Vec<_>>>()?;
+    let runner_requirements = runner_requirements(requirements)?;
+
+    let python_version = Command::new(PYTHON)
+        .arg("--version")
+        .output()
+        .await
+        .into_report()
+        .change_context_lazy(|| OrchestratorError::from(format!("Could not run {PYTHON}")))?
+        .stdout;
+    let key = environment_key(
+        &python_version,
+        &runner_requirements,
+        requirements,
+        &wheel_dirs,
+    )?;
+    let environment = environments_folder.join(key);
+
+    if environment.join(COMPLETE_MARKER).is_file() {
+        debug!("Using Python environment at {environment:?}");
+        return Ok(environment);
+    }
+    // Other engines may create the same environment at the same time.
+    let _lock = FileLock::acquire(&environment.with_extension("lock")).await?;
+    if environment.join(COMPLETE_MARKER).is_file() {
+        debug!("Using Python environment at {environment:?}");
+        return Ok(environment);
+    }
+
+    create_environment(
+        &environment,
+        requirements,
+        &runner_requirements,
+        &wheel_dirs,
+    )
+    .await?;
+    Ok(environment)
+}
+
+/// An exclusive lock on a file.
+///
+/// The lock is released when it's dropped or the process holding it exits, so an engine which
+/// crashed while holding it doesn't leave a stale lock behind.
+struct FileLock(fs::File);
+
+impl FileLock {
+    /// Locks the file at `path`, creating it if necessary, and waits while another process holds
+    /// the lock.
+    async fn acquire(path: &Path) -> Result<Self> {
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .open(path)
+            .into_report()
+            .change_context_lazy(|| OrchestratorError::from(format!("Could not open {path:?}")))?;
+        let mut waiting = false;
+        loop {
+            // SAFETY: The file descriptor is valid as long as `file` is open.
+            if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+                return Ok(Self(file));
+            }
+            let err = io::Error::last_os_error();
+            if err.kind() != io::ErrorKind::WouldBlock {
+                return Err(err).into_report().change_context_lazy(|| {
+                    OrchestratorError::from(format!("Could not lock {path:?}"))
+                });
+            }
+            if !waiting {
+                info!("Waiting for another process holding the lock on {path:?}");
+                waiting = true;
+            }
+            sleep(Duration::from_secs(1)).await;
+        }
+    }
+}
+
+/// Returns the name of the environment for the given requirements.
+///
+/// The project's path is only part of the key through the paths of the files the requirements
+/// reference, as e.g. projects installed with `-e` are used in place. Otherwise, projects with the
+/// same requirements share an environment.
+fn environment_key(
+    python_version: &[u8],
+    runner_requirements: &str,
+    requirements: &PythonRequirements,
+    wheel_dirs: &[PathBuf],
+) -> Result<String> {
+    // Every part is prefixed with its length, so different inputs can't produce the same bytes.
+    let mut hasher = Sha256::new();
+    let mut update = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_le_bytes());
+        hasher.update(bytes);
+    };
+    update(python_version);
+    update(runner_requirements.as_bytes());
+    update(requirements.requirements_txt.as_bytes());
+    for wheel_dir in wheel_dirs {
+        update(wheel_dir.to_string_lossy().as_bytes());
+    }
+    for path in referenced_files(
+        &requirements.requirements_txt,
+        &requirements.project_path,
+        &requirements.project_path,
+        &mut HashSet::new(),
+    )? {
+        update(path.to_string_lossy().as_bytes());
+        update(
+            &fs::read(&path).into_report().change_context_lazy(|| {
+                OrchestratorError::from(format!("Could not read {path:?}"))
+            })?,
+        );
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Returns the local files pip reads for `requirements_txt`: requirement and constraint files
+/// included with `-r` and `-c`, including the ones they include, wheels, and the files describing
+/// projects installed with `-e`.
+///
+/// Included files are relative to the directory of the including file, all other paths are relative
+/// to the project, where pip is run.
+fn referenced_files(
+    requirements_txt: &str,
+    directory: &Path,
+    project_path: &Path,
+    included: &mut HashSet<PathBuf>,
+) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for line in requirements_txt.lines() {
+        let line = line.split(" #").next().unwrap_or_default().trim();
+        let (option, value) = if line.starts_with('-') {
+            let (option, value) = line
+                .split_once(|c: char| c == '=' || c.is_whitespace())
+                .unwrap_or((line, ""));
+            (Some(option), value.trim())
+        } else {
+            (None, line.split(';').next().unwrap_or_default().trim())
+        };
+        if value.contains("://") {
+            continue;
+        }
+        match option {
+            Some("-r" | "--requirement" | "-c" | "--constraint") => {
+                let path = directory.join(value);
+                if included.insert(path.clone()) {
+                    let contents = fs::read_to_string(&path)
+                        .into_report()
+                        .change_context_lazy(|| {
+                            OrchestratorError::from(format!("Could not read {path:?}"))
+                        })?;
+                    let directory = path.parent().unwrap_or(directory).to_path_buf();
+                    files.push(path);
+                    files.extend(referenced_files(
+                        &contents,
+                        &directory,
+                        project_path,
+                        included,
+                    )?);
+                }
+            }
+            Some("-e" | "--editable") => {
+                let project = project_path.join(value);
+                files.extend(
+                    EDITABLE_PROJECT_FILES
+                        .into_iter()
+                        .map(|file| project.join(file))
+                        .filter(|path| path.is_file()),
+                );
+            }
+            None if value.ends_with(".whl") => files.push(project_path.join(value)),
+            _ => {}
+        }
+    }
+    Ok(files)
+}
+
+fn canonicalize(path: &Path) -> Result<PathBuf> {
+    path.canonicalize()
+        .into_report()
+        .change_context_lazy(|| OrchestratorError::from(format!("Could not find {path:?}")))
+}
+
+/// Returns the requirements of the runner without the packages the project requires itself.
+fn runner_requirements(requirements: &PythonRequirements) -> Result<String> {
+    let path = Path::new(RUNNER_DIR).join(RUNNER_REQUIREMENTS);
+    let runner_requirements = fs::read_to_string(&path)
+        .into_report()
+        .change_context_lazy(|| OrchestratorError::from(format!("Could not read {path:?}")))?;
+
+    let project_packages = requirements.package_names();
+    Ok(runner_requirements
+        .lines()
+        .filter(|line| {
+            PythonRequirements::package_name(line)
+                .map_or(true, |package| !project_packages.contains(&package))
+        })
+        .map(|line| format!("{line}\n"))
+        .collect())
+}
+
+async fn create_environment(
+    environment: &Path,
+    requirements: &PythonRequirements,
+    runner_requirements: &str,
+    wheel_dirs: &[PathBuf],
+) -> Result<()> {
+    info!("Creating Python environment at {environment:?}, this may take a while");
+    // Left over from a failed attempt
+    if environment.exists() {
+        fs::remove_dir_all(environment)
+            .into_report()
+            .change_context_lazy(|| {
+                OrchestratorError::from(format!("Could not remove {environment:?}"))
+            })?;
+    }
+    run(Command::new(PYTHON).arg("-m").arg("venv").arg(environment)).await?;
+    let python = environment.join("bin").join("python");
+
+    let runner_requirements_txt = environment.join("runner-requirements.txt");
+    let project_requirements_txt = environment.join("project-requirements.txt");
+    for (path, contents) in [
+        (&runner_requirements_txt, runner_requirements),
+        (
+            &project_requirements_txt,
+            requirements.requirements_txt.as_str(),
+        ),
+    ] {
+        fs::write(path, contents)
+            .into_report()
+            .change_context_lazy(|| OrchestratorError::from(format!("Could not write {path:?}")))?;
+    }
+
+    let mut pip = Command::new(&python);
+    pip.args(["-m", "pip", "install", "--disable-pip-version-check"]);
+    if !wheel_dirs.is_empty() {
+        pip.arg("--no-index");
+        for wheel_dir in wheel_dirs {
+            pip.arg("--find-links").arg(wheel_dir);
+        }
+    }
+    // Both are installed at once, so pip resolves the project's requirements together with the
+    // runner's. Relative paths in the project's requirements are relative to the project.
+    pip.arg("-r")
+        .arg(&runner_requirements_txt)
+        .arg("-r")
+        .arg(&project_requirements_txt)
+        .current_dir(&requirements.project_path);
+    run(&mut pip)
+        .await
+        .attach_printable("Could not install the Python requirements")?;
+
+    // `setup.py` expects the directory of the runner as third argument.
+    let extension_dir = environment.join(EXTENSION_DIR);
+    let _build_lock = FileLock::acquire(&Path::new(RUNNER_DIR).join(BUILD_LOCK)).await?;
+    run(Command::new(&python)
+        .arg("setup.py")
+        .arg("build_ext")
+        .arg(format!(
+            "--build-temp={}",
+            extension_dir.join("build").display()
+        ))
+        .arg(".")
+        .arg(format!("--build-lib={}", extension_dir.display()))
+        .current_dir(RUNNER_DIR))
+    .await
+    .attach_printable("Could not build the Cython extension of the Python runner")?;
+
+    fs::write(environment.join(COMPLETE_MARKER), "")
+        .into_report()
+        .change_context_lazy(|| {
+            OrchestratorError::from(format!("Could not finish {environment:?}"))
+        })
+}
+
+async fn run(command: &mut Command) -> Result<()> {
+    debug!("Running `{command:?}`");
+    let status = command
+        .status()
+        .await
+        .into_report()
+        .change_context_lazy(|| OrchestratorError::from(format!("Could not run `{command:?}`")))?;
+    ensure!(
+        status.success(),
+        OrchestratorError::from(format!("`{command:?}` failed with {status}"))
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn environment_key_changes_with_referenced_files() {
+        let project_path =
+            std::env::temp_dir().join(format!("python-requirements-{}", std::process::id()));
+        fs::create_dir_all(project_path.join("requirements")).unwrap();
+        fs::write(
+            project_path.join("requirements").join("base.txt"),
+            "-c constraints.txt\nnumpy\n",
+        )
+        .unwrap();
+        fs::write(
+            project_path.join("requirements").join("constraints.txt"),
+            "numpy<1.22\n",
+        )
+        .unwrap();
+        fs::write(project_path.join("model.whl"), "wheel").unwrap();
+        let requirements = PythonRequirements {
+            project_path: project_path.clone(),
+            requirements_txt: "-r requirements/base.txt\nmodel.whl ; python_version > '3.7'\n"
+                .to_owned(),
+        };
+        let key = || environment_key(b"Python 3.9.13", "", &requirements, &[]).unwrap();
+
+        let original = key();
+        assert_eq!(key(), original);
+
+        fs::write(
+            project_path.join("requirements").join("constraints.txt"),
+            "numpy<1.23\n",
+        )
+        .unwrap();
+        let changed_constraints = key();
+        assert_ne!(changed_constraints, original);
+
+        fs::write(project_path.join("model.whl"), "new wheel").unwrap();
+        assert_ne!(key(), changed_constraints);
+
+        let moved = PythonRequirements {
+            project_path: project_path.join("requirements").join(".."),
+            ..requirements.clone()
+        };
+        assert_ne!(
+            environment_key(b"Python 3.9.13", "", &moved, &[]).unwrap(),
+            key()
+        );
+
+        // Requirements without local files don't depend on the project
+        let packages = |project_path: PathBuf| {
+            let requirements = PythonRequirements {
+                project_path,
+                requirements_txt: "numpy==1.23.1\n".to_owned(),
+            };
+            environment_key(b"Python 3.9.13", "", &requirements, &[]).unwrap()
+        };
+        assert_eq!(
+            packages(project_path.clone()),
+            packages(project_path.join("requirements"))
+        );
+
+        fs::remove_dir_all(project_path).unwrap();
+    }
+}
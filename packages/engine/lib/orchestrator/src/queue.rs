@@ -0,0 +1,671 @@
+//! A persistent queue for running many experiments with a limited number of engine processes.
+//!
+//! Experiments are added to an [`ExperimentQueue`] with [`push()`] and started by [`run()`]. The
+//! state of the queue is written to [`QueueConfig::queue_file`] after every change, so an
+//! interrupted queue can be resumed by opening the same file again. Experiments which failed
+//! because the engine crashed, e.g. due to a [`ProcessError`] or errors from a runner, are retried
+//! up to [`QueueConfig::max_retries`] times. Only the simulations which did not finish are run
+//! again, single runs and sensitivity experiments (whose analysis needs all simulations of the
+//! design) are retried as a whole.
+//!
+//! [`push()`]: ExperimentQueue::push
+//! [`run()`]: ExperimentQueue::run
+//! [`ProcessError`]: EngineStatus::ProcessError
+
+use std::{
+    fs,
+    future::Future,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use error_stack::{IntoReport, ResultExt};
+use execution::package::{
+    experiment::{
+        basic::{BasicExperimentConfig, SimpleExperimentConfig},
+        ExperimentPackageConfig,
+    },
+    simulation::SimulationId,
+};
+use experiment_structure::{ExperimentRun, ExperimentType, Manifest};
+use serde::{Deserialize, Serialize};
+use simulation_control::EngineStatus;
+use tokio::sync::{broadcast, mpsc};
+
+use crate::{
+    experiment::STATUS_CHANNEL_CAPACITY,
+    summary::{ExperimentSummary, StopReason},
+    Experiment, ExperimentConfig, ExperimentMonitor, Handler, OrchestratorError, Result,
+};
+
+/// Configuration of an [`ExperimentQueue`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
+pub struct QueueConfig {
+    /// Maximum number of engine processes running at the same time.
+    ///
+    /// Defaults to the number of logical CPUs divided by the number of workers per engine.
+    #[cfg_attr(feature = "clap", clap(long, env = "HASH_MAX_ENGINE_PROCESSES"))]
+    pub max_engine_processes: Option<usize>,
+
+    /// Number of times the unfinished simulations of an experiment are retried if the engine
+    /// crashed.
+    #[cfg_attr(
+        feature = "clap",
+        clap(long, default_value = "0", env = "HASH_MAX_RETRIES")
+    )]
+    pub max_retries: usize,
+
+    /// File the state of the queue is persisted to.
+    ///
+    /// If the file already exists, the queue is resumed from it.
+    #[cfg_attr(
+        feature = "clap",
+        clap(long, default_value = "./queue.json", env = "HASH_QUEUE_FILE")
+    )]
+    pub queue_file: PathBuf,
+}
+
+/// The state of an experiment in the [`ExperimentQueue`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+}
+
+/// An experiment in the [`ExperimentQueue`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedExperiment {
+    /// Path to the project to be run.
+    pub project: PathBuf,
+    /// Experiment type to be run.
+    pub experiment: ExperimentType,
+    pub state: JobState,
+    /// Number of times the experiment was started.
+    pub attempts: usize,
+    /// The errors of all failed attempts.
+    pub errors: Vec<String>,
+    /// The changed globals of the simulations which are left to run after a failed attempt. If
+    /// `None`, all simulations of the experiment are run.
+    #[serde(default)]
+    pub remaining_simulations: Option<Vec<serde_json::Value>>,
+}
+
+/// Result of a single attempt to run an experiment.
+#[derive(Debug)]
+enum Attempt {
+    Finished,
+    Failed {
+        error: String,
+        retryable: bool,
+        /// The changed globals of the simulations which did not finish, `None` if the experiment
+        /// can only be retried as a whole.
+        remaining_simulations: Option<Vec<serde_json::Value>>,
+    },
+}
+
+/// The experiments of a queue, which are persisted to the queue file after every change.
+struct Jobs {
+    jobs: Mutex<Vec<QueuedExperiment>>,
+    queue_file: PathBuf,
+}
+
+impl Jobs {
+    /// Reads the jobs from `queue_file` if it exists. Experiments, which were running when the
+    /// queue was interrupted, are queued again.
+    fn open(queue_file: PathBuf) -> Result<Self> {
+        let mut jobs: Vec<QueuedExperiment> = if queue_file.exists() {
+            let path = &queue_file;
+            let contents = fs::read_to_string(path)
+                .into_report()
+                .change_context_lazy(|| {
+                    OrchestratorError::from(format!("Could not read queue file {path:?}"))
+                })?;
+            serde_json::from_str(&contents)
+                .into_report()
+                .change_context_lazy(|| {
+                    OrchestratorError::from(format!("Could not parse queue file {path:?}"))
+                })?
+        } else {
+            Vec::new()
+        };
+        for job in &mut jobs {
+            if job.state == JobState::Running {
+                job.state = JobState::Queued;
+            }
+        }
+
+        Ok(Self {
+            jobs: Mutex::new(jobs),
+            queue_file,
+        })
+    }
+
+    fn snapshot(&self) -> Vec<QueuedExperiment> {
+        self.jobs.lock().expect("queue lock is poisoned").clone()
+    }
+
+    /// Applies `f` to the list of experiments and persists the queue afterwards.
+    fn update<T>(&self, f: impl FnOnce(&mut Vec<QueuedExperiment>) -> T) -> Result<T> {
+        let mut jobs = self.jobs.lock().expect("queue lock is poisoned");
+        let result = f(&mut jobs);
+
+        let path = &self.queue_file;
+        let contents = serde_json::to_string_pretty(&*jobs)
+            .into_report()
+            .change_context(OrchestratorError::from("Could not serialize queue"))?;
+        // Write to a temporary file first so a crash never leaves a partially written queue
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, contents)
+            .and_then(|_| fs::rename(&tmp_path, path))
+            .into_report()
+            .change_context_lazy(|| {
+                OrchestratorError::from(format!("Could not write queue file {path:?}"))
+            })?;
+
+        Ok(result)
+    }
+
+    /// Marks the first queued experiment as running and returns it with its index.
+    fn start_next(&self) -> Result<Option<(usize, QueuedExperiment)>> {
+        self.update(|jobs| {
+            jobs.iter_mut()
+                .enumerate()
+                .find(|(_, job)| job.state == JobState::Queued)
+                .map(|(index, job)| {
+                    job.state = JobState::Running;
+                    job.attempts += 1;
+                    (index, job.clone())
+                })
+        })
+    }
+
+    /// Records the result of an attempt of the experiment at `index` and queues it again if it
+    /// can be retried.
+    fn complete(&self, index: usize, attempt: Attempt, max_retries: usize) -> Result<()> {
+        self.update(|jobs| {
+            let job = &mut jobs[index];
+            match attempt {
+                Attempt::Finished => {
+                    info!("Queued experiment {index} finished");
+                    job.state = JobState::Finished;
+                    job.remaining_simulations = None;
+                }
+                Attempt::Failed {
+                    error,
+                    retryable,
+                    remaining_simulations,
+                } => {
+                    job.errors.push(error);
+                    let has_remaining = remaining_simulations
+                        .as_ref()
+                        .map_or(true, |remaining| !remaining.is_empty());
+                    if retryable && has_remaining && job.attempts <= max_retries {
+                        warn!("Queued experiment {index} crashed, retrying");
+                        job.state = JobState::Queued;
+                        job.remaining_simulations = remaining_simulations;
+                    } else {
+                        error!("Queued experiment {index} failed");
+                        job.state = JobState::Failed;
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Runs queued experiments while limiting the number of concurrent engine processes.
+pub struct ExperimentQueue {
+    config: ExperimentConfig,
+    queue_config: QueueConfig,
+    handler: Handler,
+    jobs: Jobs,
+}
+
+impl ExperimentQueue {
+    /// Opens the queue stored at [`QueueConfig::queue_file`] or creates an empty one if the file
+    /// does not exist.
+    ///
+    /// Experiments, which were running when the queue was interrupted, are queued again.
+    ///
+    /// # Errors
+    ///
+    /// - if the queue file exists but could not be read
+    pub fn open(
+        config: ExperimentConfig,
+        queue_config: QueueConfig,
+        handler: Handler,
+    ) -> Result<Self> {
+        let jobs = Jobs::open(queue_config.queue_file.clone())?;
+        Ok(Self {
+            config,
+            queue_config,
+            handler,
+            jobs,
+        })
+    }
+
+    /// Returns the maximum number of engine processes running at the same time.
+    pub fn max_engine_processes(&self) -> usize {
+        self.queue_config.max_engine_processes.unwrap_or_else(|| {
+            std::cmp::max(
+                1,
+                num_cpus::get() / std::cmp::max(1, self.config.num_workers),
+            )
+        })
+    }
+
+    /// Returns a snapshot of all experiments in the queue.
+    pub fn jobs(&self) -> Vec<QueuedExperiment> {
+        self.jobs.snapshot()
+    }
+
+    /// Adds an experiment to the end of the queue.
+    ///
+    /// # Errors
+    ///
+    /// - if the queue could not be persisted
+    pub fn push(&self, project: PathBuf, experiment: ExperimentType) -> Result<()> {
+        self.jobs.update(|jobs| {
+            jobs.push(QueuedExperiment {
+                project,
+                experiment,
+                state: JobState::Queued,
+                attempts: 0,
+                errors: Vec::new(),
+                remaining_simulations: None,
+            });
+        })
+    }
+
+    /// Runs all queued experiments and returns once none is left.
+    ///
+    /// Returns the number of experiments which failed.
+    ///
+    /// # Errors
+    ///
+    /// - if the queue could not be persisted
+    pub async fn run(&self) -> Result<usize> {
+        let max_engine_processes = self.max_engine_processes();
+        info!("Running queued experiments with up to {max_engine_processes} engine processes");
+
+        run_jobs(
+            &self.jobs,
+            max_engine_processes,
+            self.queue_config.max_retries,
+            |job| run_attempt(self.config.clone(), self.handler.clone(), job),
+        )
+        .await
+    }
+}
+
+/// Runs the queued `jobs` with `run_attempt` until none is left, with at most
+/// `max_engine_processes` attempts running at the same time.
+///
+/// Returns the number of experiments which failed.
+async fn run_jobs<F, Fut>(
+    jobs: &Jobs,
+    max_engine_processes: usize,
+    max_retries: usize,
+    run_attempt: F,
+) -> Result<usize>
+where
+    F: Fn(QueuedExperiment) -> Fut,
+    Fut: Future<Output = Attempt> + Send + 'static,
+{
+    let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+    let mut running = 0;
+    loop {
+        while running < max_engine_processes {
+            let (index, job) = match jobs.start_next()? {
+                Some(next) => next,
+                None => break,
+            };
+
+            info!(
+                "Starting queued experiment {index} (attempt {}): {:?}",
+                job.attempts, job.experiment
+            );
+            let attempt = run_attempt(job);
+            let done_tx = done_tx.clone();
+            tokio::spawn(async move {
+                let attempt = attempt.await;
+                // The receiver only stops listening if the queue failed
+                let _ = done_tx.send((index, attempt));
+            });
+            running += 1;
+        }
+
+        if running == 0 {
+            break;
+        }
+        let (index, attempt) = done_rx
+            .recv()
+            .await
+            .expect("queue holds a sender to the channel");
+        running -= 1;
+        jobs.complete(index, attempt, max_retries)?;
+    }
+
+    Ok(jobs
+        .snapshot()
+        .iter()
+        .filter(|job| job.state == JobState::Failed)
+        .count())
+}
+
+/// Reads the project of `job` and runs the experiment once, restricted to the
+/// [remaining simulations](QueuedExperiment::remaining_simulations) of a previous attempt.
+///
+/// The attempt is considered retryable if the engine reported a [`ProcessError`] or errors from a
+/// runner, or if it stopped without exiting properly.
+///
+/// [`ProcessError`]: EngineStatus::ProcessError
+async fn run_attempt(config: ExperimentConfig, handler: Handler, job: QueuedExperiment) -> Attempt {
+    // Reading the project accesses the file system, which would block the runtime.
+    let (project, experiment_type) = (job.project, job.experiment);
+    let experiment_run =
+        tokio::task::spawn_blocking(move || read_experiment_run(&project, experiment_type))
+            .await
+            .into_report()
+            .change_context(OrchestratorError::from("Could not read project"))
+            .and_then(|experiment_run| experiment_run);
+    let experiment_run = match experiment_run {
+        Ok(experiment_run) => experiment_run,
+        Err(report) => {
+            error!("{report:?}");
+            return Attempt::Failed {
+                error: format!("{report:#}"),
+                retryable: false,
+                remaining_simulations: None,
+            };
+        }
+    };
+    let experiment_run = match job.remaining_simulations {
+        Some(changed_globals) => restrict_simulations(experiment_run, changed_globals),
+        None => experiment_run,
+    };
+
+    // The summary is only used to find the simulations which did not finish, it's not written.
+    let mut summary = ExperimentSummary::new(&experiment_run, &config.output_folder);
+    let retry_simulations = experiment_run.sensitivity_design().is_none();
+    let (status_tx, mut status_rx) = broadcast::channel(STATUS_CHANNEL_CAPACITY);
+    let crash_observer = tokio::spawn(async move {
+        let mut crashed = false;
+        let mut exited = false;
+        loop {
+            match status_rx.recv().await {
+                Ok(status) => {
+                    summary.record(&status);
+                    match &*status {
+                        EngineStatus::ProcessError(_) | EngineStatus::RunnerErrors(..) => {
+                            crashed = true;
+                        }
+                        EngineStatus::Exit => exited = true,
+                        _ => {}
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => {}
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+        summary.finish(false);
+        (crashed || !exited, summary)
+    });
+
+    let experiment_config = experiment_run.config().clone();
+    let result = Experiment::new(config)
+        .run_with_monitor(experiment_run, handler, None, ExperimentMonitor {
+            status_tx: Some(status_tx),
+            cancel_rx: None,
+        })
+        .await;
+    match result {
+        Ok(()) => Attempt::Finished,
+        Err(report) => {
+            error!("{report:?}");
+            let (retryable, remaining_simulations) = match crash_observer.await {
+                Ok((crashed, summary)) => {
+                    let finished = summary
+                        .simulations
+                        .iter()
+                        .filter(|simulation| {
+                            !matches!(
+                                simulation.stop_reason,
+                                StopReason::Error | StopReason::Unfinished
+                            )
+                        })
+                        .map(|simulation| simulation.sim_id)
+                        .collect::<Vec<_>>();
+                    (
+                        crashed,
+                        retry_simulations
+                            .then(|| remaining_simulations(&experiment_config, &finished))
+                            .flatten(),
+                    )
+                }
+                Err(_) => (true, None),
+            };
+            Attempt::Failed {
+                error: format!("{report:#}"),
+                retryable,
+                remaining_simulations,
+            }
+        }
+    }
+}
+
+fn read_experiment_run(project: &Path, experiment_type: ExperimentType) -> Result<ExperimentRun> {
+    Manifest::from_local(project)
+        .change_context(OrchestratorError::from("Could not read local project"))?
+        .read(experiment_type)
+        .change_context(OrchestratorError::from("Could not read manifest"))
+}
+
+/// Returns the changed globals of the simulations of an experiment with `config`, which are not
+/// `finished`, or `None` if the experiment can only be run as a whole.
+fn remaining_simulations(
+    config: &ExperimentPackageConfig,
+    finished: &[SimulationId],
+) -> Option<Vec<serde_json::Value>> {
+    let config = match config {
+        ExperimentPackageConfig::Basic(BasicExperimentConfig::Simple(config)) => config,
+        _ => return None,
+    };
+    Some(
+        config
+            .changed_globals
+            .iter()
+            .enumerate()
+            // Simulation ids are the one-based index into the changed globals
+            .filter(|(index, _)| !finished.contains(&SimulationId::new(*index as u32 + 1)))
+            .map(|(_, changed_globals)| changed_globals.clone())
+            .collect(),
+    )
+}
+
+/// Replaces the simulations of a `simple` experiment by the simulations with `changed_globals`.
+fn restrict_simulations(
+    experiment_run: ExperimentRun,
+    changed_globals: Vec<serde_json::Value>,
+) -> ExperimentRun {
+    let config = match experiment_run.config() {
+        ExperimentPackageConfig::Basic(BasicExperimentConfig::Simple(config)) => {
+            SimpleExperimentConfig {
+                changed_globals,
+                ..config.clone()
+            }
+        }
+        _ => return experiment_run,
+    };
+    ExperimentRun::new(
+        experiment_run.name().clone(),
+        experiment_run.simulation().clone(),
+        ExperimentPackageConfig::Basic(BasicExperimentConfig::Simple(config)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    use execution::package::experiment::{basic::SingleRunExperimentConfig, ExperimentName};
+    use serde_json::json;
+
+    use super::*;
+
+    /// Opens an empty queue persisted to a file, which is unique for every test.
+    fn open_jobs(name: &str) -> Jobs {
+        let queue_file =
+            std::env::temp_dir().join(format!("hash-queue-{}-{name}.json", std::process::id()));
+        let _ = fs::remove_file(&queue_file);
+        Jobs::open(queue_file).unwrap()
+    }
+
+    fn push_jobs(jobs: &Jobs, count: usize) {
+        jobs.update(|jobs| {
+            for _ in 0..count {
+                jobs.push(QueuedExperiment {
+                    project: PathBuf::from("project"),
+                    experiment: ExperimentType::SingleRun { num_steps: 1 },
+                    state: JobState::Queued,
+                    attempts: 0,
+                    errors: Vec::new(),
+                    remaining_simulations: None,
+                });
+            }
+        })
+        .unwrap();
+    }
+
+    fn failed(retryable: bool, remaining_simulations: Option<Vec<serde_json::Value>>) -> Attempt {
+        Attempt::Failed {
+            error: "crashed".to_owned(),
+            retryable,
+            remaining_simulations,
+        }
+    }
+
+    #[test]
+    fn test_persistence() {
+        let jobs = open_jobs("persistence");
+        push_jobs(&jobs, 2);
+        let (index, job) = jobs.start_next().unwrap().unwrap();
+        assert_eq!(index, 0);
+        assert_eq!(job.state, JobState::Running);
+        assert_eq!(job.attempts, 1);
+
+        // Experiments which were running when the queue was interrupted are queued again.
+        let reopened = Jobs::open(jobs.queue_file.clone()).unwrap().snapshot();
+        assert_eq!(reopened.len(), 2);
+        assert_eq!(reopened[0].state, JobState::Queued);
+        assert_eq!(reopened[0].attempts, 1);
+        assert_eq!(reopened[1].state, JobState::Queued);
+        assert_eq!(reopened[1].attempts, 0);
+
+        fs::remove_file(&jobs.queue_file).unwrap();
+    }
+
+    #[test]
+    fn test_retries() {
+        let jobs = open_jobs("retries");
+        push_jobs(&jobs, 3);
+        let max_retries = 1;
+
+        jobs.start_next().unwrap();
+        jobs.complete(0, failed(true, Some(vec![json!({ "a": 2 })])), max_retries)
+            .unwrap();
+        let job = &jobs.snapshot()[0];
+        assert_eq!(job.state, JobState::Queued);
+        assert_eq!(job.remaining_simulations, Some(vec![json!({ "a": 2 })]));
+
+        let (index, job) = jobs.start_next().unwrap().unwrap();
+        assert_eq!((index, job.attempts), (0, 2));
+        jobs.complete(0, failed(true, None), max_retries).unwrap();
+        let job = &jobs.snapshot()[0];
+        assert_eq!(job.state, JobState::Failed);
+        assert_eq!(job.errors.len(), 2);
+
+        // Errors in the project are not retried.
+        jobs.start_next().unwrap();
+        jobs.complete(1, failed(false, None), max_retries).unwrap();
+        assert_eq!(jobs.snapshot()[1].state, JobState::Failed);
+
+        // Nothing is left to retry if all simulations finished.
+        jobs.start_next().unwrap();
+        jobs.complete(2, failed(true, Some(Vec::new())), max_retries)
+            .unwrap();
+        assert_eq!(jobs.snapshot()[2].state, JobState::Failed);
+
+        fs::remove_file(&jobs.queue_file).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit() {
+        let jobs = open_jobs("concurrency");
+        push_jobs(&jobs, 6);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let attempts = Arc::new(AtomicUsize::new(0));
+
+        let failed_jobs = run_jobs(&jobs, 2, 1, |job| {
+            let running = Arc::clone(&running);
+            let max_running = Arc::clone(&max_running);
+            attempts.fetch_add(1, Ordering::SeqCst);
+            async move {
+                let now_running = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now_running, Ordering::SeqCst);
+                for _ in 0..10 {
+                    tokio::task::yield_now().await;
+                }
+                running.fetch_sub(1, Ordering::SeqCst);
+                // Every experiment crashes once
+                if job.attempts == 1 {
+                    failed(true, None)
+                } else {
+                    Attempt::Finished
+                }
+            }
+        })
+        .await
+        .unwrap();
+
+        assert_eq!(failed_jobs, 0);
+        assert_eq!(attempts.load(Ordering::SeqCst), 12);
+        assert!(max_running.load(Ordering::SeqCst) <= 2);
+        assert!(
+            jobs.snapshot()
+                .iter()
+                .all(|job| job.state == JobState::Finished && job.attempts == 2)
+        );
+
+        fs::remove_file(&jobs.queue_file).unwrap();
+    }
+
+    #[test]
+    fn test_remaining_simulations() {
+        let config =
+            ExperimentPackageConfig::Basic(BasicExperimentConfig::Simple(SimpleExperimentConfig {
+                experiment_name: ExperimentName::from("sweep".to_owned()),
+                changed_globals: vec![json!({ "a": 1 }), json!({ "a": 2 }), json!({ "a": 3 })],
+                num_steps: 10,
+                max_sims_in_parallel: None,
+                engine_globals: serde_json::Map::new(),
+            }));
+        assert_eq!(
+            remaining_simulations(&config, &[SimulationId::new(2)]),
+            Some(vec![json!({ "a": 1 }), json!({ "a": 3 })])
+        );
+
+        let config = ExperimentPackageConfig::Basic(BasicExperimentConfig::SingleRun(
+            SingleRunExperimentConfig { num_steps: 10 },
+        ));
+        assert_eq!(remaining_simulations(&config, &[]), None);
+    }
+}
@@ -0,0 +1,88 @@
+//! Sensitivity indices of a finished `sensitivity` experiment.
+//!
+//! After all simulations of a `sensitivity` experiment have finished, the chosen analysis output is
+//! read from the `analysis_outputs.json` of every simulation at its final step. The computed
+//! indices are written as `sensitivity.json` next to the [`summary`]:
+//!
+//! ```text
+//! <output>/<project>/<experiment name>/<experiment id>/sensitivity.json
+//! ```
+//!
+//! [`summary`]: crate::summary
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use error_stack::{IntoReport, ResultExt};
+use experiment_structure::SensitivityDesign;
+
+use crate::{
+    experiment_analysis::read_analysis_outputs, summary::ExperimentSummary, OrchestratorError,
+    Result,
+};
+
+/// Name of the file the sensitivity indices are written to.
+pub const SENSITIVITY_FILE_NAME: &str = "sensitivity.json";
+
+/// Computes the sensitivity indices of `design` from the simulations recorded in `summary` and
+/// writes them next to the summary.
+///
+/// Simulations without outputs, e.g. because they failed, are left out of the computation.
+///
+/// # Errors
+///
+/// - if the indices could not be serialized
+/// - if the indices could not be written
+pub fn write_sensitivity_indices(
+    design: &SensitivityDesign,
+    summary: &ExperimentSummary,
+) -> Result<PathBuf> {
+    let mut outputs = vec![None; design.num_runs()];
+    for simulation in &summary.simulations {
+        // Simulation ids are the one-based index into the runs of the design
+        let output = (simulation.sim_id.as_u32() as usize)
+            .checked_sub(1)
+            .and_then(|index| outputs.get_mut(index));
+        if let (Some(output), Some(path)) = (output, &simulation.output_path) {
+            *output = final_output(path, design.output());
+        }
+    }
+    let missing = outputs.iter().filter(|output| output.is_none()).count();
+    if missing > 0 {
+        warn!(
+            "Analysis output \"{}\" is missing for {missing} of {} simulation runs",
+            design.output(),
+            outputs.len()
+        );
+    }
+
+    let indices = design.analyze(&outputs);
+    let path = summary.path().with_file_name(SENSITIVITY_FILE_NAME);
+    let contents = serde_json::to_string_pretty(&indices)
+        .into_report()
+        .change_context(OrchestratorError::from(
+            "Could not serialize sensitivity indices",
+        ))?;
+    fs::write(&path, contents)
+        .into_report()
+        .change_context_lazy(|| {
+            OrchestratorError::from(format!("Could not write sensitivity indices to {path:?}"))
+        })?;
+    Ok(path)
+}
+
+/// Returns the value of the analysis output `name` at the final step, if it's a number.
+fn final_output(output_path: &Path, name: &str) -> Option<f64> {
+    let value = read_analysis_outputs(output_path)?
+        .remove(name)
+        .and_then(|steps| steps.last().copied().flatten());
+    if value.is_none() {
+        warn!(
+            "Analysis output \"{name}\" in {output_path:?} is missing or not a number at the \
+             final step"
+        );
+    }
+    value
+}
@@ -0,0 +1,426 @@
+//! Machine-readable summary of a finished experiment.
+//!
+//! While an [`Experiment`] is running, every [`EngineStatus`] is recorded into an
+//! [`ExperimentSummary`], which is written as `summary.json` next to the simulation outputs once
+//! the experiment has finished:
+//!
+//! ```text
+//! <output>/<project>/<experiment name>/<experiment id>/summary.json
+//! ```
+//!
+//! [`Experiment`]: crate::Experiment
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use error_stack::{IntoReport, ResultExt};
+use execution::package::{
+    experiment::{
+        basic::BasicExperimentConfig, ExperimentId, ExperimentName, ExperimentPackageConfig,
+    },
+    simulation::SimulationId,
+};
+use experiment_structure::ExperimentRun;
+use serde::Serialize;
+use simulation_control::{
+    command::{StopCommand, StopMessage},
+    EngineStatus,
+};
+
+use crate::{OrchestratorError, Result};
+
+/// Name of the file the [`ExperimentSummary`] is written to.
+pub const SUMMARY_FILE_NAME: &str = "summary.json";
+
+/// Reason why a simulation stopped.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum StopReason {
+    /// The simulation ran for the configured number of steps.
+    Completed,
+    /// One or more agents sent a `stop` message.
+    StopCommand { commands: Vec<StopCommand> },
+    /// A stop condition declared in the globals was met.
+    StopCondition {
+        #[serde(flatten)]
+        message: StopMessage,
+    },
+    /// The simulation stopped because of an error.
+    Error,
+    /// The engine exited before the simulation finished.
+    Unfinished,
+}
+
+/// Summary of a single simulation run within an experiment.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationSummary {
+    pub sim_id: SimulationId,
+    /// The globals changed by the experiment for this simulation.
+    pub changed_globals: serde_json::Value,
+    /// Number of steps completed.
+    pub steps: usize,
+    pub stop_reason: StopReason,
+    /// Wall time in seconds from starting to stopping the simulation.
+    pub wall_time: f64,
+    pub user_errors: usize,
+    pub user_warnings: usize,
+    pub runner_errors: usize,
+    pub runner_warnings: usize,
+    /// The folder the outputs of the simulation were persisted to.
+    pub output_path: Option<PathBuf>,
+
+    #[serde(skip)]
+    started: Instant,
+    #[serde(skip)]
+    stopped: bool,
+    #[serde(skip)]
+    errored: bool,
+}
+
+/// Summary of an experiment, written as [`SUMMARY_FILE_NAME`] at the end of the experiment.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExperimentSummary {
+    pub experiment_id: ExperimentId,
+    pub experiment_name: ExperimentName,
+    /// `true` if the engine exited gracefully and no simulation stopped with an error.
+    pub success: bool,
+    /// Wall time in seconds of the whole experiment.
+    pub wall_time: f64,
+    pub simulations: Vec<SimulationSummary>,
+
+    #[serde(skip)]
+    config: ExperimentPackageConfig,
+    #[serde(skip)]
+    started: Instant,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl ExperimentSummary {
+    /// Creates an empty summary for `experiment_run`, which will be written into the experiment
+    /// folder inside of `output_folder`.
+    pub fn new(experiment_run: &ExperimentRun, output_folder: &Path) -> Self {
+        let path = output_folder
+            .join(&experiment_run.simulation().name)
+            .join(experiment_run.name().as_str())
+            .join(experiment_run.id().to_string())
+            .join(SUMMARY_FILE_NAME);
+        Self {
+            experiment_id: experiment_run.id(),
+            experiment_name: experiment_run.name().clone(),
+            success: false,
+            wall_time: 0.0,
+            simulations: Vec::new(),
+            config: experiment_run.config().clone(),
+            started: Instant::now(),
+            path,
+        }
+    }
+
+    /// Returns the path the summary is written to.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn simulation(&mut self, sim_id: SimulationId) -> Option<&mut SimulationSummary> {
+        self.simulations
+            .iter_mut()
+            .find(|simulation| simulation.sim_id == sim_id)
+    }
+
+    /// Updates the summary with the information of `status`.
+    pub fn record(&mut self, status: &EngineStatus) {
+        match status {
+            EngineStatus::SimStart { sim_id, .. } => {
+                let changed_globals = changed_globals(&self.config, *sim_id);
+                self.simulations.push(SimulationSummary {
+                    sim_id: *sim_id,
+                    changed_globals,
+                    steps: 0,
+                    stop_reason: StopReason::Unfinished,
+                    wall_time: 0.0,
+                    user_errors: 0,
+                    user_warnings: 0,
+                    runner_errors: 0,
+                    runner_warnings: 0,
+                    output_path: None,
+                    started: Instant::now(),
+                    stopped: false,
+                    errored: false,
+                });
+            }
+            EngineStatus::SimStatus(status) => {
+                if let Some(simulation) = self.simulation(status.sim_id) {
+                    simulation.steps = simulation.steps.max(status.steps_taken.max(0) as usize);
+                    simulation.errored |= status.error.is_some();
+                    if !status.stop_msg.is_empty() {
+                        simulation.stop_reason = StopReason::StopCommand {
+                            commands: status.stop_msg.clone(),
+                        };
+                    }
+                    if let Some(message) = &status.stop_condition {
+                        simulation.stop_reason = StopReason::StopCondition {
+                            message: message.clone(),
+                        };
+                    }
+                    if let Some((_, serde_json::Value::String(path))) = &status.persistence_result {
+                        simulation.output_path = Some(PathBuf::from(path));
+                    }
+                }
+            }
+            EngineStatus::SimStop(sim_id) => {
+                if let Some(simulation) = self.simulation(*sim_id) {
+                    simulation.stopped = true;
+                    simulation.wall_time = simulation.started.elapsed().as_secs_f64();
+                }
+            }
+            EngineStatus::RunnerErrors(sim_id, errors) => {
+                if let Some(simulation) = self.simulation(*sim_id) {
+                    simulation.runner_errors += errors.len();
+                    simulation.errored = true;
+                }
+            }
+            EngineStatus::RunnerWarnings(sim_id, warnings) => {
+                if let Some(simulation) = self.simulation(*sim_id) {
+                    simulation.runner_warnings += warnings.len();
+                }
+            }
+            EngineStatus::UserErrors(sim_id, errors) => {
+                if let Some(simulation) = self.simulation(*sim_id) {
+                    simulation.user_errors += errors.len();
+                }
+            }
+            EngineStatus::UserWarnings(sim_id, warnings) => {
+                if let Some(simulation) = self.simulation(*sim_id) {
+                    simulation.user_warnings += warnings.len();
+                }
+            }
+            EngineStatus::Started
+            | EngineStatus::Exit
+            | EngineStatus::ProcessError(_)
+            | EngineStatus::Stopping
+            | EngineStatus::PackageError(..)
+            | EngineStatus::Logs(..) => {}
+        }
+    }
+
+    /// Finalizes the stop reasons and wall times.
+    ///
+    /// `graceful_finish` is whether the engine exited without errors.
+    pub fn finish(&mut self, graceful_finish: bool) {
+        self.wall_time = self.started.elapsed().as_secs_f64();
+        for simulation in &mut self.simulations {
+            if !simulation.stopped {
+                simulation.wall_time = simulation.started.elapsed().as_secs_f64();
+            }
+            if matches!(
+                simulation.stop_reason,
+                StopReason::StopCommand { .. } | StopReason::StopCondition { .. }
+            ) {
+                continue;
+            }
+            simulation.stop_reason = if simulation.errored {
+                StopReason::Error
+            } else if simulation.stopped {
+                StopReason::Completed
+            } else {
+                StopReason::Unfinished
+            };
+        }
+        self.success = graceful_finish
+            && self
+                .simulations
+                .iter()
+                .all(|simulation| !matches!(simulation.stop_reason, StopReason::Error));
+    }
+
+    /// Writes the summary to [`path()`].
+    ///
+    /// # Errors
+    ///
+    /// - if the experiment folder could not be created
+    /// - if the summary could not be written
+    ///
+    /// [`path()`]: Self::path
+    pub fn write(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)
+                .into_report()
+                .change_context_lazy(|| {
+                    OrchestratorError::from(format!("Could not create directory {parent:?}"))
+                })?;
+        }
+        let contents = serde_json::to_string_pretty(self)
+            .into_report()
+            .change_context(OrchestratorError::from("Could not serialize summary"))?;
+        fs::write(&self.path, contents)
+            .into_report()
+            .change_context_lazy(|| {
+                OrchestratorError::from(format!("Could not write summary to {:?}", self.path))
+            })
+    }
+}
+
+/// Returns the globals changed by the experiment for the simulation `sim_id`.
+fn changed_globals(config: &ExperimentPackageConfig, sim_id: SimulationId) -> serde_json::Value {
+    match config {
+        ExperimentPackageConfig::Basic(BasicExperimentConfig::Simple(config)) => {
+            // Simulation ids are the one-based index into the changed globals
+            (sim_id.as_u32() as usize)
+                .checked_sub(1)
+                .and_then(|index| config.changed_globals.get(index))
+                .cloned()
+                .unwrap_or(serde_json::Value::Null)
+        }
+        ExperimentPackageConfig::Basic(BasicExperimentConfig::SingleRun(_)) => {
+            serde_json::Value::Object(serde_json::Map::new())
+        }
+        ExperimentPackageConfig::Extended(_) => serde_json::Value::Null,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use execution::{
+        package::{
+            experiment::basic::{SimpleExperimentConfig, SingleRunExperimentConfig},
+            simulation::{
+                init::{InitialState, InitialStateName},
+                PackageInitConfig,
+            },
+        },
+        runner::RunnerError,
+    };
+    use experiment_structure::SimulationSource;
+    use serde_json::json;
+    use simulation_control::SimStatus;
+    use stateful::global::Globals;
+
+    use super::*;
+
+    fn experiment_run(config: BasicExperimentConfig) -> ExperimentRun {
+        let simulation = SimulationSource {
+            name: "project".to_owned(),
+            globals_src: "{}".to_owned(),
+            experiments_src: None,
+            datasets: Vec::new(),
+            node_modules: None,
+            python_requirements: None,
+            package_init: PackageInitConfig {
+                initial_state: InitialState {
+                    name: InitialStateName::InitJson,
+                    src: "[]".to_owned(),
+                },
+                behaviors: Vec::new(),
+                packages: Vec::new(),
+            },
+        };
+        ExperimentRun::new(
+            ExperimentName::from("experiment".to_owned()),
+            simulation,
+            ExperimentPackageConfig::Basic(config),
+        )
+    }
+
+    fn sim_status(sim_id: u32, steps_taken: isize) -> EngineStatus {
+        EngineStatus::SimStatus(SimStatus {
+            sim_id: SimulationId::new(sim_id),
+            steps_taken,
+            early_stop: false,
+            stop_msg: Vec::new(),
+            stop_condition: None,
+            stop_signal: false,
+            persistence_result: None,
+            error: None,
+            warnings: Vec::new(),
+            running: true,
+        })
+    }
+
+    fn start(summary: &mut ExperimentSummary, sim_id: u32) {
+        summary.record(&EngineStatus::SimStart {
+            sim_id: SimulationId::new(sim_id),
+            globals: Globals::default(),
+        });
+    }
+
+    #[test]
+    fn test_failed_experiment() {
+        let experiment_run =
+            experiment_run(BasicExperimentConfig::Simple(SimpleExperimentConfig {
+                experiment_name: ExperimentName::from("sweep".to_owned()),
+                changed_globals: vec![json!({ "a": 1 }), json!({ "a": 2 }), json!({ "a": 3 })],
+                num_steps: 10,
+                max_sims_in_parallel: None,
+                engine_globals: serde_json::Map::new(),
+            }));
+        let mut summary = ExperimentSummary::new(&experiment_run, Path::new("output"));
+        assert_eq!(
+            summary.path(),
+            Path::new("output/project/experiment")
+                .join(experiment_run.id().to_string())
+                .join(SUMMARY_FILE_NAME)
+        );
+
+        for sim_id in 1..=3 {
+            start(&mut summary, sim_id);
+        }
+        summary.record(&sim_status(1, 10));
+        summary.record(&EngineStatus::SimStop(SimulationId::new(1)));
+        summary.record(&sim_status(2, 4));
+        summary.record(&EngineStatus::RunnerErrors(SimulationId::new(2), vec![
+            RunnerError::default(),
+        ]));
+        summary.record(&EngineStatus::SimStop(SimulationId::new(2)));
+        // The engine crashed before the third simulation finished
+        summary.record(&sim_status(3, 7));
+        summary.finish(false);
+
+        let summary = serde_json::to_value(&summary).unwrap();
+        assert_eq!(summary["success"], json!(false));
+        let simulations = summary["simulations"].as_array().unwrap();
+        let field = |name: &str| {
+            simulations
+                .iter()
+                .map(|simulation| simulation[name].clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(field("changed_globals"), [
+            json!({ "a": 1 }),
+            json!({ "a": 2 }),
+            json!({ "a": 3 })
+        ]);
+        assert_eq!(field("steps"), [json!(10), json!(4), json!(7)]);
+        assert_eq!(field("runner_errors"), [json!(0), json!(1), json!(0)]);
+        assert_eq!(
+            simulations
+                .iter()
+                .map(|simulation| simulation["stop_reason"]["kind"].clone())
+                .collect::<Vec<_>>(),
+            [json!("completed"), json!("error"), json!("unfinished")]
+        );
+    }
+
+    #[test]
+    fn test_successful_experiment() {
+        let experiment_run = experiment_run(BasicExperimentConfig::SingleRun(
+            SingleRunExperimentConfig { num_steps: 5 },
+        ));
+        let mut summary = ExperimentSummary::new(&experiment_run, Path::new("output"));
+        start(&mut summary, 1);
+        summary.record(&sim_status(1, 5));
+        summary.record(&EngineStatus::SimStop(SimulationId::new(1)));
+        summary.finish(true);
+
+        assert!(summary.success);
+        assert_eq!(summary.simulations.len(), 1);
+        assert_eq!(summary.simulations[0].changed_globals, json!({}));
+        assert!(matches!(
+            summary.simulations[0].stop_reason,
+            StopReason::Completed
+        ));
+    }
+}
@@ -14,6 +14,8 @@ pub struct BaseBatch {
     /// Current Worker index
     worker: WorkerIndex,
     remove_indices: Vec<AgentIndex>,
+    /// Execution time per agent of the last behavior execution in seconds, if it was measured
+    cost_per_agent: Option<f64>,
 }
 
 /// Represents a batch of agents from the dynamic pool
@@ -54,10 +56,15 @@ impl PendingBatch {
                 Ok(())
             })?;
         let remove_indices_len = remove_indices.len();
+        let cost_per_agent = agent_batch
+            .execution_time
+            .filter(|_| agent_batch.num_agents() > 0)
+            .map(|time| time.as_secs_f64() / agent_batch.num_agents() as f64);
         let old_batch = BaseBatch {
             index: batch_index,
             worker: agent_batch.worker_index,
             remove_indices,
+            cost_per_agent,
         };
 
         Ok(PendingBatch {
@@ -99,6 +106,12 @@ impl PendingBatch {
         self.base.as_ref().unwrap().worker
     }
 
+    /// Returns the measured execution time per agent in seconds, `None` if the batch is new or
+    /// hasn't been executed yet.
+    pub fn cost_per_agent(&self) -> Option<f64> {
+        self.base.as_ref().and_then(|b| b.cost_per_agent)
+    }
+
     pub fn old_batch_index_unchecked(&self) -> usize {
         self.base.as_ref().unwrap().index
     }
@@ -1,6 +1,11 @@
-use crate::command::{
-    create_remove::{batch::PendingBatch, WorkerIndex},
-    Error, Result,
+use std::mem;
+
+use crate::{
+    command::{
+        create_remove::{batch::PendingBatch, WorkerIndex},
+        Error, Result,
+    },
+    load_balancing::LoadBalancing,
 };
 
 /// Represents the distribution of agents per worker.
@@ -86,7 +91,10 @@ impl BatchDistribution {
                 } else {
                     0
                 };
-                if average_total_num_agents_per_batch > self.target_max_group_size {
+                // A worker without batches needs a new batch for its inbound agents
+                if (batches.is_empty() && number_inbound > 0)
+                    || average_total_num_agents_per_batch > self.target_max_group_size
+                {
                     // Create more pending batches, as inbound count is large
                     let target_number_batches = ((total_num_agents as f64)
                         / (self.target_max_group_size as f64))
@@ -115,6 +123,53 @@ impl BatchDistribution {
         get_inbound_distribution(&current_distribution, number_inbound)
     }
 
+    /// Migrates batches between workers to balance their estimated load.
+    ///
+    /// The cost of a batch is estimated from its measured execution time per agent and the number
+    /// of agents it will contain. Batches without a measurement are assumed to be as expensive per
+    /// agent as the measured batches on average.
+    pub(crate) fn rebalance(&mut self, load_balancing: &LoadBalancing) {
+        let num_workers = self.inner.len();
+        let batches: Vec<(WorkerIndex, PendingBatch)> = mem::take(&mut self.inner)
+            .into_iter()
+            .enumerate()
+            .flat_map(|(worker_index, batches)| {
+                batches.into_iter().map(move |batch| (worker_index, batch))
+            })
+            .collect();
+
+        let (measured_cost, measured_agents) = batches
+            .iter()
+            .filter_map(|(_, batch)| {
+                batch
+                    .cost_per_agent()
+                    .map(|cost| (cost * batch.num_agents() as f64, batch.num_agents()))
+            })
+            .fold((0.0, 0), |(sum_cost, sum_agents), (cost, agents)| {
+                (sum_cost + cost, sum_agents + agents)
+            });
+        let mean_cost_per_agent = if measured_agents > 0 {
+            measured_cost / measured_agents as f64
+        } else {
+            0.0
+        };
+        let costs: Vec<(WorkerIndex, f64)> = batches
+            .iter()
+            .map(|(worker_index, batch)| {
+                let cost_per_agent = batch.cost_per_agent().unwrap_or(mean_cost_per_agent);
+                (*worker_index, cost_per_agent * batch.num_agents() as f64)
+            })
+            .collect();
+
+        self.inner = vec![vec![]; num_workers];
+        for ((_, batch), worker_index) in batches
+            .into_iter()
+            .zip(load_balancing.assign(&costs, num_workers))
+        {
+            self.inner[worker_index].push(batch);
+        }
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = (WorkerIndex, &PendingBatch)> {
         self.inner
             .iter()
@@ -1,10 +1,20 @@
+use std::collections::BTreeMap;
+
 use experiment_structure::SimulationRunConfig;
 use memory::shared_memory::MemoryId;
 use rayon::prelude::*;
-use stateful::{proxy::BatchPool, state::StateBatchPools};
+use stateful::{
+    agent::{AgentBatch, AgentSchema},
+    proxy::BatchPool,
+    state::{StateBatchPools, StateReadProxy},
+};
 
 use crate::command::{
-    create_remove::action::{CreateActions, ExistingGroupBufferActions},
+    create_remove::{
+        action::{CreateActions, ExistingGroupBufferActions},
+        migration::{BufferActions, CopyAction, RangeActions, RemoveAction},
+        BatchIndex,
+    },
     Error, Result,
 };
 
@@ -17,6 +27,84 @@ pub struct MigrationPlan<'a> {
 }
 
 impl<'a> MigrationPlan<'a> {
+    /// Moves agents between the existing batches, so the agents in `order` fill the batches one
+    /// after another.
+    ///
+    /// `order` is a permutation of all agents of `state_proxy` given as `(batch, agent)` indices.
+    /// Every batch keeps its number of agents and its worker, agents which stay in their batch are
+    /// not moved.
+    pub(crate) fn reorder(
+        state_proxy: &StateReadProxy,
+        order: &[(BatchIndex, usize)],
+        schema: &AgentSchema,
+    ) -> Result<MigrationPlan<'a>> {
+        let agent_batches: Vec<&AgentBatch> = state_proxy.agent_pool().batches_iter().collect();
+        let num_agents: usize = agent_batches.iter().map(|batch| batch.num_agents()).sum();
+        if order.len() != num_agents {
+            return Err(Error::from(format!(
+                "Expected an order of {num_agents} agents, got {}",
+                order.len()
+            )));
+        }
+
+        let mut start = 0;
+        let existing_mutations = agent_batches
+            .iter()
+            .enumerate()
+            .map(|(batch_index, batch)| {
+                let end = start + batch.num_agents();
+                let mut keep = vec![false; batch.num_agents()];
+                let mut copy: BTreeMap<BatchIndex, Vec<CopyAction>> = BTreeMap::new();
+                for &(source_index, agent_index) in &order[start..end] {
+                    if source_index == batch_index {
+                        keep[agent_index] = true;
+                    } else {
+                        copy.entry(source_index)
+                            .or_default()
+                            .push(CopyAction { val: agent_index });
+                    }
+                }
+                start = end;
+
+                if copy.is_empty() {
+                    return Ok(ExistingGroupBufferActions::Persist {
+                        worker_index: batch.worker_index,
+                    });
+                }
+                let remove: Vec<RemoveAction> = keep
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, keep)| !**keep)
+                    .map(|(val, _)| RemoveAction { val })
+                    .collect();
+                let copy: Vec<(BatchIndex, Vec<CopyAction>)> = copy
+                    .into_iter()
+                    .map(|(source_index, mut actions)| {
+                        actions.sort();
+                        (source_index, actions)
+                    })
+                    .collect();
+                let actions = BufferActions::from(
+                    &agent_batches,
+                    Some(batch_index),
+                    RangeActions::from_actions(&remove, &copy, &[]),
+                    &schema.static_meta,
+                    None,
+                )?;
+                Ok(ExistingGroupBufferActions::Update {
+                    actions,
+                    worker_index: batch.worker_index,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(MigrationPlan {
+            existing_mutations,
+            create_commands: Vec::new(),
+            num_agents_after_execution: num_agents,
+        })
+    }
+
     pub fn execute(
         self,
         state: &mut StateBatchPools,
@@ -101,3 +189,58 @@ impl<'a> MigrationPlan<'a> {
         Ok(removed_ids)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use stateful::state::{State, StateCreateParameters};
+
+    use super::*;
+    use crate::{
+        partition::read_agents,
+        tests::test_utils::{dummy_sim_run_config, gen_schema_and_test_agents},
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn reorder_agents_between_batches() -> Result<()> {
+        let config = dummy_sim_run_config();
+        let (agent_schema, agents) = gen_schema_and_test_agents(6, 0)?;
+        let mut state = State::from_agent_states(&agents, StateCreateParameters {
+            target_min_groups: 2,
+            agent_schema: Arc::clone(&agent_schema),
+            ..config.to_state_create_parameters()
+        })?;
+        assert_eq!(state.agent_pool().len(), 2);
+
+        let seeds = |state: &State| -> Result<Vec<serde_json::Value>> {
+            Ok(read_agents(state, &agent_schema)
+                .map_err(|err| Error::from(err.to_string()))?
+                .into_iter()
+                .map(|agent| agent.custom["seed"].clone())
+                .collect())
+        };
+
+        // The identity doesn't move any agent
+        let identity = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2)];
+        let plan = MigrationPlan::reorder(&state.read()?, &identity, &agent_schema)?;
+        assert!(
+            plan.existing_mutations
+                .iter()
+                .all(|action| matches!(action, ExistingGroupBufferActions::Persist { .. }))
+        );
+
+        let swapped = [(1, 2), (1, 1), (1, 0), (0, 2), (0, 1), (0, 0)];
+        let plan = MigrationPlan::reorder(&state.read()?, &swapped, &agent_schema)?;
+        plan.execute(state.state_mut(), &config)?;
+        assert_eq!(state.agent_pool().len(), 2);
+        assert_eq!(
+            seeds(&state)?,
+            [3.0, 4.0, 5.0, 0.0, 1.0, 2.0].map(serde_json::Value::from)
+        );
+
+        assert!(MigrationPlan::reorder(&state.read()?, &swapped[1..], &agent_schema).is_err());
+        Ok(())
+    }
+}
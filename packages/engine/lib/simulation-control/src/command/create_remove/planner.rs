@@ -9,16 +9,19 @@ use stateful::{
     state::StateReadProxy,
 };
 
-use crate::command::{
-    create_remove::{
-        action::{CreateActions, ExistingGroupBufferActions},
-        batch::PendingBatch,
-        command::ProcessedCommands,
-        distribution::BatchDistribution,
-        migration::{BufferActions, IndexRange, RangeActions},
-        MigrationPlan,
+use crate::{
+    command::{
+        create_remove::{
+            action::{CreateActions, ExistingGroupBufferActions},
+            batch::PendingBatch,
+            command::ProcessedCommands,
+            distribution::BatchDistribution,
+            migration::{BufferActions, IndexRange, RangeActions},
+            MigrationPlan,
+        },
+        CreateRemoveCommands, Result,
     },
-    CreateRemoveCommands, Result,
+    load_balancing::LoadBalancing,
 };
 
 pub struct CreateRemovePlanner {
@@ -40,11 +43,20 @@ impl CreateRemovePlanner {
         })
     }
 
-    pub fn run(&mut self, state_proxy: &StateReadProxy) -> Result<MigrationPlan> {
+    /// Plans the creation and removal of agents, and migrates batches between workers if
+    /// `load_balancing` is set.
+    pub(crate) fn run(
+        &mut self,
+        state_proxy: &StateReadProxy,
+        load_balancing: Option<&LoadBalancing>,
+    ) -> Result<MigrationPlan> {
         let mut pending = self.pending_plan(state_proxy.agent_pool())?;
 
         let number_inbound = self.commands.get_number_inbound();
         pending.distribute_inbound(number_inbound)?;
+        if let Some(load_balancing) = load_balancing {
+            pending.distribution.rebalance(load_balancing);
+        }
         pending.complete(state_proxy, self.commands.new_agents.as_ref(), &self.config)
     }
 
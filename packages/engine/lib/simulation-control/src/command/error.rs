@@ -50,6 +50,12 @@ pub enum Error {
     )]
     CreateAgentPayload(serde_json::error::Error, String),
 
+    #[error(
+        "Error parsing `set_globals` message payload, expected an object with the field \
+         \"globals\" and optionally \"step\", got error: {0:?}. Payload was: {1:?}"
+    )]
+    SetGlobalsPayload(serde_json::error::Error, String),
+
     #[error(
         "`create_agent` message has field \"{0}\" without respective field existing\nDetails: \
          {1:?}"
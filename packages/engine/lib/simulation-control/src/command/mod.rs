@@ -3,6 +3,7 @@
 //! * Dynamically request the creation of agents
 //! * Dynamically request the deletion of agents
 //! * Dynamically request stopping of the simulation run
+//! * Dynamically request changing globals between steps
 
 mod create_remove;
 mod error;
@@ -12,6 +13,7 @@ use std::{
     sync::Arc,
 };
 
+use execution::{package::simulation::init::AgentSchemaDeclaration, runner::comms::UserError};
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use stateful::{
@@ -26,14 +28,17 @@ pub use self::{
     create_remove::{CreateRemoveCommands, CreateRemovePlanner, MigrationPlan},
     error::{Error, Result},
 };
-use crate::command::create_remove::{CreateCommand, ProcessedCommands, RemoveCommand};
+use crate::{
+    command::create_remove::{CreateCommand, ProcessedCommands, RemoveCommand},
+    partition::SpatialPartitioning,
+};
 
 /// Variations of the protected message-target that is associated with the engine. If an agent
 /// sends a message to one of these variations, it's interpreted as a command rather than a message
 /// to be forwarded to another agent.
 ///
 /// This array also forms the list of the **only** acceptable variants, e.g. `hAsH` is not allowed
-static HASH: [&str; 3] = ["hash", "Hash", "HASH"];
+static HASH: [&str; 3] = message::SYSTEM_MESSAGE_VARIANTS;
 
 /// The commands available to simulation agents
 enum HashMessageType {
@@ -43,6 +48,8 @@ enum HashMessageType {
     Remove,
     /// Stop the simulation
     Stop,
+    /// Change globals
+    SetGlobals,
 }
 
 /// Status of the stop message that occurred.
@@ -50,7 +57,7 @@ enum HashMessageType {
 /// See the [HASH-documentation] for more information.
 ///
 /// [HASH-documentation]: https://hash.ai/docs/simulation/creating-simulations/agent-messages/built-in-message-handlers#Stopping-a-simulation
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum StopStatus {
     Success,
@@ -67,7 +74,7 @@ impl Default for StopStatus {
 /// Command to stop the simulation.
 ///
 /// Stores the [`StopMessage`] and the agent's UUID.
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StopCommand {
     pub message: StopMessage,
     pub agent: AgentId,
@@ -78,17 +85,30 @@ pub struct StopCommand {
 /// See the [HASH-documentation] for more information.
 ///
 /// [HASH-documentation]: https://hash.ai/docs/simulation/creating-simulations/agent-messages/built-in-message-handlers#Stopping-a-simulation
-#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct StopMessage {
     pub status: StopStatus,
     pub reason: Option<String>,
 }
 
+/// Command to change globals between steps.
+///
+/// Stores the globals to set, the step at which they are set, and the agent's UUID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SetGlobalsCommand {
+    pub globals: serde_json::Map<String, serde_json::Value>,
+    /// The step at which the globals are set, `None` for the next step.
+    pub step: Option<usize>,
+    /// `None` if the command was scheduled by an intervention of the experiment.
+    pub agent: Option<AgentId>,
+}
+
 /// Commands queued by agents
 #[derive(Debug, Default)]
 pub struct Commands {
     pub create_remove: CreateRemoveCommands,
     pub stop: Vec<StopCommand>,
+    pub set_globals: Vec<SetGlobalsCommand>,
 }
 
 impl Commands {
@@ -102,6 +122,21 @@ impl Commands {
         self.create_remove.remove.push(RemoveCommand { agent_id });
     }
 
+    /// Returns the agents queued for creation.
+    #[cfg(test)]
+    pub(crate) fn created_agents(&self) -> impl Iterator<Item = &Agent> {
+        self.create_remove.create.iter().map(|create| &create.agent)
+    }
+
+    /// Returns the ids of the agents queued for deletion.
+    #[cfg(test)]
+    pub(crate) fn removed_agent_ids(&self) -> impl Iterator<Item = AgentId> + '_ {
+        self.create_remove
+            .remove
+            .iter()
+            .map(|remove| remove.agent_id)
+    }
+
     /// Ensures that all agent-creation commands contain valid agent fields.
     ///
     /// Returns an error if a creation command is for an agent that has a field that hasn't been
@@ -122,6 +157,30 @@ impl Commands {
         Ok(())
     }
 
+    /// Sets the default values and checks the fields of the agents to be created against the
+    /// fields declared in the project's `schema.json`.
+    pub fn validate_created_agents(
+        &mut self,
+        declaration: &AgentSchemaDeclaration,
+    ) -> Vec<UserError> {
+        declaration.validate(
+            self.create_remove
+                .create
+                .iter_mut()
+                .map(|create| &mut create.agent),
+        )
+    }
+
+    /// Orders the agents to be created along the space-filling curve of `partitioning`.
+    ///
+    /// The planner assigns consecutive agents to the same batch, so batches of created agents
+    /// roughly correspond to spatial regions.
+    pub(crate) fn partition_created_agents(&mut self, partitioning: &SpatialPartitioning) {
+        partitioning.sort_by_position(&mut self.create_remove.create, |create| {
+            create.agent.position.as_ref()
+        });
+    }
+
     pub fn merge(&mut self, mut other: Commands) {
         self.create_remove
             .create
@@ -130,6 +189,7 @@ impl Commands {
             .remove
             .append(&mut other.create_remove.remove);
         self.stop.append(&mut other.stop);
+        self.set_globals.append(&mut other.set_globals);
     }
 
     /// Reads the messages of a simulation step, and identifies, transforms, and collects the
@@ -159,6 +219,7 @@ impl Commands {
                             // TODO: When implementing "mapbox" don't forget to update module docs.
                             "mapbox" => todo!(),
                             message::payload::StopSim::KIND => Ok(HashMessageType::Stop),
+                            message::payload::SetGlobals::KIND => Ok(HashMessageType::SetGlobals),
                             _ => Err(Error::UnexpectedSystemMessage {
                                 message_type: type_str.into(),
                             }),
@@ -247,6 +308,15 @@ fn handle_hash_message(
                 agent: AgentId::from_bytes(*from),
             });
         }
+        HashMessageType::SetGlobals => {
+            let payload: message::payload::SetGlobalsData = serde_json::from_str(data)
+                .map_err(|e| Error::SetGlobalsPayload(e, data.to_string()))?;
+            cmds.set_globals.push(SetGlobalsCommand {
+                globals: payload.globals,
+                step: payload.step,
+                agent: Some(AgentId::from_bytes(*from)),
+            });
+        }
     }
     Ok(())
 }
@@ -33,6 +33,7 @@ use stateful::{
     agent::{Agent, AgentId},
     context::Context,
     field::PackageId,
+    global::Globals,
     state::StateReadProxy,
 };
 
@@ -129,12 +130,16 @@ impl Comms {
         Ok(())
     }
 
-    /// TODO: DOC
+    /// Sends a message to workers (via the worker pool) that tells them to load the context batch
+    /// of `current_step`.
+    ///
+    /// If `globals` is provided, the workers replace the globals of the simulation run as well.
     pub async fn context_batch_sync(
         &self,
         context: &Context,
         current_step: usize,
         state_group_start_indices: Arc<Vec<usize>>,
+        globals: Option<Arc<Globals>>,
     ) -> Result<()> {
         tracing::trace!("Synchronizing context batch");
         // Synchronize the context batch
@@ -142,6 +147,8 @@ impl Comms {
             context_batch: Arc::clone(context.global_batch()),
             current_step,
             state_group_start_indices,
+            globals,
+            raster_batch: context.raster_batch().cloned(),
         };
         self.worker_pool_sender
             .send(EngineToWorkerPoolMsg::sync(
@@ -4,9 +4,9 @@ use arrow2::chunk::Chunk;
 use execution::{
     package::simulation::{
         context::ContextPackage,
-        init::InitPackage,
+        init::{AgentSchemaDeclaration, InitPackage},
         output::{Output, OutputPackage},
-        state::StatePackage,
+        state::{raster, StatePackage},
         PackageType,
     },
     runner::comms::PackageMsgs,
@@ -25,6 +25,7 @@ use tracing::{Instrument, Span};
 use crate::{
     comms::Comms,
     error::{Error, Result},
+    partition::SpatialPartitioning,
 };
 
 /// Represents the packages of a simulation engine.
@@ -185,7 +186,12 @@ impl Packages {
         ))
     }
 
-    pub async fn run_init(&mut self, sim_config: Arc<SimulationRunConfig>) -> Result<State> {
+    pub async fn run_init(
+        &mut self,
+        sim_config: Arc<SimulationRunConfig>,
+        schema_declaration: &AgentSchemaDeclaration,
+        partitioning: Option<&SpatialPartitioning>,
+    ) -> Result<State> {
         // Execute packages in parallel and collect the data
         let mut futs = FuturesOrdered::new();
 
@@ -217,6 +223,15 @@ impl Packages {
             agents.append(&mut new_agents?);
         }
 
+        let errors = schema_declaration.validate(&mut agents);
+        if !errors.is_empty() {
+            return Err(Error::InvalidAgentState(errors));
+        }
+
+        if let Some(partitioning) = partitioning {
+            partitioning.sort_by_position(&mut agents, |agent| agent.position.as_ref());
+        }
+
         tracing::trace!("Init packages finished, building state");
         let state = State::from_agent_states(&agents, sim_config.to_state_create_parameters())?;
         Ok(state)
@@ -279,18 +294,26 @@ impl Packages {
             })
             .collect::<Result<Vec<_>>>()?;
 
+        let base_id = sim_run_config
+            .experiment_config()
+            .experiment_run
+            .id()
+            .as_uuid();
         let context = Context::from_columns(
             Chunk::new(columns),
             &sim_run_config.simulation_config().schema.context_schema,
-            MemoryId::new(
-                sim_run_config
-                    .experiment_config()
-                    .experiment_run
-                    .id()
-                    .as_uuid(),
-            ),
+            MemoryId::new(base_id),
         )?;
-        Ok(context)
+
+        // Raster layers are populated with their initial values before the first step.
+        let raster_batch = raster::create_raster_batch(
+            &sim_run_config.simulation_config().package_creator.globals,
+            MemoryId::new(base_id),
+        )?;
+        Ok(match raster_batch {
+            Some(raster_batch) => context.with_raster_batch(raster_batch),
+            None => context,
+        })
     }
 
     pub async fn run_context(
@@ -45,6 +45,7 @@ enum LoopControl {
 ///   - Runs [State Packages][state] sequentially
 ///   - Runs [Output packages][output]
 /// - Persists Output
+/// - Stops if an agent sent a `stop` command or a stop condition is met
 /// - Sends an update on the Step result to the Experiment Controller
 ///
 /// [init]: execution::package::simulation::init
@@ -66,9 +67,42 @@ pub async fn sim_run<P: SimulationOutputPersistence>(
     let max_num_steps = config.simulation_config().max_num_steps;
     tracing::info!(steps = &max_num_steps, "Beginning simulation run");
 
-    let mut engine = Engine::new(packages, comms, config.clone())
-        .await
-        .map_err(|sim_err| Error::from(sim_err.to_string()))?;
+    let mut engine = match Engine::new(packages, comms, config.clone()).await {
+        Ok(engine) => engine,
+        Err(error) => {
+            if let crate::Error::InvalidAgentState(_) = &error {
+                // The initial state doesn't match the declared agent schema, report it as an error
+                // of the simulation run rather than an internal error
+                let runner_error = RunnerError {
+                    message: Some(error.to_string()),
+                    code: None,
+                    line_number: None,
+                    file_name: Some("schema.json".to_string()),
+                    details: None,
+                    is_warning: false,
+                    is_internal: false,
+                };
+                sims_to_exp
+                    .send(
+                        SimStatus::error(
+                            sim_run_id,
+                            0,
+                            runner_error,
+                            None::<P::OutputPersistenceResult>,
+                        )
+                        .map_err(|err| Error::from(format!("{:?}", err)))?,
+                    )
+                    .await
+                    .map_err(|exp_controller_err| {
+                        Error::from(format!(
+                            "Experiment controller error: {:?}",
+                            exp_controller_err
+                        ))
+                    })?;
+            }
+            return Err(Error::from(error.to_string()));
+        }
+    };
 
     tracing::trace!("Initialized the engine, running output packages to persist initial state");
     // We also store the initial state in the persistence service
@@ -76,14 +110,16 @@ pub async fn sim_run<P: SimulationOutputPersistence>(
         .run_output_packages()
         .await
         .map_err(|e| Error::from(e.to_string()))?;
+    // The initial state may already meet a stop condition, e.g. if it doesn't contain any agents
+    let mut stop_condition = engine.evaluate_stop_conditions(&initial_output);
     persistence_service.add_step_output(initial_output).await?;
     let now = std::time::Instant::now();
     let mut steps_taken = 0;
-    let mut early_stop = false;
+    let mut early_stop = stop_condition.is_some();
     let mut stop_msg = Vec::new();
 
     tracing::trace!("Starting main loop");
-    'sim_main: loop {
+    'sim_main: while !early_stop {
         // Behaviors expect context.step() to give the current step rather than steps_taken
         let current_step = steps_taken + 1;
         tracing::trace!("Current step: {}", current_step);
@@ -107,14 +143,21 @@ pub async fn sim_run<P: SimulationOutputPersistence>(
                         .finalize(&config.simulation_config().package_creator.globals)
                         .await?,
                 );
+                let is_internal = !matches!(error, crate::Error::InvalidAgentState(_));
                 let runner_error = RunnerError {
-                    message: Some(format!("{:?}", error)),
+                    message: Some(if is_internal {
+                        format!("{:?}", error)
+                    } else {
+                        error.to_string()
+                    }),
                     code: None,
                     line_number: None,
                     file_name: None,
                     details: None,
                     is_warning: false,
-                    is_internal: true, // The error is from within the engine step process.
+                    // Unless created agents don't match the declared agent schema, the error is
+                    // from within the engine step process.
+                    is_internal,
                 };
                 sims_to_exp
                     .send(
@@ -149,6 +192,14 @@ pub async fn sim_run<P: SimulationOutputPersistence>(
             // the previous step.
             break 'sim_main;
         }
+        if let Some(message) = step_result.stop_condition {
+            // Unlike stop messages, stop conditions are evaluated on the outputs of this step, so
+            // the step is complete.
+            early_stop = true;
+            stop_condition = Some(message);
+            steps_taken += 1;
+            break 'sim_main;
+        }
 
         // TODO: should the SimStatus be current_step here or steps_taken (it is after .next())
         sims_to_exp
@@ -179,6 +230,7 @@ pub async fn sim_run<P: SimulationOutputPersistence>(
                 steps_taken as isize,
                 early_stop,
                 stop_msg,
+                stop_condition,
                 persistence_result,
             )
             .map_err(|sim_err| Error::from(format!("Simulation error: {:?}", sim_err)))?,
@@ -1,12 +1,16 @@
 use std::{mem, sync::Arc};
 
-use execution::package::simulation::output::Output;
+use execution::package::simulation::{
+    init::AgentSchemaDeclaration,
+    output::{GlobalsChange, Output},
+};
 use experiment_structure::SimulationRunConfig;
 use memory::shared_memory::MemoryId;
 use stateful::{
     agent::AgentBatchPool,
     context::Context,
-    message::{MessageBatchPool, MessageMap},
+    global::Globals,
+    message::{DelayedMessages, MessageBatchPool, MessageMap},
     proxy::BatchPool,
     state::{State, StateBatchPools, StateSnapshot},
 };
@@ -14,10 +18,16 @@ use tracing::Instrument;
 
 use crate::{
     agent_control::AgentControl,
-    command::{Commands, CreateRemovePlanner, StopCommand},
+    command::{
+        Commands, CreateRemovePlanner, MigrationPlan, SetGlobalsCommand, StopCommand, StopMessage,
+    },
     comms::Comms,
     controller::Packages,
+    intervention::Interventions,
+    load_balancing::LoadBalancing,
+    partition::SpatialPartitioning,
     step_result::SimulationStepResult,
+    stop_condition::StopConditions,
     Error, Result,
 };
 
@@ -27,25 +37,64 @@ pub struct Engine {
     store: Option<(State, Context)>,
     comms: Arc<Comms>,
     config: Arc<SimulationRunConfig>,
+    schema_declaration: AgentSchemaDeclaration,
     stop_messages: Vec<StopCommand>,
+    stop_conditions: StopConditions,
+    interventions: Interventions,
+    partitioning: Option<SpatialPartitioning>,
+    load_balancing: Option<LoadBalancing>,
+    /// Messages sent with a `delay`, which are not delivered yet.
+    delayed_messages: DelayedMessages,
+    /// The current globals, which may have been changed by `"set_globals"` messages.
+    globals: Arc<Globals>,
+    /// `"set_globals"` messages scheduled for a later step.
+    scheduled_globals: Vec<SetGlobalsCommand>,
+    /// The globals changed at the beginning of the current step.
+    globals_change: Option<GlobalsChange>,
 }
 
 impl Engine {
     /// Creates a new simulation engine from a given collection of Packages, an uninitialized
     /// store, a configuration for the simulation run, and a set of Comms to communicate with the
     /// Worker Pool.
-    /// - Initializes Agent State through the init packages
+    /// - Initializes Agent State through the init packages and checks it against the fields
+    ///   declared in the project's `schema.json`
     /// - Creates an empty Context
     /// - Initializes the Store using the Agent State and empty Context
+    /// - Reads the stop conditions, interventions, partitioning, and load balancing declared in the
+    ///   globals
     pub async fn new(
         mut packages: Packages,
         comms: Comms,
         config: Arc<SimulationRunConfig>,
     ) -> Result<Engine> {
         let comms = Arc::new(comms);
+        let schema_declaration = AgentSchemaDeclaration::new(
+            &config
+                .experiment_config()
+                .experiment_run
+                .simulation()
+                .package_init,
+        )?;
+        let globals = Arc::new(config.simulation_config().package_creator.globals.clone());
+        let stop_conditions = StopConditions::from_globals(&globals)?;
+        let interventions = Interventions::from_globals(
+            &globals,
+            &config
+                .experiment_config()
+                .experiment_run
+                .simulation()
+                .datasets,
+        )?;
+        let partitioning = SpatialPartitioning::from_globals(&globals)?;
+        let load_balancing = LoadBalancing::from_globals(&globals)?;
 
         let state = packages
-            .run_init(Arc::clone(&config.clone()))
+            .run_init(
+                Arc::clone(&config.clone()),
+                &schema_declaration,
+                partitioning.as_ref(),
+            )
             .instrument(tracing::info_span!("init_packages"))
             .await?;
         tracing::trace!("Init packages completed, building empty context");
@@ -56,7 +105,16 @@ impl Engine {
             store: Some((state, context)),
             comms,
             config,
+            schema_declaration,
             stop_messages: Vec::new(),
+            stop_conditions,
+            interventions,
+            partitioning,
+            load_balancing,
+            delayed_messages: DelayedMessages::new(),
+            globals,
+            scheduled_globals: Vec::new(),
+            globals_change: None,
         })
     }
 
@@ -68,6 +126,9 @@ impl Engine {
     /// 2) Run all State packages sequentially \[write State, read Context\]
     /// 3) Calculate all of the outputs of the step with the Output packages
     ///    \[read State, read Context\]
+    /// 4) Evaluate the stop conditions on the outputs
+    ///
+    /// If globals were changed at the beginning of the step, the change is appended to the outputs.
     ///
     /// However running modules in an arbitrary order is possible and
     /// is a possible future extension. Also, while we do require that
@@ -81,7 +142,7 @@ impl Engine {
         self.run_state_packages()
             .instrument(tracing::info_span!("state_packages"))
             .await?;
-        let output = self
+        let mut output = self
             .run_output_packages()
             .instrument(tracing::info_span!("output_packages"))
             .await?;
@@ -90,16 +151,33 @@ impl Engine {
         } else {
             AgentControl::Continue
         };
+        let stop_condition = self.evaluate_stop_conditions(&output);
+        if let Some(globals_change) = self.globals_change.take() {
+            output.push(Output::GlobalsChange(globals_change));
+        }
         let result = SimulationStepResult {
             sim_id: self.config.simulation_config().id,
             output,
             errors: vec![],
             warnings: vec![],
             agent_control,
+            stop_condition,
         };
         Ok(result)
     }
 
+    /// Evaluates the stop conditions on the `output` of the current state.
+    ///
+    /// This is done by [`next()`](Self::next) for every step, and has to be called for the
+    /// output of the initial state, which is calculated before the first step.
+    pub fn evaluate_stop_conditions(&mut self, output: &[Output]) -> Option<StopMessage> {
+        let (state, _) = self
+            .store
+            .as_ref()
+            .expect("state and context should be present");
+        self.stop_conditions.evaluate(output, state.num_agents())
+    }
+
     /// TODO: DOC, the "see" is wrong
     /// Finalize state (see [`Engine::finalize_agent_state`]) and create a new context for the
     /// agents.
@@ -125,7 +203,7 @@ impl Engine {
 
         let snapshot = {
             let _span = tracing::debug_span!("prepare_context_packages").entered();
-            self.prepare_for_context_packages(&mut state, &mut context)?
+            self.prepare_for_context_packages(&mut state, &mut context, current_step)?
         };
 
         let snapshot_state_proxy = snapshot.state.read()?;
@@ -176,6 +254,9 @@ impl Engine {
                 &context,
                 current_step,
                 Arc::clone(state.group_start_indices()),
+                self.globals_change
+                    .as_ref()
+                    .map(|_| Arc::clone(&self.globals)),
             )
             .instrument(tracing::info_span!("context_sync"))
             .await?;
@@ -235,25 +316,32 @@ impl Engine {
     /// The following operations are performed:
     /// 1) A message map Recipient -> Vec<MessageReference>
     /// 2) Handling agent messages to "hash", i.e. performing
-    /// agent creation and removals.
+    /// agent creation and removals and changing globals.
     ///
     /// 3) Replacing the inbox dataframe with the outbox dataframe.
     /// This is done as context packages can take references to the previous outbox.
     /// Note that agents who have been removed will still have their messages sent out.
     /// Also, a new, empty, outbox dataframe is created.
     ///
-    /// 4) A static dataframe is created by copying the state (dynamic) dataframe.
+    /// 4) Messages sent with a `delay` are held back, and the delayed messages due at
+    /// `current_step` are appended to the inbox dataframe as an additional batch.
+    ///
+    /// 5) A static dataframe is created by copying the state (dynamic) dataframe.
     /// This is done as context packages can take references to previous state.
     /// One example of this happening is the Neighbors Context Package.
     fn prepare_for_context_packages(
         &mut self,
         state: &mut State,
         context: &mut Context,
+        current_step: usize,
     ) -> Result<StateSnapshot> {
         tracing::trace!("Preparing for context packages");
-        let message_map = state.message_map()?;
-        self.handle_messages(state, &message_map)?;
-        let message_pool = self.finalize_agent_messages(state, context)?;
+        let mut message_map = state.message_map()?;
+        self.delayed_messages
+            .collect(&state.message_pool().read_proxies()?, current_step)?;
+        self.handle_messages(state, &message_map, current_step)?;
+        let mut message_pool = self.finalize_agent_messages(state, context)?;
+        self.deliver_delayed_messages(&mut message_pool, &mut message_map, current_step)?;
         let agent_pool = self.finalize_agent_state(state, context)?;
         let mut state_view = StateBatchPools {
             agent_pool,
@@ -268,10 +356,20 @@ impl Engine {
 
     /// Handles messages from the agents
     ///
-    /// Operates based on the "create_agent", "remove_agent", and "stop" messages sent to "hash"
-    /// through agent inboxes. Also creates and removes agents that have been requested by State
-    /// packages.
-    fn handle_messages(&mut self, state: &mut State, message_map: &MessageMap) -> Result<()> {
+    /// Operates based on the "create_agent", "remove_agent", "stop", and "set_globals" messages
+    /// sent to "hash" through agent inboxes. Also creates and removes agents that have been
+    /// requested by State packages, and applies the interventions scheduled for `current_step`.
+    ///
+    /// If a spatial partitioning is configured, created agents are ordered by their position, and
+    /// all agents are moved between the batches at the steps the partitioning is re-applied, see
+    /// [`MigrationPlan::reorder()`]. If load balancing is
+    /// configured, batches are migrated between workers based on their last execution times.
+    fn handle_messages(
+        &mut self,
+        state: &mut State,
+        message_map: &MessageMap,
+        current_step: usize,
+    ) -> Result<()> {
         let mut commands = {
             // it is necessary to drop `message_proxies` after reading the commands because it
             // contains a strong reference to the `MessageBatch`; if this strong
@@ -282,18 +380,65 @@ impl Engine {
             Commands::from_hash_messages(message_map, &message_proxies)?
         };
         commands.merge(self.comms.take_commands()?);
+        self.interventions.apply(
+            current_step,
+            state,
+            &self.config.simulation_config().schema.agent_schema,
+            &mut commands,
+        )?;
         commands.verify(&self.config.simulation_config().schema.agent_schema)?;
+        let errors = commands.validate_created_agents(&self.schema_declaration);
+        if !errors.is_empty() {
+            return Err(Error::InvalidAgentState(errors));
+        }
+        if let Some(partitioning) = &self.partitioning {
+            commands.partition_created_agents(partitioning);
+        }
         self.stop_messages = commands.stop;
+        self.scheduled_globals.append(&mut commands.set_globals);
+        self.apply_scheduled_globals(current_step)?;
 
         let mut planner =
             CreateRemovePlanner::new(commands.create_remove, Arc::clone(&self.config))?;
-        let plan = planner.run(&state.read()?)?;
+        let plan = planner.run(&state.read()?, self.load_balancing.as_ref())?;
         state.set_num_agents(plan.num_agents_after_execution);
         let removed_ids = plan.execute(state.state_mut(), &self.config)?;
 
         // Register all batches that were removed
         state.removed_batches().extend(removed_ids.into_iter());
 
+        if let Some(partitioning) = &self.partitioning {
+            if partitioning.repartitions_at(current_step) {
+                let state_proxy = state.read()?;
+                let order = partitioning.order_agents(&state_proxy)?;
+                let plan = MigrationPlan::reorder(
+                    &state_proxy,
+                    &order,
+                    &self.config.simulation_config().schema.agent_schema,
+                )?;
+                drop(state_proxy);
+                plan.execute(state.state_mut(), &self.config)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Applies all scheduled globals which are due at `current_step`, see [`apply_due_globals()`].
+    ///
+    /// Only the globals sent to the language runners are updated, configuration read by the engine
+    /// when the simulation run starts (e.g. the topology or the stop conditions) is not affected.
+    fn apply_scheduled_globals(&mut self, current_step: usize) -> Result<()> {
+        if let Some((globals, change)) =
+            apply_due_globals(&self.globals, &mut self.scheduled_globals, current_step)?
+        {
+            tracing::debug!(
+                "Changed globals at step {current_step}: {:?}",
+                change.globals
+            );
+            self.globals = Arc::new(globals);
+            self.globals_change = Some(change);
+        }
         Ok(())
     }
 
@@ -304,11 +449,45 @@ impl Engine {
         state: &mut State,
         context: &mut Context,
     ) -> Result<MessageBatchPool> {
-        let message_pool = context.take_message_pool();
+        let mut message_pool = context.take_message_pool();
+        self.delayed_messages.reclaim(&mut message_pool)?;
         let finalized_message_pool = state.reset_messages(message_pool)?;
         Ok(finalized_message_pool)
     }
 
+    /// Appends the delayed messages due at `current_step` to the inbox dataframe and adds them to
+    /// the `message_map`.
+    ///
+    /// The additional batch isn't backed by an agent batch, so it's taken back before the inbox
+    /// dataframe is reset in the next step and reused for the next delayed messages.
+    fn deliver_delayed_messages(
+        &mut self,
+        message_pool: &mut MessageBatchPool,
+        message_map: &mut MessageMap,
+        current_step: usize,
+    ) -> Result<()> {
+        let batch = self.delayed_messages.take_due(
+            current_step,
+            &self.config.simulation_config().schema.message_schema,
+            MemoryId::new(
+                self.config
+                    .experiment_config()
+                    .experiment_run
+                    .id()
+                    .as_uuid(),
+            ),
+        )?;
+        if let Some(batch) = batch {
+            tracing::debug!(
+                "Delivering delayed messages at step {current_step}, {} messages are pending",
+                self.delayed_messages.len()
+            );
+            message_map.add_batch(message_pool.len(), &batch)?;
+            message_pool.push(batch);
+        }
+        Ok(())
+    }
+
     /// Update the old static dataframe with the new updated dynamic
     /// dataframe.
     fn finalize_agent_state(
@@ -330,3 +509,105 @@ impl Engine {
         Ok(context.take_agent_pool())
     }
 }
+
+/// Removes the commands due at `current_step` from `scheduled` and applies them to a copy of
+/// `globals`.
+///
+/// The changes are merged in the order the messages were received and applied at once, so workers
+/// never observe a partially updated state. Commands without a step are due immediately, i.e. at
+/// the step after they were sent. Returns the new globals and the change, if any command was due.
+fn apply_due_globals(
+    globals: &Globals,
+    scheduled: &mut Vec<SetGlobalsCommand>,
+    current_step: usize,
+) -> Result<Option<(Globals, GlobalsChange)>> {
+    let (due, pending) = mem::take(scheduled)
+        .into_iter()
+        .partition::<Vec<_>, _>(|command| command.step.map_or(true, |step| step <= current_step));
+    *scheduled = pending;
+    if due.is_empty() {
+        return Ok(None);
+    }
+
+    let mut changed = serde_json::Map::new();
+    for command in due {
+        changed.extend(command.globals);
+    }
+    let mut globals = globals.clone();
+    match &mut globals.0 {
+        serde_json::Value::Object(globals) => {
+            globals.extend(changed.clone());
+        }
+        _ => return Err(Error::from("Globals must be a JSON object to be changed")),
+    }
+    Ok(Some((globals, GlobalsChange {
+        step: current_step,
+        globals: changed,
+    })))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn set_globals(globals: serde_json::Value, step: Option<usize>) -> SetGlobalsCommand {
+        SetGlobalsCommand {
+            globals: serde_json::from_value(globals).unwrap(),
+            step,
+            agent: None,
+        }
+    }
+
+    #[test]
+    fn globals_change_at_next_step() {
+        let globals = Globals(json!({ "a": 1, "b": 1 }));
+        let mut scheduled = vec![
+            set_globals(json!({ "a": 2 }), None),
+            set_globals(json!({ "a": 3, "c": 3 }), None),
+        ];
+        let (globals, change) = apply_due_globals(&globals, &mut scheduled, 4)
+            .unwrap()
+            .unwrap();
+        assert!(scheduled.is_empty());
+        // Later messages win, other globals are kept
+        assert_eq!(globals.0, json!({ "a": 3, "b": 1, "c": 3 }));
+        assert_eq!(change.step, 4);
+        assert_eq!(
+            serde_json::Value::Object(change.globals),
+            json!({ "a": 3, "c": 3 })
+        );
+
+        assert!(
+            apply_due_globals(&globals, &mut scheduled, 5)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn globals_change_at_future_step() {
+        let globals = Globals(json!({ "a": 1 }));
+        let mut scheduled = vec![set_globals(json!({ "a": 2 }), Some(10))];
+        assert!(
+            apply_due_globals(&globals, &mut scheduled, 9)
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(scheduled.len(), 1);
+
+        let (globals, change) = apply_due_globals(&globals, &mut scheduled, 10)
+            .unwrap()
+            .unwrap();
+        assert!(scheduled.is_empty());
+        assert_eq!(globals.0, json!({ "a": 2 }));
+        assert_eq!(change.step, 10);
+    }
+
+    #[test]
+    fn globals_change_requires_object() {
+        let mut scheduled = vec![set_globals(json!({ "a": 2 }), None)];
+        assert!(apply_due_globals(&Globals(json!(null)), &mut scheduled, 1).is_err());
+    }
+}
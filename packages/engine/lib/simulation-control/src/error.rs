@@ -1,3 +1,4 @@
+use execution::runner::comms::UserError;
 use thiserror::Error as ThisError;
 use tokio::sync::mpsc::error::SendError;
 
@@ -31,6 +32,17 @@ pub enum Error {
 
     #[error("State sync failed: {0}")]
     StateSync(String),
+
+    #[error("Invalid agent state:\n{}", display_user_errors(.0))]
+    InvalidAgentState(Vec<UserError>),
+}
+
+fn display_user_errors(errors: &[UserError]) -> String {
+    errors
+        .iter()
+        .map(UserError::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 impl Error {
@@ -0,0 +1,292 @@
+//! Interventions scheduled by an experiment.
+//!
+//! Interventions are declared as a list in the `interventions` global, usually by an experiment in
+//! `experiments.json`, so intervention scenarios can be compared like any other experiment. They
+//! are applied by the [`Engine`] at the beginning of their step, together with the messages sent
+//! to `hash`:
+//!
+//! ```json
+//! {
+//!   "interventions": [
+//!     { "step": 50, "type": "set-globals", "globals": { "lockdown": true } },
+//!     { "step": 60, "type": "create-agents", "dataset": "vaccinated.json" },
+//!     { "step": 70, "type": "remove-agents", "filters": [{ "field": "age", "op": "gt", "value": 80 }] }
+//!   ]
+//! }
+//! ```
+//!
+//! [`Engine`]: crate::engine::Engine
+
+use execution::package::simulation::output::json_state::AgentFilter;
+use serde::Deserialize;
+use stateful::{
+    agent::{Agent, AgentSchema},
+    global::{Dataset, Globals},
+    state::State,
+};
+
+use crate::{
+    command::{Commands, SetGlobalsCommand},
+    partition::read_agents,
+    Error, Result,
+};
+
+/// Name of the global declaring the interventions.
+pub const INTERVENTIONS_GLOBAL: &str = "interventions";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Action {
+    /// Replaces the listed globals and keeps all others.
+    SetGlobals {
+        globals: serde_json::Map<String, serde_json::Value>,
+    },
+    /// Creates the agents listed in a JSON dataset of the project.
+    CreateAgents { dataset: String },
+    /// Removes all agents matching all filters.
+    RemoveAgents { filters: Vec<AgentFilter> },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct InterventionConfig {
+    step: usize,
+    #[serde(flatten)]
+    action: Action,
+}
+
+#[derive(Debug)]
+enum Intervention {
+    SetGlobals(serde_json::Map<String, serde_json::Value>),
+    /// The agents are deserialized when the intervention is applied, so agents without an
+    /// `agent_id` get a new one.
+    CreateAgents(Vec<serde_json::Value>),
+    RemoveAgents(Vec<AgentFilter>),
+}
+
+/// The interventions of a simulation run, by step.
+#[derive(Debug)]
+pub(crate) struct Interventions {
+    interventions: Vec<(usize, Intervention)>,
+}
+
+impl Interventions {
+    /// Reads the interventions from the [`INTERVENTIONS_GLOBAL`] global.
+    ///
+    /// The first step is step 1, so interventions at step 0 are rejected.
+    ///
+    /// Datasets referenced by `create-agents` interventions are looked up in `datasets` and
+    /// parsed up front, so a missing or invalid dataset fails the run before the first step.
+    pub(crate) fn from_globals(globals: &Globals, datasets: &[Dataset]) -> Result<Self> {
+        let configs: Vec<InterventionConfig> = match globals.get(INTERVENTIONS_GLOBAL) {
+            Some(interventions) => {
+                serde_json::from_value(interventions.clone()).map_err(|err| {
+                    Error::from(format!(
+                        "Could not parse `{INTERVENTIONS_GLOBAL}` in globals: {err}"
+                    ))
+                })?
+            }
+            None => Vec::new(),
+        };
+        let mut interventions = configs
+            .into_iter()
+            .map(|config| {
+                if config.step == 0 {
+                    // Interventions are applied at the beginning of a step, but the initial state
+                    // is not the result of a step
+                    return Err(Error::from(
+                        "Interventions can't be scheduled at step 0, use step 1 to apply them \
+                         before the first step",
+                    ));
+                }
+                let intervention = match config.action {
+                    Action::SetGlobals { globals } => Intervention::SetGlobals(globals),
+                    Action::CreateAgents { dataset } => {
+                        Intervention::CreateAgents(dataset_agents(datasets, &dataset)?)
+                    }
+                    Action::RemoveAgents { filters } => Intervention::RemoveAgents(filters),
+                };
+                Ok((config.step, intervention))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // Interventions at the same step are applied in the order they were declared
+        interventions.sort_by_key(|(step, _)| *step);
+        Ok(Self { interventions })
+    }
+
+    /// Adds the commands of the interventions scheduled for `current_step` to `commands`.
+    ///
+    /// Agents are removed if they match the filters in `state` at the beginning of the step.
+    pub(crate) fn apply(
+        &self,
+        current_step: usize,
+        state: &State,
+        agent_schema: &AgentSchema,
+        commands: &mut Commands,
+    ) -> Result<()> {
+        let start = self
+            .interventions
+            .partition_point(|(step, _)| *step < current_step);
+        for (_, intervention) in self.interventions[start..]
+            .iter()
+            .take_while(|(step, _)| *step == current_step)
+        {
+            match intervention {
+                Intervention::SetGlobals(globals) => {
+                    commands.set_globals.push(SetGlobalsCommand {
+                        globals: globals.clone(),
+                        step: None,
+                        agent: None,
+                    });
+                }
+                Intervention::CreateAgents(agents) => {
+                    for agent in agents {
+                        let agent: Agent =
+                            serde_json::from_value(agent.clone()).map_err(|err| {
+                                Error::from(format!("Could not create agent from dataset: {err}"))
+                            })?;
+                        commands.add_create(agent);
+                    }
+                }
+                Intervention::RemoveAgents(filters) => {
+                    for agent in read_agents(state, agent_schema)? {
+                        if filters.iter().all(|filter| filter.matches(&agent)) {
+                            commands.add_remove(agent.agent_id);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the agents listed in the JSON dataset `name`.
+fn dataset_agents(datasets: &[Dataset], name: &str) -> Result<Vec<serde_json::Value>> {
+    let dataset = datasets
+        .iter()
+        .find(|dataset| dataset.shortname == name || dataset.filename == name)
+        .ok_or_else(|| {
+            Error::from(format!(
+                "Dataset `{name}` used by an intervention not found"
+            ))
+        })?;
+    if dataset.raw_csv {
+        return Err(Error::from(format!(
+            "Dataset `{name}` used by an intervention must be a JSON list of agents"
+        )));
+    }
+    let data = dataset
+        .data
+        .as_deref()
+        .ok_or_else(|| Error::from(format!("Dataset `{name}` used by an intervention is empty")))?;
+    serde_json::from_str(data).map_err(|err| {
+        Error::from(format!(
+            "Dataset `{name}` used by an intervention must be a JSON list of agents: {err}"
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use serde_json::json;
+    use stateful::state::StateCreateParameters;
+
+    use super::*;
+    use crate::tests::test_utils::{dummy_sim_run_config, gen_schema_and_test_agents};
+
+    fn dataset(data: &str) -> Dataset {
+        Dataset {
+            name: None,
+            shortname: "agents.json".to_string(),
+            filename: "agents.json".to_string(),
+            url: None,
+            raw_csv: false,
+            data: Some(data.to_string()),
+        }
+    }
+
+    #[test]
+    fn parse_interventions() {
+        let globals = Globals(json!({
+            INTERVENTIONS_GLOBAL: [
+                { "step": 20, "type": "remove-agents", "filters": [{ "field": "age", "op": "gt", "value": 80 }] },
+                { "step": 10, "type": "create-agents", "dataset": "agents.json" },
+                { "step": 10, "type": "set-globals", "globals": { "lockdown": true } },
+            ]
+        }));
+        let interventions =
+            Interventions::from_globals(&globals, &[dataset(r#"[{ "age": 30 }, {}]"#)]).unwrap();
+        let steps: Vec<_> = interventions
+            .interventions
+            .iter()
+            .map(|(step, intervention)| (*step, intervention))
+            .collect();
+        assert!(matches!(steps[0], (10, Intervention::CreateAgents(agents)) if agents.len() == 2));
+        assert!(matches!(steps[1], (10, Intervention::SetGlobals(_))));
+        assert!(matches!(steps[2], (20, Intervention::RemoveAgents(_))));
+
+        assert!(Interventions::from_globals(&globals, &[]).is_err());
+        assert!(Interventions::from_globals(&globals, &[dataset("{}")]).is_err());
+    }
+
+    #[test]
+    fn reject_initial_step() {
+        let globals = Globals(json!({
+            INTERVENTIONS_GLOBAL: [{ "step": 0, "type": "set-globals", "globals": {} }]
+        }));
+        assert!(Interventions::from_globals(&globals, &[]).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn apply_interventions() {
+        let (agent_schema, agents) = gen_schema_and_test_agents(5, 0).unwrap();
+        let state = State::from_agent_states(&agents, StateCreateParameters {
+            agent_schema: Arc::clone(&agent_schema),
+            ..dummy_sim_run_config().to_state_create_parameters()
+        })
+        .unwrap();
+        let globals = Globals(json!({
+            INTERVENTIONS_GLOBAL: [
+                { "step": 2, "type": "set-globals", "globals": { "lockdown": true } },
+                { "step": 2, "type": "create-agents", "dataset": "agents.json" },
+                { "step": 3, "type": "remove-agents", "filters": [{ "field": "seed", "op": "gt", "value": 2 }] },
+            ]
+        }));
+        let interventions =
+            Interventions::from_globals(&globals, &[dataset(r#"[{ "agent_name": "new" }]"#)])
+                .unwrap();
+
+        let mut commands = Commands::default();
+        interventions
+            .apply(1, &state, &agent_schema, &mut commands)
+            .unwrap();
+        assert!(commands.set_globals.is_empty());
+        assert_eq!(commands.created_agents().count(), 0);
+        assert_eq!(commands.removed_agent_ids().count(), 0);
+
+        interventions
+            .apply(2, &state, &agent_schema, &mut commands)
+            .unwrap();
+        assert_eq!(commands.set_globals.len(), 1);
+        assert_eq!(commands.set_globals[0].globals["lockdown"], json!(true));
+        assert_eq!(commands.set_globals[0].step, None);
+        assert!(commands.set_globals[0].agent.is_none());
+        let created: Vec<_> = commands.created_agents().collect();
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].agent_name.as_ref().unwrap().0, "new");
+        assert_eq!(commands.removed_agent_ids().count(), 0);
+
+        let mut commands = Commands::default();
+        interventions
+            .apply(3, &state, &agent_schema, &mut commands)
+            .unwrap();
+        assert!(commands.set_globals.is_empty());
+        assert_eq!(commands.created_agents().count(), 0);
+        let removed: Vec<_> = commands.removed_agent_ids().collect();
+        let expected: Vec<_> = agents[3..].iter().map(|agent| agent.agent_id).collect();
+        assert_eq!(removed, expected);
+    }
+}
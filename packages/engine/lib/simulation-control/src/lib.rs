@@ -8,6 +8,13 @@
 //! The [`command`] module contains the commands that are sent to the [simulation packages] using
 //! the [`comms`] module.
 //!
+//! Besides agents sending a `stop` command, a simulation run stops early when one of the stop
+//! conditions declared in the [`STOP_CONDITIONS_GLOBAL`] global is met. Interventions declared in
+//! the [`INTERVENTIONS_GLOBAL`] global change globals and create or remove agents at given steps.
+//! If the [`PARTITIONING_GLOBAL`] global is set, agents are grouped by their position, and if the
+//! [`LOAD_BALANCING_GLOBAL`] global is set, batches are migrated between workers to balance the
+//! measured execution times.
+//!
 //! [`SimulationRuns`]: controller::SimulationRuns
 //! [`SimulationRuns::new_run`]: controller::SimulationRuns::new_run
 //! [`SimulationController`]: controller::SimulationController
@@ -25,8 +32,12 @@ mod agent_control;
 mod engine;
 mod engine_status;
 mod error;
+mod intervention;
+mod load_balancing;
+mod partition;
 mod status;
 mod step_result;
+mod stop_condition;
 
 #[cfg(test)]
 mod tests;
@@ -34,5 +45,9 @@ mod tests;
 pub use self::{
     engine_status::EngineStatus,
     error::{Error, Result},
+    intervention::INTERVENTIONS_GLOBAL,
+    load_balancing::LOAD_BALANCING_GLOBAL,
+    partition::PARTITIONING_GLOBAL,
     status::SimStatus,
+    stop_condition::STOP_CONDITIONS_GLOBAL,
 };
@@ -0,0 +1,168 @@
+//! Dynamic balancing of the load across workers.
+//!
+//! Batches are assigned to a worker when they are created. If agents differ a lot in how
+//! expensive their behaviors are, the worker holding the expensive batches becomes the straggler
+//! of every step. If the `load_balancing` global is set, the engine estimates the cost of every
+//! batch from the time its last behavior execution took and migrates batches from the most to
+//! the least loaded worker at the beginning of a step:
+//!
+//! ```json
+//! {
+//!   "load_balancing": { "threshold": 0.2 }
+//! }
+//! ```
+//!
+//! Batches are only moved while the most loaded worker exceeds the mean load by more than
+//! `threshold`, so small fluctuations in the measured times don't move batches back and forth.
+
+use serde::Deserialize;
+use stateful::global::Globals;
+
+use crate::{Error, Result};
+
+/// Name of the global configuring the load balancing.
+pub const LOAD_BALANCING_GLOBAL: &str = "load_balancing";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LoadBalancingConfig {
+    /// Tolerated imbalance, relative to the mean load of the workers.
+    threshold: f64,
+}
+
+/// Migrates batches between workers when their load is imbalanced.
+#[derive(Debug)]
+pub(crate) struct LoadBalancing {
+    config: LoadBalancingConfig,
+}
+
+impl LoadBalancing {
+    /// Reads the configuration from the [`LOAD_BALANCING_GLOBAL`] global, returns `None` if it's
+    /// not set.
+    pub(crate) fn from_globals(globals: &Globals) -> Result<Option<Self>> {
+        globals
+            .get(LOAD_BALANCING_GLOBAL)
+            .map(|load_balancing| {
+                let config: LoadBalancingConfig = serde_json::from_value(load_balancing.clone())
+                    .map_err(|err| {
+                        Error::from(format!(
+                            "Could not parse `{LOAD_BALANCING_GLOBAL}` in globals: {err}"
+                        ))
+                    })?;
+                if config.threshold < 0.0 {
+                    return Err(Error::from(format!(
+                        "`threshold` in `{LOAD_BALANCING_GLOBAL}` must not be negative"
+                    )));
+                }
+                Ok(Self { config })
+            })
+            .transpose()
+    }
+
+    /// Returns the worker every batch should be assigned to.
+    ///
+    /// `batches` contains the current worker and the estimated cost of every batch. The most
+    /// expensive batch which reduces the imbalance is moved from the most to the least loaded
+    /// worker, until the imbalance is within the threshold. A worker always keeps at least one of
+    /// its batches.
+    pub(crate) fn assign(&self, batches: &[(usize, f64)], num_workers: usize) -> Vec<usize> {
+        let mut workers: Vec<usize> = batches.iter().map(|(worker, _)| *worker).collect();
+        let mut loads = vec![0.0; num_workers];
+        let mut num_batches = vec![0_usize; num_workers];
+        for (worker, cost) in batches {
+            loads[*worker] += cost;
+            num_batches[*worker] += 1;
+        }
+        let mean = loads.iter().sum::<f64>() / num_workers as f64;
+        if num_workers < 2 || mean <= 0.0 {
+            return workers;
+        }
+
+        // Bound the number of moves, a long tail of tiny improvements isn't worth the migration
+        for _ in 0..batches.len() {
+            let by_load = |a: &usize, b: &usize| loads[*a].total_cmp(&loads[*b]);
+            let busiest = (0..num_workers).max_by(by_load).unwrap_or_default();
+            let least_busy = (0..num_workers).min_by(by_load).unwrap_or_default();
+            if loads[busiest] / mean - 1.0 <= self.config.threshold || num_batches[busiest] < 2 {
+                break;
+            }
+
+            // Moving a batch with a cost between zero and the difference of the loads reduces the
+            // imbalance, it's best if the loads end up equal.
+            let difference = loads[busiest] - loads[least_busy];
+            let candidate = batches
+                .iter()
+                .enumerate()
+                .filter(|(batch, (_, cost))| {
+                    workers[*batch] == busiest && *cost > 0.0 && *cost < difference
+                })
+                .min_by(|(_, (_, a)), (_, (_, b))| {
+                    (difference - 2.0 * a)
+                        .abs()
+                        .total_cmp(&(difference - 2.0 * b).abs())
+                });
+            match candidate {
+                Some((batch, (_, cost))) => {
+                    workers[batch] = least_busy;
+                    loads[busiest] -= cost;
+                    loads[least_busy] += cost;
+                    num_batches[busiest] -= 1;
+                    num_batches[least_busy] += 1;
+                }
+                None => break,
+            }
+        }
+        workers
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn load_balancing(threshold: f64) -> LoadBalancing {
+        LoadBalancing::from_globals(&Globals(
+            json!({ LOAD_BALANCING_GLOBAL: { "threshold": threshold } }),
+        ))
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn moves_expensive_batches() {
+        // The batches of worker 0 are 50 times as expensive as the others
+        let batches = [(0, 50.0), (0, 50.0), (1, 1.0), (1, 1.0), (2, 1.0), (2, 1.0)];
+        let workers = load_balancing(0.2).assign(&batches, 3);
+        let mut loads = [0.0; 3];
+        for ((_, cost), worker) in batches.iter().zip(&workers) {
+            loads[*worker] += cost;
+        }
+        assert_eq!(loads.iter().filter(|load| **load >= 50.0).count(), 2);
+    }
+
+    #[test]
+    fn keeps_balanced_workers() {
+        let batches = [(0, 1.1), (0, 1.0), (1, 1.0), (1, 0.9)];
+        assert_eq!(load_balancing(0.2).assign(&batches, 2), vec![0, 0, 1, 1]);
+        // Without any measured cost there is nothing to balance
+        let batches = [(0, 0.0), (0, 0.0), (1, 0.0)];
+        assert_eq!(load_balancing(0.0).assign(&batches, 2), vec![0, 0, 1]);
+    }
+
+    #[test]
+    fn parse_load_balancing() {
+        assert!(
+            LoadBalancing::from_globals(&Globals(json!({})))
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            LoadBalancing::from_globals(&Globals(
+                json!({ LOAD_BALANCING_GLOBAL: { "threshold": -1 } })
+            ))
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,284 @@
+//! Locality-preserving partitioning of agents into groups.
+//!
+//! By default, agents are spread over the groups by count only, so every group contains agents
+//! scattered across space. If the `partitioning` global is set, agents are ordered along a
+//! space-filling curve on their `position` before they are split into groups, so groups roughly
+//! correspond to spatial regions:
+//!
+//! ```json
+//! {
+//!   "partitioning": { "order": "hilbert", "every": 100 }
+//! }
+//! ```
+//!
+//! The order is applied to the initial agents and to agents created during the simulation run.
+//! As agents move, groups lose their locality, so with `every` all agents are partitioned anew at
+//! every `every`-th step by moving their rows between the existing groups.
+
+use serde::Deserialize;
+use stateful::{
+    agent::{self, Agent, AgentSchema, IntoAgents},
+    global::Globals,
+    state::{State, StateReadProxy},
+    Vec3,
+};
+
+use crate::{Error, Result};
+
+/// Name of the global configuring the partitioning.
+pub const PARTITIONING_GLOBAL: &str = "partitioning";
+
+/// Number of bits per axis used to quantize positions, so three axes fit into a `u64`.
+const BITS: u32 = 21;
+
+/// The space-filling curve agents are ordered along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum SpatialOrder {
+    /// Z-order curve, cheap to compute but with jumps between quadrants.
+    Morton,
+    /// Hilbert curve, consecutive cells are always adjacent.
+    Hilbert,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PartitioningConfig {
+    order: SpatialOrder,
+    /// Partition all agents anew at every `every`-th step.
+    every: Option<usize>,
+}
+
+/// Orders agents along a space-filling curve on their position.
+#[derive(Debug)]
+pub(crate) struct SpatialPartitioning {
+    config: PartitioningConfig,
+}
+
+impl SpatialPartitioning {
+    /// Reads the configuration from the [`PARTITIONING_GLOBAL`] global, returns `None` if it's not
+    /// set.
+    pub(crate) fn from_globals(globals: &Globals) -> Result<Option<Self>> {
+        globals
+            .get(PARTITIONING_GLOBAL)
+            .map(|partitioning| {
+                let config: PartitioningConfig = serde_json::from_value(partitioning.clone())
+                    .map_err(|err| {
+                        Error::from(format!(
+                            "Could not parse `{PARTITIONING_GLOBAL}` in globals: {err}"
+                        ))
+                    })?;
+                if config.every == Some(0) {
+                    return Err(Error::from(format!(
+                        "`every` in `{PARTITIONING_GLOBAL}` must be at least 1"
+                    )));
+                }
+                Ok(Self { config })
+            })
+            .transpose()
+    }
+
+    /// Returns if all agents should be partitioned anew at the beginning of `step`.
+    pub(crate) fn repartitions_at(&self, step: usize) -> bool {
+        self.config
+            .every
+            .map_or(false, |every| step > 0 && step % every == 0)
+    }
+
+    /// Sorts `items` along the space-filling curve on their `position`.
+    ///
+    /// The curve spans the bounding box of all positions. Items without a position are moved to
+    /// the end, the order of items in the same cell is kept.
+    pub(crate) fn sort_by_position<T>(
+        &self,
+        items: &mut [T],
+        position: impl Fn(&T) -> Option<&Vec3>,
+    ) {
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        for Vec3(x, y, z) in items.iter().filter_map(&position) {
+            for (axis, value) in [x, y, z].into_iter().enumerate() {
+                min[axis] = min[axis].min(*value);
+                max[axis] = max[axis].max(*value);
+            }
+        }
+        // Only axes with an extent are used, so 2D positions are ordered along a 2D curve
+        let axes: Vec<usize> = (0..3).filter(|&axis| max[axis] > min[axis]).collect();
+        let scale = ((1_u32 << BITS) - 1) as f64;
+
+        items.sort_by_cached_key(|item| {
+            position(item).map_or(u64::MAX, |Vec3(x, y, z)| {
+                let position = [x, y, z];
+                let mut cell: Vec<u32> = axes
+                    .iter()
+                    .map(|&axis| {
+                        ((position[axis] - min[axis]) / (max[axis] - min[axis]) * scale) as u32
+                    })
+                    .collect();
+                match self.config.order {
+                    SpatialOrder::Morton => interleave(&cell, BITS),
+                    SpatialOrder::Hilbert => {
+                        hilbert_transpose(&mut cell, BITS);
+                        interleave(&cell, BITS)
+                    }
+                }
+            })
+        });
+    }
+
+    /// Returns the `(batch, agent)` indices of all agents of `state` ordered along the curve.
+    pub(crate) fn order_agents(&self, state: &StateReadProxy) -> Result<Vec<(usize, usize)>> {
+        let agent_batches: Vec<_> = state.agent_pool().batches_iter().collect();
+        let mut agents: Vec<((usize, usize), Option<Vec3>)> =
+            agent::arrow::index_iter(&agent_batches)
+                .zip(agent::arrow::position_iter(&agent_batches)?)
+                .map(|(index, position)| {
+                    (
+                        (index.group_index as usize, index.agent_index as usize),
+                        position.map(|[x, y, z]| Vec3(x, y, z)),
+                    )
+                })
+                .collect();
+        self.sort_by_position(&mut agents, |(_, position)| position.as_ref());
+        Ok(agents.into_iter().map(|(index, _)| index).collect())
+    }
+}
+
+/// Reads all agents of `state`, in the order of the batches.
+pub(crate) fn read_agents(state: &State, agent_schema: &AgentSchema) -> Result<Vec<Agent>> {
+    let state = state.read()?;
+    let mut agents = Vec::with_capacity(state.agent_pool().len());
+    for batch in state.agent_pool().batches_iter() {
+        agents.append(&mut batch.to_agent_states(Some(agent_schema))?);
+    }
+    Ok(agents)
+}
+
+/// Interleaves the lowest `bits` bits of every coordinate, starting with the most significant bit
+/// of the first coordinate.
+fn interleave(cell: &[u32], bits: u32) -> u64 {
+    let mut key = 0;
+    for bit in (0..bits).rev() {
+        for coordinate in cell {
+            key = (key << 1) | u64::from((coordinate >> bit) & 1);
+        }
+    }
+    key
+}
+
+/// Transforms the coordinates of `cell` in place, so interleaving them yields the index along the
+/// Hilbert curve.
+///
+/// This is the `AxestoTranspose` algorithm from J. Skilling, "Programming the Hilbert curve"
+/// (2004), which works for any number of dimensions.
+fn hilbert_transpose(cell: &mut [u32], bits: u32) {
+    let n = cell.len();
+    if n == 0 {
+        return;
+    }
+    let m = 1 << (bits - 1);
+
+    // Inverse undo
+    let mut q = m;
+    while q > 1 {
+        let p = q - 1;
+        for i in 0..n {
+            if cell[i] & q != 0 {
+                cell[0] ^= p;
+            } else {
+                let t = (cell[0] ^ cell[i]) & p;
+                cell[0] ^= t;
+                cell[i] ^= t;
+            }
+        }
+        q >>= 1;
+    }
+
+    // Gray encode
+    for i in 1..n {
+        cell[i] ^= cell[i - 1];
+    }
+    let mut t = 0;
+    let mut q = m;
+    while q > 1 {
+        if cell[n - 1] & q != 0 {
+            t ^= q - 1;
+        }
+        q >>= 1;
+    }
+    for coordinate in cell {
+        *coordinate ^= t;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn partitioning(order: &str) -> SpatialPartitioning {
+        SpatialPartitioning::from_globals(&Globals(
+            json!({ PARTITIONING_GLOBAL: { "order": order, "every": 10 } }),
+        ))
+        .unwrap()
+        .unwrap()
+    }
+
+    /// Returns all cells of a `size` x `size` grid ordered along the curve.
+    fn curve(order: &str, size: usize) -> Vec<Vec3> {
+        let mut cells: Vec<Vec3> = (0..size * size)
+            .map(|i| Vec3((i % size) as f64, (i / size) as f64, 0.0))
+            .collect();
+        partitioning(order).sort_by_position(&mut cells, |cell| Some(cell));
+        cells
+    }
+
+    #[test]
+    fn hilbert_is_continuous() {
+        let cells = curve("hilbert", 8);
+        for pair in cells.windows(2) {
+            let distance = (pair[0].0 - pair[1].0).abs() + (pair[0].1 - pair[1].1).abs();
+            assert_eq!(
+                distance, 1.0,
+                "{:?} and {:?} are not adjacent",
+                pair[0], pair[1]
+            );
+        }
+    }
+
+    #[test]
+    fn morton_quadrants() {
+        let cells = curve("morton", 4);
+        // Every quadrant is completed before the next one is entered
+        for quadrant in cells.chunks(4) {
+            let x = (quadrant[0].0 / 2.0).floor();
+            let y = (quadrant[0].1 / 2.0).floor();
+            assert!(
+                quadrant
+                    .iter()
+                    .all(|cell| (cell.0 / 2.0).floor() == x && (cell.1 / 2.0).floor() == y)
+            );
+        }
+    }
+
+    #[test]
+    fn repartition_steps() {
+        let partitioning = partitioning("hilbert");
+        assert!(!partitioning.repartitions_at(0));
+        assert!(!partitioning.repartitions_at(5));
+        assert!(partitioning.repartitions_at(20));
+
+        assert!(
+            SpatialPartitioning::from_globals(&Globals(json!({})))
+                .unwrap()
+                .is_none()
+        );
+        assert!(
+            SpatialPartitioning::from_globals(&Globals(
+                json!({ PARTITIONING_GLOBAL: { "order": "hilbert", "every": 0 } })
+            ))
+            .is_err()
+        );
+    }
+}
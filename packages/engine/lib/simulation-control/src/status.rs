@@ -4,7 +4,10 @@ use execution::{
 };
 use serde::{Deserialize, Serialize};
 
-use crate::{command::StopCommand, Result};
+use crate::{
+    command::{StopCommand, StopMessage},
+    Result,
+};
 
 // Sent from sim runs to experiment main loop.
 #[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
@@ -13,6 +16,8 @@ pub struct SimStatus {
     pub steps_taken: isize,
     pub early_stop: bool,
     pub stop_msg: Vec<StopCommand>,
+    /// The stop condition which stopped the simulation run, if any.
+    pub stop_condition: Option<StopMessage>,
     pub stop_signal: bool,
     pub persistence_result: Option<(String, serde_json::Value)>,
     // TODO: OS do we need these within SimStatus or should they be handled elsewhere, such as
@@ -30,6 +35,7 @@ impl SimStatus {
             steps_taken: 0,
             early_stop: false,
             stop_msg: vec![],
+            stop_condition: None,
             stop_signal: false,
             persistence_result: None,
             error: None,
@@ -61,6 +67,7 @@ impl SimStatus {
         steps_taken: isize,
         early_stop: bool,
         stop_msg: Vec<StopCommand>,
+        stop_condition: Option<StopMessage>,
         persistence_result: P,
     ) -> Result<SimStatus> {
         let persistence_result = OutputPersistenceResult::into_value(persistence_result)
@@ -69,6 +76,7 @@ impl SimStatus {
             steps_taken,
             early_stop,
             stop_msg,
+            stop_condition,
             stop_signal: true,
             running: false,
             persistence_result: Some(persistence_result),
@@ -3,7 +3,7 @@ use execution::{
     runner::RunnerError,
 };
 
-use crate::agent_control::AgentControl;
+use crate::{agent_control::AgentControl, command::StopMessage};
 
 pub struct SimulationStepResult {
     // TODO: UNUSED: Needs triage
@@ -14,4 +14,6 @@ pub struct SimulationStepResult {
     // TODO: UNUSED: Needs triage
     pub warnings: Vec<RunnerError>,
     pub agent_control: AgentControl,
+    /// The first stop condition met by this step.
+    pub stop_condition: Option<StopMessage>,
 }
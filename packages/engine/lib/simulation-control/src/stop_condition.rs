@@ -0,0 +1,307 @@
+//! Declarative conditions to stop a simulation run early.
+//!
+//! Stop conditions are declared as a list in the `stop_conditions` global, either directly in
+//! `globals.json` or by the experiment in `experiments.json`. They are evaluated by the [`Engine`]
+//! after the output packages ran, so they don't require a behavior to be run on every step:
+//!
+//! ```json
+//! {
+//!   "stop_conditions": [
+//!     { "type": "threshold", "output": "infected", "below": 1 },
+//!     { "type": "steady-state", "output": "infected", "epsilon": 0.01, "steps": 10 },
+//!     { "type": "no-agents" },
+//!     { "type": "wall-clock", "seconds": 600, "status": "error" }
+//!   ]
+//! }
+//! ```
+//!
+//! [`Engine`]: crate::engine::Engine
+
+use std::time::Instant;
+
+use execution::package::simulation::output::{analysis::AnalysisSingleOutput, Output};
+use serde::Deserialize;
+use stateful::global::Globals;
+
+use crate::{
+    command::{StopMessage, StopStatus},
+    Error, Result,
+};
+
+/// Name of the global declaring the stop conditions.
+pub const STOP_CONDITIONS_GLOBAL: &str = "stop_conditions";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "kebab-case")]
+enum Condition {
+    /// An analysis output reached `above` or `below` (inclusive).
+    Threshold {
+        output: String,
+        above: Option<f64>,
+        below: Option<f64>,
+    },
+    /// An analysis output changed by at most `epsilon` for `steps` consecutive steps.
+    SteadyState {
+        output: String,
+        epsilon: f64,
+        steps: usize,
+    },
+    /// No agents are left in the simulation.
+    NoAgents,
+    /// The simulation run took longer than `seconds`.
+    WallClock { seconds: f64 },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct StopConditionConfig {
+    #[serde(flatten)]
+    condition: Condition,
+    /// Defaults to [`StopStatus::Success`] for conditions on analysis outputs and to
+    /// [`StopStatus::Warning`] otherwise.
+    status: Option<StopStatus>,
+    /// Replaces the generated reason.
+    reason: Option<String>,
+}
+
+#[derive(Debug)]
+struct StopCondition {
+    config: StopConditionConfig,
+    /// Value of the analysis output at the previous step.
+    previous: Option<f64>,
+    /// Number of consecutive steps the analysis output didn't change by more than `epsilon`.
+    steady_steps: usize,
+    /// Whether the analysis output was reported as missing already.
+    reported_missing: bool,
+}
+
+impl StopCondition {
+    fn new(config: StopConditionConfig) -> Result<Self> {
+        match &config.condition {
+            Condition::Threshold {
+                above: None,
+                below: None,
+                ..
+            } => {
+                return Err(Error::from(
+                    "Threshold stop condition requires `above` or `below`",
+                ));
+            }
+            Condition::SteadyState { epsilon, steps, .. } if *epsilon < 0.0 || *steps == 0 => {
+                return Err(Error::from(
+                    "Steady-state stop condition requires a non-negative `epsilon` and at least \
+                     one step",
+                ));
+            }
+            _ => {}
+        }
+        Ok(Self {
+            config,
+            previous: None,
+            steady_steps: 0,
+            reported_missing: false,
+        })
+    }
+
+    /// Returns the generated reason if the condition is met.
+    fn evaluate(
+        &mut self,
+        outputs: &[Output],
+        num_agents: usize,
+        started: Instant,
+    ) -> Option<String> {
+        match &self.config.condition {
+            Condition::Threshold {
+                output,
+                above,
+                below,
+            } => {
+                let value = analysis_output(outputs, output);
+                let value = report_missing(&mut self.reported_missing, output, value)?;
+                if let Some(above) = above.filter(|above| value >= *above) {
+                    Some(format!(
+                        "Analysis output `{output}` rose to {value} (threshold: {above})"
+                    ))
+                } else {
+                    below.filter(|below| value <= *below).map(|below| {
+                        format!("Analysis output `{output}` fell to {value} (threshold: {below})")
+                    })
+                }
+            }
+            Condition::SteadyState {
+                output,
+                epsilon,
+                steps,
+            } => {
+                let value = analysis_output(outputs, output);
+                let value = report_missing(&mut self.reported_missing, output, value);
+                match (self.previous, value) {
+                    (Some(previous), Some(value)) if (value - previous).abs() <= *epsilon => {
+                        self.steady_steps += 1;
+                    }
+                    _ => self.steady_steps = 0,
+                }
+                self.previous = value;
+                (self.steady_steps >= *steps).then(|| {
+                    format!(
+                        "Analysis output `{output}` changed by at most {epsilon} for {steps} steps"
+                    )
+                })
+            }
+            Condition::NoAgents => (num_agents == 0).then(|| "No agents left".to_string()),
+            Condition::WallClock { seconds } => {
+                (started.elapsed().as_secs_f64() >= *seconds).then(|| {
+                    format!("Simulation run exceeded its wall-clock budget of {seconds} seconds")
+                })
+            }
+        }
+    }
+
+    fn status(&self) -> StopStatus {
+        self.config.status.unwrap_or(match self.config.condition {
+            Condition::Threshold { .. } | Condition::SteadyState { .. } => StopStatus::Success,
+            Condition::NoAgents | Condition::WallClock { .. } => StopStatus::Warning,
+        })
+    }
+}
+
+/// Warns once per condition if the analysis `output` has no `value`.
+fn report_missing(reported: &mut bool, output: &str, value: Option<f64>) -> Option<f64> {
+    if value.is_none() && !*reported {
+        *reported = true;
+        tracing::warn!(
+            "Analysis output `{output}` used by a stop condition is missing or not a number"
+        );
+    }
+    value
+}
+
+/// Returns the value of the analysis output `name` at the current step, if it's a number.
+fn analysis_output(outputs: &[Output], name: &str) -> Option<f64> {
+    outputs.iter().find_map(|output| match output {
+        Output::AnalysisOutput(analysis) => analysis
+            .inner
+            .iter()
+            .find(|(output_name, _)| output_name.as_str() == name)
+            .and_then(|(_, value)| match value {
+                AnalysisSingleOutput::Number(value) => *value,
+                AnalysisSingleOutput::Vec(_) => None,
+            }),
+        Output::JsonStateOutput(_) | Output::GlobalsChange(_) => None,
+    })
+}
+
+/// The stop conditions of a simulation run.
+#[derive(Debug)]
+pub(crate) struct StopConditions {
+    conditions: Vec<StopCondition>,
+    started: Instant,
+}
+
+impl StopConditions {
+    /// Reads the stop conditions from the [`STOP_CONDITIONS_GLOBAL`] global.
+    ///
+    /// The wall-clock budget starts when this is called.
+    pub(crate) fn from_globals(globals: &Globals) -> Result<Self> {
+        let configs: Vec<StopConditionConfig> = match globals.get(STOP_CONDITIONS_GLOBAL) {
+            Some(conditions) => serde_json::from_value(conditions.clone()).map_err(|err| {
+                Error::from(format!(
+                    "Could not parse `{STOP_CONDITIONS_GLOBAL}` in globals: {err}"
+                ))
+            })?,
+            None => Vec::new(),
+        };
+        Ok(Self {
+            conditions: configs
+                .into_iter()
+                .map(StopCondition::new)
+                .collect::<Result<_>>()?,
+            started: Instant::now(),
+        })
+    }
+
+    /// Evaluates all conditions on the `outputs` of the current step.
+    ///
+    /// Returns the stop message of the first condition which is met.
+    pub(crate) fn evaluate(
+        &mut self,
+        outputs: &[Output],
+        num_agents: usize,
+    ) -> Option<StopMessage> {
+        let mut message = None;
+        // Every condition is evaluated to keep track of the steady state
+        for condition in &mut self.conditions {
+            if let Some(reason) = condition.evaluate(outputs, num_agents, self.started) {
+                message.get_or_insert_with(|| StopMessage {
+                    status: condition.status(),
+                    reason: Some(condition.config.reason.clone().unwrap_or(reason)),
+                });
+            }
+        }
+        message
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use execution::package::simulation::output::analysis::AnalysisOutput;
+    use serde_json::json;
+
+    use super::*;
+
+    fn parse(conditions: serde_json::Value) -> Result<StopConditions> {
+        StopConditions::from_globals(&Globals(json!({ STOP_CONDITIONS_GLOBAL: conditions })))
+    }
+
+    fn outputs(value: f64) -> Vec<Output> {
+        let mut inner = HashMap::new();
+        inner.insert(
+            Arc::new("infected".to_string()),
+            AnalysisSingleOutput::some_number(value),
+        );
+        vec![Output::AnalysisOutput(AnalysisOutput { inner })]
+    }
+
+    #[test]
+    fn threshold() {
+        let mut conditions =
+            parse(json!([{ "type": "threshold", "output": "infected", "below": 1 }])).unwrap();
+        assert_eq!(conditions.evaluate(&outputs(5.0), 10), None);
+        let message = conditions.evaluate(&outputs(1.0), 10).unwrap();
+        assert_eq!(message.status, StopStatus::Success);
+        assert_eq!(
+            message.reason.as_deref(),
+            Some("Analysis output `infected` fell to 1 (threshold: 1)")
+        );
+
+        assert!(parse(json!([{ "type": "threshold", "output": "infected" }])).is_err());
+    }
+
+    #[test]
+    fn steady_state() {
+        let mut conditions = parse(json!([{
+            "type": "steady-state",
+            "output": "infected",
+            "epsilon": 0.5,
+            "steps": 2,
+            "reason": "Converged"
+        }]))
+        .unwrap();
+        for value in [10.0, 5.0, 4.8, 7.0, 7.2] {
+            assert_eq!(conditions.evaluate(&outputs(value), 10), None);
+        }
+        let message = conditions.evaluate(&outputs(7.1), 10).unwrap();
+        assert_eq!(message.reason.as_deref(), Some("Converged"));
+    }
+
+    #[test]
+    fn no_agents() {
+        let mut conditions = parse(json!([{ "type": "no-agents", "status": "error" }])).unwrap();
+        assert_eq!(conditions.evaluate(&[], 1), None);
+        assert_eq!(
+            conditions.evaluate(&[], 0).map(|message| message.status),
+            Some(StopStatus::Error)
+        );
+    }
+}
@@ -283,6 +283,8 @@ pub fn dummy_sim_run_config() -> SimulationRunConfig {
         globals_src: "{}".to_string(),
         experiments_src: None,
         datasets: Vec::new(),
+        node_modules: None,
+        python_requirements: None,
         package_init,
     };
 
@@ -9,8 +9,8 @@ use arrow2::{
     datatypes::{DataType, Schema},
 };
 use memory::arrow::{
-    json_vals_to_any_type_col, json_vals_to_bool, json_vals_to_col, json_vals_to_primitive,
-    json_vals_to_utf8, record_batch::RecordBatch,
+    json_vals_to_any_type_col, json_vals_to_bool, json_vals_to_category, json_vals_to_col,
+    json_vals_to_primitive, json_vals_to_utf8, record_batch::RecordBatch,
 };
 
 use crate::{
@@ -113,19 +113,24 @@ impl IntoRecordBatch for &[&Agent] {
                 Box::new(json_vals_to_bool(vals)?)
             } else if name == PREVIOUS_INDEX_FIELD_KEY {
                 previous_index_to_empty_col(self.len(), field.data_type().clone())?
-            } else if matches!(
-                schema
+            } else {
+                match &schema
                     .field_spec_map
                     .get_field_spec(&RootFieldKey::new(name.to_string()))?
                     .inner
                     .field_type
-                    .variant,
-                FieldTypeVariant::AnyType
-            ) {
-                // Any-type (JSON string) column
-                json_vals_to_any_type_col(vals, field.data_type())?
-            } else {
-                json_vals_to_col(vals, field, field.is_nullable)?
+                    .variant
+                {
+                    // Any-type (JSON string) column
+                    FieldTypeVariant::AnyType => {
+                        json_vals_to_any_type_col(vals, field.data_type())?
+                    }
+                    // Dictionary keys of a category column
+                    FieldTypeVariant::Category(categories) => {
+                        Box::new(json_vals_to_category(vals, categories, field.is_nullable)?)
+                    }
+                    _ => json_vals_to_col(vals, field, field.is_nullable)?,
+                }
             };
             cols.push(col);
         }
@@ -1,6 +1,6 @@
 #![allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
 
-use std::{borrow::Cow, sync::Arc};
+use std::{borrow::Cow, sync::Arc, time::Duration};
 
 use arrow2::io::ipc::{
     read::deserialize_schema,
@@ -37,6 +37,9 @@ pub struct AgentBatch {
     pub batch: ArrowBatch,
     /// Describes the worker the batch is distributed to if there are multiple workers
     pub worker_index: usize,
+    /// Wall time the last behavior execution on this batch took, as measured by the worker it was
+    /// distributed to. `None` if the batch has not been executed yet.
+    pub execution_time: Option<Duration>,
 }
 
 /// Constructors for `Batch`
@@ -135,6 +138,7 @@ impl AgentBatch {
                 persisted,
             ),
             worker_index: worker_index.unwrap_or(0),
+            execution_time: None,
         })
     }
 
@@ -200,6 +204,10 @@ impl AgentBatch {
         self.worker_index = worker_index;
     }
 
+    pub fn set_execution_time(&mut self, execution_time: Duration) {
+        self.execution_time = Some(execution_time);
+    }
+
     pub fn id_iter(&self) -> Result<impl Iterator<Item = &[u8; UUID_V4_LEN]>> {
         record_batch::agent_id_iter(self.batch.record_batch()?)
     }
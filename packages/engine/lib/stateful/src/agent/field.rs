@@ -633,10 +633,20 @@ impl Agent {
                     data,
                 })
             }
+            message::payload::SetGlobals::KIND => {
+                message::Message::SetGlobals(message::payload::SetGlobals {
+                    r#type: message::SetGlobals::Type,
+                    to: to.to_vec(),
+                    data: serde_json::from_value(
+                        data.ok_or_else(|| Error::from("Missing globals to set"))?,
+                    )?,
+                })
+            }
             _ => message::Message::Generic(message::payload::Generic {
                 r#type: kind.to_string(),
                 to: to.to_vec(),
                 data,
+                delay: None,
             }),
         });
         Ok(())
@@ -798,6 +808,35 @@ impl Agent {
 
         self.custom.get(key).is_some()
     }
+
+    /// `retain_fields` removes all fields, built-in or custom, for which `f` returns `false`.
+    ///
+    /// The `agent_id` is always retained, so the agent can still be identified.
+    pub fn retain_fields<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&str) -> bool,
+    {
+        for &builtin in &BUILTIN_FIELDS {
+            if builtin == AgentStateField::AgentId.name() || f(builtin) {
+                continue;
+            }
+            match builtin {
+                "agent_name" => self.agent_name = None,
+                "messages" => self.messages.clear(),
+                "position" => self.position = None,
+                "direction" => self.direction = None,
+                "velocity" => self.velocity = None,
+                "shape" => self.shape = None,
+                "height" => self.height = None,
+                "scale" => self.scale = None,
+                "color" => self.color = None,
+                "rgb" => self.rgb = None,
+                "hidden" => self.hidden = false,
+                _ => unreachable!("unhandled built-in field `{builtin}`"),
+            }
+        }
+        self.custom.retain(|key, _| f(key));
+    }
 }
 
 impl Index<&str> for Agent {
@@ -1001,6 +1040,27 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_set_globals_message() {
+        let msg: message::Message = serde_json::from_value(json!({
+            "type": "set_globals",
+            "to": "hash",
+            "data": {
+                "globals": { "lockdown": true },
+                "step": 100
+            }
+        }))
+        .unwrap();
+
+        match msg {
+            message::Message::SetGlobals(msg) => {
+                assert_eq!(msg.data.step, Some(100));
+                assert_eq!(msg.data.globals["lockdown"], json!(true));
+            }
+            _ => panic!("Expected SetGlobals message"),
+        };
+    }
+
     #[test]
     fn test_generic_message() {
         let msg = message::Message::Generic(message::payload::Generic {
@@ -1009,6 +1069,7 @@ mod tests {
             data: Some(json!({
                 "foo": "bar",
             })),
+            delay: None,
         });
 
         let json = serde_json::to_string(&msg).unwrap();
@@ -1023,6 +1084,23 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_delayed_message() {
+        let msg: message::Message = serde_json::from_value(json!({
+            "to": "some_other_agent",
+            "type": "custom_message",
+            "delay": 3,
+        }))
+        .unwrap();
+
+        match msg {
+            message::Message::Generic(msg) => {
+                assert_eq!(msg.delay, Some(3));
+            }
+            _ => panic!("Expected Generic message"),
+        };
+    }
+
     #[test]
     fn test_add_message() {
         let mut agent = Agent::default();
@@ -1035,6 +1113,7 @@ mod tests {
                 data,
                 to: vec!["alice".to_string()],
                 r#type: "custom_message".to_string(),
+                delay: None,
             }
         )]);
     }
@@ -1052,6 +1131,7 @@ mod tests {
                 data,
                 to,
                 r#type: "custom_message".to_string(),
+                delay: None,
             }
         )]);
     }
@@ -10,22 +10,28 @@
 //! used by [`ContextBatch`]. Writing to a [`ContextBatch`] is encapsulated in [`ContextColumn`] and
 //! [`ContextColumnWriter`].
 //!
+//! Optionally, the [`Context`] holds a [`RasterBatch`] with named raster layers, which agents can
+//! read and write from the language runners.
+//!
 //! [HASH documentation]: https://hash.ai/docs/simulation/creating-simulations/anatomy-of-an-agent/context
 //! [`Globals`]: crate::global::Globals
 //! [`Dataset`]: crate::global::Dataset
 
 mod batch;
 mod column;
+mod raster;
 mod schema;
 
 use std::sync::Arc;
 
 use arrow2::{array::Array, chunk::Chunk};
 use memory::{arrow::record_batch::RecordBatch, shared_memory::MemoryId};
+use parking_lot::RwLock;
 
 pub use self::{
     batch::ContextBatch,
     column::{ContextColumn, ContextColumnWriter},
+    raster::RasterBatch,
     schema::ContextSchema,
 };
 use crate::{
@@ -55,6 +61,9 @@ pub struct Context {
 
     /// The IDs of the batches that were removed between this step and the last.
     removed_batches: Vec<String>,
+
+    /// The raster layers of the simulation run, if any are declared.
+    raster_batch: Option<Arc<RwLock<RasterBatch>>>,
 }
 
 impl Context {
@@ -82,9 +91,23 @@ impl Context {
                 message_pool: MessageBatchPool::empty(),
             },
             removed_batches: Vec::new(),
+            raster_batch: None,
         })
     }
 
+    /// Attaches the raster layers of the simulation run to the context.
+    ///
+    /// The layers keep living in the same shared memory segment for the whole simulation run.
+    pub fn with_raster_batch(mut self, raster_batch: RasterBatch) -> Self {
+        self.raster_batch = Some(Arc::new(RwLock::new(raster_batch)));
+        self
+    }
+
+    /// Returns the [`RasterBatch`] holding the raster layers, if any are declared.
+    pub fn raster_batch(&self) -> Option<&Arc<RwLock<RasterBatch>>> {
+        self.raster_batch.as_ref()
+    }
+
     pub fn take_agent_pool(&mut self) -> AgentBatchPool {
         std::mem::replace(&mut self.previous_state.agent_pool, AgentBatchPool::empty())
     }
@@ -132,6 +155,7 @@ impl Context {
         PreContext {
             batch: self.batch,
             removed_batches: self.removed_batches,
+            raster_batch: self.raster_batch,
         }
     }
 
@@ -220,6 +244,7 @@ pub struct PreContext {
     batch: Arc<ContextBatch>,
     /// Local metadata
     removed_batches: Vec<String>,
+    raster_batch: Option<Arc<RwLock<RasterBatch>>>,
 }
 
 impl PreContext {
@@ -234,6 +259,7 @@ impl PreContext {
             batch: self.batch,
             previous_state: state_snapshot,
             removed_batches: self.removed_batches,
+            raster_batch: self.raster_batch,
         };
         context
             .global_batch_mut()?
@@ -0,0 +1,187 @@
+use std::{mem, ops::Range, sync::Arc};
+
+use arrow2::{
+    array::{Array, PrimitiveArray},
+    chunk::Chunk,
+    datatypes::{DataType, Schema},
+    io::ipc::write::{default_ipc_fields, schema_to_bytes},
+};
+use memory::{
+    arrow::{
+        ipc::{
+            calculate_ipc_header_data, write_record_batch_body, write_record_batch_message_header,
+        },
+        record_batch::RecordBatch,
+    },
+    shared_memory::{MemoryId, Metaversion, Segment},
+};
+use tracing::trace;
+
+use crate::{Error, Result};
+
+/// Named raster layers stored in shared memory, which are readable and writable by the language
+/// runners.
+///
+/// The segment contains an Arrow record batch with one non-nullable `Float64` column per layer and
+/// one row per cell. Unlike the [`ContextBatch`], the schema is stored in the segment as well, so
+/// runners can read the layer names and the schema metadata without knowing about rasters.
+///
+/// The size of the layers never changes, so the memory and batch versions are never incremented
+/// and values can be read and written in place by the engine and the runners.
+///
+/// [`ContextBatch`]: crate::context::ContextBatch
+pub struct RasterBatch {
+    segment: Segment,
+    /// Byte offset of the values of each layer within the data buffer.
+    layer_offsets: Vec<usize>,
+    num_cells: usize,
+}
+
+impl RasterBatch {
+    /// Writes the layers to a new shared memory segment.
+    ///
+    /// `schema` must have one non-nullable `Float64` field per layer in `layers`, all layers must
+    /// have the same number of cells.
+    pub fn from_layers(
+        schema: Arc<Schema>,
+        layers: Vec<Vec<f64>>,
+        memory_id: MemoryId,
+    ) -> Result<Self> {
+        trace!(
+            "writing raster layers with schema {:?} to shared memory segment {}",
+            schema,
+            memory_id
+        );
+
+        if schema.fields.len() != layers.len()
+            || schema
+                .fields
+                .iter()
+                .any(|field| field.is_nullable || field.data_type() != &DataType::Float64)
+        {
+            return Err(Error::from(
+                "Raster schema must contain one non-nullable `Float64` field per layer",
+            ));
+        }
+        let num_cells = layers.first().map_or(0, Vec::len);
+        if layers.iter().any(|layer| layer.len() != num_cells) {
+            return Err(Error::from(
+                "Raster layers must have the same number of cells",
+            ));
+        }
+
+        let columns = layers
+            .into_iter()
+            .map(|values| PrimitiveArray::from_vec(values).boxed())
+            .collect::<Vec<Box<dyn Array>>>();
+        let record_batch = RecordBatch::new(Arc::clone(&schema), Chunk::try_new(columns)?);
+
+        let schema_buffer = schema_to_bytes(&schema, &default_ipc_fields(&schema.fields));
+        let header = Metaversion::default().to_le_bytes();
+        let header_data = calculate_ipc_header_data(&record_batch);
+
+        let mut metadata = vec![];
+        write_record_batch_message_header(&mut metadata, &header_data)?;
+
+        let mut body_data = vec![0; header_data.body_len];
+        write_record_batch_body(&record_batch, &mut body_data, &header_data)?;
+
+        // Every column has a (empty) validity buffer followed by its values.
+        let layer_offsets = header_data
+            .buffers
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .map(|buffer| buffer.offset as usize)
+            .collect();
+
+        let segment = Segment::from_batch_buffers(
+            memory_id,
+            &schema_buffer,
+            &header,
+            &metadata,
+            &body_data,
+            false,
+        )?;
+
+        Ok(Self {
+            segment,
+            layer_offsets,
+            num_cells,
+        })
+    }
+
+    pub fn segment(&self) -> &Segment {
+        &self.segment
+    }
+
+    pub fn num_layers(&self) -> usize {
+        self.layer_offsets.len()
+    }
+
+    pub fn num_cells(&self) -> usize {
+        self.num_cells
+    }
+
+    fn layer_range(&self, index: usize) -> Range<usize> {
+        let start = self.layer_offsets[index];
+        start..start + self.num_cells * mem::size_of::<f64>()
+    }
+
+    /// Returns the values of the layer at `index`, one per cell.
+    pub fn layer(&self, index: usize) -> Result<&[f64]> {
+        let bytes = &self.segment.get_data_buffer()?[self.layer_range(index)];
+        // SAFETY: Any bit pattern is a valid `f64`, the values were written as `f64`.
+        let (prefix, values, _) = unsafe { bytes.align_to::<f64>() };
+        if !prefix.is_empty() {
+            return Err(Error::from("Raster layer is not aligned"));
+        }
+        Ok(values)
+    }
+
+    /// Returns the values of the layer at `index` for writing them in place.
+    pub fn layer_mut(&mut self, index: usize) -> Result<&mut [f64]> {
+        let range = self.layer_range(index);
+        let bytes = &mut self.segment.get_mut_data_buffer()?[range];
+        // SAFETY: Any bit pattern is a valid `f64`, the values were written as `f64`.
+        let (prefix, values, _) = unsafe { bytes.align_to_mut::<f64>() };
+        if !prefix.is_empty() {
+            return Err(Error::from("Raster layer is not aligned"));
+        }
+        Ok(values)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow2::datatypes::Field;
+    use memory::arrow::ipc::read_record_batch;
+    use uuid::Uuid;
+
+    use super::*;
+
+    #[test]
+    fn test_write_in_place() -> Result<()> {
+        let schema = Arc::new(Schema::from(vec![
+            Field::new("food", DataType::Float64, false),
+            Field::new("water", DataType::Float64, false),
+        ]));
+        let mut batch = RasterBatch::from_layers(
+            Arc::clone(&schema),
+            vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]],
+            MemoryId::new(Uuid::new_v4()),
+        )?;
+        assert_eq!(batch.layer(1)?, [4.0, 5.0, 6.0]);
+
+        batch.layer_mut(1)?[2] = 7.0;
+
+        let record_batch = read_record_batch(batch.segment(), schema)?;
+        let water = record_batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<PrimitiveArray<f64>>()
+            .unwrap();
+        assert_eq!(water.values().as_slice(), [4.0, 5.0, 7.0]);
+        Ok(())
+    }
+}
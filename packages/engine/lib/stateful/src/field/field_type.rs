@@ -5,7 +5,7 @@
 
 use core::fmt;
 
-use arrow2::datatypes::{DataType, Field};
+use arrow2::datatypes::{DataType, Field, TimeUnit};
 
 use crate::field::{FieldSpec, IsFixedSize, UUID_V4_LEN};
 
@@ -50,8 +50,19 @@ impl IsFixedSize for PresetFieldType {
 pub enum FieldTypeVariant {
     /// A 64 bit floating point number
     Number,
+    /// A 64 bit signed integer
+    Integer,
+    /// A point in time, represented as milliseconds since the Unix epoch
+    Timestamp,
     Boolean,
     String,
+    /// A string out of a fixed set of `categories`
+    ///
+    /// Values are stored as a dictionary-encoded column with `categories` as dictionary values. As
+    /// the shared-memory batches can't hold dictionary batches, the categories are stored in the
+    /// schema metadata (see [`memory::arrow::dictionary_values`]). Only supported for top-level
+    /// fields.
+    Category(Vec<String>),
     /// A JSON-encoded String
     AnyType,
     FixedLengthArray {
@@ -67,8 +78,11 @@ impl From<FieldTypeVariant> for DataType {
     fn from(type_variant: FieldTypeVariant) -> Self {
         match type_variant {
             FieldTypeVariant::Number => Self::Float64,
+            FieldTypeVariant::Integer => Self::Int64,
+            FieldTypeVariant::Timestamp => Self::Timestamp(TimeUnit::Millisecond, None),
             FieldTypeVariant::Boolean => Self::Boolean,
             FieldTypeVariant::String => Self::Utf8,
+            FieldTypeVariant::Category(_) => memory::arrow::dictionary_data_type(),
             FieldTypeVariant::AnyType => Self::Utf8,
             FieldTypeVariant::FixedLengthArray { field_type, len } => DataType::FixedSizeList(
                 Box::new(Field::new("item", DataType::from(field_type.variant), true)),
@@ -98,7 +112,8 @@ impl From<FieldTypeVariant> for DataType {
 impl IsFixedSize for FieldTypeVariant {
     fn is_fixed_size(&self) -> bool {
         match self {
-            Self::Number | Self::Boolean => true,
+            Self::Number | Self::Integer | Self::Timestamp | Self::Boolean => true,
+            Self::Category(_) => true,
             Self::String | Self::AnyType => false,
             Self::FixedLengthArray {
                 field_type: element,
@@ -117,8 +132,11 @@ impl fmt::Debug for FieldTypeVariant {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             FieldTypeVariant::Number => write!(fmt, "number"),
+            FieldTypeVariant::Integer => write!(fmt, "integer"),
+            FieldTypeVariant::Timestamp => write!(fmt, "timestamp"),
             FieldTypeVariant::Boolean => write!(fmt, "boolean"),
             FieldTypeVariant::String => write!(fmt, "string"),
+            FieldTypeVariant::Category(categories) => write!(fmt, "category: {:?}", categories),
             FieldTypeVariant::AnyType => write!(fmt, "any"),
             FieldTypeVariant::FixedLengthArray {
                 field_type: kind,
@@ -13,6 +13,9 @@ impl IsFixedSize for DataType {
     fn is_fixed_size(&self) -> bool {
         match self {
             DataType::Float64 => true,
+            DataType::Int32 | DataType::Int64 | DataType::Timestamp(..) => true,
+            // Only the keys of dictionary-encoded columns are stored
+            DataType::Dictionary(..) => true,
             DataType::FixedSizeBinary(_) => true,
             DataType::Utf8 => false,
             DataType::FixedSizeList(val, _) => val.data_type().is_fixed_size(),
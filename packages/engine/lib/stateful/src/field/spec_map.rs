@@ -7,6 +7,7 @@ use std::{
 };
 
 use arrow2::datatypes::{Field, Schema};
+use memory::arrow::DICTIONARY_VALUES_METADATA_KEY;
 
 use crate::{
     field::{FieldScope, FieldTypeVariant, IsFixedSize, RootFieldKey, RootFieldSpec},
@@ -137,6 +138,7 @@ impl FieldSpecMap {
         let mut fixed_size_no = 0;
 
         let mut any_types = vec![];
+        let mut category_fields = BTreeMap::new();
 
         for (key, field_spec) in self.iter() {
             let key = key.value().to_string();
@@ -153,6 +155,10 @@ impl FieldSpecMap {
                 FieldTypeVariant::AnyType
             ) {
                 any_types.push(key)
+            } else if let FieldTypeVariant::Category(categories) =
+                &field_spec.inner.field_type.variant
+            {
+                category_fields.insert(key, categories.clone());
             }
         }
 
@@ -170,6 +176,13 @@ impl FieldSpecMap {
         //   Field's custom metadata instead of the schema
         metadata.insert("any_type_fields".into(), any_types.join(","));
         metadata.insert("nullable".into(), nullabilities.join(","));
+        if !category_fields.is_empty() {
+            // The dictionary values of category fields are not part of the batches
+            metadata.insert(
+                DICTIONARY_VALUES_METADATA_KEY.into(),
+                serde_json::to_string(&category_fields)?,
+            );
+        }
         Ok(Schema {
             fields: partitioned_fields
                 .into_iter()
@@ -19,6 +19,7 @@ pub enum FieldIndex {
     To = 0,
     Type = 1,
     Data = 2,
+    Delay = 3,
 }
 
 /// The "from" column contains the identifiers
@@ -30,9 +31,10 @@ pub const MESSAGE_COLUMN_INDEX: usize = 1;
 
 #[derive(Debug, Eq, PartialEq, ArrowField)]
 pub struct AgentMessage {
-    to: Vec<Option<String>>,
-    r#type: String,
-    r#data: Option<String>,
+    pub(crate) to: Vec<Option<String>>,
+    pub(crate) r#type: String,
+    pub(crate) r#data: Option<String>,
+    pub(crate) delay: Option<u32>,
 }
 
 #[derive(Debug, Clone)]
@@ -42,7 +44,7 @@ pub struct AgentMessage {
 ///
 /// The structure of the `MessageArray` is
 /// ```ignore
-/// ListArray ( StructArray (to: ListArray(Utf8), type: Utf8, data: Utf8) )
+/// ListArray ( StructArray (to: ListArray(Utf8), type: Utf8, data: Utf8, delay: UInt32) )
 /// ```
 pub(crate) struct MessageArray(pub ListArray<i32>);
 
@@ -114,26 +116,36 @@ impl MessageArray {
         for messages in column {
             let mut message_set = Vec::new();
             for message in serde_json::from_value::<Vec<Message>>(messages)? {
-                let (recipients, kind, data) = match message {
+                let (recipients, kind, data, delay) = match message {
                     Message::CreateAgent(outbound) => (
                         outbound.to,
                         payload::CreateAgent::KIND.to_string(),
                         Some(serde_json::to_string(&outbound.data).map_err(Error::from)?),
+                        None,
                     ),
                     Message::RemoveAgent(outbound) => (
                         outbound.to,
                         payload::RemoveAgent::KIND.to_string(),
                         Some(serde_json::to_string(&outbound.data).map_err(Error::from)?),
+                        None,
                     ),
                     Message::StopSim(outbound) => (
                         outbound.to,
                         payload::StopSim::KIND.to_string(),
                         outbound.data.as_ref().map(|data| data.to_string()),
+                        None,
+                    ),
+                    Message::SetGlobals(outbound) => (
+                        outbound.to,
+                        payload::SetGlobals::KIND.to_string(),
+                        Some(serde_json::to_string(&outbound.data).map_err(Error::from)?),
+                        None,
                     ),
                     Message::Generic(outbound) => (
                         outbound.to,
                         outbound.r#type,
                         outbound.data.as_ref().map(|data| data.to_string()),
+                        outbound.delay,
                     ),
                 };
 
@@ -141,12 +153,18 @@ impl MessageArray {
                     to: recipients.into_iter().map(Some).collect(),
                     r#type: kind,
                     data,
+                    delay,
                 }))
             }
             result.push(message_set)
         }
 
-        let arrow: Box<dyn Array> = result.try_into_arrow().unwrap();
+        Ok(Self::from_agent_messages(result))
+    }
+
+    /// Creates a [`MessageArray`] from the messages of every agent.
+    pub(crate) fn from_agent_messages(messages: Vec<Vec<Option<AgentMessage>>>) -> Self {
+        let arrow: Box<dyn Array> = messages.try_into_arrow().unwrap();
         let list_array = arrow
             .as_any()
             .downcast_ref::<ListArray<i32>>()
@@ -157,6 +175,6 @@ impl MessageArray {
         } else {
             unreachable!()
         });
-        Ok(Self(list_array))
+        Self(list_array)
     }
 }
@@ -1,4 +1,4 @@
-use arrow2::array::{ListArray, StructArray, Utf8Array};
+use arrow2::array::{Array, ListArray, PrimitiveArray, StructArray, Utf8Array};
 use memory::arrow::record_batch::RecordBatch;
 
 use crate::{
@@ -16,7 +16,7 @@ impl MessageColumn {
     fn from_array(array: &MessageArray) -> Result<Self> {
         let mut result = Vec::with_capacity(array.0.len());
 
-        let (to_column, r#type_column, data_column) = get_columns_from_struct_array(
+        let (to_column, r#type_column, data_column, delay_column) = get_columns_from_struct_array(
             array
                 .0
                 .values()
@@ -43,7 +43,10 @@ impl MessageColumn {
                     .collect();
                 let r#type = type_column.value(offset + j);
                 let data_string = data_column.value(offset + j);
-                messages.push(Message::new(&to, r#type, data_string)?);
+                let delay = delay_column
+                    .is_valid(offset + j)
+                    .then_some(delay_column.value(offset + j));
+                messages.push(Message::new(&to, r#type, data_string, delay)?);
                 to_offset += to_len;
             }
             result.push(messages);
@@ -64,14 +67,20 @@ impl MessageColumn {
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn get_columns_from_struct_array(
     array: &StructArray,
-) -> Result<(&ListArray<i32>, &Utf8Array<i32>, &Utf8Array<i32>)> {
+) -> Result<(
+    &ListArray<i32>,
+    &Utf8Array<i32>,
+    &Utf8Array<i32>,
+    &PrimitiveArray<u32>,
+)> {
     let columns = array.values();
-    if columns.len() != 3 {
+    if columns.len() != 4 {
         return Err(Error::UnexpectedVectorLength {
             len: columns.len(),
-            expected: 3,
+            expected: 4,
         });
     }
     let to_column = columns[0]
@@ -88,5 +97,11 @@ fn get_columns_from_struct_array(
             name: "data".into(),
         },
     )?;
-    Ok((to_column, type_column, data_column))
+    let delay_column = columns[3]
+        .as_any()
+        .downcast_ref::<PrimitiveArray<u32>>()
+        .ok_or(Error::InvalidArrowDowncast {
+            name: "delay".into(),
+        })?;
+    Ok((to_column, type_column, data_column, delay_column))
 }
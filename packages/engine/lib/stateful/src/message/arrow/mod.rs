@@ -23,6 +23,7 @@ lazy_static! {
         ),
         Field::new("type", DataType::Utf8, false),
         Field::new("data", DataType::Utf8, true),
+        Field::new("delay", DataType::UInt32, true),
     ];
     static ref MESSAGE_ARROW_TYPE: DataType =
         DataType::Struct(MESSAGE_ARROW_FIELDS.clone());
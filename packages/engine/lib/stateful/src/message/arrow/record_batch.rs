@@ -1,7 +1,7 @@
 use std::sync::Arc;
 
 use arrow2::{
-    array::{Array, ListArray, StructArray, Utf8Array},
+    array::{Array, ListArray, PrimitiveArray, StructArray, Utf8Array},
     chunk::Chunk,
     datatypes::Schema,
 };
@@ -84,6 +84,8 @@ pub(crate) fn get_message_field(
     // The "to" field is the 0th field in MESSAGE_ARROW_FIELDS
     // The "type" field is the 1st field in MESSAGE_ARROW_FIELDS
     // The "data" field is the 2nd field in MESSAGE_ARROW_FIELDS
+    // The "delay" field is not a string, use `get_message_delays` instead
+    debug_assert!(!matches!(index, FieldIndex::Delay));
     let is_nested_list = matches!(index, FieldIndex::To);
     let index_usize = index as usize;
 
@@ -130,6 +132,27 @@ pub(crate) fn get_message_field(
     (buffers, field)
 }
 
+/// Returns the offsets of the messages of every agent and the `delay` of every message.
+///
+/// The delay of the `i`-th message of agent `j` is at `offsets[j] + i`.
+pub(crate) fn get_message_delays(record_batch: &RecordBatch) -> (&[i32], &PrimitiveArray<u32>) {
+    let list_of_fields: &ListArray<i32> = record_batch
+        .column(MESSAGE_COLUMN_INDEX)
+        .as_any()
+        .downcast_ref::<ListArray<i32>>()
+        .unwrap();
+    let delays = list_of_fields
+        .values()
+        .as_any()
+        .downcast_ref::<StructArray>()
+        .unwrap()
+        .values()[FieldIndex::Delay as usize]
+        .as_any()
+        .downcast_ref::<PrimitiveArray<u32>>()
+        .unwrap();
+    (list_of_fields.offsets().as_slice(), delays)
+}
+
 pub fn from_json(
     schema: Arc<Schema>,
     ids: &[AgentId],
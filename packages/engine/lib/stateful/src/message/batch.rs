@@ -188,6 +188,51 @@ impl MessageBatch {
         Ok(())
     }
 
+    /// Replaces the messages of the batch with the messages in `record_batch`.
+    ///
+    /// In contrast to [`reset()`](Self::reset), the number of rows can change, so a batch holding
+    /// messages which aren't sent by the agents of a single agent batch can be reused. The shared
+    /// memory segment is kept, so runners reload the batch instead of loading a new segment.
+    pub fn overwrite(&mut self, record_batch: &RecordBatch) -> Result<()> {
+        let batch = &mut self.batch;
+        let mut metaversion_to_persist = batch.segment().read_persisted_metaversion();
+
+        if metaversion_to_persist.memory() != batch.loaded_metaversion().memory() {
+            return Err(Error::from(format!(
+                "Can't overwrite message batch when latest persisted memory isn't loaded: {:?}, \
+                 {:?}",
+                metaversion_to_persist,
+                batch.loaded_metaversion(),
+            )));
+        }
+        if batch.has_queued_changes() {
+            return Err(Error::from(
+                "Can't overwrite message batch when there are queued changes",
+            ));
+        }
+
+        let write_metadata = ipc::calculate_ipc_header_data(record_batch);
+        let mut metadata = vec![];
+        ipc::write_record_batch_message_header(&mut metadata, &write_metadata)?;
+        let change = batch.segment_mut().set_metadata(&metadata)?;
+        metaversion_to_persist.increment_with(&change);
+        let change = batch
+            .segment_mut()
+            .set_data_length(write_metadata.body_len)?;
+        metaversion_to_persist.increment_with(&change);
+
+        let data_buffer = batch.segment_mut().get_mut_data_buffer()?;
+        ipc::write_record_batch_body(record_batch, data_buffer, &write_metadata)?;
+
+        metaversion_to_persist.increment_batch();
+        batch
+            .segment_mut()
+            .persist_metaversion(metaversion_to_persist);
+        batch.reload_record_batch_and_dynamic_meta()?;
+        *batch.loaded_metaversion_mut() = metaversion_to_persist;
+        Ok(())
+    }
+
     pub fn empty_from_agent_batch(
         agent_batch: &AgentBatch,
         schema: &MessageSchema,
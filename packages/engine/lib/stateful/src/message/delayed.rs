@@ -0,0 +1,343 @@
+use std::{mem, sync::Arc};
+
+use arrow2::{
+    array::{Array, PrimitiveArray},
+    chunk::Chunk,
+};
+use memory::{arrow::record_batch::RecordBatch, shared_memory::MemoryId};
+
+use crate::{
+    agent::{arrow::array::get_agent_id_array, AgentId},
+    message::{
+        arrow::{
+            array::{AgentMessage, MessageArray},
+            record_batch::get_message_delays,
+        },
+        is_system_recipient, MessageBatch, MessageBatchPool, MessageLoader, MessageSchema,
+    },
+    proxy::{BatchPool, PoolReadProxy},
+    Error, Result,
+};
+
+/// Returns the number of steps until a message is delivered.
+///
+/// `offsets` and `delays` are the message offsets and delays of the batch the message is stored
+/// in. Messages without a `delay` are delivered in the next step.
+pub(in crate::message) fn message_delay(
+    offsets: &[i32],
+    delays: &PrimitiveArray<u32>,
+    agent_index: usize,
+    message_index: usize,
+) -> u32 {
+    let index = offsets[agent_index] as usize + message_index;
+    if delays.is_valid(index) {
+        delays.value(index).max(1)
+    } else {
+        1
+    }
+}
+
+#[derive(Debug)]
+struct DelayedMessage {
+    due_step: usize,
+    from: AgentId,
+    to: Vec<String>,
+    r#type: String,
+    data: Option<String>,
+}
+
+/// Messages sent with a `delay`, which are held until they are due.
+///
+/// Messages are delivered through the [`MessageMap`] in the step after they were sent. Messages
+/// with a `delay` of more than one step are left out of the [`MessageMap`] and collected here
+/// instead. When they are due, they are written to an additional [`MessageBatch`], which is
+/// appended to the messages of that step.
+///
+/// Pending messages only refer to their sender by its [`AgentId`] and to their recipients by id
+/// or name, so they are not affected by agents moving between batches.
+///
+/// The same [`MessageBatch`] is used for the due messages of every step, so the runners don't
+/// load a new shared memory segment at every step. After the step, it has to be given back with
+/// [`reclaim()`](Self::reclaim).
+///
+/// [`MessageMap`]: crate::message::MessageMap
+#[derive(Debug, Default)]
+pub struct DelayedMessages {
+    pending: Vec<DelayedMessage>,
+    /// The batch holding the due messages, `None` if it's part of the message pool.
+    batch: Option<MessageBatch>,
+    /// If the batch was returned by [`take_due()`](Self::take_due) and not reclaimed yet.
+    delivered: bool,
+}
+
+impl DelayedMessages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of messages which are not delivered yet.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Collects the messages with a `delay` from `message_pool`.
+    ///
+    /// `message_pool` contains the messages sent in the previous step, i.e. messages without a
+    /// delay are delivered at `current_step`. Messages sent to `"hash"` are always handled at
+    /// `current_step`, a `delay` only applies to the agents among the recipients.
+    pub fn collect(
+        &mut self,
+        message_pool: &PoolReadProxy<MessageBatch>,
+        current_step: usize,
+    ) -> Result<()> {
+        for batch in message_pool.batches_iter() {
+            let (offsets, delays) = get_message_delays(batch.batch.record_batch()?);
+            if delays.null_count() == delays.len() {
+                continue;
+            }
+            let loader = MessageLoader::from_batch(batch)?;
+            for agent_index in 0..offsets.len() - 1 {
+                let num_messages = (offsets[agent_index + 1] - offsets[agent_index]) as usize;
+                for message_index in 0..num_messages {
+                    let delay = message_delay(offsets, delays, agent_index, message_index);
+                    if delay <= 1 {
+                        continue;
+                    }
+                    let to: Vec<String> = loader
+                        .get_recipients(agent_index, message_index)
+                        .into_iter()
+                        .filter(|recipient| !is_system_recipient(recipient))
+                        .map(str::to_string)
+                        .collect();
+                    if to.is_empty() {
+                        continue;
+                    }
+                    let data = loader.get_data(agent_index, message_index);
+                    self.pending.push(DelayedMessage {
+                        due_step: current_step + delay as usize - 1,
+                        from: AgentId::from_bytes(*loader.get_from(agent_index)),
+                        to,
+                        r#type: loader.get_type(agent_index, message_index).to_string(),
+                        data: (!data.is_empty()).then(|| data.to_string()),
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Removes the messages which are due at `current_step` and writes them to the
+    /// [`MessageBatch`] of the delayed messages, returns `None` if no message is due.
+    ///
+    /// Every message is written to its own row, so the sender of every message is kept. The batch
+    /// is created on the first call and reused afterwards, so it has to be reclaimed before the
+    /// next call.
+    pub fn take_due(
+        &mut self,
+        current_step: usize,
+        schema: &MessageSchema,
+        memory_id: MemoryId,
+    ) -> Result<Option<MessageBatch>> {
+        if self.delivered {
+            return Err(Error::from(
+                "The batch of the delayed messages has not been reclaimed",
+            ));
+        }
+        let (due, pending) = mem::take(&mut self.pending)
+            .into_iter()
+            .partition::<Vec<_>, _>(|message| message.due_step <= current_step);
+        self.pending = pending;
+        if due.is_empty() {
+            return Ok(None);
+        }
+
+        let ids: Vec<AgentId> = due.iter().map(|message| message.from).collect();
+        let messages = due
+            .into_iter()
+            .map(|message| {
+                vec![Some(AgentMessage {
+                    to: message.to.into_iter().map(Some).collect(),
+                    r#type: message.r#type,
+                    data: message.data,
+                    delay: None,
+                })]
+            })
+            .collect();
+        let record_batch = RecordBatch::new(
+            Arc::clone(&schema.arrow),
+            Chunk::new(vec![
+                get_agent_id_array(&ids)?.boxed(),
+                MessageArray::from_agent_messages(messages).to_boxed(),
+            ]),
+        );
+        let batch = match self.batch.take() {
+            Some(mut batch) => {
+                batch.overwrite(&record_batch)?;
+                batch
+            }
+            None => MessageBatch::from_record_batch(&record_batch, schema, memory_id)?,
+        };
+        self.delivered = true;
+        Ok(Some(batch))
+    }
+
+    /// Takes back the batch returned by [`take_due()`](Self::take_due) from `message_pool`, if
+    /// it was delivered.
+    ///
+    /// The batch is appended to the pool when it's delivered, so it's the last batch of the pool.
+    pub fn reclaim(&mut self, message_pool: &mut MessageBatchPool) -> Result<()> {
+        if !self.delivered {
+            return Ok(());
+        }
+        let index = message_pool.len().checked_sub(1).ok_or_else(|| {
+            Error::from("The batch of the delayed messages is missing in the message pool")
+        })?;
+        self.batch = Some(message_pool.remove(index));
+        self.delivered = false;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::{agent::Agent, message::MessageMap};
+
+    fn memory_id() -> MemoryId {
+        MemoryId::new(Uuid::new_v4())
+    }
+
+    /// Creates a pool with a single batch holding the messages sent by `agents`.
+    fn message_pool(agents: &[Agent], schema: &MessageSchema) -> MessageBatchPool {
+        let mut pool = MessageBatchPool::empty();
+        pool.push(MessageBatch::from_agent_states(agents, schema, memory_id()).unwrap());
+        pool
+    }
+
+    fn agents() -> Vec<Agent> {
+        vec![
+            serde_json::from_value(json!({
+                "agent_name": "a",
+                "messages": [
+                    { "to": ["b", "hash"], "type": "x", "data": { "value": 1 }, "delay": 3 },
+                    { "to": ["c"], "type": "y" },
+                    { "to": ["hAsH"], "type": "z", "delay": 2 },
+                ]
+            }))
+            .unwrap(),
+            serde_json::from_value(json!({
+                "agent_name": "b",
+                "messages": [
+                    { "to": ["a"], "type": "x", "delay": 0 },
+                    { "to": ["a"], "type": "x", "delay": 1 },
+                ]
+            }))
+            .unwrap(),
+        ]
+    }
+
+    /// Returns the recipients, type, and sender of every message in `batch`.
+    fn messages(batch: &MessageBatch) -> Vec<(Vec<String>, String, AgentId)> {
+        let loader = MessageLoader::from_batch(batch).unwrap();
+        (0..batch.batch.record_batch().unwrap().num_rows())
+            .map(|agent_index| {
+                (
+                    loader
+                        .get_recipients(agent_index, 0)
+                        .into_iter()
+                        .map(str::to_string)
+                        .collect(),
+                    loader.get_type(agent_index, 0).to_string(),
+                    AgentId::from_bytes(*loader.get_from(agent_index)),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn message_delays() {
+        let schema = MessageSchema::new();
+        let pool = message_pool(&agents(), &schema);
+        let proxies = pool.read_proxies().unwrap();
+        let (offsets, delays) =
+            get_message_delays(proxies.batch(0).unwrap().batch.record_batch().unwrap());
+        let delays: Vec<_> = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1)]
+            .into_iter()
+            .map(|(agent_index, message_index)| {
+                message_delay(offsets, delays, agent_index, message_index)
+            })
+            .collect();
+        assert_eq!(delays, [3, 1, 2, 1, 1]);
+    }
+
+    #[test]
+    fn deliver_due_messages() {
+        let schema = MessageSchema::new();
+        let agents = agents();
+        let pool = message_pool(&agents, &schema);
+
+        let mut delayed = DelayedMessages::new();
+        delayed.collect(&pool.read_proxies().unwrap(), 2).unwrap();
+        // Only the agents among the recipients are delayed, `hAsH` is not the engine
+        assert_eq!(delayed.len(), 2);
+        assert!(delayed.take_due(2, &schema, memory_id()).unwrap().is_none());
+
+        let batch = delayed.take_due(3, &schema, memory_id()).unwrap().unwrap();
+        assert_eq!(messages(&batch), [(
+            vec!["hAsH".to_string()],
+            "z".to_string(),
+            agents[0].agent_id
+        )]);
+        let segment_id = batch.batch.segment().id().to_string();
+        assert_eq!(delayed.len(), 1);
+
+        // The batch has to be reclaimed before it's reused
+        let mut inbox = message_pool(&agents, &schema);
+        inbox.push(batch);
+        assert!(delayed.take_due(4, &schema, memory_id()).is_err());
+        delayed.reclaim(&mut inbox).unwrap();
+        assert_eq!(inbox.len(), 1);
+
+        let batch = delayed.take_due(4, &schema, memory_id()).unwrap().unwrap();
+        assert_eq!(batch.batch.segment().id(), segment_id);
+        assert_eq!(messages(&batch), [(
+            vec!["b".to_string()],
+            "x".to_string(),
+            agents[0].agent_id
+        )]);
+        assert!(delayed.is_empty());
+    }
+
+    #[test]
+    fn add_due_messages_to_map() {
+        let schema = MessageSchema::new();
+        let agents = agents();
+        let mut pool = message_pool(&agents, &schema);
+
+        let mut delayed = DelayedMessages::new();
+        delayed.collect(&pool.read_proxies().unwrap(), 2).unwrap();
+        let mut map = MessageMap::new(&pool.read_proxies().unwrap()).unwrap();
+        // Delayed messages are left out, except for the engine
+        assert!(map.get_msg_refs("b").is_empty());
+        assert!(map.get_msg_refs("hAsH").is_empty());
+        assert_eq!(map.get_msg_refs("c").len(), 1);
+        assert_eq!(map.get_msg_refs("hash").len(), 1);
+        assert_eq!(map.get_msg_refs("a").len(), 2);
+
+        let batch = delayed.take_due(4, &schema, memory_id()).unwrap().unwrap();
+        map.add_batch(pool.len(), &batch).unwrap();
+        pool.push(batch);
+        let refs = map.get_msg_refs("b");
+        assert_eq!(refs.len(), 1);
+        assert_eq!(refs[0].batch_index, 1);
+        assert_eq!(map.get_msg_refs("hAsH").len(), 1);
+        assert_eq!(map.get_msg_refs("hash").len(), 1);
+    }
+}
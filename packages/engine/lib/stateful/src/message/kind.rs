@@ -18,3 +18,9 @@ pub enum StopSim {
     #[serde(rename = "stop")]
     Type,
 }
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum SetGlobals {
+    #[serde(rename = "set_globals")]
+    Type,
+}
@@ -79,7 +79,6 @@ impl<'a> MessageLoader<'a> {
         &self.data[content_start..next_content_start]
     }
 
-    #[allow(dead_code)]
     pub(crate) fn get_recipients(&self, agent_index: usize, message_index: usize) -> Vec<&'a str> {
         let list_index = self.to_bufs[0][agent_index] as usize + message_index;
         let list_start = self.to_bufs[1][list_index] as usize;
@@ -7,7 +7,10 @@ use rayon::iter::ParallelIterator;
 
 use crate::{
     error::Result,
-    message::{pool::recipient_iter_all, MessageBatch},
+    message::{
+        pool::{recipient_iter, recipient_iter_all},
+        MessageBatch,
+    },
     proxy::PoolReadProxy,
     state::MessageReference,
 };
@@ -57,6 +60,25 @@ impl MessageMap {
         Ok(MessageMap { inner })
     }
 
+    /// Adds the messages of `batch`, which is stored at `batch_index` in the message pool.
+    ///
+    /// This is used to deliver messages which are not sent by the agents of the pool, e.g. the
+    /// messages returned by [`DelayedMessages::take_due`].
+    ///
+    /// [`DelayedMessages::take_due`]: crate::message::DelayedMessages::take_due
+    pub fn add_batch(&mut self, batch_index: usize, batch: &MessageBatch) -> Result<()> {
+        let messages: Vec<_> = recipient_iter(batch.batch.record_batch()?, batch_index).collect();
+        for (recipients, message_ref) in messages {
+            for recipient in recipients {
+                self.inner
+                    .entry(recipient.to_string())
+                    .or_default()
+                    .push(message_ref.clone());
+            }
+        }
+        Ok(())
+    }
+
     pub fn get_msg_refs(&self, recipient: &str) -> &[MessageReference] {
         self.inner.get(recipient).map(Deref::deref).unwrap_or(&[])
     }
@@ -9,7 +9,8 @@
 //! [`Message`]s are laid out in-memory in [`MessageBatch`]es according to the representation
 //! defined by [`MessageSchema`]. Multiple [`MessageBatch`]es are collected in a
 //! [`MessageBatchPool`] which is interacted with through the [`MessageLoader`] and
-//! [`MessageReader`].
+//! [`MessageReader`]. Messages sent with a `delay` are held in [`DelayedMessages`] until they are
+//! due.
 //!
 //! [HASH documentation]: https://hash.ai/docs/simulation/creating-simulations/agent-messages
 //! [`Agent`]: crate::agent::Agent
@@ -19,6 +20,7 @@ pub mod payload;
 pub(crate) mod arrow;
 
 mod batch;
+mod delayed;
 mod kind;
 mod loader;
 mod map;
@@ -28,6 +30,7 @@ mod schema;
 
 pub use self::{
     batch::MessageBatch,
+    delayed::DelayedMessages,
     loader::{MessageLoader, RawMessage},
     map::MessageMap,
     outbound::Message,
@@ -35,7 +38,7 @@ pub use self::{
     schema::MessageSchema,
 };
 pub(crate) use self::{
-    kind::{CreateAgent, RemoveAgent, StopSim},
+    kind::{CreateAgent, RemoveAgent, SetGlobals, StopSim},
     outbound::Error as OutboundError,
 };
 
@@ -43,3 +46,12 @@ pub(crate) use self::{
 /// another agent). Messages which can be sent to the engine include (amongst other things)
 /// instructions to remove or create agents.
 const SYSTEM_MESSAGE: &str = "hash";
+
+/// Variations of [`SYSTEM_MESSAGE`] accepted as a recipient. This is the **only** list of
+/// acceptable variants, e.g. messages to `hAsH` are sent to an agent of that name.
+pub const SYSTEM_MESSAGE_VARIANTS: [&str; 3] = ["hash", "Hash", "HASH"];
+
+/// Returns if messages sent to `recipient` are handled by the engine.
+fn is_system_recipient(recipient: &str) -> bool {
+    SYSTEM_MESSAGE_VARIANTS.contains(&recipient)
+}
@@ -4,7 +4,7 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     agent::Agent,
-    message::{payload, SYSTEM_MESSAGE},
+    message::{is_system_recipient, payload, SYSTEM_MESSAGE},
     Result,
 };
 
@@ -54,8 +54,9 @@ impl StdError for Error {}
 // Message::CreateAgent and Message::RemoveAgent.
 /// A message sent by an [`Agent`].
 ///
-/// Currently, three types of [built-in messages] are available: `"create_agent"`, `"remove_agent"`,
-/// and `"stop"`. For [messages sent] to other agents, a [`Generic`] message is used.
+/// Currently, four types of [built-in messages] are available: `"create_agent"`, `"remove_agent"`,
+/// `"stop"`, and `"set_globals"`. For [messages sent] to other agents, a [`Generic`] message is
+/// used.
 ///
 /// [built-in messages]: https://hash.ai/docs/simulation/creating-simulations/agent-messages/built-in-message-handlers
 /// [messages sent]: https://hash.ai/docs/simulation/creating-simulations/agent-messages/sending-messages
@@ -123,16 +124,25 @@ pub enum Message {
     RemoveAgent(payload::RemoveAgent),
     /// `"stop"` sent to `"hash"` will attempt to stop the current simulation run.
     StopSim(payload::StopSim),
+    /// `"set_globals"` sent to `"hash"` will change globals between steps.
+    SetGlobals(payload::SetGlobals),
     /// A message to be sent between agents with a JSON payload
     Generic(payload::Generic),
 }
 
 fn is_system_message(kind: &str) -> bool {
-    kind == payload::CreateAgent::KIND || kind == payload::RemoveAgent::KIND
+    kind == payload::CreateAgent::KIND
+        || kind == payload::RemoveAgent::KIND
+        || kind == payload::SetGlobals::KIND
 }
 
 impl Message {
-    pub(in crate::message) fn new(to: &[&str], r#type: &str, data_string: &str) -> Result<Message> {
+    pub(in crate::message) fn new(
+        to: &[&str],
+        r#type: &str,
+        data_string: &str,
+        delay: Option<u32>,
+    ) -> Result<Message> {
         let to_clone = to.iter().map(|v| (*v).to_string()).collect();
 
         Ok(Self::Generic(payload::Generic {
@@ -143,6 +153,7 @@ impl Message {
             } else {
                 Some(serde_json::Value::from(data_string))
             },
+            delay,
         }))
     }
 
@@ -202,7 +213,7 @@ impl Message {
 
     fn is_hash_engine_message(value: &mut serde_json::Value) -> bool {
         if let Some(serde_json::Value::String(recipient)) = value.get("to") {
-            return recipient.eq_ignore_ascii_case(SYSTEM_MESSAGE);
+            return is_system_recipient(recipient);
         }
         false
     }
@@ -70,6 +70,28 @@ impl StopSim {
     pub const KIND: &'static str = "stop";
 }
 
+/// Globals to be changed by a [`SetGlobals`] message.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SetGlobalsData {
+    /// The globals to set, other globals are kept.
+    pub globals: serde_json::Map<String, serde_json::Value>,
+    /// The step at which the globals are changed, defaults to the next step.
+    #[serde(default)]
+    pub step: Option<usize>,
+}
+
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct SetGlobals {
+    pub r#type: message::SetGlobals,
+    #[serde(deserialize_with = "value_or_string_array")]
+    pub to: Vec<String>,
+    pub data: SetGlobalsData,
+}
+
+impl SetGlobals {
+    pub const KIND: &'static str = "set_globals";
+}
+
 /// Payload for arbitrary JSON data.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub struct Generic {
@@ -79,4 +101,8 @@ pub struct Generic {
     pub to: Vec<String>,
 
     pub data: Option<serde_json::Value>,
+
+    /// Number of steps until the message is delivered, defaults to `1`, i.e. the next step.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay: Option<u32>,
 }
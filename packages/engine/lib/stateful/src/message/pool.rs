@@ -1,6 +1,6 @@
 use std::{borrow::Borrow, sync::Arc};
 
-use memory::shared_memory::MemoryId;
+use memory::{arrow::record_batch::RecordBatch, shared_memory::MemoryId};
 use parking_lot::RwLock;
 use rayon::iter::{
     IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
@@ -11,7 +11,9 @@ use crate::{
     agent::AgentBatch,
     field::UUID_V4_LEN,
     message,
-    message::{MessageBatch, MessageLoader, MessageSchema},
+    message::{
+        delayed::message_delay, is_system_recipient, MessageBatch, MessageLoader, MessageSchema,
+    },
     proxy::{BatchPool, BatchReadProxy, BatchWriteProxy, PoolReadProxy, PoolWriteProxy},
     state::MessageReference,
     Error, Result,
@@ -172,16 +174,44 @@ pub fn recipient_iter_all<'b: 'r, 'r>(
         .enumerate()
         .flat_map(|(i, group)| {
             let record_batch = group.batch.record_batch().unwrap(); // TODO: unwrap --> err
-            message::arrow::record_batch::message_recipients_iter(record_batch)
-                .zip_eq(message::arrow::record_batch::message_usize_index_iter(
-                    record_batch,
-                    i,
-                ))
-                .flat_map(|(recipients, references)| {
-                    let res = recipients.collect::<Vec<_>>();
-                    let refs = references.collect::<Vec<_>>();
-                    res.into_par_iter().zip_eq(refs.into_par_iter())
-                })
+            recipient_iter(record_batch, i)
+        })
+}
+
+/// Iterates the recipients of all messages in `record_batch`, which is stored at `batch_index` in
+/// the pool.
+///
+/// Messages with a `delay` of more than one step are only yielded for `"hash"`. They are delivered
+/// to agents by [`DelayedMessages`] instead.
+///
+/// [`DelayedMessages`]: crate::message::DelayedMessages
+pub(in crate::message) fn recipient_iter(
+    record_batch: &RecordBatch,
+    batch_index: usize,
+) -> impl ParallelIterator<Item = (Vec<&str>, MessageReference)> + '_ {
+    let (offsets, delays) = message::arrow::record_batch::get_message_delays(record_batch);
+    message::arrow::record_batch::message_recipients_iter(record_batch)
+        .zip_eq(message::arrow::record_batch::message_usize_index_iter(
+            record_batch,
+            batch_index,
+        ))
+        .flat_map(move |(recipients, references)| {
+            let res = recipients.collect::<Vec<_>>();
+            let refs = references.collect::<Vec<_>>();
+            res.into_par_iter().zip_eq(refs.into_par_iter()).map(
+                move |(mut recipients, reference)| {
+                    let delay = message_delay(
+                        offsets,
+                        delays,
+                        reference.agent_index,
+                        reference.message_index,
+                    );
+                    if delay > 1 {
+                        recipients.retain(|recipient| is_system_recipient(recipient));
+                    }
+                    (recipients, reference)
+                },
+            )
         })
 }
 
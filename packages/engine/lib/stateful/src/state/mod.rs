@@ -44,6 +44,8 @@ pub struct StateCreateParameters {
     pub target_min_groups: usize,
     /// Limits the number of agents per group.
     pub target_group_size: Range<usize>,
+    /// Number of workers the groups are distributed to, round-robin.
+    pub num_workers: usize,
     /// Base id used for generating a [`MemoryId`] for new batches.
     pub memory_base_id: Uuid,
     pub agent_schema: Arc<AgentSchema>,
@@ -92,17 +94,17 @@ impl State {
         let mut start = 0;
 
         // converts the `agent_state_groups` from Array-of-Structs into a Struct-of-Arrays.
-        for agent_state_group in agent_state_groups {
+        for (group_index, agent_state_group) in agent_state_groups.iter().enumerate() {
             group_start_indices.push(start);
             start += agent_state_group.len();
 
-            agent_batches.push(Arc::new(parking_lot::RwLock::new(
-                AgentBatch::from_agent_states(
-                    *agent_state_group,
-                    &create_parameters.agent_schema,
-                    MemoryId::new(create_parameters.memory_base_id),
-                )?,
-            )));
+            let mut agent_batch = AgentBatch::from_agent_states(
+                *agent_state_group,
+                &create_parameters.agent_schema,
+                MemoryId::new(create_parameters.memory_base_id),
+            )?;
+            agent_batch.set_worker_index(group_index % create_parameters.num_workers.max(1));
+            agent_batches.push(Arc::new(parking_lot::RwLock::new(agent_batch)));
             message_batches.push(Arc::new(parking_lot::RwLock::new(
                 MessageBatch::from_agent_states(
                     *agent_state_group,
@@ -217,6 +217,10 @@ pub async fn run_test_suite(
                     wait_timeout,
                     js_runner_initial_heap_constraint: None,
                     js_runner_max_heap_size: None,
+                    js_runner_disable_snapshot: false,
+                    python_environments_folder: None,
+                    python_wheel_dirs: Vec::new(),
+                    shared_memory_pool: false,
                 };
 
                 let test_result = run_test(